@@ -0,0 +1,215 @@
+use lib_entity::api_key;
+use lib_utils::password::{hash_token, random_hex};
+use sea_orm::{entity::*, query::*};
+
+use super::schema::{ApiKeyModel, CreateApiKeyRequest, CreateApiKeyResponse, RevokeApiKeyRequest};
+use crate::{error::ErrorInService, DBConnection};
+
+/// API Key 的固定前缀, 用于和 JWT 区分
+pub const API_KEY_PREFIX: &str = "ak_";
+// 列表中展示的 Key 长度
+const DISPLAY_PREFIX_LEN: usize = 11;
+// 最后使用时间的更新间隔, 避免每次请求都写数据库
+const LAST_USED_INTERVAL_SECS: i64 = 60;
+
+pub struct ApiKeyController;
+
+impl ApiKeyController {
+    /// 创建 API Key, 明文只在这里返回一次
+    pub async fn create_api_key(
+        &self,
+        req: CreateApiKeyRequest,
+        conn: &DBConnection,
+    ) -> Result<CreateApiKeyResponse, ErrorInService> {
+        let account_id = req
+            .account_id
+            .ok_or(ErrorInService::Custom("account_id is required".to_string()))?;
+        let name = req.name.trim().to_string();
+        if name.is_empty() {
+            return Err(ErrorInService::Custom("name is required".to_string()));
+        }
+        if req.scopes.is_empty() {
+            return Err(ErrorInService::Custom("scopes is required".to_string()));
+        }
+        let mut scopes = Vec::new();
+        for scope in req.scopes {
+            if !scopes.contains(&scope) {
+                scopes.push(scope);
+            }
+        }
+
+        let key = format!("{}{}", API_KEY_PREFIX, random_hex(24));
+        let model = api_key::ActiveModel {
+            account_id: Set(account_id),
+            name: Set(name),
+            prefix: Set(key[..DISPLAY_PREFIX_LEN].to_string()),
+            key_hash: Set(hash_token(&key)),
+            scopes: Set(Some(serde_json::json!(scopes))),
+            expired_at: Set(req.expired_at),
+            created_at: Set(chrono::Utc::now().naive_utc()),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await?;
+        Ok(CreateApiKeyResponse {
+            key,
+            api_key: model.into(),
+        })
+    }
+
+    /// 用户未吊销的 API Key
+    pub async fn list_api_keys(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<ApiKeyModel>, ErrorInService> {
+        let models = api_key::Entity::find()
+            .filter(api_key::Column::AccountId.eq(account_id))
+            .filter(api_key::Column::RevokedAt.is_null())
+            .order_by_desc(api_key::Column::Id)
+            .all(conn)
+            .await?;
+        Ok(models.into_iter().map(|m| m.into()).collect())
+    }
+
+    /// 吊销 API Key
+    pub async fn revoke_api_key(
+        &self,
+        req: RevokeApiKeyRequest,
+        conn: &DBConnection,
+    ) -> Result<bool, ErrorInService> {
+        let account_id = req
+            .account_id
+            .ok_or(ErrorInService::Custom("account_id is required".to_string()))?;
+        let result = api_key::Entity::update_many()
+            .col_expr(
+                api_key::Column::RevokedAt,
+                chrono::Utc::now().naive_utc().into(),
+            )
+            .filter(api_key::Column::Id.eq(req.id))
+            .filter(api_key::Column::AccountId.eq(account_id))
+            .filter(api_key::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// 校验 API Key, 有效时返回 Key 的信息并更新最后使用时间
+    pub async fn authenticate(
+        &self,
+        key: &str,
+        conn: &DBConnection,
+    ) -> Result<Option<ApiKeyModel>, ErrorInService> {
+        if !key.starts_with(API_KEY_PREFIX) {
+            return Ok(None);
+        }
+        let now = chrono::Utc::now().naive_utc();
+        let model = api_key::Entity::find()
+            .filter(api_key::Column::KeyHash.eq(hash_token(key)))
+            .filter(api_key::Column::RevokedAt.is_null())
+            .filter(
+                Condition::any()
+                    .add(api_key::Column::ExpiredAt.is_null())
+                    .add(api_key::Column::ExpiredAt.gt(now)),
+            )
+            .one(conn)
+            .await?;
+        let Some(model) = model else {
+            return Ok(None);
+        };
+        let stale = model
+            .last_used_at
+            .is_none_or(|t| (now - t).num_seconds() >= LAST_USED_INTERVAL_SECS);
+        if stale {
+            api_key::Entity::update_many()
+                .col_expr(api_key::Column::LastUsedAt, now.into())
+                .filter(api_key::Column::Id.eq(model.id))
+                .exec(conn)
+                .await?;
+        }
+        let mut model: ApiKeyModel = model.into();
+        if stale {
+            model.last_used_at = Some(now);
+        }
+        Ok(Some(model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::schema::{
+        ApiKeyScope, CreateApiKeyRequestBuilder, RevokeApiKeyRequestBuilder,
+    };
+
+    #[tokio::test]
+    async fn test_api_key_lifecycle() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = ApiKeyController;
+        let created = controller
+            .create_api_key(
+                CreateApiKeyRequestBuilder::default()
+                    .account_id(1)
+                    .name("script")
+                    .scopes(vec![ApiKeyScope::ReadFeeds])
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(created.key.starts_with(API_KEY_PREFIX));
+        assert!(created.key.starts_with(&created.api_key.prefix));
+
+        let authenticated = controller
+            .authenticate(&created.key, &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(authenticated.account_id, 1);
+        assert_eq!(authenticated.scopes, vec![ApiKeyScope::ReadFeeds]);
+        assert!(authenticated.last_used_at.is_some());
+        assert!(controller
+            .authenticate("ak_wrong", &conn)
+            .await
+            .unwrap()
+            .is_none());
+
+        // 过期的 Key 无效
+        let expired = controller
+            .create_api_key(
+                CreateApiKeyRequestBuilder::default()
+                    .account_id(1)
+                    .name("expired")
+                    .scopes(vec![ApiKeyScope::RequestSummaries])
+                    .expired_at(chrono::Utc::now().naive_utc() - chrono::Duration::minutes(1))
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(controller
+            .authenticate(&expired.key, &conn)
+            .await
+            .unwrap()
+            .is_none());
+
+        // 只能吊销自己的 Key
+        let revoke = |account_id: i64| {
+            RevokeApiKeyRequestBuilder::default()
+                .account_id(account_id)
+                .id(created.api_key.id)
+                .build()
+                .unwrap()
+        };
+        assert!(!controller.revoke_api_key(revoke(2), &conn).await.unwrap());
+        assert!(controller.revoke_api_key(revoke(1), &conn).await.unwrap());
+        assert!(controller
+            .authenticate(&created.key, &conn)
+            .await
+            .unwrap()
+            .is_none());
+        assert_eq!(controller.list_api_keys(1, &conn).await.unwrap().len(), 1);
+    }
+}
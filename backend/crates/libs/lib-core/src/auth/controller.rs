@@ -1,10 +1,11 @@
-use lib_entity::account;
+use lib_entity::account::{self, AccountRole};
+use lib_utils::password::{hash_password, verify_password, PasswordVerification};
 
 use crate::{error::ErrorInService, DBConnection};
 
 use super::schema::{
-    AccountModel, LoginAccountRequest, LoginAccountResponse, QueryAccountByIDRequest,
-    RegisterAccountRequest,
+    AccountModel, LoginAccountRequest, QueryAccountByIDRequest, RegisterAccountRequest,
+    UpdateAccountRoleRequest,
 };
 use sea_orm::{entity::*, query::*};
 
@@ -14,6 +15,46 @@ impl QueryAccountByIDRequest {
     }
 }
 
+// 密码哈希的计算量很大, 放到阻塞线程中执行, 避免阻塞异步运行时
+async fn hash_password_blocking(password: &str) -> Result<String, ErrorInService> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash_password(&password))
+        .await
+        .map_err(|e| ErrorInService::Custom(format!("hash password failed:{}", e)))
+}
+
+async fn verify_password_blocking(
+    password: &str,
+    stored: &str,
+) -> Result<PasswordVerification, ErrorInService> {
+    let (password, stored) = (password.to_string(), stored.to_string());
+    tokio::task::spawn_blocking(move || verify_password(&password, &stored))
+        .await
+        .map_err(|e| ErrorInService::Custom(format!("verify password failed:{}", e)))
+}
+
+// 管理员初始密码的最小长度
+const MIN_ADMIN_PASSWORD_LEN: usize = 12;
+
+// 拒绝过短或者容易猜到的管理员密码
+fn check_admin_password(email: &str, password: &str) -> Result<(), ErrorInService> {
+    let lowered = password.to_lowercase();
+    let name = email.split('@').next().unwrap_or(email).to_lowercase();
+    if password.chars().count() < MIN_ADMIN_PASSWORD_LEN
+        || ["admin", "password", "123456"]
+            .iter()
+            .any(|w| lowered == *w)
+        || lowered == email.to_lowercase()
+        || lowered == name
+    {
+        return Err(ErrorInService::Custom(format!(
+            "admin password is too weak, at least {} characters and not the account name",
+            MIN_ADMIN_PASSWORD_LEN
+        )));
+    }
+    Ok(())
+}
+
 pub struct AccountController;
 
 impl AccountController {
@@ -22,14 +63,13 @@ impl AccountController {
         req: RegisterAccountRequest,
         conn: &DBConnection,
     ) -> Result<AccountModel, ErrorInService> {
-        let query = account::Entity::find().filter(account::Column::Email.eq(&req.email.clone()));
+        let query = account::Entity::find().filter(account::Column::Email.eq(req.email.clone()));
         let account = query.one(conn).await.map_err(ErrorInService::DBError)?;
         if account.is_some() {
             return Err(ErrorInService::Custom("account already exists".to_string()));
         }
-        // hashed password
-        let hashed_password_dist = md5::compute(req.password.as_bytes());
-        let hashed_password = format!("{:x}", hashed_password_dist);
+        // 加盐哈希密码
+        let hashed_password = hash_password_blocking(&req.password).await?;
         // add new account
         let new_account = account::ActiveModel {
             email: Set(Some(req.email)),
@@ -42,30 +82,150 @@ impl AccountController {
     }
 
     // login account
+    // 校验通过后返回用户信息, 令牌由调用方签发
     pub async fn login_account(
         &self,
         req: LoginAccountRequest,
         conn: &DBConnection,
-    ) -> Result<LoginAccountResponse, ErrorInService> {
+    ) -> Result<AccountModel, ErrorInService> {
         // query if account exists
         let query = account::Entity::find().filter(account::Column::Email.eq(&req.email));
         let account = query.one(conn).await.map_err(ErrorInService::DBError)?;
         let account = account.ok_or(ErrorInService::Custom("account not found".to_string()))?;
         // check password
-        let hashed_password_dist = md5::compute(req.password.as_bytes());
-        let hashed_password = format!("{:x}", hashed_password_dist);
-        if account.password != Some(hashed_password) {
-            return Err(ErrorInService::Custom("password error".to_string()));
+        let stored = account.password.clone().unwrap_or_default();
+        let needs_rehash = match verify_password_blocking(&req.password, &stored).await? {
+            PasswordVerification::Valid { needs_rehash } => needs_rehash,
+            PasswordVerification::Invalid => {
+                return Err(ErrorInService::Custom("password error".to_string()))
+            }
+        };
+        // 旧的 md5 / PBKDF2 密码在登录时透明地升级为 Argon2id
+        if needs_rehash {
+            let mut active: account::ActiveModel = account.clone().into();
+            active.password = Set(Some(hash_password_blocking(&req.password).await?));
+            active.update(conn).await?;
+        }
+        Ok(account.into())
+    }
+
+    /// 修改用户角色
+    pub async fn update_role(
+        &self,
+        req: UpdateAccountRoleRequest,
+        conn: &DBConnection,
+    ) -> Result<AccountModel, ErrorInService> {
+        let account = account::Entity::find_by_id(req.account_id)
+            .one(conn)
+            .await?
+            .ok_or(ErrorInService::Custom("account not found".to_string()))?;
+        let mut active: account::ActiveModel = account.into();
+        active.role = Set(req.role);
+        let updated = active.update(conn).await?;
+        Ok(updated.into())
+    }
+
+    /// 根据配置创建管理员
+    ///
+    /// 账户已存在时, 只有密码校验通过才提升为管理员, 不修改密码
+    pub async fn bootstrap_admin(
+        &self,
+        email: &str,
+        password: &str,
+        conn: &DBConnection,
+    ) -> Result<AccountModel, ErrorInService> {
+        check_admin_password(email, password)?;
+        let account = account::Entity::find()
+            .filter(account::Column::Email.eq(email))
+            .one(conn)
+            .await?;
+        let updated = match account {
+            Some(account) if account.role == AccountRole::Admin => account,
+            Some(account) => {
+                // 避免通过配置中的邮箱把其他人的账户提升为管理员
+                let stored = account.password.clone().unwrap_or_default();
+                if let PasswordVerification::Invalid =
+                    verify_password_blocking(password, &stored).await?
+                {
+                    return Err(ErrorInService::Custom(
+                        "account already exists and admin password does not match".to_string(),
+                    ));
+                }
+                let mut active: account::ActiveModel = account.into();
+                active.role = Set(AccountRole::Admin);
+                active.update(conn).await?
+            }
+            None => {
+                account::ActiveModel {
+                    email: Set(Some(email.to_string())),
+                    nick_name: Set(Some(email.to_string())),
+                    password: Set(Some(hash_password_blocking(password).await?)),
+                    role: Set(AccountRole::Admin),
+                    ..Default::default()
+                }
+                .insert(conn)
+                .await?
+            }
+        };
+        Ok(updated.into())
+    }
+
+    /// 设置 Fever API 的密码, 保存 Fever 协议使用的 md5(email:password)
+    ///
+    /// 密码为空时关闭 Fever API
+    pub async fn update_fever_password(
+        &self,
+        account_id: i64,
+        password: &str,
+        conn: &DBConnection,
+    ) -> Result<bool, ErrorInService> {
+        let account = account::Entity::find_by_id(account_id)
+            .one(conn)
+            .await?
+            .ok_or(ErrorInService::Custom("account not found".to_string()))?;
+        let email = account.email.clone().unwrap_or_default();
+        let api_key = match password.is_empty() {
+            true => None,
+            false => Some(format!(
+                "{:x}",
+                md5::compute(format!("{}:{}", email, password).as_bytes())
+            )),
+        };
+        let enabled = api_key.is_some();
+        let mut active: account::ActiveModel = account.into();
+        active.fever_api_key = Set(api_key);
+        active.update(conn).await?;
+        Ok(enabled)
+    }
+
+    /// 根据邮箱查找用户
+    pub async fn account_by_email(
+        &self,
+        email: &str,
+        conn: &DBConnection,
+    ) -> Result<Option<AccountModel>, ErrorInService> {
+        let model = account::Entity::find()
+            .filter(account::Column::Email.eq(email))
+            .one(conn)
+            .await?;
+        Ok(model.map(|m| m.into()))
+    }
+
+    /// 根据 Fever API 的 api_key 查找用户
+    pub async fn account_by_fever_key(
+        &self,
+        api_key: &str,
+        conn: &DBConnection,
+    ) -> Result<Option<AccountModel>, ErrorInService> {
+        let api_key = api_key.trim().to_ascii_lowercase();
+        if api_key.is_empty() {
+            return Ok(None);
         }
-        // create new token, rule: email + password + salt => md5
-        let token_origin = format!("{}{}{}", req.email, req.password, "salt");
-        let token_dist = md5::compute(token_origin.as_bytes());
-        let token = format!("{:x}", token_dist);
-
-        Ok(LoginAccountResponse {
-            token,
-            account: account.into(),
-        })
+        let model = account::Entity::find()
+            .filter(account::Column::FeverApiKey.eq(api_key))
+            .one(conn)
+            .await?;
+        Ok(model.map(|m| m.into()))
     }
 
     pub async fn account_info(
@@ -97,3 +257,170 @@ impl AccountController {
         Ok(model)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_register_and_login_account() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = AccountController;
+        let account = controller
+            .register_account(
+                RegisterAccountRequest {
+                    email: "a@example.com".to_string(),
+                    nick_name: None,
+                    password: "secret".to_string(),
+                },
+                &conn,
+            )
+            .await
+            .unwrap();
+        let login = |email: &str, password: &str| LoginAccountRequest {
+            email: email.to_string(),
+            password: password.to_string(),
+        };
+        let logined = controller
+            .login_account(login("a@example.com", "secret"), &conn)
+            .await
+            .unwrap();
+        assert_eq!(logined.id, account.id);
+        assert!(controller
+            .login_account(login("a@example.com", "wrong"), &conn)
+            .await
+            .is_err());
+
+        // 旧的 md5 密码可以登录, 并在登录后升级
+        let legacy = account::ActiveModel {
+            email: Set(Some("b@example.com".to_string())),
+            password: Set(Some(format!("{:x}", md5::compute("secret".as_bytes())))),
+            ..Default::default()
+        }
+        .insert(&conn)
+        .await
+        .unwrap();
+        controller
+            .login_account(login("b@example.com", "secret"), &conn)
+            .await
+            .unwrap();
+        let upgraded = account::Entity::find_by_id(legacy.id)
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(upgraded.password.unwrap().starts_with("$argon2id$"));
+        controller
+            .login_account(login("b@example.com", "secret"), &conn)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bootstrap_admin() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = AccountController;
+        // 弱密码不会创建管理员
+        for password in ["", "admin", "short", "admin@example.com"] {
+            assert!(controller
+                .bootstrap_admin("admin@example.com", password, &conn)
+                .await
+                .is_err());
+        }
+        let admin = controller
+            .bootstrap_admin("admin@example.com", "correct-horse-battery", &conn)
+            .await
+            .unwrap();
+        assert_eq!(admin.role, AccountRole::Admin);
+        controller
+            .login_account(
+                LoginAccountRequest {
+                    email: "admin@example.com".to_string(),
+                    password: "correct-horse-battery".to_string(),
+                },
+                &conn,
+            )
+            .await
+            .unwrap();
+
+        // 已有用户只有密码匹配时才提升角色
+        let account = controller
+            .register_account(
+                RegisterAccountRequest {
+                    email: "a@example.com".to_string(),
+                    nick_name: None,
+                    password: "a-long-secret-phrase".to_string(),
+                },
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(account.role, AccountRole::Editor);
+        assert!(controller
+            .bootstrap_admin("a@example.com", "another-long-password", &conn)
+            .await
+            .is_err());
+        let unchanged = controller
+            .account_info(account.id, &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(unchanged.role, AccountRole::Editor);
+        let promoted = controller
+            .bootstrap_admin("a@example.com", "a-long-secret-phrase", &conn)
+            .await
+            .unwrap();
+        assert_eq!(promoted.id, account.id);
+        assert_eq!(promoted.role, AccountRole::Admin);
+        let demoted = controller
+            .update_role(
+                UpdateAccountRoleRequest {
+                    account_id: account.id,
+                    role: AccountRole::Reader,
+                },
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(demoted.role, AccountRole::Reader);
+    }
+
+    #[tokio::test]
+    async fn test_fever_api_key() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = AccountController;
+        let account = controller
+            .register_account(
+                RegisterAccountRequest {
+                    email: "a@example.com".to_string(),
+                    nick_name: None,
+                    password: "secret".to_string(),
+                },
+                &conn,
+            )
+            .await
+            .unwrap();
+        // Fever API 使用 md5(email:password)
+        controller
+            .update_fever_password(account.id, "fever", &conn)
+            .await
+            .unwrap();
+        let api_key = format!("{:x}", md5::compute("a@example.com:fever".as_bytes()));
+        let fever = controller
+            .account_by_fever_key(&api_key.to_uppercase(), &conn)
+            .await
+            .unwrap();
+        assert_eq!(fever.map(|a| a.id), Some(account.id));
+
+        // 密码为空时关闭
+        controller
+            .update_fever_password(account.id, "", &conn)
+            .await
+            .unwrap();
+        assert!(controller
+            .account_by_fever_key(&api_key, &conn)
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -1,3 +1,7 @@
+mod api_key_service;
 mod controller;
 pub mod schema;
+mod token_service;
+pub use api_key_service::{ApiKeyController, API_KEY_PREFIX};
 pub use controller::AccountController;
+pub use token_service::AccountTokenController;
@@ -1,9 +1,12 @@
 use crate::error::ErrorInService;
+use chrono::naive::serde::ts_milliseconds::serialize as to_milli_ts;
+use chrono::naive::serde::ts_milliseconds_option;
 use chrono::naive::serde::ts_milliseconds_option::serialize as to_milli_tsopt;
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
-use lib_entity::account::{Gender, Model as AccountModelInDB};
+use lib_entity::account::{AccountRole, Gender, Model as AccountModelInDB};
+use lib_entity::api_key;
 use sea_orm::FromQueryResult;
 
 // 用于传递给外部的整理过的订阅源数据
@@ -16,6 +19,7 @@ pub struct AccountModel {
     pub email: Option<String>,        // 邮箱
     pub birth: Option<NaiveDateTime>, // 出生日期
     pub gender: Option<Gender>,       // 性别 1 男 2 女
+    pub role: AccountRole,            // 角色
 }
 
 impl FromQueryResult for AccountModel {
@@ -30,6 +34,7 @@ impl FromQueryResult for AccountModel {
             .email(res.try_get(pre, "email").unwrap_or(None))
             .birth(res.try_get(pre, "birth").unwrap_or(None))
             .gender(res.try_get(pre, "gender").unwrap_or(None))
+            .role(res.try_get(pre, "role").unwrap_or_default())
             .build()
             .map_err(|e| {
                 sea_orm::prelude::DbErr::Custom(format!("AccountModelBuilder build error: {:?}", e))
@@ -57,6 +62,7 @@ impl From<AccountModelInDB> for AccountModel {
             email: value.email,
             birth: value.birth,
             gender: value.gender,
+            role: value.role,
         }
     }
 }
@@ -87,13 +93,143 @@ pub struct LoginAccountRequest {
 // 登录请求的响应
 #[derive(Debug, Serialize)]
 pub struct LoginAccountResponse {
+    // 访问令牌
     pub token: String,
+    // 刷新令牌, 每次刷新后都会更换
+    pub refresh_token: String,
+    // 访问令牌的有效期, 单位秒
+    pub expires_in: i64,
     pub account: AccountModel,
 }
 
+// 刷新令牌的请求
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
+// 访问令牌, 注销时需要加入注销列表直到过期
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessToken {
+    pub jti: String,
+    pub expired_at: NaiveDateTime,
+}
+
+// 刷新后的会话
+#[derive(Debug)]
+pub struct RefreshedSession {
+    pub account_id: i64,
+    // 新的刷新令牌
+    pub refresh_token: String,
+    // 被替换的访问令牌
+    pub revoked: AccessToken,
+}
+
+// 修改用户角色的请求, 只有管理员可以调用
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateAccountRoleRequest {
+    pub account_id: i64,
+    pub role: AccountRole,
+}
+
+// 设置 Fever API 密码的请求, 客户端使用邮箱和这个密码登录
+#[derive(Debug, Clone, Deserialize)]
+pub struct UpdateFeverPasswordRequest {
+    pub password: String,
+}
+
 // 更新用户信息的请求
 #[derive(Debug, Deserialize)]
 pub struct UpdateAccountRequest {
     pub id: i64,
     pub nick_name: Option<String>, // 昵称
 }
+
+// API Key 的权限范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ApiKeyScope {
+    // 读取订阅源和文章
+    #[serde(rename = "feeds:read")]
+    ReadFeeds,
+    // 修改阅读状态以及保存文章
+    #[serde(rename = "feeds:write")]
+    WriteFeeds,
+    // 管理订阅源和分类
+    #[serde(rename = "subscriptions:write")]
+    ManageSubscriptions,
+    // 请求生成文章摘要
+    #[serde(rename = "summaries:write")]
+    RequestSummaries,
+}
+
+// 创建 API Key 的请求
+#[derive(Debug, Clone, Deserialize, Default, Builder)]
+#[builder(setter(into, strip_option), default)]
+#[builder(derive(Debug))]
+#[builder(build_fn(error = "ErrorInService"))]
+pub struct CreateApiKeyRequest {
+    #[serde(skip)]
+    pub account_id: Option<i64>,
+    // 名称
+    pub name: String,
+    // 权限范围
+    pub scopes: Vec<ApiKeyScope>,
+    // 过期时间, 毫秒 13 位, 为空表示不过期
+    #[builder(default = "Option::None")]
+    #[serde(default)]
+    #[serde(with = "ts_milliseconds_option")]
+    pub expired_at: Option<NaiveDateTime>,
+}
+
+// API Key 信息, 不包含 Key 本身
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyModel {
+    pub id: i64,
+    pub account_id: i64,
+    pub name: String,
+    // Key 的前几位
+    pub prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    #[serde(serialize_with = "to_milli_tsopt")]
+    pub last_used_at: Option<NaiveDateTime>,
+    #[serde(serialize_with = "to_milli_tsopt")]
+    pub expired_at: Option<NaiveDateTime>,
+    #[serde(serialize_with = "to_milli_ts")]
+    pub created_at: NaiveDateTime,
+}
+
+impl From<api_key::Model> for ApiKeyModel {
+    fn from(value: api_key::Model) -> Self {
+        Self {
+            id: value.id,
+            account_id: value.account_id,
+            name: value.name,
+            prefix: value.prefix,
+            scopes: value
+                .scopes
+                .and_then(|v| serde_json::from_value(v).ok())
+                .unwrap_or_default(),
+            last_used_at: value.last_used_at,
+            expired_at: value.expired_at,
+            created_at: value.created_at,
+        }
+    }
+}
+
+// 创建 API Key 的响应, Key 只在创建时返回一次
+#[derive(Debug, Serialize)]
+pub struct CreateApiKeyResponse {
+    pub key: String,
+    pub api_key: ApiKeyModel,
+}
+
+// 吊销 API Key 的请求
+#[derive(Debug, Clone, Deserialize, Default, Builder)]
+#[builder(setter(into, strip_option), default)]
+#[builder(derive(Debug))]
+#[builder(build_fn(error = "ErrorInService"))]
+pub struct RevokeApiKeyRequest {
+    #[serde(skip)]
+    pub account_id: Option<i64>,
+    pub id: i64,
+}
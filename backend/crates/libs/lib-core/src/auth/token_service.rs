@@ -0,0 +1,329 @@
+use chrono::NaiveDateTime;
+use lib_entity::{account_token, revoked_token};
+use lib_utils::password::{hash_token, random_hex};
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{entity::*, query::*};
+
+use super::schema::{AccessToken, RefreshedSession};
+use crate::{error::ErrorInService, DBConnection};
+
+pub struct AccountTokenController;
+
+impl AccountTokenController {
+    /// 登录后创建会话, 返回刷新令牌的明文
+    pub async fn create_session(
+        &self,
+        account_id: i64,
+        access: &AccessToken,
+        expired_at: NaiveDateTime,
+        conn: &DBConnection,
+    ) -> Result<String, ErrorInService> {
+        let refresh_token = random_hex(32);
+        let now = chrono::Utc::now().naive_utc();
+        account_token::ActiveModel {
+            account_id: Set(account_id),
+            token: Set(hash_token(&refresh_token)),
+            access_jti: Set(access.jti.clone()),
+            access_expired_at: Set(access.expired_at),
+            expired_at: Set(expired_at),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await?;
+        Ok(refresh_token)
+    }
+
+    /// 使用刷新令牌换取新的访问令牌, 刷新令牌同时轮换, 旧的刷新令牌和访问令牌都会失效
+    ///
+    /// 已经轮换过的刷新令牌再次出现时, 说明令牌可能泄露, 整个会话都会被注销
+    pub async fn refresh_session(
+        &self,
+        refresh_token: &str,
+        access: &AccessToken,
+        expired_at: NaiveDateTime,
+        conn: &DBConnection,
+    ) -> Result<RefreshedSession, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let token_hash = hash_token(refresh_token);
+        let session = account_token::Entity::find()
+            .filter(account_token::Column::Token.eq(token_hash.clone()))
+            .filter(account_token::Column::RevokedAt.is_null())
+            .filter(account_token::Column::ExpiredAt.gt(now))
+            .one(conn)
+            .await?;
+        let Some(session) = session else {
+            self.revoke_reused(&token_hash, conn).await?;
+            return Err(ErrorInService::Custom("invalid refresh token".to_string()));
+        };
+
+        // 只有令牌没有被并发轮换时才更新
+        let new_refresh_token = random_hex(32);
+        let result = account_token::Entity::update_many()
+            .col_expr(
+                account_token::Column::Token,
+                hash_token(&new_refresh_token).into(),
+            )
+            .col_expr(
+                account_token::Column::PreviousToken,
+                token_hash.clone().into(),
+            )
+            .col_expr(account_token::Column::AccessJti, access.jti.clone().into())
+            .col_expr(
+                account_token::Column::AccessExpiredAt,
+                access.expired_at.into(),
+            )
+            .col_expr(account_token::Column::ExpiredAt, expired_at.into())
+            .col_expr(account_token::Column::UpdatedAt, now.into())
+            .filter(account_token::Column::Id.eq(session.id))
+            .filter(account_token::Column::Token.eq(token_hash))
+            .filter(account_token::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await?;
+        if result.rows_affected != 1 {
+            return Err(ErrorInService::Custom("invalid refresh token".to_string()));
+        }
+
+        let revoked = AccessToken {
+            jti: session.access_jti,
+            expired_at: session.access_expired_at,
+        };
+        self.insert_revoked(session.account_id, std::slice::from_ref(&revoked), conn)
+            .await?;
+        Ok(RefreshedSession {
+            account_id: session.account_id,
+            refresh_token: new_refresh_token,
+            revoked,
+        })
+    }
+
+    // 已经轮换过的刷新令牌再次使用时注销整个会话, 包括会话当前的访问令牌
+    async fn revoke_reused(
+        &self,
+        token_hash: &str,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let Some(session) = account_token::Entity::find()
+            .filter(account_token::Column::PreviousToken.eq(token_hash))
+            .filter(account_token::Column::RevokedAt.is_null())
+            .one(conn)
+            .await?
+        else {
+            return Ok(());
+        };
+        tracing::warn!(
+            "刷新令牌被重复使用, 注销会话: account_id={}",
+            session.account_id
+        );
+        let revoked = AccessToken {
+            jti: session.access_jti.clone(),
+            expired_at: session.access_expired_at,
+        };
+        let account_id = session.account_id;
+        let mut active: account_token::ActiveModel = session.into();
+        active.revoked_at = Set(Some(chrono::Utc::now().naive_utc()));
+        active.update(conn).await?;
+        self.insert_revoked(account_id, &[revoked], conn).await
+    }
+
+    /// 注销当前访问令牌所在的会话
+    ///
+    /// 返回需要加入注销列表的访问令牌
+    pub async fn revoke_session(
+        &self,
+        account_id: i64,
+        current: AccessToken,
+        conn: &DBConnection,
+    ) -> Result<Vec<AccessToken>, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        account_token::Entity::update_many()
+            .col_expr(account_token::Column::RevokedAt, now.into())
+            .filter(account_token::Column::AccountId.eq(account_id))
+            .filter(account_token::Column::AccessJti.eq(current.jti.clone()))
+            .filter(account_token::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await?;
+        let revoked = vec![current];
+        self.insert_revoked(account_id, &revoked, conn).await?;
+        Ok(revoked)
+    }
+
+    /// 注销用户的所有会话, 即退出所有设备
+    ///
+    /// 返回需要加入注销列表的访问令牌
+    pub async fn revoke_all_sessions(
+        &self,
+        account_id: i64,
+        current: AccessToken,
+        conn: &DBConnection,
+    ) -> Result<Vec<AccessToken>, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let sessions = account_token::Entity::find()
+            .filter(account_token::Column::AccountId.eq(account_id))
+            .filter(account_token::Column::RevokedAt.is_null())
+            .all(conn)
+            .await?;
+        let mut revoked = vec![current];
+        for session in sessions {
+            if session.access_jti != revoked[0].jti {
+                revoked.push(AccessToken {
+                    jti: session.access_jti,
+                    expired_at: session.access_expired_at,
+                });
+            }
+        }
+        account_token::Entity::update_many()
+            .col_expr(account_token::Column::RevokedAt, now.into())
+            .filter(account_token::Column::AccountId.eq(account_id))
+            .filter(account_token::Column::RevokedAt.is_null())
+            .exec(conn)
+            .await?;
+        self.insert_revoked(account_id, &revoked, conn).await?;
+        Ok(revoked)
+    }
+
+    /// 还没过期的已注销访问令牌, 启动时加载到内存中; 同时清理已过期的记录
+    pub async fn active_revocations(
+        &self,
+        conn: &DBConnection,
+    ) -> Result<Vec<AccessToken>, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        revoked_token::Entity::delete_many()
+            .filter(revoked_token::Column::ExpiredAt.lte(now))
+            .exec(conn)
+            .await?;
+        account_token::Entity::delete_many()
+            .filter(account_token::Column::ExpiredAt.lte(now))
+            .exec(conn)
+            .await?;
+        let tokens = revoked_token::Entity::find()
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|m| AccessToken {
+                jti: m.jti,
+                expired_at: m.expired_at,
+            })
+            .collect();
+        Ok(tokens)
+    }
+
+    // 记录注销的访问令牌, 已经过期的不需要记录
+    async fn insert_revoked(
+        &self,
+        account_id: i64,
+        tokens: &[AccessToken],
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let models: Vec<revoked_token::ActiveModel> = tokens
+            .iter()
+            .filter(|t| t.expired_at > now)
+            .map(|t| revoked_token::ActiveModel {
+                jti: Set(t.jti.clone()),
+                account_id: Set(account_id),
+                expired_at: Set(t.expired_at),
+                created_at: Set(now),
+                ..Default::default()
+            })
+            .collect();
+        if models.is_empty() {
+            return Ok(());
+        }
+        revoked_token::Entity::insert_many(models)
+            .on_conflict(
+                OnConflict::column(revoked_token::Column::Jti)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(conn)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn access(jti: &str) -> AccessToken {
+        AccessToken {
+            jti: jti.to_string(),
+            expired_at: chrono::Utc::now().naive_utc() + chrono::Duration::minutes(15),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_refresh_and_revoke_sessions() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = AccountTokenController;
+        let expired_at = chrono::Utc::now().naive_utc() + chrono::Duration::days(30);
+
+        let refresh_token = controller
+            .create_session(1, &access("a1"), expired_at, &conn)
+            .await
+            .unwrap();
+        let refreshed = controller
+            .refresh_session(&refresh_token, &access("a2"), expired_at, &conn)
+            .await
+            .unwrap();
+        assert_eq!(refreshed.account_id, 1);
+        assert_eq!(refreshed.revoked.jti, "a1");
+        // 刷新令牌只能使用一次
+        assert!(controller
+            .refresh_session(&refresh_token, &access("a3"), expired_at, &conn)
+            .await
+            .is_err());
+        // 旧令牌再次出现视为泄露, 轮换后的令牌也一起失效
+        assert!(controller
+            .refresh_session(&refreshed.refresh_token, &access("a3"), expired_at, &conn)
+            .await
+            .is_err());
+        assert!(controller
+            .active_revocations(&conn)
+            .await
+            .unwrap()
+            .iter()
+            .any(|t| t.jti == "a2"));
+
+        // 注销当前会话后刷新令牌失效
+        let revoked = controller
+            .revoke_session(1, access("a2"), &conn)
+            .await
+            .unwrap();
+        assert_eq!(revoked.len(), 1);
+        assert!(controller
+            .refresh_session(&refreshed.refresh_token, &access("a3"), expired_at, &conn)
+            .await
+            .is_err());
+
+        // 退出所有设备
+        controller
+            .create_session(1, &access("b1"), expired_at, &conn)
+            .await
+            .unwrap();
+        let other = controller
+            .create_session(2, &access("c1"), expired_at, &conn)
+            .await
+            .unwrap();
+        let revoked = controller
+            .revoke_all_sessions(1, access("b1"), &conn)
+            .await
+            .unwrap();
+        assert_eq!(revoked.len(), 1);
+        let jtis: Vec<String> = controller
+            .active_revocations(&conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|t| t.jti)
+            .collect();
+        assert_eq!(jtis, vec!["a1", "a2", "b1"]);
+        // 其他用户不受影响
+        assert!(controller
+            .refresh_session(&other, &access("c2"), expired_at, &conn)
+            .await
+            .is_ok());
+    }
+}
@@ -1,4 +1,5 @@
 use derive_builder::UninitializedFieldError;
+use lib_crawler::FetchError;
 use sea_orm::DbErr;
 use sqlx::error;
 use thiserror::Error;
@@ -19,6 +20,20 @@ pub enum ErrorInService {
     Custom(String),
     #[error("`{0}`")]
     DBError(DbErr),
+    #[error("fetch `{0}`")]
+    Fetch(#[from] FetchError),
+}
+
+impl ErrorInService {
+    // 错误类型的简短标识, 会记录到订阅源的更新记录中
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::ErrorInRss(_) => "rss",
+            Self::Custom(_) => "custom",
+            Self::DBError(_) => "db",
+            Self::Fetch(e) => e.kind(),
+        }
+    }
 }
 
 impl From<DbErr> for ErrorInService {
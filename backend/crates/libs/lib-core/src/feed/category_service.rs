@@ -19,13 +19,21 @@ impl CategoryController {
         req: CreateOrUpdateCategoryRequest,
         conn: &DBConnection,
     ) -> Result<schema::CategoryModel, ErrorInService> {
-        let query = match req.id.clone() {
+        let mut query = match req.id {
             Some(idf) => feed_category::Entity::find().filter(feed_category::Column::Id.eq(idf)),
             None => feed_category::Entity::find()
                 .filter(feed_category::Column::Title.eq(req.title.clone())),
         };
+        // 用户只能修改自己的分类
+        query = match req.account_id {
+            Some(account_id) => query.filter(feed_category::Column::AccountId.eq(account_id)),
+            None => query.filter(feed_category::Column::AccountId.is_null()),
+        };
 
         let category = query.one(conn).await.map_err(ErrorInService::DBError)?;
+        if category.is_none() && req.id.is_some() && req.account_id.is_some() {
+            return Err(ErrorInService::Custom("category not found".to_string()));
+        }
         let prefer_update = category.is_some();
         let mut new_model = match category {
             Some(m) => m.into_active_model(),
@@ -37,6 +45,7 @@ impl CategoryController {
         new_model.title = Set(req.title.clone());
         new_model.description = Set(req.description.clone());
         new_model.sort_order = Set(req.sort_order);
+        new_model.account_id = Set(req.account_id);
         let updated = match prefer_update {
             true => new_model.update(conn).await?,
             false => new_model.insert(conn).await?,
@@ -53,6 +62,9 @@ impl CategoryController {
         C: ConnectionTrait,
     {
         let mut select = feed_category::Entity::find();
+        if let Some(account_id) = req.account_id {
+            select = select.filter(feed_category::Column::AccountId.eq(account_id))
+        }
         if let Some(ids) = &req.ids {
             if !ids.is_empty() {
                 select = select.filter(feed_category::Column::Id.is_in(ids.clone()))
@@ -106,6 +118,7 @@ mod tests {
             title: "test".to_owned(),
             description: None,
             sort_order: Some(0),
+            account_id: None,
         };
         let res = contoller.insert_category(req, &db).await.unwrap();
         assert_eq!(res.title, "test");
@@ -116,6 +129,7 @@ mod tests {
             title: "test_updated".to_owned(),
             description: None,
             sort_order: Some(0),
+            account_id: None,
         };
         let updated = contoller.insert_category(req, &db).await.unwrap();
         assert_eq!(updated.title, "test_updated");
@@ -133,6 +147,7 @@ mod tests {
             title: "test".to_owned(),
             description: None,
             sort_order: Some(0),
+            account_id: None,
         };
         let res = contoller.insert_category(req, &conn).await.unwrap();
         assert_eq!(res.title, "test");
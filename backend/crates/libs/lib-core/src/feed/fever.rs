@@ -0,0 +1,410 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::link_state::{read_link_ids_query, starred_link_ids_query};
+use super::schema::{
+    FeverFeed, FeverFeedsGroup, FeverGroup, FeverItem, FeverItemsRequest, FeverMarkAs,
+    FeverMarkRequest, FeverMarkTarget, MarkLinksReadRequest, UpdateLinkStateRequest,
+};
+use super::subscription_service::subscribed_ids_query;
+use super::LinkStateController;
+use crate::error::ErrorInService;
+use crate::DBConnection;
+use lib_entity::{account_subscription, feed_category, feed_link, feed_subscription, link_state};
+use sea_orm::{entity::*, query::*};
+
+// Fever API 每次最多返回的文章数
+const FEVER_ITEMS_LIMIT: u64 = 50;
+
+/// Fever API 兼容层, 把用户的分类、订阅和文章转换为 Fever 的格式
+pub struct FeverController;
+
+impl FeverController {
+    /// 用户的分类
+    pub async fn groups(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<FeverGroup>, ErrorInService> {
+        let categories = feed_category::Entity::find()
+            .filter(feed_category::Column::AccountId.eq(account_id))
+            .order_by_asc(feed_category::Column::SortOrder)
+            .order_by_asc(feed_category::Column::Id)
+            .all(conn)
+            .await?;
+        Ok(categories
+            .into_iter()
+            .map(|c| FeverGroup {
+                id: c.id,
+                title: c.title,
+            })
+            .collect())
+    }
+
+    /// 用户订阅的订阅源
+    pub async fn feeds(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<FeverFeed>, ErrorInService> {
+        let subscriptions = account_subscription::Entity::find()
+            .filter(account_subscription::Column::AccountId.eq(account_id))
+            .order_by_asc(account_subscription::Column::SortOrder)
+            .order_by_asc(account_subscription::Column::Id)
+            .find_also_related(feed_subscription::Entity)
+            .all(conn)
+            .await?;
+        Ok(subscriptions
+            .into_iter()
+            .filter_map(|(subscribed, feed)| {
+                let feed = feed?;
+                Some(FeverFeed {
+                    id: feed.id,
+                    favicon_id: 0,
+                    title: subscribed.custom_title.unwrap_or(feed.title),
+                    url: feed.link.unwrap_or_default(),
+                    site_url: feed.site_link.unwrap_or_default(),
+                    is_spark: 0,
+                    last_updated_on_time: feed.updated_at.and_utc().timestamp(),
+                })
+            })
+            .collect())
+    }
+
+    /// 分类和订阅源的对应关系
+    pub async fn feeds_groups(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<FeverFeedsGroup>, ErrorInService> {
+        let rows: Vec<(i64, i64)> = account_subscription::Entity::find()
+            .select_only()
+            .column(account_subscription::Column::CategoryId)
+            .column(account_subscription::Column::SubscriptionId)
+            .filter(account_subscription::Column::AccountId.eq(account_id))
+            .order_by_asc(account_subscription::Column::SubscriptionId)
+            .into_tuple()
+            .all(conn)
+            .await?;
+        let mut groups: BTreeMap<i64, Vec<String>> = BTreeMap::new();
+        for (category_id, subscription_id) in rows {
+            groups
+                .entry(category_id)
+                .or_default()
+                .push(subscription_id.to_string());
+        }
+        Ok(groups
+            .into_iter()
+            .map(|(group_id, feed_ids)| FeverFeedsGroup {
+                group_id,
+                feed_ids: feed_ids.join(","),
+            })
+            .collect())
+    }
+
+    /// 用户订阅的文章, 每次最多 50 篇
+    pub async fn items(
+        &self,
+        account_id: i64,
+        req: FeverItemsRequest,
+        conn: &DBConnection,
+    ) -> Result<Vec<FeverItem>, ErrorInService> {
+        let mut select = feed_link::Entity::find().filter(
+            feed_link::Column::SubscriptionId.in_subquery(subscribed_ids_query(account_id, None)),
+        );
+        if !req.with_ids.is_empty() {
+            select = select
+                .filter(feed_link::Column::Id.is_in(req.with_ids))
+                .order_by_asc(feed_link::Column::Id);
+        } else if let Some(max_id) = req.max_id {
+            select = select
+                .filter(feed_link::Column::Id.lt(max_id))
+                .order_by_desc(feed_link::Column::Id);
+        } else {
+            select = select
+                .filter(feed_link::Column::Id.gt(req.since_id.unwrap_or(0)))
+                .order_by_asc(feed_link::Column::Id);
+        }
+        let links = select.limit(FEVER_ITEMS_LIMIT).all(conn).await?;
+
+        let states: HashMap<i64, link_state::Model> = link_state::Entity::find()
+            .filter(link_state::Column::AccountId.eq(account_id))
+            .filter(link_state::Column::LinkId.is_in(links.iter().map(|l| l.id)))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|s| (s.link_id, s))
+            .collect();
+        Ok(links
+            .into_iter()
+            .map(|link| {
+                let state = states.get(&link.id);
+                let author = link
+                    .authors
+                    .as_ref()
+                    .and_then(|v| v.as_array())
+                    .and_then(|authors| authors.first())
+                    .and_then(|a| a.get("name"))
+                    .and_then(|n| n.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                FeverItem {
+                    id: link.id,
+                    feed_id: link.subscription_id,
+                    title: link.title,
+                    author,
+                    // 和 GReader 一致, 优先使用 html 内容, 没有时使用纯文本描述
+                    html: link
+                        .description
+                        .filter(|d| !d.is_empty())
+                        .or(link.desc_pure_txt)
+                        .unwrap_or_default(),
+                    url: link.link,
+                    is_saved: state.is_some_and(|s| s.starred_at.is_some()) as i32,
+                    is_read: state.is_some_and(|s| s.read_at.is_some()) as i32,
+                    created_on_time: link
+                        .published_at
+                        .unwrap_or(link.created_at)
+                        .and_utc()
+                        .timestamp(),
+                }
+            })
+            .collect())
+    }
+
+    /// 用户订阅的文章总数
+    pub async fn total_items(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<u64, ErrorInService> {
+        let count = feed_link::Entity::find()
+            .filter(
+                feed_link::Column::SubscriptionId
+                    .in_subquery(subscribed_ids_query(account_id, None)),
+            )
+            .count(conn)
+            .await?;
+        Ok(count)
+    }
+
+    /// 未读文章的 id
+    pub async fn unread_item_ids(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<i64>, ErrorInService> {
+        let ids = feed_link::Entity::find()
+            .select_only()
+            .column(feed_link::Column::Id)
+            .filter(
+                feed_link::Column::SubscriptionId
+                    .in_subquery(subscribed_ids_query(account_id, None)),
+            )
+            .filter(feed_link::Column::Id.not_in_subquery(read_link_ids_query(account_id)))
+            .order_by_asc(feed_link::Column::Id)
+            .into_tuple()
+            .all(conn)
+            .await?;
+        Ok(ids)
+    }
+
+    /// 收藏文章的 id
+    pub async fn saved_item_ids(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<i64>, ErrorInService> {
+        let ids = feed_link::Entity::find()
+            .select_only()
+            .column(feed_link::Column::Id)
+            .filter(feed_link::Column::Id.in_subquery(starred_link_ids_query(account_id)))
+            .order_by_asc(feed_link::Column::Id)
+            .into_tuple()
+            .all(conn)
+            .await?;
+        Ok(ids)
+    }
+
+    /// 标记文章、订阅源或分组
+    ///
+    /// 订阅源和分组只支持标记已读, 分组 id 为 0 表示所有文章
+    pub async fn mark(
+        &self,
+        account_id: i64,
+        req: FeverMarkRequest,
+        conn: &DBConnection,
+    ) -> Result<u64, ErrorInService> {
+        let controller = LinkStateController;
+        if req.target == FeverMarkTarget::Item {
+            let mut state = UpdateLinkStateRequest {
+                account_id: Some(account_id),
+                link_ids: vec![req.id],
+                ..Default::default()
+            };
+            match req.mark_as {
+                FeverMarkAs::Read => state.read = Some(true),
+                FeverMarkAs::Unread => state.read = Some(false),
+                FeverMarkAs::Saved => state.starred = Some(true),
+                FeverMarkAs::Unsaved => state.starred = Some(false),
+            }
+            return controller.update_link_state(state, conn).await;
+        }
+        // 负数的分组是 Fever 的 sparks, 不支持
+        if req.mark_as != FeverMarkAs::Read || req.id < 0 {
+            return Ok(0);
+        }
+        let mut mark = MarkLinksReadRequest {
+            account_id: Some(account_id),
+            published_before: req.before,
+            ..Default::default()
+        };
+        match req.target {
+            FeverMarkTarget::Feed => mark.subscription_id = Some(req.id),
+            FeverMarkTarget::Group if req.id > 0 => mark.category_id = Some(req.id),
+            _ => {}
+        }
+        controller.mark_read_until(mark, conn).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::schema::{
+        CreateOrUpdateCategoryRequestBuilder, CreateOrUpdateRssLinkRequestBuilder,
+        CreateOrUpdateSubscriptionRequestBuilder,
+    };
+    use crate::feed::{CategoryController, LinkController, SubscriptionController};
+
+    #[tokio::test]
+    async fn test_fever_items_and_mark() {
+        let conn = crate::test_runner::setup_database().await;
+        let account_id = 1;
+        let category = CategoryController
+            .insert_category(
+                CreateOrUpdateCategoryRequestBuilder::default()
+                    .title("科技")
+                    .account_id(account_id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let (_, subscription_id) = SubscriptionController
+            .subscribe(
+                account_id,
+                CreateOrUpdateSubscriptionRequestBuilder::default()
+                    .title("feed")
+                    .link("https://example.com/feed.xml")
+                    .category_id(category.id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let mut link_ids = Vec::new();
+        for i in 0..3 {
+            let mut req = CreateOrUpdateRssLinkRequestBuilder::default();
+            req.title(format!("link {}", i))
+                .link(format!("https://example.com/{}", i))
+                .subscrption_id(subscription_id)
+                .desc_pure_txt(format!("summary {}", i))
+                .published_at(chrono::Utc::now().naive_utc() - chrono::Duration::hours(3 - i));
+            if i == 2 {
+                req.description("<p>full 2</p>");
+            }
+            let req = req.build().unwrap();
+            let (_, link) = LinkController.insert_link(req, &conn).await.unwrap();
+            link_ids.push(link.id);
+        }
+
+        let controller = FeverController;
+        let groups = controller.groups(account_id, &conn).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        let feeds_groups = controller.feeds_groups(account_id, &conn).await.unwrap();
+        assert_eq!(feeds_groups[0].group_id, category.id);
+        assert_eq!(feeds_groups[0].feed_ids, subscription_id.to_string());
+        let feeds = controller.feeds(account_id, &conn).await.unwrap();
+        assert_eq!(feeds[0].url, "https://example.com/feed.xml");
+
+        let items = controller
+            .items(
+                account_id,
+                FeverItemsRequest {
+                    since_id: Some(link_ids[0]),
+                    ..Default::default()
+                },
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            items.iter().map(|i| i.id).collect::<Vec<_>>(),
+            link_ids[1..].to_vec()
+        );
+        // 有 html 内容时优先使用, 没有时使用纯文本描述
+        assert_eq!(items[0].html, "summary 1");
+        assert_eq!(items[1].html, "<p>full 2</p>");
+        let items = controller
+            .items(
+                account_id,
+                FeverItemsRequest {
+                    max_id: Some(link_ids[2]),
+                    ..Default::default()
+                },
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(items[0].id, link_ids[1]);
+
+        let mark = |target, mark_as, id| FeverMarkRequest {
+            target,
+            mark_as,
+            id,
+            before: None,
+        };
+        controller
+            .mark(
+                account_id,
+                mark(FeverMarkTarget::Item, FeverMarkAs::Saved, link_ids[0]),
+                &conn,
+            )
+            .await
+            .unwrap();
+        controller
+            .mark(
+                account_id,
+                mark(FeverMarkTarget::Item, FeverMarkAs::Read, link_ids[0]),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            controller.saved_item_ids(account_id, &conn).await.unwrap(),
+            vec![link_ids[0]]
+        );
+        assert_eq!(
+            controller.unread_item_ids(account_id, &conn).await.unwrap(),
+            link_ids[1..].to_vec()
+        );
+
+        // 分组 0 表示所有文章
+        controller
+            .mark(
+                account_id,
+                mark(FeverMarkTarget::Group, FeverMarkAs::Read, 0),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(controller
+            .unread_item_ids(account_id, &conn)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(controller.total_items(account_id, &conn).await.unwrap(), 3);
+    }
+}
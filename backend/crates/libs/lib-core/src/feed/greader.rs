@@ -0,0 +1,542 @@
+use std::collections::HashMap;
+
+use super::schema::{
+    GReaderCategory, GReaderContent, GReaderEditTagRequest, GReaderItem, GReaderItemIds,
+    GReaderItemRef, GReaderLink, GReaderOrigin, GReaderStream, GReaderStreamContents,
+    GReaderStreamRequest, GReaderSubscription, GReaderTag, LinkModel, QueryCategoryRequest,
+    QueryRssLinkRequest, QuerySubscriptionRequest, SubscriptionModel, UpdateLinkStateRequest,
+};
+use super::{CategoryController, LinkController, LinkStateController, SubscriptionController};
+use crate::common_schema::PageRequest;
+use crate::error::ErrorInService;
+use crate::DBConnection;
+
+pub const GREADER_READING_LIST: &str = "user/-/state/com.google/reading-list";
+pub const GREADER_STARRED: &str = "user/-/state/com.google/starred";
+pub const GREADER_READ: &str = "user/-/state/com.google/read";
+pub const GREADER_KEPT_UNREAD: &str = "user/-/state/com.google/kept-unread";
+const GREADER_LABEL_PREFIX: &str = "user/-/label/";
+const GREADER_FEED_PREFIX: &str = "feed/";
+const GREADER_ITEM_PREFIX: &str = "tag:google.com,2005:reader/item/";
+// 每页的默认数量和最大数量
+const GREADER_DEFAULT_COUNT: u64 = 20;
+const GREADER_MAX_COUNT: u64 = 1000;
+
+impl GReaderStream {
+    /// 解析流的 id, 不支持的流返回 None
+    pub fn parse(stream_id: &str) -> Option<Self> {
+        // 部分客户端使用 user/{user_id}/... 而不是 user/-/...
+        let stream_id = match stream_id.strip_prefix("user/") {
+            Some(rest) => format!("user/-/{}", rest.split_once('/')?.1),
+            None => stream_id.to_string(),
+        };
+        match stream_id.as_str() {
+            GREADER_READING_LIST => Some(Self::ReadingList),
+            GREADER_STARRED => Some(Self::Starred),
+            s => {
+                if let Some(id) = s.strip_prefix(GREADER_FEED_PREFIX) {
+                    return id.parse().ok().map(Self::Feed);
+                }
+                s.strip_prefix(GREADER_LABEL_PREFIX)
+                    .map(|label| Self::Label(label.to_string()))
+            }
+        }
+    }
+
+    pub fn id(&self) -> String {
+        match self {
+            Self::ReadingList => GREADER_READING_LIST.to_string(),
+            Self::Starred => GREADER_STARRED.to_string(),
+            Self::Feed(id) => feed_stream_id(*id),
+            Self::Label(label) => label_stream_id(label),
+        }
+    }
+}
+
+fn feed_stream_id(subscription_id: i64) -> String {
+    format!("{}{}", GREADER_FEED_PREFIX, subscription_id)
+}
+
+fn label_stream_id(label: &str) -> String {
+    format!("{}{}", GREADER_LABEL_PREFIX, label)
+}
+
+/// 文章 id 的长格式, 即 16 位十六进制
+pub fn greader_item_id(link_id: i64) -> String {
+    format!("{}{:016x}", GREADER_ITEM_PREFIX, link_id)
+}
+
+/// 解析文章 id, 支持长格式、十六进制短格式以及十进制
+pub fn parse_greader_item_id(id: &str) -> Option<i64> {
+    if let Some(hex) = id.strip_prefix(GREADER_ITEM_PREFIX) {
+        return u64::from_str_radix(hex, 16).ok().map(|id| id as i64);
+    }
+    if id.len() == 16 {
+        return u64::from_str_radix(id, 16).ok().map(|id| id as i64);
+    }
+    id.parse().ok()
+}
+
+/// Google Reader API 兼容层, 把用户的分类、订阅和文章转换为 Google Reader 的格式
+pub struct GReaderController;
+
+impl GReaderController {
+    /// 用户订阅的订阅源
+    pub async fn subscriptions(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<GReaderSubscription>, ErrorInService> {
+        let labels: HashMap<i64, String> = self
+            .categories(account_id, conn)
+            .await?
+            .into_iter()
+            .collect();
+        let subscriptions = self.subscribed(account_id, None, conn).await?;
+        Ok(subscriptions
+            .into_iter()
+            .map(|s| {
+                let categories = labels
+                    .get(&s.category_id)
+                    .map(|label| GReaderCategory {
+                        id: label_stream_id(label),
+                        label: label.clone(),
+                    })
+                    .into_iter()
+                    .collect();
+                GReaderSubscription {
+                    id: feed_stream_id(s.id),
+                    title: s.custom_title.unwrap_or(s.title),
+                    categories,
+                    url: s.link,
+                    html_url: s.site_link.unwrap_or_default(),
+                    icon_url: s.icon.or(s.logo).unwrap_or_default(),
+                }
+            })
+            .collect())
+    }
+
+    /// 收藏状态和用户的分类
+    pub async fn tags(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<GReaderTag>, ErrorInService> {
+        let mut tags = vec![GReaderTag {
+            id: GREADER_STARRED.to_string(),
+            tag_type: None,
+        }];
+        for (_, label) in self.categories(account_id, conn).await? {
+            tags.push(GReaderTag {
+                id: label_stream_id(&label),
+                tag_type: Some("folder".to_string()),
+            });
+        }
+        Ok(tags)
+    }
+
+    /// 流中的文章, continuation 为下一页的页码
+    pub async fn stream_contents(
+        &self,
+        account_id: i64,
+        req: GReaderStreamRequest,
+        conn: &DBConnection,
+    ) -> Result<GReaderStreamContents, ErrorInService> {
+        let id = req.stream.id();
+        let (links, continuation) = self.query_stream(account_id, req, conn).await?;
+        Ok(GReaderStreamContents {
+            id,
+            updated: chrono::Utc::now().timestamp(),
+            items: self.to_items(account_id, links, conn).await?,
+            continuation,
+        })
+    }
+
+    /// 流中文章的 id
+    pub async fn stream_item_ids(
+        &self,
+        account_id: i64,
+        req: GReaderStreamRequest,
+        conn: &DBConnection,
+    ) -> Result<GReaderItemIds, ErrorInService> {
+        let (links, continuation) = self.query_stream(account_id, req, conn).await?;
+        Ok(GReaderItemIds {
+            item_refs: links
+                .into_iter()
+                .map(|l| GReaderItemRef {
+                    id: l.id.to_string(),
+                })
+                .collect(),
+            continuation,
+        })
+    }
+
+    /// 指定 id 的文章
+    pub async fn items_contents(
+        &self,
+        account_id: i64,
+        link_ids: Vec<i64>,
+        conn: &DBConnection,
+    ) -> Result<Vec<GReaderItem>, ErrorInService> {
+        if link_ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let query = QueryRssLinkRequest {
+            page: Some(PageRequest::single_page(link_ids.len())),
+            ids: Some(link_ids),
+            account_id: Some(account_id),
+            ..Default::default()
+        };
+        let links = LinkController.query_links(query, conn).await?.data;
+        self.to_items(account_id, links, conn).await
+    }
+
+    /// 添加或移除文章的已读、收藏标签, 其他标签会被忽略
+    pub async fn edit_tag(
+        &self,
+        account_id: i64,
+        req: GReaderEditTagRequest,
+        conn: &DBConnection,
+    ) -> Result<u64, ErrorInService> {
+        let mut state = UpdateLinkStateRequest {
+            account_id: Some(account_id),
+            link_ids: req.link_ids,
+            ..Default::default()
+        };
+        for tag in &req.add {
+            match tag.as_str() {
+                GREADER_READ => state.read = Some(true),
+                GREADER_KEPT_UNREAD => state.read = Some(false),
+                GREADER_STARRED => state.starred = Some(true),
+                _ => {}
+            }
+        }
+        for tag in &req.remove {
+            match tag.as_str() {
+                GREADER_READ => state.read = Some(false),
+                GREADER_STARRED => state.starred = Some(false),
+                _ => {}
+            }
+        }
+        if state.read.is_none() && state.starred.is_none() {
+            return Ok(0);
+        }
+        LinkStateController.update_link_state(state, conn).await
+    }
+
+    // 用户的分类, (id, 标题)
+    async fn categories(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<(i64, String)>, ErrorInService> {
+        let query = QueryCategoryRequest {
+            account_id: Some(account_id),
+            ..Default::default()
+        };
+        let categories = CategoryController.query_category(query, conn).await?;
+        Ok(categories.into_iter().map(|c| (c.id, c.title)).collect())
+    }
+
+    async fn subscribed(
+        &self,
+        account_id: i64,
+        category_id: Option<i64>,
+        conn: &DBConnection,
+    ) -> Result<Vec<SubscriptionModel>, ErrorInService> {
+        let query = QuerySubscriptionRequest {
+            account_id: Some(account_id),
+            category_id,
+            page: Some(PageRequest::max_page()),
+            ..Default::default()
+        };
+        Ok(SubscriptionController
+            .query_subscription(query, conn)
+            .await?
+            .data)
+    }
+
+    // 按分页查询流中的文章, 返回的数量等于每页数量时才有下一页
+    async fn query_stream(
+        &self,
+        account_id: i64,
+        req: GReaderStreamRequest,
+        conn: &DBConnection,
+    ) -> Result<(Vec<LinkModel>, Option<String>), ErrorInService> {
+        let page = req
+            .continuation
+            .as_deref()
+            .and_then(|c| c.parse::<u64>().ok())
+            .filter(|p| *p > 0)
+            .unwrap_or(1);
+        let page_size = match req.count {
+            0 => GREADER_DEFAULT_COUNT,
+            count => count.min(GREADER_MAX_COUNT),
+        };
+        let mut query = QueryRssLinkRequest {
+            account_id: Some(account_id),
+            unread_only: Some(req.exclude_read),
+            published_at_lower: req.newer_than,
+            published_at_upper: req.older_than,
+            page: Some(PageRequest { page, page_size }),
+            ..Default::default()
+        };
+        match req.stream {
+            GReaderStream::ReadingList => {}
+            GReaderStream::Starred => query.starred_only = Some(true),
+            GReaderStream::Feed(id) => query.subscrption_ids = Some(vec![id]),
+            GReaderStream::Label(label) => {
+                let category_id = self
+                    .categories(account_id, conn)
+                    .await?
+                    .into_iter()
+                    .find(|(_, title)| *title == label)
+                    .map(|(id, _)| id);
+                let subscription_ids: Vec<i64> = match category_id {
+                    Some(category_id) => self
+                        .subscribed(account_id, Some(category_id), conn)
+                        .await?
+                        .into_iter()
+                        .map(|s| s.id)
+                        .collect(),
+                    None => vec![],
+                };
+                // 空的订阅源列表不会过滤, 直接返回
+                if subscription_ids.is_empty() {
+                    return Ok((vec![], None));
+                }
+                query.subscrption_ids = Some(subscription_ids);
+            }
+        }
+        let links = LinkController.query_links(query, conn).await?.data;
+        let continuation = (links.len() as u64 == page_size).then(|| (page + 1).to_string());
+        Ok((links, continuation))
+    }
+
+    async fn to_items(
+        &self,
+        account_id: i64,
+        links: Vec<LinkModel>,
+        conn: &DBConnection,
+    ) -> Result<Vec<GReaderItem>, ErrorInService> {
+        if links.is_empty() {
+            return Ok(vec![]);
+        }
+        let labels: HashMap<i64, String> = self
+            .categories(account_id, conn)
+            .await?
+            .into_iter()
+            .collect();
+        let subscriptions: HashMap<i64, SubscriptionModel> = self
+            .subscribed(account_id, None, conn)
+            .await?
+            .into_iter()
+            .map(|s| (s.id, s))
+            .collect();
+        Ok(links
+            .into_iter()
+            .map(|link| {
+                let subscription = subscriptions.get(&link.subscription_id);
+                let published = link.published_at.unwrap_or_default().and_utc();
+                let mut categories = vec![GREADER_READING_LIST.to_string()];
+                if link.is_read {
+                    categories.push(GREADER_READ.to_string());
+                }
+                if link.is_starred {
+                    categories.push(GREADER_STARRED.to_string());
+                }
+                if let Some(label) = subscription.and_then(|s| labels.get(&s.category_id)) {
+                    categories.push(label_stream_id(label));
+                }
+                let author = link
+                    .authors
+                    .as_ref()
+                    .and_then(|authors| authors.first())
+                    .map(|a| a.name.clone())
+                    .unwrap_or_default();
+                GReaderItem {
+                    id: greader_item_id(link.id),
+                    crawl_time_msec: published.timestamp_millis().to_string(),
+                    timestamp_usec: published.timestamp_micros().to_string(),
+                    published: published.timestamp(),
+                    title: link.title,
+                    canonical: vec![GReaderLink {
+                        href: link.link.clone(),
+                    }],
+                    alternate: vec![GReaderLink { href: link.link }],
+                    summary: GReaderContent {
+                        content: link.content.or(link.description).unwrap_or_default(),
+                    },
+                    author,
+                    categories,
+                    origin: GReaderOrigin {
+                        stream_id: feed_stream_id(link.subscription_id),
+                        title: subscription
+                            .map(|s| s.custom_title.clone().unwrap_or(s.title.clone()))
+                            .unwrap_or_default(),
+                        html_url: subscription
+                            .and_then(|s| s.site_link.clone())
+                            .unwrap_or_default(),
+                    },
+                }
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::schema::{
+        CreateOrUpdateCategoryRequestBuilder, CreateOrUpdateRssLinkRequestBuilder,
+        CreateOrUpdateSubscriptionRequestBuilder,
+    };
+
+    #[test]
+    fn test_parse_stream_and_item_id() {
+        assert_eq!(
+            GReaderStream::parse("user/-/state/com.google/reading-list"),
+            Some(GReaderStream::ReadingList)
+        );
+        assert_eq!(
+            GReaderStream::parse("user/1/state/com.google/starred"),
+            Some(GReaderStream::Starred)
+        );
+        assert_eq!(
+            GReaderStream::parse("feed/12"),
+            Some(GReaderStream::Feed(12))
+        );
+        assert_eq!(
+            GReaderStream::parse("user/-/label/Tech"),
+            Some(GReaderStream::Label("Tech".to_string()))
+        );
+        assert_eq!(GReaderStream::parse("feed/abc"), None);
+
+        assert_eq!(
+            greader_item_id(255),
+            "tag:google.com,2005:reader/item/00000000000000ff"
+        );
+        assert_eq!(parse_greader_item_id(&greader_item_id(255)), Some(255));
+        assert_eq!(parse_greader_item_id("00000000000000ff"), Some(255));
+        assert_eq!(parse_greader_item_id("255"), Some(255));
+    }
+
+    #[tokio::test]
+    async fn test_greader_stream_and_edit_tag() {
+        let conn = crate::test_runner::setup_database().await;
+        let account_id = 1;
+        let category = CategoryController
+            .insert_category(
+                CreateOrUpdateCategoryRequestBuilder::default()
+                    .title("Tech")
+                    .account_id(account_id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let (_, subscription_id) = SubscriptionController
+            .subscribe(
+                account_id,
+                CreateOrUpdateSubscriptionRequestBuilder::default()
+                    .title("feed")
+                    .link("https://example.com/feed.xml")
+                    .category_id(category.id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let mut link_ids = Vec::new();
+        for i in 0..3 {
+            let req = CreateOrUpdateRssLinkRequestBuilder::default()
+                .title(format!("link {}", i))
+                .link(format!("https://example.com/{}", i))
+                .subscrption_id(subscription_id)
+                .published_at(chrono::Utc::now().naive_utc() - chrono::Duration::hours(3 - i))
+                .build()
+                .unwrap();
+            let (_, link) = LinkController.insert_link(req, &conn).await.unwrap();
+            link_ids.push(link.id);
+        }
+
+        let controller = GReaderController;
+        let subscriptions = controller.subscriptions(account_id, &conn).await.unwrap();
+        assert_eq!(subscriptions[0].id, format!("feed/{}", subscription_id));
+        assert_eq!(subscriptions[0].categories[0].id, "user/-/label/Tech");
+        let tags = controller.tags(account_id, &conn).await.unwrap();
+        assert_eq!(tags.len(), 2);
+
+        let stream = |stream: GReaderStream, continuation: Option<String>| GReaderStreamRequest {
+            stream,
+            count: 2,
+            continuation,
+            exclude_read: false,
+            newer_than: None,
+            older_than: None,
+        };
+        // 按发布时间降序, 第一页满了才有下一页
+        let page = controller
+            .stream_contents(
+                account_id,
+                stream(GReaderStream::Label("Tech".to_string()), None),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.items.len(), 2);
+        assert_eq!(page.items[0].id, greader_item_id(link_ids[2]));
+        assert_eq!(page.items[0].origin.title, "feed");
+        assert_eq!(page.continuation.as_deref(), Some("2"));
+        let page = controller
+            .stream_item_ids(
+                account_id,
+                stream(GReaderStream::ReadingList, page.continuation),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(page.item_refs[0].id, link_ids[0].to_string());
+        assert!(page.continuation.is_none());
+        let empty = controller
+            .stream_item_ids(
+                account_id,
+                stream(GReaderStream::Label("Other".to_string()), None),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(empty.item_refs.is_empty());
+
+        // 标记已读和收藏
+        let updated = controller
+            .edit_tag(
+                account_id,
+                GReaderEditTagRequest {
+                    link_ids: vec![link_ids[0]],
+                    add: vec![GREADER_READ.to_string(), GREADER_STARRED.to_string()],
+                    remove: vec![],
+                },
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated, 1);
+        let mut unread = stream(GReaderStream::ReadingList, None);
+        unread.count = 10;
+        unread.exclude_read = true;
+        let ids = controller
+            .stream_item_ids(account_id, unread, &conn)
+            .await
+            .unwrap();
+        assert_eq!(ids.item_refs.len(), 2);
+        let starred = controller
+            .items_contents(account_id, vec![link_ids[0]], &conn)
+            .await
+            .unwrap();
+        assert!(starred[0].categories.contains(&GREADER_STARRED.to_string()));
+        assert!(starred[0].categories.contains(&GREADER_READ.to_string()));
+    }
+}
@@ -0,0 +1,481 @@
+use super::schema::{LinkModel, LinkSearchHit, QueryRssLinkRequestBuilder, SearchLinkRequest};
+use crate::common_schema::{PageRequestBuilder, PageResponse};
+use crate::error::ErrorInService;
+use crate::DBConnection;
+use lib_entity::{feed_link, feed_link_summary};
+use lib_utils::math::{get_page_count, get_page_offset};
+use lib_utils::text::{desegment_cjk, segment_cjk};
+use sea_orm::sea_query::SelectStatement;
+use sea_orm::{entity::*, query::*, DbBackend, Statement, Value};
+use std::collections::HashMap;
+
+const MARK_START: &str = "<mark>";
+const MARK_END: &str = "</mark>";
+// 片段包含的词数 (FTS5) / 字符数 (LIKE)
+const SNIPPET_TOKENS: i32 = 24;
+const SNIPPET_CHARS: usize = 80;
+
+/// 拆分搜索词, 双引号包含的部分作为一个短语
+pub(crate) fn parse_search_terms(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    for (index, part) in query.split('"').enumerate() {
+        // 奇数段在引号内
+        if index % 2 == 1 {
+            let phrase = part.split_whitespace().collect::<Vec<_>>().join(" ");
+            if !phrase.is_empty() {
+                terms.push(phrase);
+            }
+        } else {
+            terms.extend(part.split_whitespace().map(|t| t.to_string()));
+        }
+    }
+    terms
+}
+
+// 每个搜索词都作为 FTS5 的短语, 避免用户输入被当作查询语法
+fn build_match_expression(terms: &[String]) -> String {
+    terms
+        .iter()
+        .map(|t| format!("\"{}\"", segment_cjk(t).replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 不区分大小写地高亮所有搜索词
+fn highlight_terms(text: &str, terms: &[String]) -> String {
+    let lower = text.to_lowercase();
+    // 大小写转换后长度变化时, 不做高亮
+    if lower.len() != text.len() {
+        return text.to_string();
+    }
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for term in terms {
+        let term = term.to_lowercase();
+        if term.is_empty() {
+            continue;
+        }
+        for (start, _) in lower.match_indices(&term) {
+            ranges.push((start, start + term.len()));
+        }
+    }
+    ranges.sort();
+    let mut result = String::with_capacity(text.len());
+    let mut cursor = 0;
+    for (start, end) in ranges {
+        if start < cursor {
+            continue;
+        }
+        result.push_str(&text[cursor..start]);
+        result.push_str(MARK_START);
+        result.push_str(&text[start..end]);
+        result.push_str(MARK_END);
+        cursor = end;
+    }
+    result.push_str(&text[cursor..]);
+    result
+}
+
+// 截取第一个搜索词附近的片段
+fn snippet_around_terms(text: &str, terms: &[String]) -> String {
+    let lower = text.to_lowercase();
+    let position = terms
+        .iter()
+        .filter_map(|t| lower.find(&t.to_lowercase()))
+        .min()
+        .filter(|_| lower.len() == text.len())
+        .unwrap_or(0);
+    let chars_before = text[..position].chars().count();
+    let start = chars_before.saturating_sub(SNIPPET_CHARS / 4);
+    let total = text.chars().count();
+    let snippet: String = text.chars().skip(start).take(SNIPPET_CHARS).collect();
+    let mut snippet = highlight_terms(&snippet, terms);
+    if start > 0 {
+        snippet = format!("…{}", snippet);
+    }
+    if start + SNIPPET_CHARS < total {
+        snippet.push('…');
+    }
+    snippet
+}
+
+pub struct LinkSearchController;
+
+impl LinkSearchController {
+    /// 更新文章的全文索引, 只有 sqlite 使用 FTS5 索引
+    pub async fn index_link(
+        &self,
+        link: &feed_link::Model,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        if conn.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+        // 总结中的关键词
+        let keywords = feed_link_summary::Entity::find()
+            .filter(feed_link_summary::Column::LinkUrl.eq(link.link.clone()))
+            .one(conn)
+            .await?
+            .and_then(|s| s.keywords)
+            .unwrap_or_default();
+        conn.execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "DELETE FROM feed_link_fts WHERE rowid = ?",
+            [link.id.into()],
+        ))
+        .await?;
+        conn.execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "INSERT INTO feed_link_fts (rowid, title, content, keywords) VALUES (?, ?, ?, ?)",
+            [
+                link.id.into(),
+                segment_cjk(&link.title).into(),
+                segment_cjk(link.desc_pure_txt.as_deref().unwrap_or_default()).into(),
+                segment_cjk(&keywords).into(),
+            ],
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// 删除文章的全文索引, `link_ids` 是查询文章 id 的子查询, 需要在删除文章之前调用
+    pub(crate) async fn remove_links(
+        &self,
+        link_ids: SelectStatement,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        if conn.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+        let query = DbBackend::Sqlite.build(&link_ids);
+        conn.execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            format!("DELETE FROM feed_link_fts WHERE rowid IN ({})", query.sql),
+            query.values.map(|values| values.0).unwrap_or_default(),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    /// 重新索引使用这个地址的文章, 总结更新后调用
+    pub async fn index_links_by_url(
+        &self,
+        link_url: &str,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let links = feed_link::Entity::find()
+            .filter(feed_link::Column::Link.eq(link_url))
+            .all(conn)
+            .await?;
+        for link in links {
+            self.index_link(&link, conn).await?;
+        }
+        Ok(())
+    }
+
+    /// 全文搜索文章的标题、内容和总结的关键词
+    ///
+    /// sqlite 使用 FTS5 按相关度排序, 其他数据库使用 LIKE 查询, 按发布时间排序
+    pub async fn search(
+        &self,
+        req: SearchLinkRequest,
+        conn: &DBConnection,
+    ) -> Result<PageResponse<LinkSearchHit>, ErrorInService> {
+        let terms = parse_search_terms(&req.query);
+        if terms.is_empty() {
+            return Err(ErrorInService::Custom("搜索词不能为空".to_string()));
+        }
+        let page_info = req
+            .page
+            .clone()
+            .unwrap_or(PageRequestBuilder::default().build().unwrap());
+        match conn.get_database_backend() {
+            DbBackend::Sqlite => {
+                self.search_by_fts(&req, &terms, page_info.page, page_info.page_size, conn)
+                    .await
+            }
+            _ => {
+                self.search_by_like(&req, &terms, page_info.page, page_info.page_size, conn)
+                    .await
+            }
+        }
+    }
+
+    async fn search_by_fts(
+        &self,
+        req: &SearchLinkRequest,
+        terms: &[String],
+        page: u64,
+        page_size: u64,
+        conn: &DBConnection,
+    ) -> Result<PageResponse<LinkSearchHit>, ErrorInService> {
+        let mut filter = String::from("feed_link_fts MATCH ?");
+        let mut values: Vec<Value> = vec![build_match_expression(terms).into()];
+        if let Some(ids) = req.subscrption_ids.as_ref().filter(|ids| !ids.is_empty()) {
+            let placeholders = vec!["?"; ids.len()].join(", ");
+            filter.push_str(&format!(
+                " AND feed_link.subscription_id IN ({})",
+                placeholders
+            ));
+            values.extend(ids.iter().map(|id| Value::from(*id)));
+        }
+        if let Some(account_id) = req.account_id {
+            filter.push_str(
+                " AND feed_link.subscription_id IN \
+                (SELECT subscription_id FROM account_subscription WHERE account_id = ?)",
+            );
+            values.push(account_id.into());
+        }
+        let from = format!(
+            "FROM feed_link_fts JOIN feed_link ON feed_link.id = feed_link_fts.rowid WHERE {}",
+            filter
+        );
+
+        let count = conn
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                format!("SELECT COUNT(*) AS count {}", from),
+                values.clone(),
+            ))
+            .await?
+            .map(|row| row.try_get::<i64>("", "count"))
+            .transpose()?
+            .unwrap_or(0) as u64;
+
+        // bm25 越小越相关, 标题和关键词的权重更高
+        let sql = format!(
+            "SELECT feed_link_fts.rowid AS id, bm25(feed_link_fts, 10.0, 1.0, 5.0) AS rank, \
+            highlight(feed_link_fts, 0, '{start}', '{end}') AS title_highlight, \
+            snippet(feed_link_fts, 1, '{start}', '{end}', '…', {tokens}) AS snippet \
+            {from} ORDER BY rank LIMIT ? OFFSET ?",
+            start = MARK_START,
+            end = MARK_END,
+            tokens = SNIPPET_TOKENS,
+            from = from
+        );
+        let mut page_values = values;
+        page_values.push((page_size as i64).into());
+        page_values.push((get_page_offset(page, page_size) as i64).into());
+        let rows = conn
+            .query_all(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                sql,
+                page_values,
+            ))
+            .await?;
+
+        let markers = [MARK_START, MARK_END];
+        let mut hits: Vec<(i64, f64, String, String)> = Vec::new();
+        for row in rows {
+            hits.push((
+                row.try_get("", "id")?,
+                -row.try_get::<f64>("", "rank")?,
+                desegment_cjk(&row.try_get::<String>("", "title_highlight")?, &markers),
+                desegment_cjk(&row.try_get::<String>("", "snippet")?, &markers),
+            ));
+        }
+        let mut links = self
+            .find_links(hits.iter().map(|h| h.0).collect(), conn)
+            .await?;
+        let data = hits
+            .into_iter()
+            .filter_map(|(id, score, title_highlight, snippet)| {
+                Some(LinkSearchHit {
+                    link: links.remove(&id)?,
+                    score,
+                    title_highlight,
+                    snippet,
+                })
+            })
+            .collect();
+        Ok(PageResponse::new(
+            get_page_count(count, page_size),
+            page,
+            page_size,
+            data,
+        ))
+    }
+
+    // 没有全文索引时, 每个搜索词都需要出现在标题或内容中
+    async fn search_by_like(
+        &self,
+        req: &SearchLinkRequest,
+        terms: &[String],
+        page: u64,
+        page_size: u64,
+        conn: &DBConnection,
+    ) -> Result<PageResponse<LinkSearchHit>, ErrorInService> {
+        let mut condition = Condition::all();
+        for term in terms {
+            let pattern = format!("%{}%", term);
+            condition = condition.add(
+                Condition::any()
+                    .add(feed_link::Column::Title.like(pattern.clone()))
+                    .add(feed_link::Column::DescPureTxt.like(pattern)),
+            );
+        }
+        let mut query = QueryRssLinkRequestBuilder::default();
+        if let Some(ids) = &req.subscrption_ids {
+            query.subscrption_ids(ids.clone());
+        }
+        if let Some(account_id) = req.account_id {
+            query.account_id(account_id);
+        }
+        let select = query.build()?.build_query().filter(condition);
+        let count = select.clone().count(conn).await?;
+        let models: Vec<LinkModel> = select
+            .order_by_desc(feed_link::Column::PublishedAt)
+            .limit(page_size)
+            .offset(get_page_offset(page, page_size))
+            .into_model()
+            .all(conn)
+            .await?;
+        let data = models
+            .into_iter()
+            .map(|link| LinkSearchHit {
+                score: 0.0,
+                title_highlight: highlight_terms(&link.title, terms),
+                snippet: snippet_around_terms(link.description.as_deref().unwrap_or(""), terms),
+                link,
+            })
+            .collect();
+        Ok(PageResponse::new(
+            get_page_count(count, page_size),
+            page,
+            page_size,
+            data,
+        ))
+    }
+
+    async fn find_links(
+        &self,
+        ids: Vec<i64>,
+        conn: &DBConnection,
+    ) -> Result<HashMap<i64, LinkModel>, ErrorInService> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let models: Vec<LinkModel> = QueryRssLinkRequestBuilder::default()
+            .ids(ids)
+            .build()?
+            .build_query()
+            .into_model()
+            .all(conn)
+            .await?;
+        Ok(models.into_iter().map(|m| (m.id, m)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::schema::{CreateOrUpdateRssLinkRequestBuilder, SearchLinkRequestBuilder};
+    use crate::feed::LinkController;
+
+    #[test]
+    fn test_parse_search_terms() {
+        assert_eq!(
+            parse_search_terms(r#"rust "full text"  search "#),
+            vec!["rust", "full text", "search"]
+        );
+        assert_eq!(
+            build_match_expression(&["全文".to_string(), "a\"b".to_string()]),
+            r#""全 文" "a""b""#
+        );
+    }
+
+    #[test]
+    fn test_highlight_and_snippet() {
+        let terms = vec!["rust".to_string()];
+        assert_eq!(
+            highlight_terms("Rust and rust", &terms),
+            "<mark>Rust</mark> and <mark>rust</mark>"
+        );
+        let text = format!("{} rust {}", "a".repeat(100), "b".repeat(100));
+        let snippet = snippet_around_terms(&text, &terms);
+        assert!(snippet.starts_with('…') && snippet.ends_with('…'));
+        assert!(snippet.contains("<mark>rust</mark>"));
+    }
+
+    #[tokio::test]
+    async fn test_search_links() {
+        let conn = crate::test_runner::setup_database().await;
+        let build = |subscription_id: i64, link: &str, title: &str, text: &str| {
+            CreateOrUpdateRssLinkRequestBuilder::default()
+                .title(title.to_owned())
+                .link(link.to_owned())
+                .subscrption_id(subscription_id)
+                .desc_pure_txt(text.to_owned())
+                .build()
+                .unwrap()
+        };
+        for req in [
+            build(
+                31,
+                "https://example.com/1",
+                "Full text search with SQLite",
+                "FTS5 gives ranked results.",
+            ),
+            build(
+                31,
+                "https://example.com/2",
+                "Weekly notes",
+                "We tried full text search and other text tricks.",
+            ),
+            build(
+                32,
+                "https://example.com/3",
+                "全文搜索入门",
+                "介绍如何在文章中进行全文搜索。",
+            ),
+        ] {
+            LinkController.insert_link(req, &conn).await.unwrap();
+        }
+
+        let search = |query: &str, subscription_ids: Vec<i64>| {
+            SearchLinkRequestBuilder::default()
+                .query(query.to_owned())
+                .subscrption_ids(subscription_ids)
+                .build()
+                .unwrap()
+        };
+
+        // 标题命中的排在前面
+        let res = LinkSearchController
+            .search(search("\"full text\"", vec![]), &conn)
+            .await
+            .unwrap();
+        assert_eq!(res.data.len(), 2);
+        assert_eq!(res.data[0].link.link, "https://example.com/1");
+        assert!(res.data[0].score >= res.data[1].score);
+        assert_eq!(
+            res.data[0].title_highlight,
+            "<mark>Full text</mark> search with SQLite"
+        );
+        assert!(res.data[1].snippet.contains("<mark>full text</mark>"));
+
+        // 短语需要连续出现
+        let res = LinkSearchController
+            .search(search("\"text full\"", vec![]), &conn)
+            .await
+            .unwrap();
+        assert!(res.data.is_empty());
+
+        let res = LinkSearchController
+            .search(search("搜索", vec![]), &conn)
+            .await
+            .unwrap();
+        assert_eq!(res.data.len(), 1);
+        assert_eq!(res.data[0].title_highlight, "全文<mark>搜索</mark>入门");
+
+        let res = LinkSearchController
+            .search(search("search", vec![32]), &conn)
+            .await
+            .unwrap();
+        assert!(res.data.is_empty());
+
+        assert!(LinkSearchController
+            .search(search("  ", vec![]), &conn)
+            .await
+            .is_err());
+    }
+}
@@ -2,15 +2,27 @@ use chrono::naive::serde::ts_milliseconds_option::deserialize as from_milli_tsop
 
 use crate::common_schema::{PageRequest, PageRequestBuilder, PageResponse};
 
-use super::schema::{CreateOrUpdateRssLinkRequest, LinkModel, QueryRssLinkRequest};
+use super::link_search::LinkSearchController;
+use super::link_state::{read_link_ids_query, starred_link_ids_query, LinkStateController};
+use super::schema::{
+    CreateOrUpdateRssLinkRequest, LinkModel, QueryRssLinkRequest, UpdateLinkContentRequest,
+};
+use super::subscription_service::subscribed_ids_query;
 use crate::error::ErrorInService;
 
 use crate::DBConnection;
 use chrono::NaiveDateTime;
-use lib_entity::{feed_link, feed_subscription};
+use lib_crawler::try_get_all_text_from_html_content;
+use lib_entity::{feed_link, feed_subscription, link_state, saved_link};
+use lib_utils::canonicalize_url;
 use lib_utils::math::{get_page_count, get_page_offset};
+use lib_utils::simhash::{hamming_distance, is_near_duplicate, simhash};
 use sea_orm::{entity::*, query::*};
 use serde::Deserialize;
+use std::collections::HashMap;
+
+// 查找重复文章时比较的最近文章数量
+const CLUSTER_CANDIDATE_LIMIT: u64 = 2000;
 
 pub struct LinkController;
 
@@ -20,16 +32,15 @@ impl LinkController {
         req: CreateOrUpdateRssLinkRequest,
         conn: &DBConnection,
     ) -> Result<(bool, feed_link::Model), ErrorInService> {
-        // 构建查找条件
-        let query = match req.id.clone() {
-            Some(id) => feed_link::Entity::find().filter(feed_link::Column::Id.eq(id)),
-            None => feed_link::Entity::find()
-                .left_join(feed_subscription::Entity)
-                .filter(feed_link::Column::Link.eq(req.link.clone()))
-                .filter(feed_subscription::Column::Id.eq(req.subscrption_id)),
+        let canonical_link = canonicalize_url(&req.link);
+        let guid = req.guid.clone().filter(|g| !g.trim().is_empty());
+        let link = match req.id {
+            Some(id) => feed_link::Entity::find_by_id(id).one(conn).await?,
+            None => {
+                self.find_existing_link(req.subscrption_id, guid.as_deref(), &canonical_link, conn)
+                    .await?
+            }
         };
-        // 执行查找
-        let link = query.one(conn).await.map_err(ErrorInService::DBError)?;
 
         // 判断是否需要更新
         let should_update = link.is_some();
@@ -46,22 +57,162 @@ impl LinkController {
         let image_value = serde_json::to_value(req.images.clone()).unwrap();
         let author_value = serde_json::to_value(req.authors.clone()).unwrap();
         new_model.link = Set(req.link.clone());
+        new_model.canonical_link = Set(Some(canonical_link));
+        if guid.is_some() {
+            new_model.guid = Set(guid);
+        }
 
         new_model.description = Set(req.description.clone());
         new_model.desc_pure_txt = Set(req.desc_pure_txt.clone());
         // images is serder_json value. vec of Image
         new_model.images = Set(Some(image_value));
         new_model.authors = Set(Some(author_value));
+        if let Some(tags) = &req.tags {
+            new_model.tags = Set(serde_json::to_value(tags).ok());
+        }
         new_model.published_at = Set(req.published_at);
+        // 标题和纯文本内容的指纹, 用于查找其他订阅源中的重复文章
+        let fingerprint = simhash(&format!(
+            "{} {}",
+            req.title,
+            req.desc_pure_txt.clone().unwrap_or_default()
+        ));
+        new_model.simhash = Set(fingerprint.map(|f| f as i64));
 
         // 执行更新或者创建
         let updated = match should_update {
             true => new_model.update(conn).await?,
             false => new_model.insert(conn).await?,
         };
+        let updated = self.assign_cluster(updated, conn).await?;
+        LinkSearchController.index_link(&updated, conn).await?;
         Ok((should_update, updated))
     }
 
+    /// 补全链接的正文和图片, 其他字段保持不变
+    ///
+    /// 链接已经有描述时不会覆盖, 链接不存在时返回 None
+    pub async fn update_link_content(
+        &self,
+        req: UpdateLinkContentRequest,
+        conn: &DBConnection,
+    ) -> Result<Option<feed_link::Model>, ErrorInService> {
+        let Some(link) = feed_link::Entity::find_by_id(req.id).one(conn).await? else {
+            return Ok(None);
+        };
+        let has_description = !link.description.as_deref().unwrap_or_default().is_empty();
+        let title = link.title.clone();
+        let mut active = link.into_active_model();
+        let mut content_changed = false;
+        if let Some(description) = req.description.filter(|_| !has_description) {
+            let text = try_get_all_text_from_html_content(description.clone()).ok();
+            // 正文变化后重新计算指纹
+            let fingerprint = simhash(&format!("{} {}", title, text.clone().unwrap_or_default()));
+            active.simhash = Set(fingerprint.map(|f| f as i64));
+            active.desc_pure_txt = Set(text);
+            active.description = Set(Some(description));
+            content_changed = true;
+        }
+        if let Some(images) = req.images {
+            active.images = Set(serde_json::to_value(images).ok());
+        }
+        if !active.is_changed() {
+            return Ok(Some(active.try_into_model()?));
+        }
+        let updated = active.update(conn).await?;
+        if !content_changed {
+            return Ok(Some(updated));
+        }
+        let updated = self.assign_cluster(updated, conn).await?;
+        LinkSearchController.index_link(&updated, conn).await?;
+        Ok(Some(updated))
+    }
+
+    // 在其他订阅源最近的文章中查找近似重复的文章, 加入它所在的分组
+    //
+    // 分组的 id 是组内第一篇文章的 id, 第一篇文章在出现重复时才设置分组
+    async fn assign_cluster(
+        &self,
+        model: feed_link::Model,
+        conn: &DBConnection,
+    ) -> Result<feed_link::Model, ErrorInService> {
+        let Some(fingerprint) = model.simhash else {
+            return Ok(model);
+        };
+        if model.cluster_id.is_some() {
+            return Ok(model);
+        }
+        let candidates: Vec<(i64, i64, Option<i64>)> = feed_link::Entity::find()
+            .select_only()
+            .columns([
+                feed_link::Column::Id,
+                feed_link::Column::Simhash,
+                feed_link::Column::ClusterId,
+            ])
+            .filter(feed_link::Column::Simhash.is_not_null())
+            .filter(feed_link::Column::SubscriptionId.ne(model.subscription_id))
+            .filter(feed_link::Column::Id.ne(model.id))
+            .order_by_desc(feed_link::Column::Id)
+            .limit(CLUSTER_CANDIDATE_LIMIT)
+            .into_tuple()
+            .all(conn)
+            .await?;
+        let matched = candidates
+            .into_iter()
+            .filter(|(_, other, _)| is_near_duplicate(fingerprint as u64, *other as u64))
+            .min_by_key(|(_, other, _)| hamming_distance(fingerprint as u64, *other as u64));
+        let Some((other_id, _, other_cluster)) = matched else {
+            return Ok(model);
+        };
+        let cluster_id = match other_cluster {
+            Some(cluster_id) => cluster_id,
+            None => {
+                feed_link::Entity::update_many()
+                    .col_expr(
+                        feed_link::Column::ClusterId,
+                        sea_orm::sea_query::Expr::value(other_id),
+                    )
+                    .filter(feed_link::Column::Id.eq(other_id))
+                    .exec(conn)
+                    .await?;
+                other_id
+            }
+        };
+        let mut active = model.into_active_model();
+        active.cluster_id = Set(Some(cluster_id));
+        Ok(active.update(conn).await?)
+    }
+
+    // 查找订阅源中已有的同一篇文章: 优先按 guid 匹配, 其次按规范化的链接匹配
+    //
+    // 有 guid 时, 链接相同但 guid 不同的视为不同的文章
+    async fn find_existing_link(
+        &self,
+        subscription_id: i64,
+        guid: Option<&str>,
+        canonical_link: &str,
+        conn: &DBConnection,
+    ) -> Result<Option<feed_link::Model>, ErrorInService> {
+        if let Some(guid) = guid {
+            let link = feed_link::Entity::find()
+                .filter(feed_link::Column::SubscriptionId.eq(subscription_id))
+                .filter(feed_link::Column::Guid.eq(guid))
+                .one(conn)
+                .await?;
+            if link.is_some() {
+                return Ok(link);
+            }
+        }
+        let mut query = feed_link::Entity::find()
+            .filter(feed_link::Column::SubscriptionId.eq(subscription_id))
+            .filter(feed_link::Column::CanonicalLink.eq(canonical_link));
+        if guid.is_some() {
+            query = query.filter(feed_link::Column::Guid.is_null());
+        }
+        let link = query.one(conn).await?;
+        Ok(link)
+    }
+
     pub async fn query_links(
         &self,
         req: QueryRssLinkRequest,
@@ -87,11 +238,50 @@ impl LinkController {
         let all_count = select.clone().count(conn).await.unwrap_or(0);
 
         let page_count = get_page_count(all_count, page_size);
-        let models = select.into_model().all(conn).await?;
+        let mut models: Vec<LinkModel> = select.into_model().all(conn).await?;
+        if req.collapse_duplicates.unwrap_or(false) {
+            self.fill_duplicate_count(&req, &mut models, conn).await?;
+        }
+        if let Some(account_id) = req.account_id {
+            LinkStateController
+                .fill_link_state(account_id, &mut models, conn)
+                .await?;
+        }
         let resp = PageResponse::new(page_count, page, page_size, models);
         Ok(resp)
     }
 
+    // 统计每篇文章所在分组中, 符合查询条件的其他文章数量
+    async fn fill_duplicate_count(
+        &self,
+        req: &QueryRssLinkRequest,
+        models: &mut [LinkModel],
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let cluster_ids: Vec<i64> = models.iter().filter_map(|m| m.cluster_id).collect();
+        if cluster_ids.is_empty() {
+            return Ok(());
+        }
+        let counts: Vec<(i64, i64)> = feed_link::Entity::find()
+            .left_join(feed_subscription::Entity)
+            .select_only()
+            .column(feed_link::Column::ClusterId)
+            .column_as(feed_link::Column::Id.count(), "count")
+            .filter(req.build_condition())
+            .filter(feed_link::Column::ClusterId.is_in(cluster_ids))
+            .group_by(feed_link::Column::ClusterId)
+            .into_tuple()
+            .all(conn)
+            .await?;
+        let counts: HashMap<i64, i64> = counts.into_iter().collect();
+        for model in models.iter_mut() {
+            if let Some(count) = model.cluster_id.and_then(|c| counts.get(&c)) {
+                model.duplicate_count = (*count as u64).saturating_sub(1);
+            }
+        }
+        Ok(())
+    }
+
     pub async fn fetch_count(
         &self,
         req: QueryRssLinkRequest,
@@ -102,13 +292,45 @@ impl LinkController {
         Ok(count)
     }
 
+    pub async fn find_link(
+        &self,
+        id: i64,
+        conn: &DBConnection,
+    ) -> Result<Option<feed_link::Model>, ErrorInService> {
+        let link = feed_link::Entity::find_by_id(id).one(conn).await?;
+        Ok(link)
+    }
+
+    /// 删除发布时间早于 `expired_at` 的文章, 用户保存过的文章会保留
     pub async fn remove_expired_links(
         &self,
         expired_at: NaiveDateTime,
         conn: &DBConnection,
     ) -> Result<u64, ErrorInService> {
-        let result = lib_entity::feed_link::Entity::delete_many()
-            .filter(lib_entity::feed_link::Column::PublishedAt.lt(expired_at))
+        let expired = Condition::all()
+            .add(feed_link::Column::PublishedAt.lt(expired_at))
+            .add(
+                feed_link::Column::Id.not_in_subquery(
+                    saved_link::Entity::find()
+                        .select_only()
+                        .column(saved_link::Column::LinkId)
+                        .filter(saved_link::Column::LinkId.is_not_null())
+                        .into_query(),
+                ),
+            );
+        let expired_ids = feed_link::Entity::find()
+            .select_only()
+            .column(feed_link::Column::Id)
+            .filter(expired.clone())
+            .into_query();
+        // 先清理这些文章的阅读状态和全文索引
+        link_state::Entity::delete_many()
+            .filter(link_state::Column::LinkId.in_subquery(expired_ids.clone()))
+            .exec(conn)
+            .await?;
+        LinkSearchController.remove_links(expired_ids, conn).await?;
+        let result = feed_link::Entity::delete_many()
+            .filter(expired)
             .exec(conn)
             .await?;
         Ok(result.rows_affected)
@@ -123,7 +345,7 @@ impl QueryRssLinkRequest {
         Ok(count)
     }
 
-    fn build_query(&self) -> Select<feed_link::Entity> {
+    pub(crate) fn build_query(&self) -> Select<feed_link::Entity> {
         let mut select = feed_link::Entity::find().left_join(feed_subscription::Entity);
         select = select
             .select_only()
@@ -138,30 +360,69 @@ impl QueryRssLinkRequest {
             ])
             .column_as(feed_link::Column::Images, "images")
             // authors 是 authors_json 的解析结果
-            .column_as(feed_link::Column::Authors, "authors");
+            .column_as(feed_link::Column::Authors, "authors")
+            .column_as(feed_link::Column::Tags, "tags")
+            .column_as(feed_link::Column::ClusterId, "cluster_id")
+            .filter(self.build_condition());
+
+        // 每个分组只保留符合条件的第一篇文章
+        if self.collapse_duplicates.unwrap_or(false) {
+            let representatives = feed_link::Entity::find()
+                .left_join(feed_subscription::Entity)
+                .select_only()
+                .column_as(feed_link::Column::Id.min(), "id")
+                .filter(self.build_condition())
+                .filter(feed_link::Column::ClusterId.is_not_null())
+                .group_by(feed_link::Column::ClusterId)
+                .into_query();
+            select = select.filter(
+                Condition::any()
+                    .add(feed_link::Column::ClusterId.is_null())
+                    .add(feed_link::Column::Id.in_subquery(representatives)),
+            );
+        }
+        select
+    }
 
+    fn build_condition(&self) -> Condition {
+        let mut condition = Condition::all();
         if let Some(ids) = &self.ids {
             if !ids.is_empty() {
-                select = select.filter(feed_link::Column::Id.is_in(ids.clone()))
+                condition = condition.add(feed_link::Column::Id.is_in(ids.clone()))
             }
         }
         if let Some(title) = &self.title {
-            select = select.filter(feed_link::Column::Title.like(format!("%{}%", title)))
+            condition = condition.add(feed_link::Column::Title.like(format!("%{}%", title)))
         }
         if let Some(subscription_ids) = &self.subscrption_ids {
             if !subscription_ids.is_empty() {
-                select =
-                    select.filter(feed_subscription::Column::Id.is_in(subscription_ids.clone()))
+                condition =
+                    condition.add(feed_subscription::Column::Id.is_in(subscription_ids.clone()))
+            }
+        }
+        // 只返回用户订阅的订阅源下的文章
+        if let Some(account_id) = self.account_id {
+            condition = condition.add(
+                feed_link::Column::SubscriptionId
+                    .in_subquery(subscribed_ids_query(account_id, None)),
+            );
+            if self.unread_only.unwrap_or(false) {
+                condition = condition
+                    .add(feed_link::Column::Id.not_in_subquery(read_link_ids_query(account_id)));
+            }
+            if self.starred_only.unwrap_or(false) {
+                condition = condition
+                    .add(feed_link::Column::Id.in_subquery(starred_link_ids_query(account_id)));
             }
         }
 
         if let Some(published_at_lower) = &self.published_at_lower {
-            select = select.filter(feed_link::Column::PublishedAt.gt(*published_at_lower))
+            condition = condition.add(feed_link::Column::PublishedAt.gt(*published_at_lower))
         }
         if let Some(published_at_upper) = &self.published_at_upper {
-            select = select.filter(feed_link::Column::PublishedAt.lt(*published_at_upper))
+            condition = condition.add(feed_link::Column::PublishedAt.lt(*published_at_upper))
         }
-        select
+        condition
     }
 }
 
@@ -170,7 +431,10 @@ mod tests {
 
     use migration::{Migrator, MigratorTrait};
 
-    use crate::feed::schema::{CreateOrUpdateRssLinkRequestBuilder, QueryRssLinkRequestBuilder};
+    use crate::feed::schema::{
+        Author, CreateOrUpdateRssLinkRequestBuilder, Image, QueryRssLinkRequestBuilder,
+        UpdateLinkContentRequestBuilder,
+    };
 
     use super::*;
 
@@ -217,7 +481,7 @@ mod tests {
             let res = controller.insert_link(req, &conn).await.unwrap();
             assert_eq!(res.1.title, "test");
 
-            let id = res.1.id.clone();
+            let id = res.1.id;
 
             let req = CreateOrUpdateRssLinkRequestBuilder::default()
                 .id(id)
@@ -241,4 +505,238 @@ mod tests {
             assert_eq!(res.data.len(), 1);
         }
     }
+
+    #[tokio::test]
+    async fn test_create_link_with_tags() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = LinkController;
+
+        let req = CreateOrUpdateRssLinkRequestBuilder::default()
+            .title("tagged".to_owned())
+            .link("https://example.com/tagged".to_owned())
+            .subscrption_id(12)
+            .tags(vec!["rust".to_string(), "feeds".to_string()])
+            .published_at(chrono::Utc::now().naive_utc())
+            .build()
+            .unwrap();
+        let (_, created) = controller.insert_link(req, &conn).await.unwrap();
+
+        let query_req = QueryRssLinkRequestBuilder::default()
+            .ids(vec![created.id])
+            .build()
+            .unwrap();
+        let res = controller.query_links(query_req, &conn).await.unwrap();
+        assert_eq!(
+            res.data[0].tags,
+            Some(vec!["rust".to_string(), "feeds".to_string()])
+        );
+        let model: LinkModel = created.into();
+        assert_eq!(model.tags.map(|t| t.len()), Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_insert_link_dedup_by_guid_and_canonical_link() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = LinkController;
+        let build = |link: &str, guid: Option<&str>, title: &str| {
+            let mut req = CreateOrUpdateRssLinkRequestBuilder::default();
+            req.title(title.to_owned())
+                .link(link.to_owned())
+                .subscrption_id(13);
+            if let Some(guid) = guid {
+                req.guid(guid.to_owned());
+            }
+            req.build().unwrap()
+        };
+
+        // 链接带上追踪参数后仍然是同一篇文章
+        let (_, first) = controller
+            .insert_link(build("https://example.com/a", None, "a"), &conn)
+            .await
+            .unwrap();
+        let (updated, second) = controller
+            .insert_link(
+                build("http://Example.com/a/?utm_source=rss", None, "a2"),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(updated);
+        assert_eq!(first.id, second.id);
+        assert_eq!(
+            second.canonical_link.as_deref(),
+            Some("https://example.com/a")
+        );
+
+        // 相同 guid 的文章换了地址也会更新原来的记录
+        let (_, with_guid) = controller
+            .insert_link(build("https://example.com/b", Some("post-b"), "b"), &conn)
+            .await
+            .unwrap();
+        let (updated, moved) = controller
+            .insert_link(
+                build("https://example.com/b-new", Some("post-b"), "b2"),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(updated);
+        assert_eq!(with_guid.id, moved.id);
+        assert_eq!(moved.link, "https://example.com/b-new");
+
+        // 地址相同但 guid 不同的是新文章
+        let (updated, other) = controller
+            .insert_link(
+                build("https://example.com/b-new", Some("post-c"), "c"),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(!updated);
+        assert_ne!(other.id, moved.id);
+    }
+
+    #[tokio::test]
+    async fn test_collapse_duplicate_links() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = LinkController;
+        let story = "The central bank raised interest rates by a quarter point on Wednesday, \
+            citing persistent inflation and a strong labor market across the country.";
+        let build = |subscription_id: i64, link: &str, text: &str| {
+            CreateOrUpdateRssLinkRequestBuilder::default()
+                .title("Central bank raises rates".to_owned())
+                .link(link.to_owned())
+                .subscrption_id(subscription_id)
+                .desc_pure_txt(text.to_owned())
+                .published_at(chrono::Utc::now().naive_utc())
+                .build()
+                .unwrap()
+        };
+
+        let (_, first) = controller
+            .insert_link(build(21, "https://a.example.com/rates", story), &conn)
+            .await
+            .unwrap();
+        let (_, second) = controller
+            .insert_link(
+                build(
+                    22,
+                    "https://b.example.com/rates",
+                    &format!("{} (Reuters)", story),
+                ),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let (_, other) = controller
+            .insert_link(
+                build(
+                    23,
+                    "https://c.example.com/frog",
+                    "A new species of frog was discovered in the rainforest by a team of \
+                    biologists who spent three months surveying remote river valleys.",
+                ),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(second.cluster_id, Some(first.id));
+        assert_eq!(other.cluster_id, None);
+
+        let query = |collapse: bool| {
+            QueryRssLinkRequestBuilder::default()
+                .subscrption_ids(vec![])
+                .collapse_duplicates(collapse)
+                .build()
+                .unwrap()
+        };
+        let res = controller.query_links(query(false), &conn).await.unwrap();
+        assert_eq!(res.data.len(), 3);
+
+        let res = controller.query_links(query(true), &conn).await.unwrap();
+        assert_eq!(res.data.len(), 2);
+        let representative = res.data.iter().find(|l| l.id == first.id).unwrap();
+        assert_eq!(representative.duplicate_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_link_content() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = LinkController;
+        let published_at = chrono::Utc::now().naive_utc() - chrono::Duration::days(3);
+        let (_, link) = controller
+            .insert_link(
+                CreateOrUpdateRssLinkRequestBuilder::default()
+                    .title("rust release")
+                    .link("https://example.com/rust")
+                    .subscrption_id(1)
+                    .published_at(published_at)
+                    .authors(vec![Author {
+                        name: "Jane".to_string(),
+                        email: None,
+                        uri: None,
+                    }])
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let image = Image {
+            url: "https://example.com/a.png".to_string(),
+            title: None,
+            link: None,
+            width: None,
+            height: None,
+            description: None,
+        };
+        let updated = controller
+            .update_link_content(
+                UpdateLinkContentRequestBuilder::default()
+                    .id(link.id)
+                    .description("<p>full article</p>")
+                    .images(vec![image.clone()])
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        // 只补全正文和图片, 发布时间和作者保持不变
+        assert_eq!(updated.published_at, link.published_at);
+        assert_eq!(updated.authors, link.authors);
+        assert_eq!(updated.description.as_deref(), Some("<p>full article</p>"));
+        assert_eq!(updated.desc_pure_txt.as_deref(), Some("full article"));
+        assert_eq!(
+            updated.images.unwrap()[0]["url"],
+            "https://example.com/a.png"
+        );
+
+        // 已有正文时不会被覆盖
+        let updated = controller
+            .update_link_content(
+                UpdateLinkContentRequestBuilder::default()
+                    .id(link.id)
+                    .description("<p>other</p>")
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.description.as_deref(), Some("<p>full article</p>"));
+        assert!(controller
+            .update_link_content(
+                UpdateLinkContentRequestBuilder::default()
+                    .id(link.id + 100)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap()
+            .is_none());
+    }
 }
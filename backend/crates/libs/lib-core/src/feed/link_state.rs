@@ -0,0 +1,367 @@
+use std::collections::HashMap;
+
+use super::schema::{LinkModel, MarkLinksReadRequest, UpdateLinkStateRequest};
+use super::subscription_service::subscribed_ids_query;
+use crate::error::ErrorInService;
+use crate::DBConnection;
+use lib_entity::{feed_link, link_state};
+use sea_orm::sea_query::{Expr, Func, OnConflict, SelectStatement};
+use sea_orm::{entity::*, query::*};
+
+// 每次写入的状态数量, 避免超过 sqlite 的参数上限
+const STATE_BATCH_SIZE: usize = 500;
+
+// 用户已读的文章 id, 用于子查询
+pub(crate) fn read_link_ids_query(account_id: i64) -> SelectStatement {
+    link_state::Entity::find()
+        .select_only()
+        .column(link_state::Column::LinkId)
+        .filter(link_state::Column::AccountId.eq(account_id))
+        .filter(link_state::Column::ReadAt.is_not_null())
+        .into_query()
+}
+
+// 用户收藏的文章 id, 用于子查询
+pub(crate) fn starred_link_ids_query(account_id: i64) -> SelectStatement {
+    link_state::Entity::find()
+        .select_only()
+        .column(link_state::Column::LinkId)
+        .filter(link_state::Column::AccountId.eq(account_id))
+        .filter(link_state::Column::StarredAt.is_not_null())
+        .into_query()
+}
+
+pub struct LinkStateController;
+
+impl LinkStateController {
+    /// 修改文章的已读 / 收藏 / 归档状态, 只会修改用户订阅的订阅源下的文章
+    ///
+    /// 返回修改的文章数量
+    pub async fn update_link_state(
+        &self,
+        req: UpdateLinkStateRequest,
+        conn: &DBConnection,
+    ) -> Result<u64, ErrorInService> {
+        let account_id = req
+            .account_id
+            .ok_or(ErrorInService::Custom("account_id is required".to_string()))?;
+        if req.link_ids.is_empty() {
+            return Ok(0);
+        }
+        let link_ids: Vec<i64> = feed_link::Entity::find()
+            .select_only()
+            .column(feed_link::Column::Id)
+            .filter(feed_link::Column::Id.is_in(req.link_ids.clone()))
+            .filter(
+                feed_link::Column::SubscriptionId
+                    .in_subquery(subscribed_ids_query(account_id, None)),
+            )
+            .into_tuple()
+            .all(conn)
+            .await?;
+        self.upsert_states(
+            account_id,
+            &link_ids,
+            req.read,
+            req.starred,
+            req.archived,
+            conn,
+        )
+        .await
+    }
+
+    /// 把订阅源或分类下, 发布时间不晚于指定时间的未读文章标记为已读
+    ///
+    /// 都不指定时标记用户订阅的所有文章
+    pub async fn mark_read_until(
+        &self,
+        req: MarkLinksReadRequest,
+        conn: &DBConnection,
+    ) -> Result<u64, ErrorInService> {
+        let account_id = req
+            .account_id
+            .ok_or(ErrorInService::Custom("account_id is required".to_string()))?;
+        let published_before = req
+            .published_before
+            .unwrap_or(chrono::Utc::now().naive_utc());
+        let mut select = feed_link::Entity::find()
+            .select_only()
+            .column(feed_link::Column::Id)
+            .filter(
+                feed_link::Column::SubscriptionId
+                    .in_subquery(subscribed_ids_query(account_id, req.category_id)),
+            )
+            // 没有发布时间的文章按抓取时间计算
+            .filter(
+                Expr::expr(Func::coalesce([
+                    Expr::col((feed_link::Entity, feed_link::Column::PublishedAt)).into(),
+                    Expr::col((feed_link::Entity, feed_link::Column::CreatedAt)).into(),
+                ]))
+                .lte(published_before),
+            )
+            .filter(feed_link::Column::Id.not_in_subquery(read_link_ids_query(account_id)));
+        if let Some(subscription_id) = req.subscription_id {
+            select = select.filter(feed_link::Column::SubscriptionId.eq(subscription_id));
+        }
+        let link_ids: Vec<i64> = select.into_tuple().all(conn).await?;
+        self.upsert_states(account_id, &link_ids, Some(true), None, None, conn)
+            .await
+    }
+
+    // 填充文章列表中当前用户的已读和收藏状态
+    pub(crate) async fn fill_link_state(
+        &self,
+        account_id: i64,
+        models: &mut [LinkModel],
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        if models.is_empty() {
+            return Ok(());
+        }
+        let states: HashMap<i64, link_state::Model> = link_state::Entity::find()
+            .filter(link_state::Column::AccountId.eq(account_id))
+            .filter(link_state::Column::LinkId.is_in(models.iter().map(|m| m.id)))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|s| (s.link_id, s))
+            .collect();
+        for model in models.iter_mut() {
+            if let Some(state) = states.get(&model.id) {
+                model.is_read = state.read_at.is_some();
+                model.is_starred = state.starred_at.is_some();
+            }
+        }
+        Ok(())
+    }
+
+    // 写入或更新状态, 只修改指定的字段
+    async fn upsert_states(
+        &self,
+        account_id: i64,
+        link_ids: &[i64],
+        read: Option<bool>,
+        starred: Option<bool>,
+        archived: Option<bool>,
+        conn: &DBConnection,
+    ) -> Result<u64, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let mut update_columns = Vec::new();
+        if read.is_some() {
+            update_columns.push(link_state::Column::ReadAt);
+        }
+        if starred.is_some() {
+            update_columns.push(link_state::Column::StarredAt);
+        }
+        if archived.is_some() {
+            update_columns.push(link_state::Column::Archived);
+        }
+        if link_ids.is_empty() || update_columns.is_empty() {
+            return Ok(0);
+        }
+
+        let mut affected = 0;
+        for chunk in link_ids.chunks(STATE_BATCH_SIZE) {
+            let models = chunk.iter().map(|link_id| {
+                let mut model = link_state::ActiveModel {
+                    account_id: Set(account_id),
+                    link_id: Set(*link_id),
+                    ..Default::default()
+                };
+                if let Some(read) = read {
+                    model.read_at = Set(read.then_some(now));
+                }
+                if let Some(starred) = starred {
+                    model.starred_at = Set(starred.then_some(now));
+                }
+                if let Some(archived) = archived {
+                    model.archived = Set(archived);
+                }
+                model
+            });
+            link_state::Entity::insert_many(models)
+                .on_conflict(
+                    OnConflict::columns([
+                        link_state::Column::AccountId,
+                        link_state::Column::LinkId,
+                    ])
+                    .update_columns(update_columns.clone())
+                    .to_owned(),
+                )
+                .exec_without_returning(conn)
+                .await?;
+            affected += chunk.len() as u64;
+        }
+        Ok(affected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::schema::{
+        CreateOrUpdateCategoryRequestBuilder, CreateOrUpdateRssLinkRequestBuilder,
+        CreateOrUpdateSubscriptionRequestBuilder, MarkLinksReadRequestBuilder,
+        QueryRssLinkRequestBuilder, QuerySubscriptionRequestBuilder, UpdateLinkStateRequestBuilder,
+    };
+    use crate::feed::{CategoryController, LinkController, SubscriptionController};
+
+    #[tokio::test]
+    async fn test_read_and_starred_state() {
+        let conn = crate::test_runner::setup_database().await;
+        let account_id = 1;
+        let category = CategoryController
+            .insert_category(
+                CreateOrUpdateCategoryRequestBuilder::default()
+                    .title("科技")
+                    .account_id(account_id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let (_, subscription_id) = SubscriptionController
+            .subscribe(
+                account_id,
+                CreateOrUpdateSubscriptionRequestBuilder::default()
+                    .title("feed")
+                    .link("https://example.com/feed.xml")
+                    .category_id(category.id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let now = chrono::Utc::now().naive_utc();
+        let mut link_ids = Vec::new();
+        for i in 0..3 {
+            let req = CreateOrUpdateRssLinkRequestBuilder::default()
+                .title(format!("link {}", i))
+                .link(format!("https://example.com/{}", i))
+                .subscrption_id(subscription_id)
+                .published_at(now - chrono::Duration::hours(i))
+                .build()
+                .unwrap();
+            let (_, link) = LinkController.insert_link(req, &conn).await.unwrap();
+            link_ids.push(link.id);
+        }
+
+        let query_links = |unread_only: bool, starred_only: bool| {
+            QueryRssLinkRequestBuilder::default()
+                .account_id(account_id)
+                .unread_only(unread_only)
+                .starred_only(starred_only)
+                .build()
+                .unwrap()
+        };
+        let controller = LinkStateController;
+        let req = UpdateLinkStateRequestBuilder::default()
+            .account_id(account_id)
+            .link_ids(vec![link_ids[0]])
+            .read(true)
+            .starred(true)
+            .build()
+            .unwrap();
+        assert_eq!(controller.update_link_state(req, &conn).await.unwrap(), 1);
+        let unread = LinkController
+            .query_links(query_links(true, false), &conn)
+            .await
+            .unwrap();
+        assert_eq!(unread.data.len(), 2);
+        let starred = LinkController
+            .query_links(query_links(false, true), &conn)
+            .await
+            .unwrap();
+        assert_eq!(starred.data.len(), 1);
+        assert!(starred.data[0].is_read && starred.data[0].is_starred);
+
+        // 标记未读不影响收藏
+        let req = UpdateLinkStateRequestBuilder::default()
+            .account_id(account_id)
+            .link_ids(vec![link_ids[0]])
+            .read(false)
+            .build()
+            .unwrap();
+        controller.update_link_state(req, &conn).await.unwrap();
+        let starred = LinkController
+            .query_links(query_links(false, true), &conn)
+            .await
+            .unwrap();
+        assert!(!starred.data[0].is_read && starred.data[0].is_starred);
+
+        // 只标记半小时之前发布的文章
+        let req = MarkLinksReadRequestBuilder::default()
+            .account_id(account_id)
+            .category_id(category.id)
+            .published_before(now - chrono::Duration::minutes(30))
+            .build()
+            .unwrap();
+        assert_eq!(controller.mark_read_until(req, &conn).await.unwrap(), 2);
+        let unread = LinkController
+            .query_links(query_links(true, false), &conn)
+            .await
+            .unwrap();
+        assert_eq!(unread.data.len(), 1);
+        assert_eq!(unread.data[0].id, link_ids[0]);
+
+        let subscriptions = SubscriptionController
+            .query_subscription(
+                QuerySubscriptionRequestBuilder::default()
+                    .account_id(account_id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(subscriptions.data[0].unread_count, Some(1));
+
+        // 其他用户无法修改未订阅的文章
+        let req = UpdateLinkStateRequestBuilder::default()
+            .account_id(2)
+            .link_ids(link_ids.clone())
+            .read(true)
+            .build()
+            .unwrap();
+        assert_eq!(controller.update_link_state(req, &conn).await.unwrap(), 0);
+
+        // 没有发布时间的文章按抓取时间判断
+        let req = CreateOrUpdateRssLinkRequestBuilder::default()
+            .title("undated")
+            .link("https://example.com/undated")
+            .subscrption_id(subscription_id)
+            .build()
+            .unwrap();
+        let (_, undated) = LinkController.insert_link(req, &conn).await.unwrap();
+        assert!(undated.published_at.is_none());
+        let mark_read = |published_before: chrono::NaiveDateTime| {
+            MarkLinksReadRequestBuilder::default()
+                .account_id(account_id)
+                .subscription_id(subscription_id)
+                .published_before(published_before)
+                .build()
+                .unwrap()
+        };
+        assert_eq!(
+            controller
+                .mark_read_until(mark_read(now - chrono::Duration::minutes(30)), &conn)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            controller
+                .mark_read_until(mark_read(now + chrono::Duration::minutes(1)), &conn)
+                .await
+                .unwrap(),
+            2
+        );
+        let unread = LinkController
+            .query_links(query_links(true, false), &conn)
+            .await
+            .unwrap();
+        assert!(unread.data.is_empty());
+    }
+}
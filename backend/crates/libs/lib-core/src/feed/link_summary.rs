@@ -1,3 +1,4 @@
+use super::link_search::LinkSearchController;
 use super::schema::{
     CreateAiTokenRecordRequest, LinkMindMapRequest, LinkSummaryModel, LinkSummaryRequest,
 };
@@ -103,6 +104,10 @@ impl LinkSummaryController {
             true => new_model.update(conn).await?,
             false => new_model.insert(conn).await?,
         };
+        // 关键词参与全文搜索
+        LinkSearchController
+            .index_links_by_url(&req.link_url, conn)
+            .await?;
         Ok(updated.into())
     }
 }
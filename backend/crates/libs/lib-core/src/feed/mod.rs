@@ -1,15 +1,28 @@
 mod category_service;
+mod fever;
+mod greader;
+mod link_search;
 mod link_service;
+mod link_state;
 mod link_summary;
+mod opml;
+mod saved_link;
 pub mod schema;
 mod subscription_parse;
 mod subscription_service;
 mod subscription_update;
+mod websub;
 
 pub use category_service::CategoryController;
+pub use fever::FeverController;
+pub use greader::{greader_item_id, parse_greader_item_id, GReaderController};
 pub use lib_entity::feed_build_record::Status as SubscriptionBuildRecordStatus;
+pub use link_search::LinkSearchController;
 pub use link_service::LinkController;
+pub use link_state::LinkStateController;
 pub use link_summary::LinkSummaryController;
+pub use opml::{OpmlController, OpmlOutline};
+pub use saved_link::SavedLinkController;
 pub use schema::{CreateOrUpdateCategoryRequest, QueryCategoryRequest};
 pub use schema::{CreateOrUpdateRssLinkRequest, QueryRssLinkRequest};
 pub use schema::{
@@ -19,5 +32,6 @@ pub use schema::{
 pub use subscription_parse::SubscriptionParseController;
 pub use subscription_service::SubscriptionController;
 pub use subscription_update::SubscritionConfigController;
+pub use websub::{WebSubController, WebSubState};
 
 pub use lib_entity::feed_build_config::SourceType as SubscriptionBuildSourceType;
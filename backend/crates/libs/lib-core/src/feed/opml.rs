@@ -0,0 +1,613 @@
+use std::collections::{HashMap, HashSet};
+
+use quick_xml::events::{BytesDecl, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer, XmlVersion};
+
+use super::schema::{
+    CreateOrUpdateCategoryRequestBuilder, CreateOrUpdateSubscriptionRequestBuilder,
+    OpmlImportReport, OpmlInvalidOutline,
+};
+use super::{CategoryController, SubscriptionController};
+use crate::error::ErrorInService;
+use crate::DBConnection;
+use lib_entity::{account_subscription, feed_category, feed_subscription};
+use lib_utils::canonicalize_url;
+use sea_orm::{entity::*, query::*};
+
+// 没有放在文件夹中的订阅源, 导入到这个分类下
+const DEFAULT_CATEGORY_TITLE: &str = "未分类";
+// 分类标题的最大长度
+const CATEGORY_TITLE_MAX_LEN: usize = 32;
+
+/// OPML 中的 `<outline>`, 有 `xml_url` 的是订阅源, 否则是文件夹
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpmlOutline {
+    pub title: String,
+    pub xml_url: Option<String>,
+    pub html_url: Option<String>,
+    pub description: Option<String>,
+    pub outline_type: Option<String>,
+    pub children: Vec<OpmlOutline>,
+}
+
+impl OpmlOutline {
+    pub fn is_feed(&self) -> bool {
+        self.xml_url.is_some()
+    }
+
+    fn from_element(e: &BytesStart) -> Result<Self, ErrorInService> {
+        let mut outline = OpmlOutline::default();
+        let mut text = None;
+        for attr in e.attributes().flatten() {
+            let value = attr
+                .normalized_value(XmlVersion::Implicit1_0)
+                .map_err(|e| ErrorInService::Custom(format!("OPML 解析失败:{}", e)))?
+                .trim()
+                .to_string();
+            if value.is_empty() {
+                continue;
+            }
+            // 不同阅读器导出的属性大小写不一致
+            match attr
+                .key
+                .local_name()
+                .as_ref()
+                .to_ascii_lowercase()
+                .as_slice()
+            {
+                b"text" => text = Some(value),
+                b"title" => outline.title = value,
+                b"xmlurl" => outline.xml_url = Some(value),
+                b"htmlurl" => outline.html_url = Some(value),
+                b"description" => outline.description = Some(value),
+                b"type" => outline.outline_type = Some(value),
+                _ => {}
+            }
+        }
+        if outline.title.is_empty() {
+            outline.title = text.unwrap_or_default();
+        }
+        Ok(outline)
+    }
+}
+
+// outline 闭合后挂到父节点上
+fn attach(stack: &mut [OpmlOutline], roots: &mut Vec<OpmlOutline>, outline: OpmlOutline) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(outline),
+        None => roots.push(outline),
+    }
+}
+
+/// 解析 OPML 文件, 返回 `<body>` 下的所有 outline
+pub fn parse_opml(content: &str) -> Result<Vec<OpmlOutline>, ErrorInService> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(true);
+
+    let mut roots: Vec<OpmlOutline> = Vec::new();
+    // 还没有闭合的 outline
+    let mut stack: Vec<OpmlOutline> = Vec::new();
+    let mut is_opml = false;
+    let mut in_body = false;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => match e.local_name().as_ref() {
+                b"opml" => is_opml = true,
+                b"body" => in_body = true,
+                b"outline" if in_body => stack.push(OpmlOutline::from_element(&e)?),
+                _ => {}
+            },
+            Ok(Event::Empty(e)) if in_body && e.local_name().as_ref() == b"outline" => {
+                let outline = OpmlOutline::from_element(&e)?;
+                attach(&mut stack, &mut roots, outline);
+            }
+            Ok(Event::End(e)) => match e.local_name().as_ref() {
+                b"outline" if in_body => {
+                    if let Some(outline) = stack.pop() {
+                        attach(&mut stack, &mut roots, outline);
+                    }
+                }
+                b"body" => in_body = false,
+                _ => {}
+            },
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(ErrorInService::Custom(format!("OPML 解析失败:{}", e))),
+            _ => {}
+        }
+    }
+    if !is_opml {
+        return Err(ErrorInService::Custom("不是有效的 OPML 文件".to_string()));
+    }
+    Ok(roots)
+}
+
+/// 生成 OPML 2.0 文件
+pub fn write_opml(title: &str, outlines: &[OpmlOutline]) -> Result<String, ErrorInService> {
+    let map_err = |e: std::io::Error| ErrorInService::Custom(format!("OPML 生成失败:{}", e));
+    let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+    writer
+        .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))
+        .map_err(map_err)?;
+    let date_created = chrono::Utc::now().to_rfc2822();
+    writer
+        .create_element("opml")
+        .with_attribute(("version", "2.0"))
+        .write_inner_content(|w| {
+            w.create_element("head").write_inner_content(|w| {
+                w.create_element("title")
+                    .write_text_content(BytesText::new(title))?;
+                w.create_element("dateCreated")
+                    .write_text_content(BytesText::new(&date_created))?;
+                Ok(())
+            })?;
+            w.create_element("body").write_inner_content(|w| {
+                for outline in outlines {
+                    write_outline(w, outline)?;
+                }
+                Ok(())
+            })?;
+            Ok(())
+        })
+        .map_err(map_err)?;
+    String::from_utf8(writer.into_inner()).map_err(|e| ErrorInService::Custom(e.to_string()))
+}
+
+fn write_outline<W: std::io::Write>(
+    writer: &mut Writer<W>,
+    outline: &OpmlOutline,
+) -> std::io::Result<()> {
+    let mut attrs: Vec<(&str, &str)> = vec![("text", &outline.title), ("title", &outline.title)];
+    if let Some(xml_url) = &outline.xml_url {
+        attrs.push(("type", outline.outline_type.as_deref().unwrap_or("rss")));
+        attrs.push(("xmlUrl", xml_url));
+    }
+    if let Some(html_url) = &outline.html_url {
+        attrs.push(("htmlUrl", html_url));
+    }
+    if let Some(description) = &outline.description {
+        attrs.push(("description", description));
+    }
+    let element = writer.create_element("outline").with_attributes(attrs);
+    match outline.children.is_empty() {
+        true => element.write_empty()?,
+        false => element.write_inner_content(|w| {
+            for child in &outline.children {
+                write_outline(w, child)?;
+            }
+            Ok(())
+        })?,
+    };
+    Ok(())
+}
+
+// 展开后的订阅源, 记录所在的文件夹
+struct FlatFeed {
+    folder: Option<Vec<String>>,
+    outline: OpmlOutline,
+}
+
+// 按文件夹的层级展开, 父文件夹总是在子文件夹之前
+fn flatten_outlines(
+    outlines: Vec<OpmlOutline>,
+    path: &[String],
+    folders: &mut Vec<Vec<String>>,
+    feeds: &mut Vec<FlatFeed>,
+    invalid: &mut Vec<OpmlInvalidOutline>,
+) {
+    for mut outline in outlines {
+        if outline.is_feed() {
+            feeds.push(FlatFeed {
+                folder: (!path.is_empty()).then(|| path.to_vec()),
+                outline,
+            });
+            continue;
+        }
+        // 标记为订阅源但是没有地址
+        if outline
+            .outline_type
+            .as_deref()
+            .is_some_and(|t| t.eq_ignore_ascii_case("rss"))
+        {
+            invalid.push(OpmlInvalidOutline {
+                title: outline.title,
+                xml_url: None,
+                reason: "缺少 xmlUrl".to_string(),
+            });
+            continue;
+        }
+        let title: String = outline
+            .title
+            .trim()
+            .chars()
+            .take(CATEGORY_TITLE_MAX_LEN)
+            .collect();
+        if title.is_empty() {
+            invalid.push(OpmlInvalidOutline {
+                title: outline.title,
+                xml_url: None,
+                reason: "文件夹没有标题".to_string(),
+            });
+            continue;
+        }
+        let mut folder = path.to_vec();
+        folder.push(title);
+        folders.push(folder.clone());
+        let children = std::mem::take(&mut outline.children);
+        flatten_outlines(children, &folder, folders, feeds, invalid);
+    }
+}
+
+fn is_valid_feed_url(url: &str) -> bool {
+    let rest = url
+        .strip_prefix("http://")
+        .or_else(|| url.strip_prefix("https://"));
+    match rest {
+        Some(rest) => !rest.is_empty() && !rest.contains(char::is_whitespace),
+        None => false,
+    }
+}
+
+pub struct OpmlController;
+
+impl OpmlController {
+    /// 为用户导入 OPML, 文件夹对应用户的分类, 带有 `xmlUrl` 的条目对应订阅源
+    ///
+    /// 用户已经订阅的订阅源(按规范化的地址判断)不会重复订阅, 记录在 `duplicates` 中;
+    /// 其他用户已经添加过的订阅源会直接共享
+    pub async fn import_opml(
+        &self,
+        account_id: i64,
+        content: &str,
+        conn: &DBConnection,
+    ) -> Result<OpmlImportReport, ErrorInService> {
+        let outlines = parse_opml(content)?;
+        let mut report = OpmlImportReport::default();
+
+        let mut folders: Vec<Vec<String>> = Vec::new();
+        let mut feeds: Vec<FlatFeed> = Vec::new();
+        flatten_outlines(outlines, &[], &mut folders, &mut feeds, &mut report.invalid);
+
+        // 用户已有的分类, 按标题复用
+        let mut category_ids: HashMap<String, i64> = feed_category::Entity::find()
+            .filter(feed_category::Column::AccountId.eq(account_id))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|c| (c.title, c.id))
+            .collect();
+        let mut folder_ids: HashMap<Vec<String>, i64> = HashMap::new();
+        let category_controller = CategoryController;
+
+        for folder in folders {
+            if folder_ids.contains_key(&folder) {
+                continue;
+            }
+            let title = folder.last().cloned().unwrap_or_default();
+            let parent_id = folder_ids.get(&folder[..folder.len() - 1]).copied();
+            let id = match category_ids.get(&title) {
+                Some(id) => *id,
+                None => {
+                    let mut req = CreateOrUpdateCategoryRequestBuilder::default();
+                    req.title(title.clone()).account_id(account_id);
+                    if let Some(parent_id) = parent_id {
+                        req.parent_id(parent_id);
+                    }
+                    let created = category_controller
+                        .insert_category(req.build()?, conn)
+                        .await?;
+                    report.created_categories += 1;
+                    category_ids.insert(title, created.id);
+                    created.id
+                }
+            };
+            folder_ids.insert(folder, id);
+        }
+
+        // 所有共享的订阅源, 按规范化的地址查找
+        let shared_feeds: HashMap<String, i64> = feed_subscription::Entity::find()
+            .order_by_desc(feed_subscription::Column::Id)
+            .all(conn)
+            .await?
+            .into_iter()
+            .filter_map(|s| s.link.map(|link| (canonicalize_url(&link), s.id)))
+            .collect();
+        let subscribed_ids: HashSet<i64> = account_subscription::Entity::find()
+            .filter(account_subscription::Column::AccountId.eq(account_id))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|s| s.subscription_id)
+            .collect();
+        let mut existing_links: HashSet<String> = shared_feeds
+            .iter()
+            .filter(|(_, id)| subscribed_ids.contains(id))
+            .map(|(link, _)| link.clone())
+            .collect();
+
+        for feed in feeds {
+            let outline = feed.outline;
+            let xml_url = outline.xml_url.clone().unwrap_or_default();
+            if !is_valid_feed_url(&xml_url) {
+                report.invalid.push(OpmlInvalidOutline {
+                    title: outline.title,
+                    xml_url: Some(xml_url),
+                    reason: "xmlUrl 不是有效的 http 地址".to_string(),
+                });
+                continue;
+            }
+            let canonical_url = canonicalize_url(&xml_url);
+            if !existing_links.insert(canonical_url.clone()) {
+                report.duplicates.push(xml_url);
+                continue;
+            }
+            let category_id = match feed.folder.and_then(|f| folder_ids.get(&f).copied()) {
+                Some(id) => id,
+                None => match category_ids.get(DEFAULT_CATEGORY_TITLE) {
+                    Some(id) => *id,
+                    None => {
+                        let req = CreateOrUpdateCategoryRequestBuilder::default()
+                            .title(DEFAULT_CATEGORY_TITLE)
+                            .account_id(account_id)
+                            .build()?;
+                        let created = category_controller.insert_category(req, conn).await?;
+                        report.created_categories += 1;
+                        category_ids.insert(DEFAULT_CATEGORY_TITLE.to_string(), created.id);
+                        created.id
+                    }
+                },
+            };
+
+            let mut req = CreateOrUpdateSubscriptionRequestBuilder::default();
+            req.title(match outline.title.is_empty() {
+                true => xml_url.clone(),
+                false => outline.title.clone(),
+            });
+            req.link(xml_url.clone());
+            req.category_id(category_id);
+            // 地址写法不同的同一个订阅源也共享
+            if let Some(id) = shared_feeds.get(&canonical_url) {
+                req.id(*id);
+            }
+            if let Some(value) = outline.html_url {
+                req.site_link(value);
+            }
+            if let Some(value) = outline.description {
+                req.description(value);
+            }
+            match SubscriptionController
+                .subscribe(account_id, req.build()?, conn)
+                .await
+            {
+                Ok(_) => report.created_subscriptions += 1,
+                Err(e) => {
+                    tracing::error!("导入订阅源失败 {}: {}", xml_url, e);
+                    report.invalid.push(OpmlInvalidOutline {
+                        title: outline.title,
+                        xml_url: Some(xml_url),
+                        reason: e.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(report)
+    }
+
+    /// 导出用户的分类树和订阅源
+    pub async fn export_opml(
+        &self,
+        account_id: i64,
+        conn: &DBConnection,
+    ) -> Result<String, ErrorInService> {
+        let categories = feed_category::Entity::find()
+            .filter(feed_category::Column::AccountId.eq(account_id))
+            .order_by_desc(feed_category::Column::SortOrder)
+            .order_by_asc(feed_category::Column::Id)
+            .all(conn)
+            .await?;
+        let subscribed = account_subscription::Entity::find()
+            .filter(account_subscription::Column::AccountId.eq(account_id))
+            .order_by_desc(account_subscription::Column::SortOrder)
+            .order_by_asc(account_subscription::Column::Id)
+            .all(conn)
+            .await?;
+        let subscriptions: HashMap<i64, feed_subscription::Model> =
+            feed_subscription::Entity::find()
+                .filter(
+                    feed_subscription::Column::Id
+                        .is_in(subscribed.iter().map(|s| s.subscription_id)),
+                )
+                .all(conn)
+                .await?
+                .into_iter()
+                .map(|s| (s.id, s))
+                .collect();
+        // 用户的分类和订阅源条目, 自定义标题优先
+        let feeds: Vec<(i64, OpmlOutline)> = subscribed
+            .iter()
+            .filter_map(|item| {
+                let s = subscriptions.get(&item.subscription_id)?;
+                let outline = OpmlOutline {
+                    title: item.custom_title.clone().unwrap_or_else(|| s.title.clone()),
+                    xml_url: Some(s.link.clone()?),
+                    html_url: s.site_link.clone(),
+                    description: s.description.clone(),
+                    outline_type: Some("rss".to_string()),
+                    children: Vec::new(),
+                };
+                Some((item.category_id, outline))
+            })
+            .collect();
+
+        let category_ids: HashSet<i64> = categories.iter().map(|c| c.id).collect();
+
+        // 递归构建分类下的子分类和订阅源
+        fn build_folder(
+            category: &feed_category::Model,
+            categories: &[feed_category::Model],
+            feeds: &[(i64, OpmlOutline)],
+            depth: usize,
+        ) -> OpmlOutline {
+            let mut children: Vec<OpmlOutline> = Vec::new();
+            // 避免错误的 parent_id 形成环
+            if depth < 8 {
+                children.extend(
+                    categories
+                        .iter()
+                        .filter(|c| c.parent_id == Some(category.id) && c.id != category.id)
+                        .map(|c| build_folder(c, categories, feeds, depth + 1)),
+                );
+            }
+            children.extend(
+                feeds
+                    .iter()
+                    .filter(|(category_id, _)| *category_id == category.id)
+                    .map(|(_, outline)| outline.clone()),
+            );
+            OpmlOutline {
+                title: category.title.clone(),
+                description: category.description.clone(),
+                children,
+                ..Default::default()
+            }
+        }
+
+        let mut outlines: Vec<OpmlOutline> = categories
+            .iter()
+            .filter(|c| c.parent_id.is_none_or(|id| !category_ids.contains(&id)))
+            .map(|c| build_folder(c, &categories, &feeds, 0))
+            .collect();
+        // 分类已经不存在的订阅源放在最外层
+        outlines.extend(
+            feeds
+                .iter()
+                .filter(|(category_id, _)| !category_ids.contains(category_id))
+                .map(|(_, outline)| outline.clone()),
+        );
+        write_opml("article-crawler subscriptions", &outlines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<opml version="2.0">
+  <head><title>export</title></head>
+  <body>
+    <outline text="Tech" title="Tech">
+      <outline text="Rust" title="Rust">
+        <outline type="rss" text="Rust Blog" xmlUrl="https://blog.rust-lang.org/feed.xml" htmlUrl="https://blog.rust-lang.org/"/>
+      </outline>
+      <outline type="rss" text="HN" xmlUrl="https://news.ycombinator.com/rss"/>
+      <outline type="rss" text="HN again" xmlUrl="https://news.ycombinator.com/rss"/>
+    </outline>
+    <outline type="rss" text="Loose &amp; Free" xmlUrl="https://example.com/feed"/>
+    <outline type="rss" text="No url"/>
+    <outline type="rss" text="Bad url" xmlUrl="ftp://example.com/feed"/>
+  </body>
+</opml>"#;
+
+    #[test]
+    fn test_parse_opml() {
+        let outlines = parse_opml(OPML).unwrap();
+        assert_eq!(outlines.len(), 4);
+        assert_eq!(outlines[0].title, "Tech");
+        assert!(!outlines[0].is_feed());
+        assert_eq!(outlines[0].children[0].children[0].title, "Rust Blog");
+        assert_eq!(outlines[1].title, "Loose & Free");
+        assert_eq!(
+            outlines[1].xml_url.as_deref(),
+            Some("https://example.com/feed")
+        );
+
+        assert!(parse_opml("<html><body></body></html>").is_err());
+    }
+
+    #[test]
+    fn test_write_and_parse_opml() {
+        let outlines = vec![OpmlOutline {
+            title: "A & B".to_string(),
+            children: vec![OpmlOutline {
+                title: "feed".to_string(),
+                xml_url: Some("https://example.com/rss?a=1&b=2".to_string()),
+                outline_type: Some("rss".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let content = write_opml("test", &outlines).unwrap();
+        assert_eq!(parse_opml(&content).unwrap(), outlines);
+    }
+
+    #[tokio::test]
+    async fn test_import_and_export_opml() {
+        let conn = crate::test_runner::setup_database().await;
+
+        let report = OpmlController.import_opml(1, OPML, &conn).await.unwrap();
+        // Tech / Rust / 未分类
+        assert_eq!(report.created_categories, 3);
+        assert_eq!(report.created_subscriptions, 3);
+        assert_eq!(report.duplicates, vec!["https://news.ycombinator.com/rss"]);
+        assert_eq!(report.invalid.len(), 2);
+
+        let rust = feed_category::Entity::find()
+            .filter(feed_category::Column::Title.eq("Rust"))
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        let tech = feed_category::Entity::find()
+            .filter(feed_category::Column::Title.eq("Tech"))
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rust.parent_id, Some(tech.id));
+        assert_eq!(rust.account_id, Some(1));
+
+        // 重复导入不会创建新的数据
+        let report = OpmlController.import_opml(1, OPML, &conn).await.unwrap();
+        assert_eq!(report.created_categories, 0);
+        assert_eq!(report.created_subscriptions, 0);
+        assert_eq!(report.duplicates.len(), 4);
+
+        let exported = OpmlController.export_opml(1, &conn).await.unwrap();
+        let outlines = parse_opml(&exported).unwrap();
+        let tech_outline = outlines.iter().find(|o| o.title == "Tech").unwrap();
+        assert_eq!(tech_outline.children[0].title, "Rust");
+        assert_eq!(
+            tech_outline.children[0].children[0].xml_url.as_deref(),
+            Some("https://blog.rust-lang.org/feed.xml")
+        );
+
+        // 其他用户导入时使用自己的分类, 写法不同的同一个地址共享订阅源
+        let other = r#"<opml version="2.0"><body>
+            <outline text="Tech"><outline type="rss" text="Rust" xmlUrl="https://Blog.Rust-Lang.org/feed.xml/"/></outline>
+        </body></opml>"#;
+        let report = OpmlController.import_opml(2, other, &conn).await.unwrap();
+        assert_eq!(report.created_categories, 1);
+        assert_eq!(report.created_subscriptions, 1);
+        let count = feed_subscription::Entity::find()
+            .count(&conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+        let subscribed = account_subscription::Entity::find()
+            .filter(account_subscription::Column::AccountId.eq(2))
+            .all(&conn)
+            .await
+            .unwrap();
+        assert_eq!(subscribed.len(), 1);
+
+        // 只导出自己的订阅
+        let outlines = parse_opml(&OpmlController.export_opml(2, &conn).await.unwrap()).unwrap();
+        assert_eq!(outlines.len(), 1);
+        assert_eq!(outlines[0].children.len(), 1);
+        assert_eq!(
+            outlines[0].children[0].xml_url.as_deref(),
+            Some("https://blog.rust-lang.org/feed.xml")
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use super::schema::{
+    Image, QuerySavedLinkRequest, SaveLinkRequest, SavedLinkExportFormat, SavedLinkModel,
+};
+use super::subscription_service::subscribed_ids_query;
+use crate::common_schema::{PageRequestBuilder, PageResponse};
+use crate::error::ErrorInService;
+use crate::DBConnection;
+use lib_entity::{feed_link, saved_link};
+use lib_utils::markdown::{escape_html, markdown_images, markdown_to_html};
+use lib_utils::math::{get_page_count, get_page_offset};
+use sea_orm::{entity::*, query::*};
+
+pub struct SavedLinkController;
+
+impl SavedLinkController {
+    /// 保存文章快照, 同一个用户重复保存同一篇文章时更新快照、标签和笔记
+    ///
+    /// 关联了订阅源中文章的快照, 原文章不会被过期清理
+    pub async fn save_link(
+        &self,
+        req: SaveLinkRequest,
+        conn: &DBConnection,
+    ) -> Result<SavedLinkModel, ErrorInService> {
+        let account_id = req
+            .account_id
+            .ok_or(ErrorInService::Custom("account_id is required".to_string()))?;
+        // 只能保存自己订阅的文章
+        let source = match req.link_id {
+            Some(link_id) => Some(
+                feed_link::Entity::find_by_id(link_id)
+                    .filter(
+                        feed_link::Column::SubscriptionId
+                            .in_subquery(subscribed_ids_query(account_id, None)),
+                    )
+                    .one(conn)
+                    .await?
+                    .ok_or(ErrorInService::Custom("link not found".to_string()))?,
+            ),
+            None => None,
+        };
+        let link = match (&source, &req.link) {
+            (Some(source), _) => source.link.clone(),
+            (None, Some(link)) if !link.trim().is_empty() => link.trim().to_string(),
+            _ => return Err(ErrorInService::Custom("link is required".to_string())),
+        };
+
+        let existing = saved_link::Entity::find()
+            .filter(saved_link::Column::AccountId.eq(account_id))
+            .filter(saved_link::Column::Link.eq(link.clone()))
+            .one(conn)
+            .await?;
+        let is_update = existing.is_some();
+        let mut model = match existing {
+            Some(m) => m.into_active_model(),
+            None => saved_link::ActiveModel {
+                account_id: Set(account_id),
+                link: Set(link.clone()),
+                title: Set(link.clone()),
+                ..Default::default()
+            },
+        };
+        if let Some(source) = &source {
+            model.link_id = Set(Some(source.id));
+            if !is_update {
+                model.title = Set(source.title.clone());
+                model.images = Set(Some(serde_json::json!(source_images(source))));
+            }
+        }
+        if let Some(title) = req.title.filter(|t| !t.trim().is_empty()) {
+            model.title = Set(title);
+        }
+        if let Some(content) = req.content {
+            // 快照中的图片排在订阅源提供的图片后面
+            let mut images = match (&source, &model.images) {
+                (Some(source), _) => source_images(source),
+                (None, ActiveValue::Unchanged(Some(value))) => {
+                    serde_json::from_value::<Vec<String>>(value.clone()).unwrap_or_default()
+                }
+                _ => Vec::new(),
+            };
+            for image in markdown_images(&content) {
+                if !images.contains(&image) {
+                    images.push(image);
+                }
+            }
+            model.images = Set(Some(serde_json::json!(images)));
+            model.content = Set(Some(content));
+        }
+        if let Some(tags) = req.tags {
+            let mut unique: Vec<String> = Vec::new();
+            for tag in tags.iter().map(|t| t.trim()).filter(|t| !t.is_empty()) {
+                if !unique.iter().any(|t| t == tag) {
+                    unique.push(tag.to_string());
+                }
+            }
+            model.tags = Set(Some(serde_json::json!(unique)));
+        }
+        if let Some(notes) = req.notes {
+            model.notes = Set(Some(notes));
+        }
+        let saved = match is_update {
+            true => {
+                model.updated_at = Set(chrono::Utc::now().naive_utc());
+                model.update(conn).await?
+            }
+            false => model.insert(conn).await?,
+        };
+        Ok(saved.into())
+    }
+
+    pub async fn remove_saved_link(
+        &self,
+        account_id: i64,
+        id: i64,
+        conn: &DBConnection,
+    ) -> Result<bool, ErrorInService> {
+        let res = saved_link::Entity::delete_many()
+            .filter(saved_link::Column::AccountId.eq(account_id))
+            .filter(saved_link::Column::Id.eq(id))
+            .exec(conn)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    pub async fn query_saved_links(
+        &self,
+        req: QuerySavedLinkRequest,
+        conn: &DBConnection,
+    ) -> Result<PageResponse<SavedLinkModel>, ErrorInService> {
+        let select = Self::build_query(&req)?;
+        let page_info = req
+            .page
+            .clone()
+            .unwrap_or(PageRequestBuilder::default().build().unwrap());
+        let page_size = page_info.page_size;
+        let page = page_info.page;
+        let all_count = select.clone().count(conn).await?;
+        let models: Vec<SavedLinkModel> = select
+            .order_by_desc(saved_link::Column::CreatedAt)
+            .order_by_desc(saved_link::Column::Id)
+            .limit(page_size)
+            .offset(get_page_offset(page, page_size))
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|m| m.into())
+            .collect();
+        Ok(PageResponse::new(
+            get_page_count(all_count, page_size),
+            page,
+            page_size,
+            models,
+        ))
+    }
+
+    /// 导出用户保存的所有文章
+    pub async fn export_saved_links(
+        &self,
+        account_id: i64,
+        format: SavedLinkExportFormat,
+        conn: &DBConnection,
+    ) -> Result<String, ErrorInService> {
+        let models: Vec<SavedLinkModel> = saved_link::Entity::find()
+            .filter(saved_link::Column::AccountId.eq(account_id))
+            .order_by_desc(saved_link::Column::CreatedAt)
+            .order_by_desc(saved_link::Column::Id)
+            .all(conn)
+            .await?
+            .into_iter()
+            .map(|m| m.into())
+            .collect();
+        let content = match format {
+            SavedLinkExportFormat::Markdown => render_markdown(&models),
+            SavedLinkExportFormat::Html => render_html(&models),
+        };
+        Ok(content)
+    }
+
+    fn build_query(
+        req: &QuerySavedLinkRequest,
+    ) -> Result<Select<saved_link::Entity>, ErrorInService> {
+        let account_id = req
+            .account_id
+            .ok_or(ErrorInService::Custom("account_id is required".to_string()))?;
+        let mut select =
+            saved_link::Entity::find().filter(saved_link::Column::AccountId.eq(account_id));
+        if let Some(title) = &req.title {
+            select = select.filter(saved_link::Column::Title.like(format!("%{}%", title)));
+        }
+        // 标签以 json 数组保存, 按带引号的完整标签匹配
+        if let Some(tag) = &req.tag {
+            let quoted = serde_json::to_string(tag).unwrap_or_default();
+            select = select.filter(saved_link::Column::Tags.like(format!("%{}%", quoted)));
+        }
+        Ok(select)
+    }
+}
+
+// 订阅源提供的图片地址
+fn source_images(source: &feed_link::Model) -> Vec<String> {
+    source
+        .images
+        .clone()
+        .and_then(|v| serde_json::from_value::<Vec<Image>>(v).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .map(|i| i.url)
+        .collect()
+}
+
+fn saved_at(model: &SavedLinkModel) -> String {
+    model.created_at.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn render_markdown(models: &[SavedLinkModel]) -> String {
+    let mut output = String::from("# 收藏的文章\n");
+    for model in models {
+        output.push_str(&format!("\n## [{}]({})\n\n", model.title, model.link));
+        output.push_str(&format!("- 保存时间: {}\n", saved_at(model)));
+        if !model.tags.is_empty() {
+            output.push_str(&format!("- 标签: {}\n", model.tags.join(", ")));
+        }
+        if let Some(notes) = model.notes.as_ref().filter(|n| !n.trim().is_empty()) {
+            output.push('\n');
+            for line in notes.lines() {
+                output.push_str(&format!("> {}\n", line));
+            }
+        }
+        if let Some(content) = &model.content {
+            output.push_str(&format!("\n{}\n", content.trim()));
+        }
+        output.push_str("\n---\n");
+    }
+    output
+}
+
+fn render_html(models: &[SavedLinkModel]) -> String {
+    let mut output = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\" />\n\
+        <title>收藏的文章</title>\n</head>\n<body>\n<h1>收藏的文章</h1>\n",
+    );
+    for model in models {
+        output.push_str("<article>\n");
+        output.push_str(&format!(
+            "<h2><a href=\"{}\">{}</a></h2>\n",
+            escape_html(&model.link),
+            escape_html(&model.title)
+        ));
+        output.push_str(&format!("<p>保存时间: {}", saved_at(model)));
+        if !model.tags.is_empty() {
+            output.push_str(&format!(" · 标签: {}", escape_html(&model.tags.join(", "))));
+        }
+        output.push_str("</p>\n");
+        if let Some(notes) = model.notes.as_ref().filter(|n| !n.trim().is_empty()) {
+            output.push_str(&format!(
+                "<blockquote>{}</blockquote>\n",
+                escape_html(notes).replace('\n', "<br />")
+            ));
+        }
+        if let Some(content) = &model.content {
+            output.push_str(&markdown_to_html(content));
+        }
+        output.push_str("</article>\n");
+    }
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::link_search::LinkSearchController;
+    use crate::feed::schema::{
+        CreateOrUpdateCategoryRequestBuilder, CreateOrUpdateRssLinkRequestBuilder,
+        CreateOrUpdateSubscriptionRequestBuilder, SaveLinkRequestBuilder, SearchLinkRequest,
+    };
+    use crate::feed::{CategoryController, LinkController, SubscriptionController};
+    use sea_orm::{DbBackend, Statement};
+
+    #[tokio::test]
+    async fn test_saved_link_survives_expiry() {
+        let conn = crate::test_runner::setup_database().await;
+        let account_id = 1;
+        let category = CategoryController
+            .insert_category(
+                CreateOrUpdateCategoryRequestBuilder::default()
+                    .title("科技")
+                    .account_id(account_id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let (_, subscription_id) = SubscriptionController
+            .subscribe(
+                account_id,
+                CreateOrUpdateSubscriptionRequestBuilder::default()
+                    .title("feed")
+                    .link("https://example.com/feed.xml")
+                    .category_id(category.id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let published_at = chrono::Utc::now().naive_utc() - chrono::Duration::days(200);
+        let mut link_ids = Vec::new();
+        for i in 0..2 {
+            let req = CreateOrUpdateRssLinkRequestBuilder::default()
+                .title(format!("old {}", i))
+                .link(format!("https://example.com/old/{}", i))
+                .subscrption_id(subscription_id)
+                .published_at(published_at)
+                .build()
+                .unwrap();
+            let (_, link) = LinkController.insert_link(req, &conn).await.unwrap();
+            link_ids.push(link.id);
+        }
+
+        let controller = SavedLinkController;
+        let req = SaveLinkRequestBuilder::default()
+            .account_id(account_id)
+            .link_id(link_ids[0])
+            .content("正文\n\n![图](https://example.com/a.png)")
+            .tags(vec!["rust".to_string(), "rust".to_string()])
+            .notes("值得再读")
+            .build()
+            .unwrap();
+        let saved = controller.save_link(req, &conn).await.unwrap();
+        assert_eq!(saved.title, "old 0");
+        assert_eq!(saved.tags, vec!["rust".to_string()]);
+        assert_eq!(saved.images, vec!["https://example.com/a.png".to_string()]);
+
+        // 再次保存只更新笔记
+        let req = SaveLinkRequestBuilder::default()
+            .account_id(account_id)
+            .link_id(link_ids[0])
+            .notes("已读完")
+            .build()
+            .unwrap();
+        let updated = controller.save_link(req, &conn).await.unwrap();
+        assert_eq!(updated.id, saved.id);
+        assert_eq!(updated.content, saved.content);
+        assert_eq!(updated.notes.as_deref(), Some("已读完"));
+
+        let expired_at = chrono::Utc::now().naive_utc() - chrono::Duration::days(180);
+        let removed = LinkController
+            .remove_expired_links(expired_at, &conn)
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+        assert!(feed_link::Entity::find_by_id(link_ids[0])
+            .one(&conn)
+            .await
+            .unwrap()
+            .is_some());
+        // 过期文章的全文索引一起删除
+        let req = SearchLinkRequest {
+            query: "old".to_string(),
+            subscrption_ids: None,
+            account_id: None,
+            page: None,
+        };
+        let hits = LinkSearchController.search(req, &conn).await.unwrap();
+        assert_eq!(
+            hits.data.iter().map(|h| h.link.id).collect::<Vec<_>>(),
+            vec![link_ids[0]]
+        );
+        let indexed = conn
+            .query_one(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                "SELECT COUNT(*) AS count FROM feed_link_fts WHERE rowid = ?",
+                [link_ids[1].into()],
+            ))
+            .await
+            .unwrap()
+            .unwrap()
+            .try_get::<i64>("", "count")
+            .unwrap();
+        assert_eq!(indexed, 0);
+
+        let markdown = controller
+            .export_saved_links(account_id, SavedLinkExportFormat::Markdown, &conn)
+            .await
+            .unwrap();
+        assert!(markdown.contains("## [old 0](https://example.com/old/0)"));
+        assert!(markdown.contains("> 已读完"));
+        let html = controller
+            .export_saved_links(account_id, SavedLinkExportFormat::Html, &conn)
+            .await
+            .unwrap();
+        assert!(html.contains("<img src=\"https://example.com/a.png\" alt=\"图\" />"));
+
+        let query = QuerySavedLinkRequest {
+            account_id: Some(account_id),
+            tag: Some("rust".to_string()),
+            ..Default::default()
+        };
+        let page = controller.query_saved_links(query, &conn).await.unwrap();
+        assert_eq!(page.data.len(), 1);
+        assert!(controller
+            .remove_saved_link(account_id, saved.id, &conn)
+            .await
+            .unwrap());
+    }
+}
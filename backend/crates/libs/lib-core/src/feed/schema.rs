@@ -1,10 +1,12 @@
 use crate::common_schema::PageRequest;
 use crate::error::ErrorInService;
 use crate::feed::SubscriptionBuildSourceType;
+use chrono::naive::serde::ts_milliseconds::serialize as to_milli_ts;
 use chrono::naive::serde::ts_milliseconds_option;
 use chrono::NaiveDateTime;
 use lib_crawler::{try_get_all_image_from_html_content, try_get_all_text_from_html_content};
-use lib_entity::{feed_build_record, feed_category};
+pub use lib_crawler::{DiscoveredFeed, FeedDiscovery, FeedFormat, FeedValidators, WebSubHub};
+use lib_entity::{feed_build_record, feed_category, saved_link};
 use sea_orm::FromQueryResult;
 use serde::{Deserialize, Serialize};
 
@@ -57,6 +59,9 @@ pub struct SubscriptionModel {
     // 排序序列
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sort_order: Option<i32>,
+    // 用户未读的文章数量
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unread_count: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub links: Option<Vec<LinkModel>>,
 }
@@ -93,6 +98,7 @@ impl FromQueryResult for SubscriptionModel {
             .subscribed_at(res.try_get(pre, "subscribed_at").unwrap_or(None))
             .custom_title(res.try_get(pre, "custom_title").unwrap_or(None))
             .sort_order(res.try_get(pre, "sort_order").unwrap_or(None))
+            .unread_count(res.try_get(pre, "unread_count").unwrap_or(None))
             .links(link_models)
             .build()
             .map_err(|e| {
@@ -165,6 +171,26 @@ pub struct LinkModel {
     #[serde(deserialize_with = "deserialize_images_json")]
     #[builder(default)]
     pub images: Option<Vec<Image>>,
+    // 标签
+    #[serde(default)]
+    #[builder(default)]
+    pub tags: Option<Vec<String>>,
+    // 近似重复文章的分组
+    #[serde(default)]
+    #[builder(default)]
+    pub cluster_id: Option<i64>,
+    // 折叠重复文章时, 同组中其他文章的数量
+    #[serde(default)]
+    #[builder(default)]
+    pub duplicate_count: u64,
+    // 当前用户是否已读
+    #[serde(default)]
+    #[builder(default)]
+    pub is_read: bool,
+    // 当前用户是否收藏
+    #[serde(default)]
+    #[builder(default)]
+    pub is_starred: bool,
 }
 
 impl From<lib_entity::feed_link::Model> for LinkModel {
@@ -183,6 +209,7 @@ impl From<lib_entity::feed_link::Model> for LinkModel {
                 .to_string(),
         )
         .unwrap_or(None);
+        let tags: Option<Vec<String>> = value.tags.and_then(|t| serde_json::from_value(t).ok());
         let description = value.description.unwrap_or("".to_string());
         let text_desc = value.desc_pure_txt.unwrap_or("".to_string());
 
@@ -196,6 +223,11 @@ impl From<lib_entity::feed_link::Model> for LinkModel {
             published_at: value.published_at,
             authors,
             images,
+            tags,
+            cluster_id: value.cluster_id,
+            duplicate_count: 0,
+            is_read: false,
+            is_starred: false,
         }
     }
 }
@@ -209,6 +241,10 @@ impl FromQueryResult for LinkModel {
             serde_json::from_str(&res.try_get::<String>(pre, "authors")?).unwrap_or(None);
         let images: Option<Vec<Image>> =
             serde_json::from_str(&res.try_get::<String>(pre, "images")?).unwrap_or(None);
+        let tags: Option<Vec<String>> = res
+            .try_get::<Option<String>>(pre, "tags")
+            .unwrap_or(None)
+            .and_then(|t| serde_json::from_str(&t).ok());
         let description = res
             .try_get::<String>(pre, "description")
             .unwrap_or("".to_string());
@@ -228,6 +264,8 @@ impl FromQueryResult for LinkModel {
             .published_at(res.try_get(pre, "published_at").unwrap_or(None))
             .authors(authors)
             .images(images)
+            .tags(tags)
+            .cluster_id(res.try_get(pre, "cluster_id").unwrap_or(None))
             .build()
             .map_err(|e| sea_orm::prelude::DbErr::Custom(format!("build LinkModel error:{}", e)))?;
 
@@ -294,24 +332,15 @@ pub struct LinkSummaryModel {
 impl From<lib_entity::feed_link_summary::Model> for LinkSummaryModel {
     fn from(value: lib_entity::feed_link_summary::Model) -> Self {
         // parse str to array
-        let key_points = match serde_json::from_str::<Vec<String>>(
-            &value.key_points.unwrap_or("[]".to_string()),
-        ) {
-            Ok(key_points) => key_points,
-            Err(_) => Vec::new(),
-        };
-        let action_items = match serde_json::from_str::<Vec<String>>(
-            &value.action_items.unwrap_or("[]".to_string()),
-        ) {
-            Ok(action_items) => action_items,
-            Err(_) => Vec::new(),
-        };
-        let keywords = match serde_json::from_str::<Vec<String>>(
-            &value.keywords.unwrap_or("[]".to_string()),
-        ) {
-            Ok(keywords) => keywords,
-            Err(_) => Vec::new(),
-        };
+        let key_points =
+            serde_json::from_str::<Vec<String>>(&value.key_points.unwrap_or("[]".to_string()))
+                .unwrap_or_default();
+        let action_items =
+            serde_json::from_str::<Vec<String>>(&value.action_items.unwrap_or("[]".to_string()))
+                .unwrap_or_default();
+        let keywords =
+            serde_json::from_str::<Vec<String>>(&value.keywords.unwrap_or("[]".to_string()))
+                .unwrap_or_default();
         let summary = match value.summary {
             Some(summary) => summary,
             None => "".to_string(),
@@ -419,6 +448,9 @@ pub struct CreateOrUpdateCategoryRequest {
     // 驼峰命名法
     #[serde(rename(deserialize = "sortOrder"))]
     pub sort_order: Option<i64>,
+    // 所属用户, 由服务端根据登录信息填充
+    #[serde(skip)]
+    pub account_id: Option<i64>,
 }
 
 // 查找分类的请求
@@ -437,6 +469,9 @@ pub struct QueryCategoryRequest {
     pub parent_ids: Option<Vec<i64>>,
     // 是否需要订阅源的链接，需要几个
     pub need_feed_logo_count: Option<u64>,
+    // 只查找该用户的分类
+    #[serde(skip)]
+    pub account_id: Option<i64>,
     // 分页
     pub page: Option<PageRequest>,
 }
@@ -476,6 +511,8 @@ pub struct CreateOrUpdateSubscriptionRequest {
     pub last_build_date: Option<NaiveDateTime>,
     // 排序序列
     pub sort_order: Option<i32>,
+    // 用户自定义的标题, 只在用户自己的订阅中生效
+    pub custom_title: Option<String>,
 }
 
 impl From<lib_entity::feed_subscription::Model> for CreateOrUpdateSubscriptionRequest {
@@ -531,10 +568,20 @@ pub struct QuerySubscriptionRequest {
     pub category_id: Option<i64>,
     // 语言
     pub language: Option<Vec<String>>,
+    // 只查找该用户订阅的订阅源
+    #[serde(skip)]
+    pub account_id: Option<i64>,
 
     pub page: Option<PageRequest>,
 }
 
+// 取消订阅的请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct UnsubscribeRequest {
+    // 订阅源Id
+    pub subscription_id: i64,
+}
+
 // 订阅源的请求 + 链接的请求 = 订阅源的响应
 
 #[derive(Debug, Clone, Deserialize, Default, Builder)]
@@ -544,6 +591,89 @@ pub struct QuerySubscriptionRequest {
 pub struct SubscriptionWithLinksResp {
     pub subscription: CreateOrUpdateSubscriptionRequest,
     pub links: Vec<CreateOrUpdateRssLinkRequest>,
+    // 本次拉取的缓存校验信息
+    pub validators: FeedValidators,
+    // 订阅源声明的 WebSub hub
+    #[serde(skip)]
+    pub websub: Option<WebSubHub>,
+}
+
+// WebSub 推送订阅的参数
+#[derive(Debug, Clone)]
+pub struct WebSubOption {
+    // hub 回调使用的外部地址, 不包含 `/websub/callback`
+    pub callback_base: String,
+    // 期望的租期, 单位秒
+    pub lease_seconds: i64,
+    // 租期到期前多久续订, 单位秒
+    pub renew_before_secs: i64,
+    // 推送生效时的兜底拉取频率, 单位分钟
+    pub poll_frequency: f32,
+    // 是否允许请求本机和内网地址的 hub, 只应在测试中打开
+    pub allow_private: bool,
+}
+
+impl WebSubOption {
+    /// 未开启或没有配置回调地址时返回 None
+    pub fn from_setting(setting: &lib_utils::WebSub) -> Option<Self> {
+        let callback_base = setting.callback_base.as_ref().filter(|_| setting.enabled)?;
+        Some(Self {
+            callback_base: callback_base.trim_end_matches('/').to_string(),
+            lease_seconds: setting.lease_seconds,
+            renew_before_secs: setting.renew_before_secs,
+            poll_frequency: setting.poll_frequency as f32,
+            allow_private: false,
+        })
+    }
+
+    // hub 验证订阅意图和推送内容的地址
+    pub fn callback(&self, subscription_id: i64) -> String {
+        format!("{}/websub/callback/{}", self.callback_base, subscription_id)
+    }
+}
+
+// 订阅源解析的结果
+#[derive(Debug, Clone)]
+pub enum SubscriptionParseResult {
+    Modified(Box<SubscriptionWithLinksResp>),
+    // 订阅源自上次拉取后没有变化 (304)
+    Unchanged,
+}
+
+// 导入 OPML 的请求
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImportOpmlRequest {
+    // OPML 文件的内容
+    pub content: String,
+}
+
+// OPML 中无法导入的条目
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct OpmlInvalidOutline {
+    pub title: String,
+    pub xml_url: Option<String>,
+    // 无法导入的原因
+    pub reason: String,
+}
+
+// 导入 OPML 的结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OpmlImportReport {
+    // 新建的分类数量
+    pub created_categories: u64,
+    // 新建的订阅源数量
+    pub created_subscriptions: u64,
+    // 已经存在的订阅源地址
+    pub duplicates: Vec<String>,
+    // 无法导入的条目
+    pub invalid: Vec<OpmlInvalidOutline>,
+}
+
+// 根据页面地址查找订阅源
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiscoverSubscriptionRequest {
+    // 任意页面地址, 也可以直接是订阅源地址
+    pub url: String,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -570,6 +700,10 @@ pub struct InsertSubscriptionRecordRequest {
     pub subscription_id: i64,
     // 状态 表示订阅源此次更新的状态， 成功 / 失败 / 其他 如果是失败，需要记录失败原因
     pub status: feed_build_record::Status,
+    // 备注
+    pub remark: Option<String>,
+    // 失败原因的分类
+    pub reason: Option<String>,
     // 创建时间
     pub create_time: Option<NaiveDateTime>,
 }
@@ -582,6 +716,8 @@ pub struct QuerySubscriptionRecordRequest {
     pub subscription_ids: Option<Vec<i64>>,
     // 状态列表
     pub status: Option<Vec<feed_build_record::Status>>,
+    // 失败原因的分类列表
+    pub reasons: Option<Vec<String>>,
     // 时间范围 低值  毫秒 13 位
     #[serde(default, with = "ts_milliseconds_option")]
     pub create_time_lower: Option<NaiveDateTime>,
@@ -652,6 +788,8 @@ pub struct CreateOrUpdateRssLinkRequest {
     pub subscrption_id: i64,
     // 链接
     pub link: String,
+    // 订阅源提供的唯一标识
+    pub guid: Option<String>,
     // 描述
     pub description: Option<String>,
     // 纯文本描述
@@ -662,6 +800,22 @@ pub struct CreateOrUpdateRssLinkRequest {
     pub authors: Option<Vec<Author>>,
     // 图片
     pub images: Option<Vec<Image>>,
+    // 标签
+    pub tags: Option<Vec<String>>,
+}
+
+// 补全链接正文和图片的请求, 只修改提供的字段
+#[derive(Debug, Clone, Default, Builder)]
+#[builder(setter(into, strip_option), default)]
+#[builder(derive(Debug))]
+#[builder(build_fn(error = "ErrorInService"))]
+pub struct UpdateLinkContentRequest {
+    // 链接 id
+    pub id: i64,
+    // 解析出的正文(html), 只有链接没有描述时才使用
+    pub description: Option<String>,
+    // 图片
+    pub images: Option<Vec<Image>>,
 }
 
 // 构建查找链接的请求
@@ -687,10 +841,187 @@ pub struct QueryRssLinkRequest {
     #[serde(default)]
     #[serde(with = "ts_milliseconds_option")]
     pub published_at_upper: Option<NaiveDateTime>,
+    // 近似重复的文章只返回一篇
+    pub collapse_duplicates: Option<bool>,
+    // 只查找该用户订阅的订阅源下的文章
+    #[serde(skip)]
+    pub account_id: Option<i64>,
+    // 只返回未读的文章, 需要登录
+    pub unread_only: Option<bool>,
+    // 只返回收藏的文章, 需要登录
+    pub starred_only: Option<bool>,
+    // 分页信息
+    pub page: Option<PageRequest>,
+}
+
+// 修改文章阅读状态的请求, 为空的字段保持不变
+#[derive(Debug, Clone, Deserialize, Default, Builder)]
+#[builder(setter(into, strip_option), default)]
+#[builder(derive(Debug))]
+#[builder(build_fn(error = "ErrorInService"))]
+pub struct UpdateLinkStateRequest {
+    #[serde(skip)]
+    pub account_id: Option<i64>,
+    // 文章 ids
+    pub link_ids: Vec<i64>,
+    // 已读 / 未读
+    pub read: Option<bool>,
+    // 收藏 / 取消收藏
+    pub starred: Option<bool>,
+    // 归档 / 取消归档
+    pub archived: Option<bool>,
+}
+
+// 把订阅源或分类下某个时间之前的文章标记为已读
+#[derive(Debug, Clone, Deserialize, Default, Builder)]
+#[builder(setter(into, strip_option), default)]
+#[builder(derive(Debug))]
+#[builder(build_fn(error = "ErrorInService"))]
+pub struct MarkLinksReadRequest {
+    #[serde(skip)]
+    pub account_id: Option<i64>,
+    // 订阅源Id
+    pub subscription_id: Option<i64>,
+    // 用户的分类Id
+    pub category_id: Option<i64>,
+    // 发布时间不晚于该时间的文章, 毫秒 13 位, 默认为当前时间
+    #[builder(default = "Option::None")]
+    #[serde(default)]
+    #[serde(with = "ts_milliseconds_option")]
+    pub published_before: Option<NaiveDateTime>,
+}
+
+// 保存文章的请求, 同一篇文章重复保存时更新快照、标签和笔记
+#[derive(Debug, Clone, Deserialize, Default, Builder)]
+#[builder(setter(into, strip_option), default)]
+#[builder(derive(Debug))]
+#[builder(build_fn(error = "ErrorInService"))]
+pub struct SaveLinkRequest {
+    #[serde(skip)]
+    pub account_id: Option<i64>,
+    // 订阅源中的文章 id
+    pub link_id: Option<i64>,
+    // 文章地址, 不是订阅源中的文章时必填
+    pub link: Option<String>,
+    // 标题
+    pub title: Option<String>,
+    // 正文的 Markdown, 为空时由服务端解析
+    pub content: Option<String>,
+    // 标签
+    pub tags: Option<Vec<String>>,
+    // 笔记
+    pub notes: Option<String>,
+}
+
+// 保存的文章
+#[derive(Debug, Clone, Serialize)]
+pub struct SavedLinkModel {
+    pub id: i64,
+    pub link_id: Option<i64>,
+    pub link: String,
+    pub title: String,
+    // 正文的 Markdown 快照
+    pub content: Option<String>,
+    pub images: Vec<String>,
+    pub tags: Vec<String>,
+    pub notes: Option<String>,
+    #[serde(serialize_with = "to_milli_ts")]
+    pub created_at: NaiveDateTime,
+    #[serde(serialize_with = "to_milli_ts")]
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<saved_link::Model> for SavedLinkModel {
+    fn from(value: saved_link::Model) -> Self {
+        Self {
+            id: value.id,
+            link_id: value.link_id,
+            link: value.link,
+            title: value.title,
+            content: value.content,
+            images: value
+                .images
+                .and_then(|v| serde_json::from_value(v).ok())
+                .unwrap_or_default(),
+            tags: value
+                .tags
+                .and_then(|v| serde_json::from_value(v).ok())
+                .unwrap_or_default(),
+            notes: value.notes,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+        }
+    }
+}
+
+// 查找保存的文章
+#[derive(Debug, Clone, Deserialize, Default, Builder)]
+#[builder(setter(into, strip_option), default)]
+#[builder(derive(Debug))]
+#[builder(build_fn(error = "ErrorInService"))]
+pub struct QuerySavedLinkRequest {
+    #[serde(skip)]
+    pub account_id: Option<i64>,
+    // 标题
+    pub title: Option<String>,
+    // 标签
+    pub tag: Option<String>,
     // 分页信息
     pub page: Option<PageRequest>,
 }
 
+// 删除保存的文章
+#[derive(Debug, Clone, Deserialize)]
+pub struct RemoveSavedLinkRequest {
+    pub id: i64,
+}
+
+// 导出保存的文章的格式
+#[derive(Debug, Clone, Copy, Deserialize, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SavedLinkExportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+// 导出保存的文章
+#[derive(Debug, Clone, Deserialize, Default)]
+pub struct ExportSavedLinksRequest {
+    #[serde(default)]
+    pub format: SavedLinkExportFormat,
+}
+
+// 全文搜索文章的请求
+#[derive(Debug, Clone, Deserialize, Default, Builder)]
+#[builder(setter(into, strip_option), default)]
+#[builder(derive(Debug))]
+#[builder(build_fn(error = "ErrorInService"))]
+pub struct SearchLinkRequest {
+    // 搜索词, 多个词之间用空格分隔, 双引号包含的是短语
+    pub query: String,
+    // 订阅源 ids
+    pub subscrption_ids: Option<Vec<i64>>,
+    // 只搜索该用户订阅的订阅源下的文章
+    #[serde(skip)]
+    pub account_id: Option<i64>,
+    // 分页信息
+    pub page: Option<PageRequest>,
+}
+
+// 全文搜索的结果
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkSearchHit {
+    #[serde(flatten)]
+    pub link: LinkModel,
+    // 相关度, 越大越相关
+    pub score: f64,
+    // 高亮后的标题, 匹配的部分使用 <mark> 包裹
+    pub title_highlight: String,
+    // 内容中匹配的片段
+    pub snippet: String,
+}
+
 // 构建总结文章的请求
 #[derive(Debug, Deserialize)]
 pub struct LinkSummaryRequest {
@@ -718,6 +1049,216 @@ pub struct CreateAiTokenRecordRequest {
     pub remark: Option<String>,
 }
 
+// Fever API 的分组, 对应用户的分类
+#[derive(Debug, Clone, Serialize)]
+pub struct FeverGroup {
+    pub id: i64,
+    pub title: String,
+}
+
+// Fever API 的订阅源
+#[derive(Debug, Clone, Serialize)]
+pub struct FeverFeed {
+    pub id: i64,
+    pub favicon_id: i64,
+    pub title: String,
+    pub url: String,
+    pub site_url: String,
+    pub is_spark: i32,
+    // 秒级时间戳
+    pub last_updated_on_time: i64,
+}
+
+// Fever API 中分组和订阅源的对应关系, feed_ids 以逗号分隔
+#[derive(Debug, Clone, Serialize)]
+pub struct FeverFeedsGroup {
+    pub group_id: i64,
+    pub feed_ids: String,
+}
+
+// Fever API 的文章
+#[derive(Debug, Clone, Serialize)]
+pub struct FeverItem {
+    pub id: i64,
+    pub feed_id: i64,
+    pub title: String,
+    pub author: String,
+    pub html: String,
+    pub url: String,
+    pub is_saved: i32,
+    pub is_read: i32,
+    // 秒级时间戳
+    pub created_on_time: i64,
+}
+
+// Fever API 查询文章的参数, 每次最多返回 50 篇
+#[derive(Debug, Clone, Default)]
+pub struct FeverItemsRequest {
+    // 返回 id 大于 since_id 的文章, 升序
+    pub since_id: Option<i64>,
+    // 返回 id 小于 max_id 的文章, 降序
+    pub max_id: Option<i64>,
+    // 返回指定 id 的文章
+    pub with_ids: Vec<i64>,
+}
+
+// Fever API 标记的对象
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeverMarkTarget {
+    Item,
+    Feed,
+    Group,
+}
+
+// Fever API 标记的状态, 收藏对应文章的 starred 状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeverMarkAs {
+    Read,
+    Unread,
+    Saved,
+    Unsaved,
+}
+
+// Fever API 的标记请求
+#[derive(Debug, Clone)]
+pub struct FeverMarkRequest {
+    pub target: FeverMarkTarget,
+    pub mark_as: FeverMarkAs,
+    pub id: i64,
+    // 订阅源和分组标记已读时, 只标记该时间之前的文章
+    pub before: Option<NaiveDateTime>,
+}
+
+// Google Reader API 的流, 即文章列表的来源
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GReaderStream {
+    // user/-/state/com.google/reading-list
+    ReadingList,
+    // user/-/state/com.google/starred
+    Starred,
+    // feed/{id}
+    Feed(i64),
+    // user/-/label/{title}, 对应用户的分类
+    Label(String),
+}
+
+// Google Reader API 中的分类或标签
+#[derive(Debug, Clone, Serialize)]
+pub struct GReaderCategory {
+    pub id: String,
+    pub label: String,
+}
+
+// Google Reader API 的订阅源
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GReaderSubscription {
+    // feed/{id}
+    pub id: String,
+    pub title: String,
+    pub categories: Vec<GReaderCategory>,
+    pub url: String,
+    pub html_url: String,
+    pub icon_url: String,
+}
+
+// Google Reader API 的标签, 分类的 type 为 folder
+#[derive(Debug, Clone, Serialize)]
+pub struct GReaderTag {
+    pub id: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub tag_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GReaderLink {
+    pub href: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GReaderContent {
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GReaderOrigin {
+    pub stream_id: String,
+    pub title: String,
+    pub html_url: String,
+}
+
+// Google Reader API 的文章
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GReaderItem {
+    // tag:google.com,2005:reader/item/{16 位十六进制}
+    pub id: String,
+    // 毫秒时间戳, 协议中为字符串
+    pub crawl_time_msec: String,
+    // 微秒时间戳, 协议中为字符串
+    pub timestamp_usec: String,
+    // 秒级时间戳
+    pub published: i64,
+    pub title: String,
+    pub canonical: Vec<GReaderLink>,
+    pub alternate: Vec<GReaderLink>,
+    pub summary: GReaderContent,
+    pub author: String,
+    // 已读、收藏状态以及所在的分类
+    pub categories: Vec<String>,
+    pub origin: GReaderOrigin,
+}
+
+// Google Reader API 查询流的参数
+#[derive(Debug, Clone)]
+pub struct GReaderStreamRequest {
+    pub stream: GReaderStream,
+    // 每页数量
+    pub count: u64,
+    // 上一页返回的 continuation, 即下一页的页码
+    pub continuation: Option<String>,
+    // 排除已读的文章
+    pub exclude_read: bool,
+    // 只返回该时间之后发布的文章
+    pub newer_than: Option<NaiveDateTime>,
+    // 只返回该时间之前发布的文章
+    pub older_than: Option<NaiveDateTime>,
+}
+
+// Google Reader API 的文章列表
+#[derive(Debug, Clone, Serialize)]
+pub struct GReaderStreamContents {
+    pub id: String,
+    pub updated: i64,
+    pub items: Vec<GReaderItem>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GReaderItemRef {
+    // 十进制的文章 id
+    pub id: String,
+}
+
+// Google Reader API 的文章 id 列表
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GReaderItemIds {
+    pub item_refs: Vec<GReaderItemRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continuation: Option<String>,
+}
+
+// Google Reader API 修改文章标签的请求, 只支持已读和收藏
+#[derive(Debug, Clone, Default)]
+pub struct GReaderEditTagRequest {
+    pub link_ids: Vec<i64>,
+    pub add: Vec<String>,
+    pub remove: Vec<String>,
+}
+
 #[cfg(test)]
 mod tests {
 
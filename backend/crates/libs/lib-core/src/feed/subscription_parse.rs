@@ -1,163 +1,380 @@
 use super::schema::{
     self, Author, CreateOrUpdateRssLinkRequest, CreateOrUpdateRssLinkRequestBuilder,
-    CreateOrUpdateSubscriptionRequest, CreateOrUpdateSubscriptionRequestBuilder, Image,
+    CreateOrUpdateSubscriptionRequest, CreateOrUpdateSubscriptionRequestBuilder,
+    DiscoverSubscriptionRequest, FeedDiscovery, Image, InsertSubscriptionRecordRequestBuilder,
     QueryPreferUpdateSubscriptionRequest, QueryRssLinkRequestBuilder, QuerySubscriptionRequest,
     QuerySubscriptionRequestBuilder, QuerySubscriptionsWithLinksRequest, SubscriptionModel,
-    SubscriptionWithLinksResp, UpdateSubscriptionCountRequest,
+    SubscriptionParseResult, SubscriptionWithLinksResp, UpdateSubscriptionCountRequest,
+};
+use super::{
+    LinkController, SubscriptionBuildRecordStatus, SubscriptionController,
+    SubscritionConfigController,
 };
 use crate::error::ErrorInService;
-use chrono::{DateTime, Datelike, NaiveDateTime, Timelike};
-use lib_crawler::{try_get_all_image_from_html_content, try_get_all_text_from_html_content};
-use std::collections::{BTreeMap, HashSet};
+use crate::DBConnection;
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use lib_crawler::{
+    try_get_all_image_from_html_content, try_get_all_text_from_html_content, FeedFetchResult,
+    FeedValidators, ParsedEntry, ParsedFeed,
+};
+use lib_entity::feed_link;
+use std::collections::HashSet;
 
 pub struct SubscriptionParseController;
 
 impl SubscriptionParseController {
     /// 从 url 中解析 rss 订阅源
+    ///
+    /// 传入上次保存的 `validators` 时发起条件请求, 订阅源没有变化则返回 `Unchanged`
     pub async fn parser_rss_from_url<T: AsRef<str>>(
         url: T,
-    ) -> Result<SubscriptionWithLinksResp, ErrorInService> {
-        let rss_feed = lib_crawler::fetch_rss_from_url(url.as_ref())
-            .await
-            .map_err(|e| ErrorInService::Custom(format!("解析RSS失败:{}", e)))?;
-        let mut links: Vec<CreateOrUpdateRssLinkRequest> = Vec::new();
-        let pub_date = match rss_feed.pub_date() {
-            Some(d) => match dateparser::parse(d) {
-                Ok(d) => Some(d.to_utc().naive_utc()),
-                Err(_) => None,
-            },
-            None => None,
+        validators: Option<&FeedValidators>,
+    ) -> Result<SubscriptionParseResult, ErrorInService> {
+        let default_validators = FeedValidators::default();
+        let fetched = lib_crawler::fetch_rss_from_url_if_modified(
+            url.as_ref(),
+            validators.unwrap_or(&default_validators),
+        )
+        .await?;
+        let (feed, validators) = match fetched {
+            FeedFetchResult::Modified(fetched) => (fetched.feed, fetched.validators),
+            FeedFetchResult::NotModified => return Ok(SubscriptionParseResult::Unchanged),
         };
-        let language = rss_feed.language().map(|l| l.to_string());
 
+        let resp = Self::build_parse_result(url.as_ref(), &feed, validators)?;
+        Ok(SubscriptionParseResult::Modified(Box::new(resp)))
+    }
+
+    /// 把解析后的订阅源转换为订阅源和链接的更新请求, `url` 为订阅源的地址
+    pub fn build_parse_result(
+        url: &str,
+        feed: &ParsedFeed,
+        validators: FeedValidators,
+    ) -> Result<SubscriptionWithLinksResp, ErrorInService> {
         // 构建订阅源
         let mut subscription_req = CreateOrUpdateSubscriptionRequestBuilder::default();
-        subscription_req.title(rss_feed.title().to_string());
-
-        subscription_req.description(rss_feed.description().to_string());
-        subscription_req.link(url.as_ref().to_string());
-
-        subscription_req.site_link(rss_feed.link().to_string());
-        if let Some(value) = pub_date {
-            subscription_req.pub_date(value);
+        subscription_req.title(feed.title.clone());
+        subscription_req.description(feed.description.clone().unwrap_or_default());
+        subscription_req.link(url.to_string());
+        subscription_req.site_link(feed.site_link.clone().unwrap_or_default());
+        if let Some(value) = feed.published_at.or(feed.updated_at) {
+            subscription_req.pub_date(value.naive_utc());
         }
-        if let Some(value) = language {
+        if let Some(value) = feed.language.clone() {
             subscription_req.language(value);
         }
-        let subscription = subscription_req.clone().build()?;
+        let subscription = subscription_req.build()?;
 
-        let current_timestamp = chrono::Utc::now().timestamp();
+        let links = feed
+            .entries
+            .iter()
+            .filter_map(Self::build_link_request)
+            .collect::<Vec<_>>();
+        Ok(SubscriptionWithLinksResp {
+            subscription,
+            links,
+            validators,
+            websub: feed.websub_hub(url),
+        })
+    }
 
-        // 遍历所有的item
-        rss_feed.items().iter().for_each(|item| {
-            // 没有链接的item，直接返回
-            let item_link = match item.link() {
-                Some(link) => link,
-                None => return,
-            };
-            // 出版时间，如果没有默认使用当前时间
-            let pub_date = match item.pub_date() {
-                Some(d) => match dateparser::parse(d) {
-                    Ok(d) => Some(d.naive_utc()), // 包装在Some中
-                    Err(_) => DateTime::from_timestamp(current_timestamp, 0)
-                        .and_then(|d| Some(d.to_utc().naive_utc())),
-                    // 修改这里，确保返回Option
-                },
-                None => DateTime::from_timestamp(current_timestamp, 0)
-                    .and_then(|d| Some(d.to_utc().naive_utc())),
-            };
-            // 解析额外参数
-            let ext = item.extensions();
-            let mut ext_map: BTreeMap<String, Vec<rss::extension::Extension>> = BTreeMap::new();
-            if let Some(e_map) = ext.get("ext") {
-                ext_map = e_map.to_owned();
+    /// 把拉取或推送得到的订阅源写入数据库, 返回新增的链接
+    ///
+    /// `existing` 为数据库中的订阅源, 分类和语言以它为准
+    pub async fn apply_parse_result(
+        existing: &CreateOrUpdateSubscriptionRequest,
+        resp: SubscriptionWithLinksResp,
+        conn: &DBConnection,
+    ) -> Result<Vec<feed_link::Model>, ErrorInService> {
+        let subscription_id = existing
+            .id
+            .ok_or(ErrorInService::Custom("订阅源ID不能为空".to_string()))?;
+        let SubscriptionWithLinksResp {
+            subscription: rss_subscription,
+            links,
+            validators,
+            ..
+        } = resp;
+        // 首先更新订阅源部分，更新订阅源的最后更新时间
+        let mut update_subscription_req = rss_subscription;
+        update_subscription_req.id = Some(subscription_id);
+        update_subscription_req.last_build_date = Some(chrono::Utc::now().naive_utc());
+        update_subscription_req.category_id = existing.category_id;
+        if existing.language.is_some() {
+            update_subscription_req.language = existing.language.clone();
+        }
+        SubscriptionController
+            .insert_subscription(update_subscription_req, conn)
+            .await?;
+
+        // 推送的内容没有缓存校验信息, 不覆盖上次拉取保存的
+        if !validators.is_empty() {
+            if let Err(e) = SubscritionConfigController
+                .update_subscription_validators(subscription_id, validators, conn)
+                .await
+            {
+                tracing::error!("保存订阅源缓存校验信息失败:{}", e);
             }
-            let authors_json = match ext_map.get("authors") {
-                Some(authors) => {
-                    let mut authors_j = Vec::new();
-                    // insert from extension's attr
-                    authors.iter().for_each(|author| {
-                        let attr = author.attrs();
-                        authors_j.push(attr);
-                    });
-                    serde_json::to_string(&authors_j).ok()
-                }
-                None => None,
-            };
-            let mut images_json = match ext_map.get("images") {
-                Some(images) => {
-                    let mut urls = Vec::new();
-                    images.iter().for_each(|image| {
-                        if let Some(url) = image.value() {
-                            urls.push(url.to_string());
-                        }
-                    });
-                    serde_json::to_string(&urls).ok()
-                }
-                None => None,
-            };
-            // 如果没有图片，尝试从描述中解析
-            if let (true, Some(desc)) = (images_json.is_none(), item.description()) {
-                if let Ok(images) = try_get_all_image_from_html_content(desc.to_string()) {
-                    let images = images
-                        .iter()
-                        .map(|i| rss::Image {
-                            url: i.to_string(),
-                            ..Default::default()
-                        })
-                        .collect::<Vec<_>>();
-                    images_json = serde_json::to_string(&images).ok();
-                }
+        }
+
+        // 链接不存在时插入, 已经存在的链接不会有后续的操作
+        let all_link_count = links.len();
+        let mut inserted_links = vec![];
+        for mut link in links {
+            link.subscrption_id = subscription_id;
+            match LinkController.insert_link(link, conn).await {
+                Ok((false, l)) => inserted_links.push(l),
+                Ok((true, _)) => {}
+                Err(e) => tracing::error!("更新链接失败:{}", e),
             }
+        }
+        // 每次成功拉取都要记录, 拟合频率时用于计算失败的比例
+        // 新链接数量大于总链接数量的三分之一时记为成功, 否则记为少量更新
+        let status = match inserted_links.len() > all_link_count / 3 {
+            true => SubscriptionBuildRecordStatus::Success,
+            false => SubscriptionBuildRecordStatus::FweSuccess,
+        };
+        let req = InsertSubscriptionRecordRequestBuilder::default()
+            .subscription_id(subscription_id)
+            .status(status)
+            .build()?;
+        SubscritionConfigController
+            .insert_subscription_update_record(req, conn)
+            .await?;
+        Ok(inserted_links)
+    }
 
-            // 解析纯文本
-            let mut pure_desc: Option<String> = None;
+    // 把订阅源的条目转换为链接, 没有链接的条目忽略
+    fn build_link_request(entry: &ParsedEntry) -> Option<CreateOrUpdateRssLinkRequest> {
+        let item_link = entry.link.as_ref()?;
+        // 完整内容优先, 没有时使用摘要
+        let html = entry.content.as_ref().or(entry.summary.as_ref());
+        // 纯文本优先使用摘要
+        let pure_desc = entry
+            .summary
+            .as_ref()
+            .or(entry.content.as_ref())
+            .and_then(|desc| try_get_all_text_from_html_content(desc.to_string()).ok());
+        // 出版时间，如果没有默认使用当前时间
+        let pub_date = entry
+            .published_at
+            .or(entry.updated_at)
+            .unwrap_or_else(chrono::Utc::now)
+            .naive_utc();
 
-            if let Some(desc) = item.description() {
-                if let Ok(desc) = try_get_all_text_from_html_content(desc.to_string()) {
-                    pure_desc = Some(desc);
-                }
-            }
+        // 如果没有图片，尝试从内容中解析
+        let mut image_urls = entry.images.clone();
+        if let (true, Some(desc)) = (image_urls.is_empty(), html) {
+            image_urls = try_get_all_image_from_html_content(desc.to_string()).unwrap_or_default();
+        }
+        let images: Vec<Image> = image_urls
+            .into_iter()
+            .map(|url| Image {
+                url,
+                title: None,
+                link: None,
+                width: None,
+                height: None,
+                description: None,
+            })
+            .collect();
 
-            // build feed_item links
-            let mut link_req = CreateOrUpdateRssLinkRequestBuilder::default();
+        let authors: Vec<Author> = entry
+            .authors
+            .iter()
+            .map(|a| Author {
+                name: a.name.clone(),
+                email: a.email.clone(),
+                uri: a.uri.clone(),
+            })
+            .collect();
 
-            link_req.title(match item.title() {
-                Some(t) => t.to_string(),
-                None => "".to_string(),
-            });
-            link_req.link(item_link.to_string());
-            if let Some(value) = item.description.clone() {
-                link_req.description(value);
-            }
+        // build feed_item links
+        let mut link_req = CreateOrUpdateRssLinkRequestBuilder::default();
+        link_req.title(entry.title.clone().unwrap_or_default());
+        link_req.link(item_link.to_string());
+        if let Some(value) = &entry.guid {
+            link_req.guid(value.clone());
+        }
+        if let Some(value) = html {
+            link_req.description(value.clone());
+        }
+        if let Some(value) = pure_desc {
+            link_req.desc_pure_txt(value);
+        }
+        if !images.is_empty() {
+            link_req.images(images);
+        }
+        if !authors.is_empty() {
+            link_req.authors(authors);
+        }
+        link_req.published_at(pub_date);
+        let tags = entry.tags();
+        if !tags.is_empty() {
+            link_req.tags(tags);
+        }
+        link_req
+            .build()
+            .map_err(|e| tracing::warn!("构建链接失败:{}", e))
+            .ok()
+    }
 
-            if let Some(value) = pure_desc {
-                link_req.desc_pure_txt(value);
-            }
+    /// 根据任意页面地址查找候选的订阅源
+    ///
+    /// 返回每个候选订阅源的标题、格式和条目数量, 只返回可以正常解析的订阅源
+    pub async fn discover_subscriptions(
+        req: DiscoverSubscriptionRequest,
+    ) -> Result<FeedDiscovery, ErrorInService> {
+        let url = req.url.trim();
+        if url.is_empty() {
+            return Err(ErrorInService::Custom("地址不能为空".to_string()));
+        }
+        let discovery =
+            lib_crawler::discover_feeds(url, &lib_crawler::DiscoveryOption::default()).await?;
+        Ok(discovery)
+    }
+}
 
-            if let Some(value) = images_json {
-                let images: Vec<Image> =
-                    serde_json::from_str::<Vec<Image>>(&value).unwrap_or_default();
-                link_req.images(images.iter().map(|i| i.clone()).collect::<Vec<_>>());
-            }
-            if let Some(value) = authors_json {
-                let authors = serde_json::from_str::<Vec<Author>>(&value).unwrap_or_default();
-                link_req.authors(authors.iter().map(|a| a.clone()).collect::<Vec<_>>());
-            }
-            if let Some(value) = pub_date {
-                link_req.published_at(value);
-            }
-            if let Ok(link) = link_req
-                .build()
-                .map_err(|e| ErrorInService::Custom(format!("构建链接失败:{}", e)))
-            {
-                links.push(link);
-            }
-        });
-        let resp = SubscriptionWithLinksResp {
-            subscription,
-            links,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lib_crawler::{ParsedCategory, ParsedPerson};
+
+    #[test]
+    fn test_build_link_request() {
+        let entry = ParsedEntry {
+            guid: Some("post-1".to_string()),
+            title: Some("title".to_string()),
+            link: Some("https://example.com/1".to_string()),
+            summary: Some("<p>summary</p>".to_string()),
+            content: Some("<p>content <img src=\"https://example.com/a.png\"></p>".to_string()),
+            authors: vec![ParsedPerson {
+                name: "Jane".to_string(),
+                email: Some("jane@example.com".to_string()),
+                uri: None,
+            }],
+            categories: vec![ParsedCategory {
+                term: "rust".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
         };
-        Ok(resp)
+        let req = SubscriptionParseController::build_link_request(&entry).unwrap();
+        // 完整内容作为描述, 摘要作为纯文本
+        assert!(req.description.unwrap().contains("content"));
+        assert_eq!(req.desc_pure_txt.as_deref(), Some("summary"));
+        assert_eq!(req.images.unwrap()[0].url, "https://example.com/a.png");
+        assert_eq!(
+            req.authors.unwrap()[0].email.as_deref(),
+            Some("jane@example.com")
+        );
+        assert_eq!(req.tags, Some(vec!["rust".to_string()]));
+        assert!(req.published_at.is_some());
+        assert_eq!(req.guid.as_deref(), Some("post-1"));
+
+        // 没有链接的条目忽略
+        let entry = ParsedEntry {
+            link: None,
+            ..entry
+        };
+        assert!(SubscriptionParseController::build_link_request(&entry).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_record_every_fetch() {
+        let conn = crate::test_runner::setup_database().await;
+        let category = crate::feed::CategoryController
+            .insert_category(
+                crate::feed::schema::CreateOrUpdateCategoryRequestBuilder::default()
+                    .title("blog")
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let (_, subscription_id) = SubscriptionController
+            .insert_subscription(
+                CreateOrUpdateSubscriptionRequestBuilder::default()
+                    .category_id(category.id)
+                    .title("blog")
+                    .link("http://example.com/blog.xml")
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let existing: CreateOrUpdateSubscriptionRequest = SubscriptionController
+            .find_subscription(subscription_id, &conn)
+            .await
+            .unwrap()
+            .unwrap()
+            .into();
+        // 每天一篇的博客, 拉取多次但没有新文章
+        let now = chrono::Utc::now().naive_utc();
+        let links = (0..3)
+            .map(|i| {
+                crate::feed::schema::CreateOrUpdateRssLinkRequestBuilder::default()
+                    .title(format!("post {}", i))
+                    .link(format!("http://example.com/blog/{}", i))
+                    .published_at(now - chrono::Duration::hours(1 + 24 * i))
+                    .build()
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+        for _ in 0..4 {
+            let resp = SubscriptionWithLinksResp {
+                subscription: CreateOrUpdateSubscriptionRequestBuilder::default()
+                    .title("blog")
+                    .link("http://example.com/blog.xml")
+                    .build()
+                    .unwrap(),
+                links: links.clone(),
+                validators: FeedValidators::default(),
+                websub: None,
+            };
+            SubscriptionParseController::apply_parse_result(&existing, resp, &conn)
+                .await
+                .unwrap();
+        }
+        let req = InsertSubscriptionRecordRequestBuilder::default()
+            .subscription_id(subscription_id)
+            .status(SubscriptionBuildRecordStatus::Faild)
+            .build()
+            .unwrap();
+        SubscritionConfigController
+            .insert_subscription_update_record(req, &conn)
+            .await
+            .unwrap();
+
+        let controller = SubscritionConfigController;
+        let query = crate::feed::schema::QuerySubscriptionRecordRequestBuilder::default()
+            .subscription_ids(vec![subscription_id])
+            .page(crate::common_schema::PageRequest::max_page())
+            .build()
+            .unwrap();
+        let records = controller
+            .query_subscription_record(query, &conn)
+            .await
+            .unwrap();
+        assert_eq!(records.data.len(), 5);
+
+        // 一次失败和四次成功, 拉取间隔不会被当作大部分失败而放大到上限
+        controller
+            .update_subscription_config(vec![subscription_id], &conn)
+            .await
+            .unwrap();
+        let config = controller
+            .query_subscription_config(
+                crate::feed::schema::QuerySubscriptionConfigRequest::new(Some(vec![
+                    subscription_id,
+                ])),
+                &conn,
+            )
+            .await
+            .unwrap()
+            .remove(0);
+        assert!(config.get_frequency() < super::super::subscription_update::MAX_FETCH_FREQUENCY);
     }
 }
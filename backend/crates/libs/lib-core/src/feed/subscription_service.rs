@@ -14,14 +14,28 @@ use crate::error::ErrorInService;
 use crate::{auth, DBConnection};
 use chrono::{DateTime, Datelike, NaiveDateTime, Timelike};
 use lib_crawler::{try_get_all_image_from_html_content, try_get_all_text_from_html_content};
-use lib_entity::{feed_build_config, feed_category, feed_link, feed_subscription};
+use lib_entity::{
+    account_subscription, feed_build_config, feed_category, feed_link, feed_subscription,
+};
 use lib_utils::math::{get_page_count, get_page_offset};
-use sea_orm::sea_query::{Expr, IntoCondition};
+use sea_orm::sea_query::{Expr, IntoCondition, SelectStatement};
 use sea_orm::DbBackend;
 use sea_orm::{entity::*, query::*};
 use serde::Deserialize;
 use tokio::sync::TryAcquireError;
 
+// 用户订阅的订阅源 id, 用于子查询
+pub(crate) fn subscribed_ids_query(account_id: i64, category_id: Option<i64>) -> SelectStatement {
+    let mut select = account_subscription::Entity::find()
+        .select_only()
+        .column(account_subscription::Column::SubscriptionId)
+        .filter(account_subscription::Column::AccountId.eq(account_id));
+    if let Some(category_id) = category_id {
+        select = select.filter(account_subscription::Column::CategoryId.eq(category_id));
+    }
+    select.into_query()
+}
+
 pub struct SubscriptionController;
 
 impl SubscriptionController {
@@ -30,7 +44,7 @@ impl SubscriptionController {
         req: CreateOrUpdateSubscriptionRequest,
         conn: &DBConnection,
     ) -> Result<(bool, i64), ErrorInService> {
-        let query = match req.id.clone() {
+        let query = match req.id {
             Some(id) => {
                 feed_subscription::Entity::find().filter(feed_subscription::Column::Id.eq(id))
             }
@@ -75,18 +89,157 @@ impl SubscriptionController {
 
         let is_update = prefer_update;
         if !is_update {
-            let build_config = feed_build_config::ActiveModel {
-                subscription_id: Set(updated.id),
-                initial_frequency: Set(3600.0),
-                source_type: Set(feed_build_config::SourceType::Unknown),
+            Self::insert_build_config(updated.id, conn).await;
+        }
+
+        Ok((is_update, updated.id))
+    }
+
+    // 新的订阅源需要一份抓取配置
+    async fn insert_build_config(subscription_id: i64, conn: &DBConnection) {
+        let build_config = feed_build_config::ActiveModel {
+            subscription_id: Set(subscription_id),
+            initial_frequency: Set(3600.0),
+            source_type: Set(feed_build_config::SourceType::Unknown),
+            ..Default::default()
+        };
+        if let Err(e) = build_config.insert(conn).await {
+            tracing::error!("insert build config error: {:?}", e);
+        }
+    }
+
+    /// 用户订阅一个订阅源, 返回 (是否已经订阅过, 订阅源 id)
+    ///
+    /// 订阅源按地址全局去重, 多个用户订阅同一个地址只会抓取一次;
+    /// 用户只能修改自己的分类、自定义标题和排序, 不会改动共享的订阅源
+    pub async fn subscribe(
+        &self,
+        account_id: i64,
+        req: CreateOrUpdateSubscriptionRequest,
+        conn: &DBConnection,
+    ) -> Result<(bool, i64), ErrorInService> {
+        if let Some(category_id) = req.category_id {
+            let category = feed_category::Entity::find_by_id(category_id)
+                .filter(feed_category::Column::AccountId.eq(account_id))
+                .one(conn)
+                .await?;
+            if category.is_none() {
+                return Err(ErrorInService::Custom("category not found".to_string()));
+            }
+        }
+
+        let subscription = match req.id {
+            Some(id) => feed_subscription::Entity::find_by_id(id).one(conn).await?,
+            None => {
+                feed_subscription::Entity::find()
+                    .filter(feed_subscription::Column::Link.eq(req.link.clone()))
+                    .order_by_asc(feed_subscription::Column::Id)
+                    .one(conn)
+                    .await?
+            }
+        };
+        let subscribed = match &subscription {
+            Some(m) => {
+                account_subscription::Entity::find()
+                    .filter(account_subscription::Column::AccountId.eq(account_id))
+                    .filter(account_subscription::Column::SubscriptionId.eq(m.id))
+                    .one(conn)
+                    .await?
+            }
+            None => None,
+        };
+        if subscribed.is_none() && req.category_id.is_none() {
+            return Err(ErrorInService::Custom(
+                "category_id is required".to_string(),
+            ));
+        }
+
+        let subscription_id = match subscription {
+            Some(m) => m.id,
+            None if req.id.is_some() => {
+                return Err(ErrorInService::Custom("subscription not found".to_string()))
+            }
+            None => {
+                // 共享的订阅源不属于任何用户的分类
+                let new_model = feed_subscription::ActiveModel {
+                    title: Set(req.title.clone()),
+                    description: Set(req.description.clone()),
+                    link: Set(Some(req.link.clone())),
+                    site_link: Set(req.site_link.clone()),
+                    logo: Set(req.logo.clone()),
+                    pub_date: Set(req.pub_date),
+                    language: Set(req.language.clone()),
+                    rating: Set(req.rating),
+                    visual_url: Set(req.visual_url.clone()),
+                    ..Default::default()
+                };
+                let created = new_model.insert(conn).await?;
+                Self::insert_build_config(created.id, conn).await;
+                created.id
+            }
+        };
+
+        let is_update = subscribed.is_some();
+        let mut model = match subscribed {
+            Some(m) => m.into_active_model(),
+            None => account_subscription::ActiveModel {
+                account_id: Set(account_id),
+                subscription_id: Set(subscription_id),
                 ..Default::default()
-            };
-            if let Err(e) = build_config.insert(conn).await {
-                tracing::error!("insert build config error: {:?}", e);
+            },
+        };
+        if let Some(category_id) = req.category_id {
+            model.category_id = Set(category_id);
+        }
+        model.custom_title = Set(req.custom_title.clone());
+        model.sort_order = Set(req.sort_order);
+        match is_update {
+            true => {
+                model.updated_at = Set(chrono::Utc::now().naive_utc());
+                model.update(conn).await?;
+            }
+            false => {
+                model.insert(conn).await?;
             }
         }
+        Ok((is_update, subscription_id))
+    }
 
-        Ok((is_update, updated.id))
+    /// 取消订阅, 共享的订阅源保留, 其他用户仍然可以继续订阅
+    pub async fn unsubscribe(
+        &self,
+        account_id: i64,
+        subscription_id: i64,
+        conn: &DBConnection,
+    ) -> Result<bool, ErrorInService> {
+        let res = account_subscription::Entity::delete_many()
+            .filter(account_subscription::Column::AccountId.eq(account_id))
+            .filter(account_subscription::Column::SubscriptionId.eq(subscription_id))
+            .exec(conn)
+            .await?;
+        Ok(res.rows_affected > 0)
+    }
+
+    pub async fn find_subscription(
+        &self,
+        id: i64,
+        conn: &DBConnection,
+    ) -> Result<Option<feed_subscription::Model>, ErrorInService> {
+        let subscription = feed_subscription::Entity::find_by_id(id).one(conn).await?;
+        Ok(subscription)
+    }
+
+    /// 按订阅地址查找订阅源
+    pub async fn find_subscription_by_link(
+        &self,
+        link: &str,
+        conn: &DBConnection,
+    ) -> Result<Option<feed_subscription::Model>, ErrorInService> {
+        let subscription = feed_subscription::Entity::find()
+            .filter(feed_subscription::Column::Link.eq(link))
+            .one(conn)
+            .await?;
+        Ok(subscription)
     }
 
     pub async fn query_subscription(
@@ -107,24 +260,28 @@ impl SubscriptionController {
             .unwrap_or(current_date)
             .with_second(0);
 
-        let mut select = feed_subscription::Entity::find()
-            .left_join(feed_category::Entity)
-            .left_join(feed_build_config::Entity)
-            .join(
-                JoinType::LeftJoin,
-                lib_entity::feed_subscription::Relation::Links
-                    .def()
-                    .on_condition(move |_left, right| {
-                        Expr::col(lib_entity::feed_link::Column::PublishedAt)
-                            .gte(week_start_date)
-                            .and(
-                                Expr::col(lib_entity::feed_link::Column::PublishedAt)
-                                    .lt(current_date),
-                            )
-                            .into_condition()
-                    })
-                    .into(),
-            );
+        let mut select = feed_subscription::Entity::find();
+        // 登录用户只能看到自己订阅的订阅源, 分类、标题和排序也使用用户自己的
+        select = match req.account_id {
+            Some(account_id) => select
+                .join(
+                    JoinType::InnerJoin,
+                    account_subscription::Relation::Subscription.def().rev(),
+                )
+                .filter(account_subscription::Column::AccountId.eq(account_id)),
+            None => select.left_join(feed_category::Entity),
+        };
+        select = select.left_join(feed_build_config::Entity).join(
+            JoinType::LeftJoin,
+            lib_entity::feed_subscription::Relation::Links
+                .def()
+                .on_condition(move |_left, right| {
+                    Expr::col(lib_entity::feed_link::Column::PublishedAt)
+                        .gte(week_start_date)
+                        .and(Expr::col(lib_entity::feed_link::Column::PublishedAt).lt(current_date))
+                        .into_condition()
+                }),
+        );
 
         // 查询 `SubscriptionModel` 的所有字段
         select = select
@@ -139,7 +296,6 @@ impl SubscriptionController {
                 feed_subscription::Column::VisualUrl,
                 feed_subscription::Column::Logo,
                 feed_subscription::Column::Language,
-                feed_subscription::Column::SortOrder,
                 feed_subscription::Column::Rating,
             ])
             .group_by(feed_subscription::Column::Id)
@@ -148,11 +304,37 @@ impl SubscriptionController {
                 feed_build_config::Column::LastBuildAt.is_not_null(),
                 "is_completed",
             )
-            // sort 字段设置为 -1
-            .column_as(feed_subscription::Column::SortOrder, "sort_order")
-            .column_as(feed_category::Column::Id, "category_id")
             // article_count_for_this_week 查询本周文章数量
-            .column_as(feed_link::Column::Id.count(), "article_count_for_this_week");
+            .column_as(feed_link::Column::Id.count(), "article_count_for_this_week")
+            // 订阅人数
+            .column_as(
+                Expr::cust(
+                    "(SELECT COUNT(*) FROM account_subscription AS s \
+                    WHERE s.subscription_id = feed_subscription.id)",
+                ),
+                "subscribers",
+            );
+        select = match req.account_id {
+            Some(account_id) => select
+                // 没有已读记录的文章都是未读
+                .column_as(
+                    Expr::cust_with_values(
+                        "(SELECT COUNT(*) FROM feed_link AS l \
+                        WHERE l.subscription_id = feed_subscription.id AND NOT EXISTS \
+                        (SELECT 1 FROM link_state AS st WHERE st.link_id = l.id \
+                        AND st.account_id = ? AND st.read_at IS NOT NULL))",
+                        [account_id],
+                    ),
+                    "unread_count",
+                )
+                .column_as(account_subscription::Column::CategoryId, "category_id")
+                .column_as(account_subscription::Column::CustomTitle, "custom_title")
+                .column_as(account_subscription::Column::SortOrder, "sort_order")
+                .column_as(account_subscription::Column::CreatedAt, "subscribed_at"),
+            None => select
+                .column_as(feed_subscription::Column::SortOrder, "sort_order")
+                .column_as(feed_category::Column::Id, "category_id"),
+        };
 
         if let Some(ids) = &req.ids {
             if !ids.is_empty() {
@@ -163,10 +345,21 @@ impl SubscriptionController {
         }
 
         if let Some(title) = &req.title {
-            select = select.filter(feed_subscription::Column::Title.like(format!("%{}%", title)))
+            let pattern = format!("%{}%", title);
+            select = match req.account_id {
+                Some(_) => select.filter(
+                    Condition::any()
+                        .add(feed_subscription::Column::Title.like(pattern.clone()))
+                        .add(account_subscription::Column::CustomTitle.like(pattern)),
+                ),
+                None => select.filter(feed_subscription::Column::Title.like(pattern)),
+            }
         }
         if let Some(category_id) = &req.category_id {
-            select = select.filter(feed_category::Column::Id.eq(*category_id))
+            select = match req.account_id {
+                Some(_) => select.filter(account_subscription::Column::CategoryId.eq(*category_id)),
+                None => select.filter(feed_category::Column::Id.eq(*category_id)),
+            }
         }
         let page_info = req
             .page
@@ -176,6 +369,10 @@ impl SubscriptionController {
         let page = page_info.page;
         let offset = get_page_offset(page, page_size);
 
+        // 用户的订阅先按用户的排序
+        if req.account_id.is_some() {
+            select = select.order_by_desc(account_subscription::Column::SortOrder)
+        }
         // 根据时间排序, 默认是降序
         select = select
             .order_by_desc(feed_subscription::Column::UpdatedAt)
@@ -183,12 +380,23 @@ impl SubscriptionController {
             .offset(offset)
             .select();
 
-        let all_count = feed_subscription::Entity::find()
-            .select_only()
-            .column(feed_subscription::Column::Id)
-            .count(conn)
-            .await
-            .unwrap_or(0);
+        let all_count = match req.account_id {
+            Some(account_id) => {
+                let mut count_select = account_subscription::Entity::find()
+                    .filter(account_subscription::Column::AccountId.eq(account_id));
+                if let Some(category_id) = req.category_id {
+                    count_select = count_select
+                        .filter(account_subscription::Column::CategoryId.eq(category_id))
+                }
+                count_select.count(conn).await.unwrap_or(0)
+            }
+            None => feed_subscription::Entity::find()
+                .select_only()
+                .column(feed_subscription::Column::Id)
+                .count(conn)
+                .await
+                .unwrap_or(0),
+        };
         let page_count = get_page_count(all_count, page_size);
 
         let models = select
@@ -213,9 +421,15 @@ mod tests {
     #[tokio::test]
     async fn test_parser_rss_from_url() {
         let url: &str = "https://www.elconfidencialdigital.com/rss?seccion=el_confidencial_digital";
-        let resp = SubscriptionParseController::parser_rss_from_url(url)
+        let resp = match SubscriptionParseController::parser_rss_from_url(url, None)
             .await
-            .unwrap();
+            .unwrap()
+        {
+            crate::feed::schema::SubscriptionParseResult::Modified(resp) => *resp,
+            crate::feed::schema::SubscriptionParseResult::Unchanged => {
+                panic!("without validators the feed is always fetched")
+            }
+        };
         // assert_eq!(resp.subscription.title, "数字尾巴");
         assert!(!resp.links.is_empty());
     }
@@ -250,10 +464,10 @@ mod tests {
             .insert_subscription(req.clone(), &conn)
             .await
             .unwrap();
-        assert_eq!(is_update, false);
+        assert!(!is_update);
 
         let (is_update, id) = controller.insert_subscription(req, &conn).await.unwrap();
-        assert_eq!(is_update, true);
+        assert!(is_update);
 
         let req = CreateOrUpdateSubscriptionRequestBuilder::default()
             .title("updated".to_string())
@@ -264,7 +478,7 @@ mod tests {
             .build()
             .unwrap();
         let (is_update, id) = controller.insert_subscription(req, &conn).await.unwrap();
-        assert_eq!(is_update, false);
+        assert!(!is_update);
 
         let current_date = chrono::Utc::now().naive_utc();
         // get start of this week, datetime is 00:00:00
@@ -293,8 +507,7 @@ mod tests {
                                     .lt(current_date),
                             )
                             .into_condition()
-                    })
-                    .into(),
+                    }),
             )
             .left_join(lib_entity::feed_category::Entity)
             .left_join(lib_entity::feed_build_config::Entity)
@@ -338,4 +551,150 @@ mod tests {
 
         assert_eq!(models.len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_subscriptions_are_scoped_to_account() {
+        let conn = crate::test_runner::setup_database().await;
+        let category_controller = CategoryController;
+        let mut category_ids = Vec::new();
+        for account_id in [1_i64, 2] {
+            let req = crate::feed::schema::CreateOrUpdateCategoryRequestBuilder::default()
+                .title("科技")
+                .account_id(account_id)
+                .build()
+                .unwrap();
+            let category = category_controller
+                .insert_category(req, &conn)
+                .await
+                .unwrap();
+            category_ids.push(category.id);
+        }
+        // 同名分类属于不同的用户
+        assert_ne!(category_ids[0], category_ids[1]);
+
+        let controller = SubscriptionController;
+        let build_req = |category_id: i64, custom_title: &str| {
+            CreateOrUpdateSubscriptionRequestBuilder::default()
+                .title("shared")
+                .link("https://example.com/feed.xml")
+                .category_id(category_id)
+                .custom_title(custom_title)
+                .build()
+                .unwrap()
+        };
+        let (is_update, first_id) = controller
+            .subscribe(1, build_req(category_ids[0], "mine"), &conn)
+            .await
+            .unwrap();
+        assert!(!is_update);
+        let (is_update, second_id) = controller
+            .subscribe(2, build_req(category_ids[1], "theirs"), &conn)
+            .await
+            .unwrap();
+        assert!(!is_update);
+        // 同一个地址只保留一个订阅源, 只抓取一次
+        assert_eq!(first_id, second_id);
+        let count = feed_subscription::Entity::find()
+            .count(&conn)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // 不能使用其他用户的分类
+        assert!(controller
+            .subscribe(1, build_req(category_ids[1], "mine"), &conn)
+            .await
+            .is_err());
+
+        let query = |account_id: i64| {
+            QuerySubscriptionRequestBuilder::default()
+                .account_id(account_id)
+                .build()
+                .unwrap()
+        };
+        let resp = controller
+            .query_subscription(query(1), &conn)
+            .await
+            .unwrap();
+        assert_eq!(resp.data.len(), 1);
+        assert_eq!(resp.data[0].custom_title.as_deref(), Some("mine"));
+        assert_eq!(resp.data[0].category_id, category_ids[0]);
+        assert_eq!(resp.data[0].subscribers, Some(2));
+        let resp = controller
+            .query_subscription(query(3), &conn)
+            .await
+            .unwrap();
+        assert!(resp.data.is_empty());
+
+        let link_req = CreateOrUpdateRssLinkRequestBuilder::default()
+            .title("hello")
+            .link("https://example.com/hello")
+            .subscrption_id(first_id)
+            .build()
+            .unwrap();
+        LinkController.insert_link(link_req, &conn).await.unwrap();
+        let query_links = |account_id: i64| {
+            QueryRssLinkRequestBuilder::default()
+                .account_id(account_id)
+                .build()
+                .unwrap()
+        };
+        let links = LinkController
+            .query_links(query_links(2), &conn)
+            .await
+            .unwrap();
+        assert_eq!(links.data.len(), 1);
+
+        assert!(controller.unsubscribe(2, first_id, &conn).await.unwrap());
+        let links = LinkController
+            .query_links(query_links(2), &conn)
+            .await
+            .unwrap();
+        assert!(links.data.is_empty());
+        let resp = controller
+            .query_subscription(query(1), &conn)
+            .await
+            .unwrap();
+        assert_eq!(resp.data.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_migrate_existing_subscriptions() {
+        let db = crate::get_db_conn("sqlite::memory:?mode=rwc".to_owned()).await;
+        // 迁移到按用户订阅之前, 准备旧的数据
+        Migrator::up(&db, Some(10)).await.unwrap();
+        for sql in [
+            "INSERT INTO account (id, email) VALUES (1, 'a@example.com'), (2, 'b@example.com')",
+            "INSERT INTO feed_category (id, title) VALUES (1, 'tech')",
+            "INSERT INTO feed_subscription (id, title, link, category_id) VALUES (1, 'a', 'https://a.com/feed', 1), (2, 'b', 'https://b.com/feed', NULL)",
+        ] {
+            db.execute_unprepared(sql).await.unwrap();
+        }
+        Migrator::up(&db, None).await.unwrap();
+
+        for account_id in [1, 2] {
+            let subscriptions = account_subscription::Entity::find()
+                .filter(account_subscription::Column::AccountId.eq(account_id))
+                .all(&db)
+                .await
+                .unwrap();
+            assert_eq!(subscriptions.len(), 2);
+            // 分类属于该用户
+            for subscription in subscriptions {
+                let category = feed_category::Entity::find_by_id(subscription.category_id)
+                    .one(&db)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(category.account_id, Some(account_id));
+            }
+        }
+        // 第一个用户接管已有的分类
+        let category = feed_category::Entity::find_by_id(1)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(category.account_id, Some(1));
+    }
 }
@@ -1,17 +1,17 @@
 use std::{collections::HashMap, ops::Sub};
 
 use chrono::{NaiveDateTime, Timelike};
-use lib_entity::{feed_build_config, feed_build_record, feed_subscription};
+use lib_entity::{feed_build_config, feed_build_record, feed_link, feed_subscription};
 use lib_utils::math::{get_page_count, get_page_offset};
 
 use super::{
     schema::{
-        InsertSubscriptionRecordRequest, QuerySubscriptionConfigRequest,
+        FeedValidators, InsertSubscriptionRecordRequest, QuerySubscriptionConfigRequest,
         QuerySubscriptionRecordRequest, QuerySubscriptionRecordRequestBuilder,
         UpdateSubscriptionConfigRequest, UpdateSubscriptionConfigRequestBuilder,
     },
     CreateOrUpdateSubscriptionRequest, QueryPreferUpdateSubscriptionRequest,
-    SubscriptionBuildSourceType,
+    SubscriptionBuildSourceType, WebSubController,
 };
 use crate::{
     common_schema::{PageRequest, PageRequestBuilder, PageResponse},
@@ -20,6 +20,42 @@ use crate::{
 };
 use sea_orm::{entity::*, query::*};
 
+// 拟合拉取频率时参考的文章发布历史, 单位天
+const FIT_HISTORY_DAYS: i64 = 30;
+// 拟合出的拉取频率的上下限, 单位分钟
+pub const MIN_FETCH_FREQUENCY: f32 = 15.0;
+pub const MAX_FETCH_FREQUENCY: f32 = 60.0 * 24.0;
+
+/// 根据文章的发布时间和拉取的成功失败次数, 计算拉取频率, 单位分钟
+///
+/// `published` 为近期按时间排序的发布时间, `last_published` 为最近一篇文章的发布时间
+pub fn fit_frequency(
+    published: &[NaiveDateTime],
+    last_published: Option<NaiveDateTime>,
+    succeeded: usize,
+    failed: usize,
+    now: NaiveDateTime,
+) -> f32 {
+    let minutes = |from: NaiveDateTime, to: NaiveDateTime| (to - from).num_seconds() as f32 / 60.0;
+    // 每个发布间隔内拉取两次, 文章太少时无法估计发布频率
+    let mut frequency = match (published.first(), published.last()) {
+        (Some(first), Some(last)) if published.len() > 1 => {
+            minutes(*first, *last) / (published.len() - 1) as f32 / 2.0
+        }
+        _ => MAX_FETCH_FREQUENCY,
+    };
+    // 长时间没有新文章时, 拉取间隔随沉寂的时长增加
+    if let Some(last_published) = last_published {
+        frequency = frequency.max(minutes(last_published, now) / 4.0);
+    }
+    // 拉取失败的比例越高, 拉取间隔越长, 最多为四倍
+    let total = succeeded + failed;
+    if total > 0 {
+        frequency *= 1.0 + 3.0 * failed as f32 / total as f32;
+    }
+    frequency.clamp(MIN_FETCH_FREQUENCY, MAX_FETCH_FREQUENCY)
+}
+
 pub struct SubscritionConfigController;
 
 impl SubscritionConfigController {
@@ -38,12 +74,13 @@ impl SubscritionConfigController {
             Some(m) => m.into_active_model(),
             None => feed_build_config::ActiveModel {
                 subscription_id: Set(req.subscription_id),
-                initial_frequency: Set(req.initial_frequency.clone()),
+                initial_frequency: Set(req.initial_frequency),
                 fitted_adaptive: Set(Some(req.fitted_adaptive)),
                 ..Default::default()
             },
         };
 
+        new_model.fitted_adaptive = Set(Some(req.fitted_adaptive));
         if req.fitted_adaptive {
             // 更新 fitted_frequency, 限制小数点后四位
             if let Some(fitted_frequency) = req.fitted_frequency {
@@ -70,100 +107,105 @@ impl SubscritionConfigController {
         };
         Ok(())
     }
-    // 更新订阅源的更新配置，基于订阅源的更新记录
+    /// 根据文章的发布历史和拉取记录, 拟合订阅源的拉取频率
+    ///
+    /// 推送生效中的订阅源使用固定的兜底频率, 不参与拟合
     pub async fn update_subscription_config(
         &self,
         subscription_ids: Vec<i64>,
         conn: &DBConnection,
     ) -> Result<(), ErrorInService> {
-        // 基于 records, 计算出时间间隔: 需要过滤出 status 为 0 的记录
-        // 通过 subscription_id 分组, 计算出每个订阅源的更新时间间隔, 按照时间排序
-        // 7天前的现在
-
         let now = chrono::Utc::now().naive_utc();
-        let seven_days_ago = now - chrono::Duration::days(7);
+        let active_push_ids = WebSubController.active_subscription_ids(conn).await?;
+        let subscription_ids = subscription_ids
+            .into_iter()
+            .filter(|id| !active_push_ids.contains(id))
+            .collect::<Vec<i64>>();
+        if subscription_ids.is_empty() {
+            return Ok(());
+        }
+
+        // 近期每篇文章的发布时间
+        let history_lower = now - chrono::Duration::days(FIT_HISTORY_DAYS);
+        let published = feed_link::Entity::find()
+            .select_only()
+            .column(feed_link::Column::SubscriptionId)
+            .column(feed_link::Column::PublishedAt)
+            .filter(feed_link::Column::SubscriptionId.is_in(subscription_ids.clone()))
+            .filter(feed_link::Column::PublishedAt.between(history_lower, now))
+            .into_tuple::<(i64, NaiveDateTime)>()
+            .all(conn)
+            .await?;
+        let mut published_map: HashMap<i64, Vec<NaiveDateTime>> = HashMap::new();
+        for (subscription_id, published_at) in published {
+            published_map
+                .entry(subscription_id)
+                .or_default()
+                .push(published_at);
+        }
+        // 最近一篇文章的发布时间, 可能早于参考的历史
+        let last_published = feed_link::Entity::find()
+            .select_only()
+            .column(feed_link::Column::SubscriptionId)
+            .column_as(feed_link::Column::PublishedAt.max(), "published_at")
+            .filter(feed_link::Column::SubscriptionId.is_in(subscription_ids.clone()))
+            .filter(feed_link::Column::PublishedAt.lte(now))
+            .group_by(feed_link::Column::SubscriptionId)
+            .into_tuple::<(i64, Option<NaiveDateTime>)>()
+            .all(conn)
+            .await?
+            .into_iter()
+            .collect::<HashMap<i64, Option<NaiveDateTime>>>();
+
+        // 近期拉取的成功和失败次数
         let req = QuerySubscriptionRecordRequestBuilder::default()
-            .subscription_ids(subscription_ids)
-            .status(vec![
-                feed_build_record::Status::Success,
-                feed_build_record::Status::MostSuccess,
-            ])
-            .create_time_lower(seven_days_ago)
+            .subscription_ids(subscription_ids.clone())
+            .create_time_lower(now - chrono::Duration::days(7))
             .create_time_upper(now)
             .page(PageRequest::max_page())
             .build()?;
-
         let records = self.query_subscription_record(req, conn).await?.data;
-
-        let mut subscription_record_map: HashMap<i64, Vec<NaiveDateTime>> = HashMap::new();
+        let mut build_count_map: HashMap<i64, (usize, usize)> = HashMap::new();
         let mut last_build_at_map: HashMap<i64, NaiveDateTime> = HashMap::new();
-
         for record in records {
-            let subscription_id = record.subscription_id;
-            let create_time = record.created_at;
-            let status = record.status;
-            match status {
-                feed_build_record::Status::Faild
-                | feed_build_record::Status::Unknow
-                | feed_build_record::Status::FweSuccess => {
-                    subscription_record_map
-                        .entry(subscription_id)
-                        .or_insert(vec![])
-                        .push(create_time);
-                }
+            let count = build_count_map.entry(record.subscription_id).or_default();
+            match record.status {
+                feed_build_record::Status::Faild => count.1 += 1,
+                feed_build_record::Status::Unknow => {}
                 _ => {
-                    if let Some(records) = subscription_record_map.get_mut(&subscription_id) {
-                        records.push(create_time);
-                    }
-
-                    // 比较最新的时间， 更新 last_build_at
-                    if let Some(last_build_at) = last_build_at_map.get_mut(&subscription_id) {
-                        if create_time > *last_build_at {
-                            *last_build_at = create_time;
-                        }
-                    } else {
-                        last_build_at_map.insert(subscription_id, create_time);
+                    count.0 += 1;
+                    let last_build_at = last_build_at_map
+                        .entry(record.subscription_id)
+                        .or_insert(record.created_at);
+                    if record.created_at > *last_build_at {
+                        *last_build_at = record.created_at;
                     }
                 }
             }
         }
 
-        // 计算出每个订阅源的更新时间间隔
-        let mut subscription_update_interval: HashMap<i64, f32> = HashMap::new();
-        for (subscription_id, records) in subscription_record_map {
-            // 先排序
-            let mut records = records;
-            records.sort();
-            let mut interval = vec![];
-            for i in 0..records.len() - 1 {
-                // 计算出时间间隔，单位为分钟
-                let time = records[i + 1].and_utc().timestamp() - records[i].and_utc().timestamp();
-                // cast time to minutes
-                let minute = time / 60;
-                interval.push(minute);
-            }
-            // 去掉最大值和最小值
-            if interval.len() > 2 {
-                interval.sort();
-                interval.pop();
-                interval.remove(0);
-            }
-            if interval.is_empty() {
-                continue;
-            }
-            let avg_interval = interval.iter().sum::<i64>() as f32 / interval.len() as f32;
-            subscription_update_interval.insert(subscription_id, avg_interval);
-        }
         // 更新订阅源的更新配置
-        for (subscription_id, interval) in subscription_update_interval {
+        for subscription_id in subscription_ids {
+            let mut published = published_map.remove(&subscription_id).unwrap_or_default();
+            published.sort();
+            let (succeeded, failed) = build_count_map
+                .get(&subscription_id)
+                .copied()
+                .unwrap_or_default();
+            let frequency = fit_frequency(
+                &published,
+                last_published.get(&subscription_id).copied().flatten(),
+                succeeded,
+                failed,
+                now,
+            );
+
             let mut update_builder = UpdateSubscriptionConfigRequestBuilder::default();
             update_builder.subscription_id(subscription_id);
             update_builder.initial_frequency(3600.0);
-
-            update_builder.fitted_frequency(interval);
+            update_builder.fitted_frequency(frequency);
             update_builder.fitted_adaptive(true);
             update_builder.source_type(SubscriptionBuildSourceType::Rss);
-
             if let Some(last_build_at) = last_build_at_map.get(&subscription_id) {
                 update_builder.last_build_at(*last_build_at);
             }
@@ -206,12 +248,7 @@ impl SubscritionConfigController {
             0 => 0,
             _ => {
                 null_last_build_date_count
-                    .checked_sub(
-                        all_count
-                            .checked_sub(null_last_build_date_count)
-                            .unwrap_or(0),
-                    )
-                    .unwrap_or(0)
+                    .saturating_sub(all_count.saturating_sub(null_last_build_date_count))
                     .checked_div(req.expect_update_times as u64)
                     .unwrap_or(0)
                     + null_last_build_date_count
@@ -272,6 +309,66 @@ impl SubscritionConfigController {
         Ok(reqs)
     }
 
+    // 设置推送生效时的拉取频率, 为空时恢复基于更新记录的自适应频率
+    pub async fn update_push_frequency(
+        &self,
+        subscription_id: i64,
+        frequency: Option<f32>,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let origin_model = feed_build_config::Entity::find()
+            .filter(feed_build_config::Column::SubscriptionId.eq(subscription_id))
+            .one(conn)
+            .await?;
+        let mut model = match origin_model {
+            Some(m) => m.into_active_model(),
+            None => feed_build_config::ActiveModel {
+                subscription_id: Set(subscription_id),
+                initial_frequency: Set(3600.0),
+                source_type: Set(SubscriptionBuildSourceType::Rss),
+                ..Default::default()
+            },
+        };
+        model.fitted_frequency = Set(frequency);
+        model.fitted_adaptive = Set(Some(frequency.is_none()));
+        model.save(conn).await?;
+        Ok(())
+    }
+
+    // 保存订阅源最近一次响应的 ETag / Last-Modified, 下次拉取时发起条件请求
+    pub async fn update_subscription_validators(
+        &self,
+        subscription_id: i64,
+        validators: FeedValidators,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let origin_model = feed_build_config::Entity::find()
+            .filter(feed_build_config::Column::SubscriptionId.eq(subscription_id))
+            .one(conn)
+            .await?;
+        match origin_model {
+            Some(m) => {
+                let mut model = m.into_active_model();
+                model.etag = Set(validators.etag);
+                model.last_modified = Set(validators.last_modified);
+                model.update(conn).await?;
+            }
+            None => {
+                feed_build_config::ActiveModel {
+                    subscription_id: Set(subscription_id),
+                    initial_frequency: Set(3600.0),
+                    source_type: Set(SubscriptionBuildSourceType::Rss),
+                    etag: Set(validators.etag),
+                    last_modified: Set(validators.last_modified),
+                    ..Default::default()
+                }
+                .insert(conn)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
     // 添加订阅源的更新记录
     pub async fn insert_subscription_update_record(
         &self,
@@ -286,7 +383,10 @@ impl SubscritionConfigController {
 
         model.status = Set(req.status);
 
-        model.remark = Set("".to_string());
+        // remark 最长 255 个字符
+        let remark = req.remark.unwrap_or_default();
+        model.remark = Set(remark.chars().take(255).collect());
+        model.reason = Set(req.reason);
         if let Some(create_at) = req.create_time {
             model.created_at = Set(create_at);
         }
@@ -309,6 +409,11 @@ impl SubscritionConfigController {
                 );
             }
         }
+        if let Some(reasons) = &req.reasons {
+            if !reasons.is_empty() {
+                select = select.filter(feed_build_record::Column::Reason.is_in(reasons.clone()));
+            }
+        }
         select = select.order_by_desc(feed_build_record::Column::CreatedAt);
 
         // filter with time
@@ -390,6 +495,8 @@ mod tests {
             let req = InsertSubscriptionRecordRequest {
                 subscription_id: 1,
                 status: feed_build_record::Status::Success,
+                remark: None,
+                reason: None,
                 create_time: Some(date),
             };
             let record = controller
@@ -403,6 +510,7 @@ mod tests {
         let query = QuerySubscriptionRecordRequest {
             subscription_ids: Some(vec![1]),
             status: None,
+            reasons: None,
             create_time_lower: Some(dates[6]),
             create_time_upper: Some(dates[0]),
             page: PageRequest::max_page(),
@@ -549,4 +657,163 @@ mod tests {
             .unwrap();
         assert_eq!(query_preference_update_subscription_res.len(), 1);
     }
+
+    #[tokio::test]
+    async fn test_update_subscription_validators() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = SubscritionConfigController;
+        let validators = FeedValidators {
+            etag: Some("\"v1\"".to_string()),
+            last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+        };
+        controller
+            .update_subscription_validators(1, validators.clone(), &conn)
+            .await
+            .unwrap();
+        let configs = controller
+            .query_subscription_config(QuerySubscriptionConfigRequest::new(Some(vec![1])), &conn)
+            .await
+            .unwrap();
+        assert_eq!(configs.len(), 1);
+        assert_eq!(configs[0].etag, validators.etag);
+        assert_eq!(configs[0].last_modified, validators.last_modified);
+
+        // 再次保存时覆盖原有的值
+        controller
+            .update_subscription_validators(1, FeedValidators::default(), &conn)
+            .await
+            .unwrap();
+        let configs = controller
+            .query_subscription_config(QuerySubscriptionConfigRequest::new(Some(vec![1])), &conn)
+            .await
+            .unwrap();
+        assert!(configs[0].etag.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_subscription_record_by_reason() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = SubscritionConfigController;
+        let err = ErrorInService::from(lib_crawler::FetchError::HttpStatus(404));
+        let req = InsertSubscriptionRecordRequestBuilder::default()
+            .subscription_id(1)
+            .status(feed_build_record::Status::Faild)
+            .reason(err.kind())
+            .remark(err.to_string())
+            .build()
+            .unwrap();
+        controller
+            .insert_subscription_update_record(req, &conn)
+            .await
+            .unwrap();
+        let req = InsertSubscriptionRecordRequestBuilder::default()
+            .subscription_id(1)
+            .status(feed_build_record::Status::Success)
+            .build()
+            .unwrap();
+        controller
+            .insert_subscription_update_record(req, &conn)
+            .await
+            .unwrap();
+
+        let query = QuerySubscriptionRecordRequestBuilder::default()
+            .reasons(vec!["http_status".to_string()])
+            .page(PageRequest::max_page())
+            .build()
+            .unwrap();
+        let records = controller
+            .query_subscription_record(query, &conn)
+            .await
+            .unwrap();
+        assert_eq!(records.data.len(), 1);
+        assert_eq!(records.data[0].reason.as_deref(), Some("http_status"));
+        assert!(records.data[0].remark.contains("404"));
+    }
+
+    #[test]
+    fn test_fit_frequency() {
+        let now = chrono::Utc::now().naive_utc();
+        let hours_ago = |hours: i64| now - chrono::Duration::hours(hours);
+        // 每小时一篇的新闻, 半小时拉取一次
+        let busy = (0..24).rev().map(hours_ago).collect::<Vec<_>>();
+        let frequency = fit_frequency(&busy, busy.last().copied(), 10, 0, now);
+        assert!((frequency - 30.0).abs() < 0.1);
+        // 拉取失败一半时, 拉取间隔变为 2.5 倍
+        let frequency = fit_frequency(&busy, busy.last().copied(), 5, 5, now);
+        assert!((frequency - 75.0).abs() < 0.1);
+        // 每周一篇的博客
+        let quiet = vec![hours_ago(24 * 14), hours_ago(24 * 7), hours_ago(1)];
+        let frequency = fit_frequency(&quiet, quiet.last().copied(), 1, 0, now);
+        assert_eq!(frequency, MAX_FETCH_FREQUENCY);
+        // 曾经很活跃但已经沉寂了两天
+        let dormant = (48..72).rev().map(hours_ago).collect::<Vec<_>>();
+        let frequency = fit_frequency(&dormant, dormant.last().copied(), 1, 0, now);
+        assert!((frequency - 12.0 * 60.0).abs() < 0.1);
+        // 发布很频繁时不低于下限
+        let burst = (0..60)
+            .rev()
+            .map(|i| now - chrono::Duration::minutes(i))
+            .collect::<Vec<_>>();
+        let frequency = fit_frequency(&burst, burst.last().copied(), 1, 0, now);
+        assert_eq!(frequency, MIN_FETCH_FREQUENCY);
+        assert_eq!(fit_frequency(&[], None, 0, 0, now), MAX_FETCH_FREQUENCY);
+    }
+
+    #[tokio::test]
+    async fn test_update_subscription_config() {
+        let conn = crate::test_runner::setup_database().await;
+        let category = CategoryController
+            .insert_category(
+                crate::feed::schema::CreateOrUpdateCategoryRequestBuilder::default()
+                    .title("test")
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let (_, subscription_id) = SubscriptionController
+            .insert_subscription(
+                crate::feed::schema::CreateOrUpdateSubscriptionRequestBuilder::default()
+                    .category_id(category.id)
+                    .title("news")
+                    .link("http://example.com/news.xml")
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let now = chrono::Utc::now().naive_utc();
+        for i in 0..12 {
+            let req = crate::feed::schema::CreateOrUpdateRssLinkRequestBuilder::default()
+                .title(format!("news {}", i))
+                .link(format!("http://example.com/news/{}", i))
+                .subscrption_id(subscription_id)
+                .published_at(now - chrono::Duration::hours(2 * i))
+                .build()
+                .unwrap();
+            crate::feed::LinkController
+                .insert_link(req, &conn)
+                .await
+                .unwrap();
+        }
+
+        let controller = SubscritionConfigController;
+        controller
+            .update_subscription_config(vec![subscription_id], &conn)
+            .await
+            .unwrap();
+        let config = controller
+            .query_subscription_config(
+                QuerySubscriptionConfigRequest::new(Some(vec![subscription_id])),
+                &conn,
+            )
+            .await
+            .unwrap()
+            .remove(0);
+        // 每两小时一篇, 每小时拉取一次
+        assert_eq!(config.fitted_adaptive, Some(true));
+        assert!((config.get_frequency() - 60.0).abs() < 0.1);
+    }
 }
@@ -0,0 +1,494 @@
+use lib_crawler::{request_websub, verify_websub_signature, WebSubHub, WebSubMode, WebSubRequest};
+use lib_entity::{feed_link, feed_subscription, websub_subscription};
+use lib_utils::password::random_hex;
+use sea_orm::{entity::*, query::*};
+
+use super::schema::{CreateOrUpdateSubscriptionRequest, WebSubOption};
+use super::{SubscriptionParseController, SubscritionConfigController};
+use crate::{error::ErrorInService, DBConnection};
+
+pub use websub_subscription::State as WebSubState;
+
+// 等待 hub 验证的订阅, 超过这个时间没有验证时重新订阅, 单位秒
+const PENDING_RETRY_SECS: i64 = 60 * 60;
+// 被 hub 拒绝的订阅, 超过这个时间后重新尝试, 单位秒
+const DENIED_RETRY_SECS: i64 = 60 * 60 * 24;
+
+pub struct WebSubController;
+
+impl WebSubController {
+    /// 向订阅源声明的 hub 订阅推送, 已经生效且不需要续订时跳过
+    ///
+    /// 返回是否向 hub 发送了请求, hub 之后会请求回调地址验证订阅意图
+    pub async fn request_subscription(
+        &self,
+        subscription_id: i64,
+        hub: WebSubHub,
+        option: &WebSubOption,
+        conn: &DBConnection,
+    ) -> Result<bool, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let origin = self.find(subscription_id, conn).await?;
+        let mut model = match origin {
+            Some(m) => {
+                let same_hub = m.hub == hub.hub && m.topic == hub.topic;
+                let skip = match m.state {
+                    WebSubState::Active => m.expired_at.is_some_and(|expired_at| {
+                        expired_at - chrono::Duration::seconds(option.renew_before_secs) > now
+                    }),
+                    WebSubState::Pending => {
+                        m.updated_at + chrono::Duration::seconds(PENDING_RETRY_SECS) > now
+                    }
+                    WebSubState::Denied => {
+                        m.updated_at + chrono::Duration::seconds(DENIED_RETRY_SECS) > now
+                    }
+                    WebSubState::Unsubscribed => false,
+                };
+                if same_hub && skip {
+                    return Ok(false);
+                }
+                let lease_alive = m.expired_at.is_some_and(|expired_at| expired_at > now);
+                let mut model = m.clone().into_active_model();
+                // 续订时保持生效状态和密钥, 避免续订期间的推送校验失败; 已经过期的重新订阅
+                if !(same_hub && m.state == WebSubState::Active && lease_alive) {
+                    model.state = Set(WebSubState::Pending);
+                    model.secret = Set(random_hex(20));
+                }
+                model
+            }
+            None => websub_subscription::ActiveModel {
+                subscription_id: Set(subscription_id),
+                state: Set(WebSubState::Pending),
+                secret: Set(random_hex(20)),
+                created_at: Set(now),
+                ..Default::default()
+            },
+        };
+        model.hub = Set(hub.hub.clone());
+        model.topic = Set(hub.topic.clone());
+        model.updated_at = Set(now);
+        let model = model.save(conn).await?.try_into_model()?;
+
+        let req = WebSubRequest {
+            hub,
+            mode: WebSubMode::Subscribe,
+            callback: option.callback(subscription_id),
+            secret: model.secret,
+            lease_seconds: option.lease_seconds,
+            allow_private: option.allow_private,
+        };
+        request_websub(&req).await?;
+        Ok(true)
+    }
+
+    /// 订阅源不再声明 hub 时取消订阅, 并恢复自适应的拉取频率
+    pub async fn unsubscribe(
+        &self,
+        subscription_id: i64,
+        option: &WebSubOption,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let Some(m) = self.find(subscription_id, conn).await? else {
+            return Ok(());
+        };
+        if m.state == WebSubState::Unsubscribed {
+            return Ok(());
+        }
+        let req = WebSubRequest {
+            hub: WebSubHub {
+                hub: m.hub.clone(),
+                topic: m.topic.clone(),
+            },
+            mode: WebSubMode::Unsubscribe,
+            callback: option.callback(subscription_id),
+            secret: m.secret.clone(),
+            lease_seconds: option.lease_seconds,
+            allow_private: option.allow_private,
+        };
+        self.update_state(m, WebSubState::Unsubscribed, conn)
+            .await?;
+        // hub 不可用时也认为已经取消, 租期到期后 hub 会自动停止推送
+        if let Err(e) = request_websub(&req).await {
+            tracing::warn!("取消 WebSub 订阅失败:{}", e);
+        }
+        Ok(())
+    }
+
+    /// 处理 hub 的订阅意图验证, 返回 true 时需要原样返回 `hub.challenge`
+    pub async fn verify_intent(
+        &self,
+        subscription_id: i64,
+        mode: WebSubMode,
+        topic: &str,
+        lease_seconds: Option<i64>,
+        option: &WebSubOption,
+        conn: &DBConnection,
+    ) -> Result<bool, ErrorInService> {
+        let Some(m) = self.find(subscription_id, conn).await? else {
+            return Ok(false);
+        };
+        if m.topic != topic {
+            return Ok(false);
+        }
+        match mode {
+            WebSubMode::Subscribe => {
+                if m.state == WebSubState::Unsubscribed {
+                    return Ok(false);
+                }
+                let lease_seconds = lease_seconds.unwrap_or(option.lease_seconds);
+                let now = chrono::Utc::now().naive_utc();
+                let mut model = m.into_active_model();
+                model.state = Set(WebSubState::Active);
+                model.lease_seconds = Set(Some(lease_seconds));
+                model.expired_at = Set(Some(now + chrono::Duration::seconds(lease_seconds)));
+                model.updated_at = Set(now);
+                model.update(conn).await?;
+                // 推送生效后只需要低频拉取兜底
+                SubscritionConfigController
+                    .update_push_frequency(subscription_id, Some(option.poll_frequency), conn)
+                    .await?;
+                Ok(true)
+            }
+            WebSubMode::Unsubscribe => Ok(m.state == WebSubState::Unsubscribed),
+        }
+    }
+
+    /// hub 拒绝订阅, 恢复自适应的拉取频率
+    pub async fn deny(
+        &self,
+        subscription_id: i64,
+        topic: &str,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let Some(m) = self.find(subscription_id, conn).await? else {
+            return Ok(());
+        };
+        if m.topic != topic {
+            return Ok(());
+        }
+        self.update_state(m, WebSubState::Denied, conn).await
+    }
+
+    /// 接收 hub 推送的订阅源内容, 校验签名后按照拉取的方式写入
+    ///
+    /// 订阅未生效或签名不正确时返回 None, 推送的内容会被丢弃
+    pub async fn receive(
+        &self,
+        subscription_id: i64,
+        signature: Option<&str>,
+        body: &[u8],
+        conn: &DBConnection,
+    ) -> Result<Option<Vec<feed_link::Model>>, ErrorInService> {
+        let Some(m) = self.find(subscription_id, conn).await? else {
+            return Ok(None);
+        };
+        if m.state != WebSubState::Active {
+            return Ok(None);
+        }
+        if !signature.is_some_and(|s| verify_websub_signature(&m.secret, s, body)) {
+            tracing::warn!(
+                "WebSub 推送的签名不正确, subscription_id:{}",
+                subscription_id
+            );
+            return Ok(None);
+        }
+        let Some(subscription) = feed_subscription::Entity::find_by_id(subscription_id)
+            .one(conn)
+            .await?
+        else {
+            return Ok(None);
+        };
+        let existing: CreateOrUpdateSubscriptionRequest = subscription.into();
+
+        let content = String::from_utf8_lossy(body);
+        let feed = lib_crawler::parse_feed(&content)?;
+        let resp = SubscriptionParseController::build_parse_result(
+            &existing.link,
+            &feed,
+            Default::default(),
+        )?;
+        let links = SubscriptionParseController::apply_parse_result(&existing, resp, conn).await?;
+
+        let mut model = m.into_active_model();
+        model.last_pushed_at = Set(Some(chrono::Utc::now().naive_utc()));
+        model.update(conn).await?;
+        Ok(Some(links))
+    }
+
+    /// 续订即将到期的订阅, 已经过期的订阅先恢复自适应的拉取频率
+    ///
+    /// 返回发送了续订请求的数量
+    pub async fn renew_leases(
+        &self,
+        option: &WebSubOption,
+        conn: &DBConnection,
+    ) -> Result<usize, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let renew_before = now + chrono::Duration::seconds(option.renew_before_secs);
+        let models = websub_subscription::Entity::find()
+            .filter(websub_subscription::Column::State.eq(WebSubState::Active))
+            .filter(websub_subscription::Column::ExpiredAt.lt(renew_before))
+            .all(conn)
+            .await?;
+
+        let mut renewed = 0;
+        for m in models {
+            let subscription_id = m.subscription_id;
+            let hub = WebSubHub {
+                hub: m.hub.clone(),
+                topic: m.topic.clone(),
+            };
+            // 不在这里修改状态, 否则刚更新的 updated_at 会让重新订阅被当作等待验证而跳过
+            if m.expired_at.is_some_and(|expired_at| expired_at <= now) {
+                SubscritionConfigController
+                    .update_push_frequency(subscription_id, None, conn)
+                    .await?;
+            }
+            match self
+                .request_subscription(subscription_id, hub, option, conn)
+                .await
+            {
+                Ok(true) => renewed += 1,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("续订 WebSub 失败:{} {}", subscription_id, e),
+            }
+        }
+        Ok(renewed)
+    }
+
+    /// 推送生效中的订阅源 id
+    pub async fn active_subscription_ids(
+        &self,
+        conn: &DBConnection,
+    ) -> Result<Vec<i64>, ErrorInService> {
+        let ids = websub_subscription::Entity::find()
+            .select_only()
+            .column(websub_subscription::Column::SubscriptionId)
+            .filter(websub_subscription::Column::State.eq(WebSubState::Active))
+            .filter(websub_subscription::Column::ExpiredAt.gt(chrono::Utc::now().naive_utc()))
+            .into_tuple::<i64>()
+            .all(conn)
+            .await?;
+        Ok(ids)
+    }
+
+    pub async fn find(
+        &self,
+        subscription_id: i64,
+        conn: &DBConnection,
+    ) -> Result<Option<websub_subscription::Model>, ErrorInService> {
+        let model = websub_subscription::Entity::find()
+            .filter(websub_subscription::Column::SubscriptionId.eq(subscription_id))
+            .one(conn)
+            .await?;
+        Ok(model)
+    }
+
+    // 推送不再生效, 恢复自适应的拉取频率
+    async fn update_state(
+        &self,
+        m: websub_subscription::Model,
+        state: WebSubState,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let subscription_id = m.subscription_id;
+        let mut model = m.into_active_model();
+        model.state = Set(state);
+        model.updated_at = Set(chrono::Utc::now().naive_utc());
+        model.update(conn).await?;
+        SubscritionConfigController
+            .update_push_frequency(subscription_id, None, conn)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::schema::{
+        CreateOrUpdateCategoryRequestBuilder, CreateOrUpdateSubscriptionRequestBuilder,
+    };
+    use crate::feed::{CategoryController, SubscriptionController};
+    use lib_crawler::sign_websub_payload;
+    use lib_entity::feed_build_config;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // 本地的 hub, 接受所有订阅请求
+    async fn serve_hub() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 16 * 1024];
+                _ = stream.read(&mut buf).await;
+                let resp =
+                    "HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+                _ = stream.write_all(resp.as_bytes()).await;
+                _ = stream.shutdown().await;
+            }
+        });
+        format!("http://{}/hub", addr)
+    }
+
+    #[tokio::test]
+    async fn test_websub_subscribe_and_push() {
+        let conn = crate::test_runner::setup_database().await;
+        let category = CategoryController
+            .insert_category(
+                CreateOrUpdateCategoryRequestBuilder::default()
+                    .title("科技")
+                    .account_id(1)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let (_, subscription_id) = SubscriptionController
+            .subscribe(
+                1,
+                CreateOrUpdateSubscriptionRequestBuilder::default()
+                    .title("feed")
+                    .link("http://example.com/feed.xml")
+                    .category_id(category.id)
+                    .build()
+                    .unwrap(),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let option = WebSubOption {
+            callback_base: "http://localhost:9000".to_string(),
+            lease_seconds: 3600,
+            renew_before_secs: 600,
+            poll_frequency: 1440.0,
+            allow_private: true,
+        };
+        let hub = WebSubHub {
+            hub: serve_hub().await,
+            topic: "http://example.com/feed.xml".to_string(),
+        };
+        let controller = WebSubController;
+        assert!(controller
+            .request_subscription(subscription_id, hub.clone(), &option, &conn)
+            .await
+            .unwrap());
+        // 等待验证期间不重复订阅
+        assert!(!controller
+            .request_subscription(subscription_id, hub.clone(), &option, &conn)
+            .await
+            .unwrap());
+
+        // topic 不一致时拒绝验证
+        let verified = controller
+            .verify_intent(
+                subscription_id,
+                WebSubMode::Subscribe,
+                "http://other",
+                None,
+                &option,
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(!verified);
+        let verified = controller
+            .verify_intent(
+                subscription_id,
+                WebSubMode::Subscribe,
+                &hub.topic,
+                Some(3600),
+                &option,
+                &conn,
+            )
+            .await
+            .unwrap();
+        assert!(verified);
+        assert_eq!(
+            controller.active_subscription_ids(&conn).await.unwrap(),
+            vec![subscription_id]
+        );
+        let config = feed_build_config::Entity::find()
+            .filter(feed_build_config::Column::SubscriptionId.eq(subscription_id))
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.get_frequency(), 1440.0);
+        assert_eq!(config.fitted_adaptive, Some(false));
+
+        let body = r#"<?xml version="1.0"?>
+<rss version="2.0"><channel><title>feed</title><link>http://example.com</link><description>d</description>
+<item><title>pushed</title><link>http://example.com/pushed</link></item>
+</channel></rss>"#;
+        let secret = controller
+            .find(subscription_id, &conn)
+            .await
+            .unwrap()
+            .unwrap()
+            .secret;
+        // 签名不正确的推送被丢弃
+        let pushed = controller
+            .receive(subscription_id, Some("sha256=00"), body.as_bytes(), &conn)
+            .await
+            .unwrap();
+        assert!(pushed.is_none());
+        let signature = sign_websub_payload(&secret, body.as_bytes());
+        let pushed = controller
+            .receive(subscription_id, Some(&signature), body.as_bytes(), &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(pushed.len(), 1);
+        assert_eq!(pushed[0].link, "http://example.com/pushed");
+
+        // 租期即将到期时续订
+        assert_eq!(controller.renew_leases(&option, &conn).await.unwrap(), 0);
+        let option = WebSubOption {
+            renew_before_secs: 7200,
+            ..option
+        };
+        assert_eq!(controller.renew_leases(&option, &conn).await.unwrap(), 1);
+
+        // 租期已经过期时重新订阅, 等待 hub 验证
+        let mut model = controller
+            .find(subscription_id, &conn)
+            .await
+            .unwrap()
+            .unwrap()
+            .into_active_model();
+        model.expired_at = Set(Some(
+            chrono::Utc::now().naive_utc() - chrono::Duration::hours(1),
+        ));
+        model.update(&conn).await.unwrap();
+        assert_eq!(controller.renew_leases(&option, &conn).await.unwrap(), 1);
+        let m = controller
+            .find(subscription_id, &conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(m.state, WebSubState::Pending);
+        assert!(controller
+            .active_subscription_ids(&conn)
+            .await
+            .unwrap()
+            .is_empty());
+
+        controller
+            .deny(subscription_id, &hub.topic, &conn)
+            .await
+            .unwrap();
+        assert!(controller
+            .active_subscription_ids(&conn)
+            .await
+            .unwrap()
+            .is_empty());
+        let config = feed_build_config::Entity::find()
+            .filter(feed_build_config::Column::SubscriptionId.eq(subscription_id))
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.fitted_adaptive, Some(true));
+    }
+}
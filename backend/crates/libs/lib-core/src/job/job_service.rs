@@ -0,0 +1,438 @@
+use chrono::NaiveDateTime;
+use lib_entity::job_queue::{self, Kind as JobKind, Status as JobStatus};
+use sea_orm::sea_query::Expr;
+use sea_orm::{entity::*, query::*};
+
+use super::schema::{EnqueueJobRequest, JobPayload, JobStats, LeasedJob};
+use crate::common_schema::{PageRequest, PageResponse};
+use crate::{error::ErrorInService, DBConnection};
+use lib_utils::math::{get_page_count, get_page_offset};
+
+// 第一次重试的等待时间, 之后每次翻倍, 单位秒
+const RETRY_BASE_SECS: i64 = 30;
+// 重试的最长等待时间, 单位秒
+const RETRY_MAX_SECS: i64 = 60 * 60;
+
+/// 第 `attempts` 次执行失败后, 下一次执行前等待的秒数
+pub fn retry_delay(attempts: i32) -> i64 {
+    let exp = attempts.clamp(1, 20) as u32 - 1;
+    RETRY_BASE_SECS
+        .saturating_mul(2_i64.saturating_pow(exp))
+        .min(RETRY_MAX_SECS)
+}
+
+pub struct JobController;
+
+impl JobController {
+    /// 加入任务队列, 已经有相同的未完成任务时不重复加入
+    ///
+    /// 返回是否新建了任务, 以及任务的 id
+    pub async fn enqueue(
+        &self,
+        req: EnqueueJobRequest,
+        conn: &DBConnection,
+    ) -> Result<(bool, i64), ErrorInService> {
+        let dedup_key = req.payload.dedup_key();
+        let exists = job_queue::Entity::find()
+            .filter(job_queue::Column::DedupKey.eq(dedup_key.clone()))
+            .filter(job_queue::Column::Status.is_in([JobStatus::Pending, JobStatus::Running]))
+            .one(conn)
+            .await?;
+        if let Some(m) = exists {
+            return Ok((false, m.id));
+        }
+        let payload = serde_json::to_string(&req.payload)
+            .map_err(|e| ErrorInService::Custom(format!("任务参数序列化失败:{}", e)))?;
+        let now = chrono::Utc::now().naive_utc();
+        let model = job_queue::ActiveModel {
+            kind: Set(req.payload.kind()),
+            payload: Set(payload),
+            dedup_key: Set(Some(dedup_key)),
+            priority: Set(req.priority),
+            status: Set(JobStatus::Pending),
+            attempts: Set(0),
+            max_attempts: Set(req.max_attempts.max(1)),
+            run_at: Set(req.run_at.unwrap_or(now)),
+            created_at: Set(now),
+            updated_at: Set(now),
+            ..Default::default()
+        }
+        .insert(conn)
+        .await?;
+        Ok((true, model.id))
+    }
+
+    /// 租用最多 `limit` 个可以执行的任务, 优先级高的先执行
+    ///
+    /// 租期过后还没有完成的任务视为 worker 已经退出, 会被重新租用
+    pub async fn lease(
+        &self,
+        worker: &str,
+        limit: u64,
+        lease_secs: i64,
+        conn: &DBConnection,
+    ) -> Result<Vec<LeasedJob>, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let candidates = job_queue::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(
+                        Condition::all()
+                            .add(job_queue::Column::Status.eq(JobStatus::Pending))
+                            .add(job_queue::Column::RunAt.lte(now)),
+                    )
+                    .add(
+                        Condition::all()
+                            .add(job_queue::Column::Status.eq(JobStatus::Running))
+                            .add(job_queue::Column::LeaseUntil.lt(now)),
+                    ),
+            )
+            .order_by_desc(job_queue::Column::Priority)
+            .order_by_asc(job_queue::Column::RunAt)
+            .order_by_asc(job_queue::Column::Id)
+            .limit(limit)
+            .all(conn)
+            .await?;
+
+        let mut leased = vec![];
+        for m in candidates {
+            // 租期过期且没有重试次数的任务直接放弃
+            if m.status == JobStatus::Running && m.attempts >= m.max_attempts {
+                self.finish(m, JobStatus::Dead, Some("租期过期".to_string()), conn)
+                    .await?;
+                continue;
+            }
+            let payload = match serde_json::from_str::<JobPayload>(&m.payload) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    let error = format!("任务参数解析失败:{}", e);
+                    self.finish(m, JobStatus::Dead, Some(error), conn).await?;
+                    continue;
+                }
+            };
+            // 以 updated_at 作为版本, 只有一个 worker 能够租用成功
+            let result = job_queue::Entity::update_many()
+                .col_expr(job_queue::Column::Status, Expr::value(JobStatus::Running))
+                .col_expr(
+                    job_queue::Column::Attempts,
+                    Expr::col(job_queue::Column::Attempts).add(1),
+                )
+                .col_expr(job_queue::Column::LockedBy, Expr::value(worker))
+                .col_expr(
+                    job_queue::Column::LeaseUntil,
+                    Expr::value(now + chrono::Duration::seconds(lease_secs)),
+                )
+                .col_expr(job_queue::Column::UpdatedAt, Expr::value(now))
+                .filter(job_queue::Column::Id.eq(m.id))
+                .filter(job_queue::Column::Status.eq(m.status))
+                .filter(job_queue::Column::UpdatedAt.eq(m.updated_at))
+                .exec(conn)
+                .await?;
+            if result.rows_affected == 1 {
+                leased.push(LeasedJob {
+                    id: m.id,
+                    payload,
+                    attempts: m.attempts + 1,
+                    max_attempts: m.max_attempts,
+                });
+            }
+        }
+        Ok(leased)
+    }
+
+    /// 任务执行成功, 租约已经被其他 worker 接管时返回 false
+    pub async fn complete(
+        &self,
+        id: i64,
+        worker: &str,
+        conn: &DBConnection,
+    ) -> Result<bool, ErrorInService> {
+        let Some(m) = self.find_leased(id, worker, conn).await? else {
+            return Ok(false);
+        };
+        self.finish(m, JobStatus::Succeeded, None, conn).await?;
+        Ok(true)
+    }
+
+    /// 任务执行失败, 还有重试次数时按指数退避重新排队, 否则进入死信
+    ///
+    /// 返回任务的新状态, 租约已经被其他 worker 接管时返回 None
+    pub async fn fail(
+        &self,
+        id: i64,
+        worker: &str,
+        error: &str,
+        conn: &DBConnection,
+    ) -> Result<Option<JobStatus>, ErrorInService> {
+        let Some(m) = self.find_leased(id, worker, conn).await? else {
+            return Ok(None);
+        };
+        // 错误信息最长 1024 个字符
+        let error = error.chars().take(1024).collect::<String>();
+        if m.attempts >= m.max_attempts {
+            self.finish(m, JobStatus::Dead, Some(error), conn).await?;
+            return Ok(Some(JobStatus::Dead));
+        }
+        let now = chrono::Utc::now().naive_utc();
+        let run_at = now + chrono::Duration::seconds(retry_delay(m.attempts));
+        let mut model = m.into_active_model();
+        model.status = Set(JobStatus::Pending);
+        model.run_at = Set(run_at);
+        model.locked_by = Set(None);
+        model.lease_until = Set(None);
+        model.last_error = Set(Some(error));
+        model.updated_at = Set(now);
+        model.update(conn).await?;
+        Ok(Some(JobStatus::Pending))
+    }
+
+    /// 把死信任务重新排队, 重新计算执行次数
+    pub async fn retry_dead(
+        &self,
+        ids: Option<Vec<i64>>,
+        conn: &DBConnection,
+    ) -> Result<u64, ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let mut update = job_queue::Entity::update_many()
+            .col_expr(job_queue::Column::Status, Expr::value(JobStatus::Pending))
+            .col_expr(job_queue::Column::Attempts, Expr::value(0))
+            .col_expr(job_queue::Column::RunAt, Expr::value(now))
+            .col_expr(
+                job_queue::Column::FinishedAt,
+                Expr::value(Option::<NaiveDateTime>::None),
+            )
+            .col_expr(job_queue::Column::UpdatedAt, Expr::value(now))
+            .filter(job_queue::Column::Status.eq(JobStatus::Dead));
+        if let Some(ids) = ids {
+            update = update.filter(job_queue::Column::Id.is_in(ids));
+        }
+        let result = update.exec(conn).await?;
+        Ok(result.rows_affected)
+    }
+
+    /// 删除早于 `before` 完成的任务, 死信任务保留
+    pub async fn purge_finished(
+        &self,
+        before: NaiveDateTime,
+        conn: &DBConnection,
+    ) -> Result<u64, ErrorInService> {
+        let result = job_queue::Entity::delete_many()
+            .filter(job_queue::Column::Status.eq(JobStatus::Succeeded))
+            .filter(job_queue::Column::FinishedAt.lt(before))
+            .exec(conn)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// 查询任务, 按照创建时间倒序
+    pub async fn query_jobs(
+        &self,
+        kind: Option<JobKind>,
+        status: Option<JobStatus>,
+        page: PageRequest,
+        conn: &DBConnection,
+    ) -> Result<PageResponse<job_queue::Model>, ErrorInService> {
+        let mut query = job_queue::Entity::find();
+        if let Some(kind) = kind {
+            query = query.filter(job_queue::Column::Kind.eq(kind));
+        }
+        if let Some(status) = status {
+            query = query.filter(job_queue::Column::Status.eq(status));
+        }
+        let total = query.clone().count(conn).await?;
+        let page_size = page.page_size;
+        let page_index = page.page;
+        let data = query
+            .order_by_desc(job_queue::Column::CreatedAt)
+            .order_by_desc(job_queue::Column::Id)
+            .offset(get_page_offset(page_index, page_size))
+            .limit(page_size)
+            .all(conn)
+            .await?;
+        Ok(PageResponse::new(
+            get_page_count(total, page_size),
+            page_index,
+            page_size,
+            data,
+        ))
+    }
+
+    /// 各个类型和状态的任务数量
+    pub async fn stats(&self, conn: &DBConnection) -> Result<Vec<JobStats>, ErrorInService> {
+        let rows = job_queue::Entity::find()
+            .select_only()
+            .column(job_queue::Column::Kind)
+            .column(job_queue::Column::Status)
+            .column_as(job_queue::Column::Id.count(), "count")
+            .group_by(job_queue::Column::Kind)
+            .group_by(job_queue::Column::Status)
+            .into_tuple::<(JobKind, JobStatus, i64)>()
+            .all(conn)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .map(|(kind, status, count)| JobStats {
+                kind,
+                status,
+                count,
+            })
+            .collect())
+    }
+
+    // 当前 worker 租用中的任务
+    async fn find_leased(
+        &self,
+        id: i64,
+        worker: &str,
+        conn: &DBConnection,
+    ) -> Result<Option<job_queue::Model>, ErrorInService> {
+        let model = job_queue::Entity::find_by_id(id)
+            .filter(job_queue::Column::Status.eq(JobStatus::Running))
+            .filter(job_queue::Column::LockedBy.eq(worker))
+            .one(conn)
+            .await?;
+        Ok(model)
+    }
+
+    async fn finish(
+        &self,
+        m: job_queue::Model,
+        status: JobStatus,
+        error: Option<String>,
+        conn: &DBConnection,
+    ) -> Result<(), ErrorInService> {
+        let now = chrono::Utc::now().naive_utc();
+        let mut model = m.into_active_model();
+        model.status = Set(status);
+        model.locked_by = Set(None);
+        model.lease_until = Set(None);
+        if error.is_some() {
+            model.last_error = Set(error);
+        }
+        model.finished_at = Set(Some(now));
+        model.updated_at = Set(now);
+        model.update(conn).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), 30);
+        assert_eq!(retry_delay(2), 60);
+        assert_eq!(retry_delay(4), 240);
+        assert_eq!(retry_delay(100), RETRY_MAX_SECS);
+    }
+
+    #[tokio::test]
+    async fn test_job_queue() {
+        let conn = crate::test_runner::setup_database().await;
+        let controller = JobController;
+        let refresh = JobPayload::FeedRefresh { subscription_id: 1 };
+        let (created, id) = controller
+            .enqueue(EnqueueJobRequest::new(refresh.clone()), &conn)
+            .await
+            .unwrap();
+        assert!(created);
+        // 相同的未完成任务不重复加入
+        let (created, same_id) = controller
+            .enqueue(EnqueueJobRequest::new(refresh.clone()), &conn)
+            .await
+            .unwrap();
+        assert!(!created);
+        assert_eq!(id, same_id);
+        let (_, meta_id) = controller
+            .enqueue(
+                EnqueueJobRequest::new(JobPayload::LinkMeta { link_id: 1 }),
+                &conn,
+            )
+            .await
+            .unwrap();
+        let tomorrow = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+        controller
+            .enqueue(
+                EnqueueJobRequest::new(JobPayload::Prune { keep_days: 180 }).with_run_at(tomorrow),
+                &conn,
+            )
+            .await
+            .unwrap();
+
+        // 优先级高的先执行, 未到时间的任务不执行
+        let leased = controller.lease("a", 10, 60, &conn).await.unwrap();
+        assert_eq!(
+            leased.iter().map(|j| j.id).collect::<Vec<_>>(),
+            vec![id, meta_id]
+        );
+        assert_eq!(leased[0].payload, refresh);
+        assert_eq!(leased[0].attempts, 1);
+        // 已经租用的任务不会被其他 worker 租用
+        assert!(controller
+            .lease("b", 10, 60, &conn)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(!controller.complete(id, "b", &conn).await.unwrap());
+        assert!(controller.complete(id, "a", &conn).await.unwrap());
+
+        // 失败后按退避时间重新排队
+        let status = controller
+            .fail(meta_id, "a", "timeout", &conn)
+            .await
+            .unwrap();
+        assert_eq!(status, Some(JobStatus::Pending));
+        let job = job_queue::Entity::find_by_id(meta_id)
+            .one(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(job.run_at > chrono::Utc::now().naive_utc());
+        assert_eq!(job.last_error.as_deref(), Some("timeout"));
+
+        // 重试次数用完后进入死信
+        job_queue::Entity::update_many()
+            .col_expr(job_queue::Column::RunAt, Expr::value(job.created_at))
+            .col_expr(job_queue::Column::Attempts, Expr::value(4))
+            .filter(job_queue::Column::Id.eq(meta_id))
+            .exec(&conn)
+            .await
+            .unwrap();
+        let leased = controller.lease("a", 10, 60, &conn).await.unwrap();
+        assert_eq!(leased[0].attempts, 5);
+        let status = controller.fail(meta_id, "a", "gone", &conn).await.unwrap();
+        assert_eq!(status, Some(JobStatus::Dead));
+        assert_eq!(controller.retry_dead(None, &conn).await.unwrap(), 1);
+
+        // 租期过期的任务会被其他 worker 接管
+        let leased = controller.lease("a", 10, -1, &conn).await.unwrap();
+        assert_eq!(leased[0].id, meta_id);
+        let leased = controller.lease("b", 10, 60, &conn).await.unwrap();
+        assert_eq!(leased[0].id, meta_id);
+        assert_eq!(
+            controller.fail(meta_id, "a", "late", &conn).await.unwrap(),
+            None
+        );
+
+        let stats = controller.stats(&conn).await.unwrap();
+        let count = |kind: JobKind, status: JobStatus| {
+            stats
+                .iter()
+                .find(|s| s.kind == kind && s.status == status)
+                .map(|s| s.count)
+        };
+        assert_eq!(count(JobKind::FeedRefresh, JobStatus::Succeeded), Some(1));
+        assert_eq!(count(JobKind::LinkMeta, JobStatus::Running), Some(1));
+        assert_eq!(count(JobKind::Prune, JobStatus::Pending), Some(1));
+
+        // 完成的任务被清理后可以重新加入
+        assert_eq!(controller.purge_finished(tomorrow, &conn).await.unwrap(), 1);
+        let (created, _) = controller
+            .enqueue(EnqueueJobRequest::new(refresh), &conn)
+            .await
+            .unwrap();
+        assert!(created);
+    }
+}
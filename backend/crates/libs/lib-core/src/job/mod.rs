@@ -0,0 +1,4 @@
+mod job_service;
+pub mod schema;
+pub use job_service::{retry_delay, JobController};
+pub use lib_entity::job_queue::{Kind as JobKind, Status as JobStatus};
@@ -0,0 +1,106 @@
+use chrono::NaiveDateTime;
+use lib_entity::job_queue::{Kind as JobKind, Status as JobStatus};
+use serde::{Deserialize, Serialize};
+
+// 任务的参数, 序列化后保存在 `payload` 中
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobPayload {
+    // 拉取订阅源
+    FeedRefresh { subscription_id: i64 },
+    // 补全文章的正文和图片
+    LinkMeta { link_id: i64 },
+    // 生成文章的总结
+    LinkSummary { link_id: i64 },
+    // 清理发布时间早于 `keep_days` 天前的文章
+    Prune { keep_days: i64 },
+}
+
+impl JobPayload {
+    pub fn kind(&self) -> JobKind {
+        match self {
+            Self::FeedRefresh { .. } => JobKind::FeedRefresh,
+            Self::LinkMeta { .. } => JobKind::LinkMeta,
+            Self::LinkSummary { .. } => JobKind::LinkSummary,
+            Self::Prune { .. } => JobKind::Prune,
+        }
+    }
+
+    // 同一个订阅源或文章同时只有一个未完成的任务
+    pub fn dedup_key(&self) -> String {
+        match self {
+            Self::FeedRefresh { subscription_id } => format!("feed_refresh:{}", subscription_id),
+            Self::LinkMeta { link_id } => format!("link_meta:{}", link_id),
+            Self::LinkSummary { link_id } => format!("link_summary:{}", link_id),
+            Self::Prune { .. } => "prune".to_string(),
+        }
+    }
+
+    // 拉取订阅源最优先, 清理最后
+    pub fn default_priority(&self) -> i32 {
+        match self {
+            Self::FeedRefresh { .. } => 100,
+            Self::LinkMeta { .. } => 50,
+            Self::LinkSummary { .. } => 10,
+            Self::Prune { .. } => 0,
+        }
+    }
+
+    pub fn default_max_attempts(&self) -> i32 {
+        match self {
+            // 订阅源会被定期重新拉取, 不需要太多重试
+            Self::FeedRefresh { .. } => 3,
+            Self::LinkMeta { .. } | Self::LinkSummary { .. } => 5,
+            Self::Prune { .. } => 3,
+        }
+    }
+}
+
+// 加入任务队列的请求
+#[derive(Debug, Clone)]
+pub struct EnqueueJobRequest {
+    pub payload: JobPayload,
+    pub priority: i32,
+    pub max_attempts: i32,
+    // 为空时立即执行
+    pub run_at: Option<NaiveDateTime>,
+}
+
+impl EnqueueJobRequest {
+    pub fn new(payload: JobPayload) -> Self {
+        Self {
+            priority: payload.default_priority(),
+            max_attempts: payload.default_max_attempts(),
+            payload,
+            run_at: None,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn with_run_at(mut self, run_at: NaiveDateTime) -> Self {
+        self.run_at = Some(run_at);
+        self
+    }
+}
+
+// 被 worker 租用的任务
+#[derive(Debug, Clone)]
+pub struct LeasedJob {
+    pub id: i64,
+    pub payload: JobPayload,
+    // 包括本次在内的执行次数
+    pub attempts: i32,
+    pub max_attempts: i32,
+}
+
+// 各个类型和状态的任务数量
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct JobStats {
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub count: i64,
+}
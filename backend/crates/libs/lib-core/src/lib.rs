@@ -4,6 +4,7 @@ pub mod auth;
 pub mod common_schema;
 pub mod error;
 pub mod feed;
+pub mod job;
 
 use std::time::Duration;
 
@@ -23,7 +24,7 @@ pub async fn get_db_conn(uri: String) -> DBConnection {
         .sqlx_logging(false);
     let db = Database::connect(opt)
         .await
-        .expect(format!("Failed to connect to database: {}", uri).as_str());
+        .unwrap_or_else(|_| panic!("Failed to connect to database: {}", uri));
     println!("Database connected, {}", uri);
     tracing::info!("Database connected");
     db
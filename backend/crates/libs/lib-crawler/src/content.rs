@@ -65,6 +65,7 @@ pub async fn try_get_metadata_from_content(content: String) -> Result<HtmlMetada
             vec![
                 "meta[name=url]",
                 "meta[name='og:url']",
+                "meta[property='og:url']",
                 "meta[name='twitter:url']",
                 "meta[name='weibo:article:url']",
             ],
@@ -75,6 +76,7 @@ pub async fn try_get_metadata_from_content(content: String) -> Result<HtmlMetada
                 "title",
                 "meta[name=title]",
                 "meta[name='og:title']",
+                "meta[property='og:title']",
                 "meta[name='twitter:title']",
                 "meta[name='weibo:article:title']",
             ],
@@ -84,6 +86,7 @@ pub async fn try_get_metadata_from_content(content: String) -> Result<HtmlMetada
             vec![
                 "meta[name=description]",
                 "meta[name='og:description']",
+                "meta[property='og:description']",
                 "meta[name='twitter:description']",
                 "meta[name='weibo:article:description']",
             ],
@@ -103,6 +106,7 @@ pub async fn try_get_metadata_from_content(content: String) -> Result<HtmlMetada
                 "meta[name=image]",
                 "meta[name=promote_image]",
                 "meta[name='og:image']",
+                "meta[property='og:image']",
                 "meta[name='twitter:image']",
                 "meta[name='weibo:article:image']",
             ],
@@ -0,0 +1,383 @@
+use derive_builder::Builder;
+use reqwest::Url;
+use scraper::{Html, Selector};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::content::try_get_metadata_from_content;
+use crate::error::FetchError;
+use crate::model::{FeedFormat, ParsedFeed};
+use crate::rss::{parse_feed_from_content, FEED_CONTENT_TYPES};
+use crate::url::{get_response_from_url, RequestOptionBuilder};
+
+// `<link rel="alternate">` 中表示订阅源的 type
+const FEED_LINK_TYPES: &[&str] = &[
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+    "application/rdf+xml",
+];
+
+// 页面没有声明订阅源时, 依次尝试的常见路径
+const COMMON_FEED_PATHS: &[&str] = &[
+    "/feed",
+    "/rss",
+    "/feed.xml",
+    "/rss.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/feed.json",
+];
+
+// 页面中声明的订阅源链接
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedLink {
+    pub url: String,
+    pub title: Option<String>,
+}
+
+// 验证过可以解析的订阅源
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiscoveredFeed {
+    pub url: String,
+    pub title: String,
+    pub format: FeedFormat,
+    pub item_count: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FeedDiscovery {
+    // 页面的标题 / 描述, 来自 html 的 metadata
+    pub site_title: Option<String>,
+    pub site_description: Option<String>,
+    pub feeds: Vec<DiscoveredFeed>,
+}
+
+/// 从 html 中找出 `<link rel="alternate" type="application/rss+xml">` 等订阅源链接
+///
+/// 相对地址会按照 `base_url` 转换为绝对地址, 重复的链接只保留第一个
+pub fn find_feed_links_in_html(base_url: &str, content: &str) -> Vec<FeedLink> {
+    let Ok(base) = Url::parse(base_url) else {
+        return Vec::new();
+    };
+    let html = Html::parse_document(content);
+    let selector = Selector::parse("link[rel][href]").unwrap();
+    let mut links: Vec<FeedLink> = Vec::new();
+    for node in html.select(&selector) {
+        let element = node.value();
+        let is_alternate = element
+            .attr("rel")
+            .unwrap_or("")
+            .split_whitespace()
+            .any(|r| r.eq_ignore_ascii_case("alternate"));
+        let link_type = element.attr("type").unwrap_or("").trim().to_lowercase();
+        if !is_alternate || !FEED_LINK_TYPES.contains(&link_type.as_str()) {
+            continue;
+        }
+        let Some(url) = element
+            .attr("href")
+            .and_then(|href| base.join(href.trim()).ok())
+        else {
+            continue;
+        };
+        let url = url.to_string();
+        if links.iter().any(|l| l.url == url) {
+            continue;
+        }
+        links.push(FeedLink {
+            url,
+            title: element
+                .attr("title")
+                .map(|t| t.trim().to_string())
+                .filter(|t| !t.is_empty()),
+        });
+    }
+    links
+}
+
+/// 站点常见的订阅源地址
+pub fn common_feed_urls(base_url: &str) -> Vec<String> {
+    let Ok(base) = Url::parse(base_url) else {
+        return Vec::new();
+    };
+    COMMON_FEED_PATHS
+        .iter()
+        .filter_map(|path| base.join(path).ok())
+        .map(|u| u.to_string())
+        .collect()
+}
+
+#[derive(Debug, Clone, Builder)]
+pub struct DiscoveryOption {
+    // 最多验证的候选地址数量, 按页面中出现的顺序截取
+    #[builder(default = "10")]
+    pub max_candidates: usize,
+    // 同时验证的候选地址数量
+    #[builder(default = "4")]
+    pub concurrency: usize,
+    // 是否允许请求本机和内网地址, 只应在测试中打开
+    #[builder(default = "false")]
+    pub allow_private: bool,
+}
+
+impl Default for DiscoveryOption {
+    fn default() -> Self {
+        DiscoveryOptionBuilder::default()
+            .build()
+            .expect("默认配置不会构建失败")
+    }
+}
+
+/// 根据任意页面地址查找可用的订阅源
+///
+/// 地址本身就是订阅源时直接返回; 否则读取页面中声明的订阅源, 页面没有声明时尝试常见路径。
+/// 每个候选地址都会实际拉取并解析, 无法解析的会被丢弃
+pub async fn discover_feeds<T: AsRef<str>>(
+    url: T,
+    option: &DiscoveryOption,
+) -> Result<FeedDiscovery, FetchError> {
+    let url = url.as_ref();
+    let req = RequestOptionBuilder::default()
+        .url(url.to_string())
+        .timeout(15)
+        .retry_times(2)
+        .public_only(!option.allow_private)
+        .build()
+        .map_err(|e| FetchError::Request(e.to_string()))?;
+    let page = get_response_from_url(req).await?;
+
+    // 地址本身就是订阅源
+    if let Ok(feed) = parse_feed_from_content(page.body.clone()) {
+        return Ok(FeedDiscovery {
+            site_title: Some(feed.title.clone()),
+            site_description: feed.description.clone(),
+            feeds: vec![DiscoveredFeed {
+                url: url.to_string(),
+                title: feed.title,
+                format: feed.format,
+                item_count: feed.entries.len(),
+            }],
+        });
+    }
+
+    let metadata = try_get_metadata_from_content(page.body.clone())
+        .await
+        .unwrap_or_default();
+    let non_empty = |v: Option<String>| v.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    let mut discovery = FeedDiscovery {
+        site_title: non_empty(metadata.title()),
+        site_description: non_empty(metadata.description()),
+        feeds: Vec::new(),
+    };
+
+    let mut candidates = find_feed_links_in_html(url, &page.body);
+    if candidates.is_empty() {
+        candidates = common_feed_urls(url)
+            .into_iter()
+            .map(|url| FeedLink { url, title: None })
+            .collect();
+    }
+    candidates.truncate(option.max_candidates);
+    discovery.feeds = validate_candidates(candidates, option).await;
+    Ok(discovery)
+}
+
+// 并发拉取候选地址, 按候选的顺序返回可以解析的订阅源
+async fn validate_candidates(
+    candidates: Vec<FeedLink>,
+    option: &DiscoveryOption,
+) -> Vec<DiscoveredFeed> {
+    let semaphore = Arc::new(Semaphore::new(option.concurrency.max(1)));
+    let allow_private = option.allow_private;
+    let mut tasks = JoinSet::new();
+    for (index, candidate) in candidates.into_iter().enumerate() {
+        let semaphore = Arc::clone(&semaphore);
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.ok()?;
+            let feed = match fetch_candidate(&candidate.url, allow_private).await {
+                Ok(feed) => feed,
+                Err(e) => {
+                    tracing::debug!("候选订阅源不可用 {}: {}", candidate.url, e);
+                    return None;
+                }
+            };
+            let title = match feed.title.trim() {
+                "" => candidate.title.unwrap_or_default(),
+                title => title.to_string(),
+            };
+            Some((
+                index,
+                DiscoveredFeed {
+                    url: candidate.url,
+                    title,
+                    format: feed.format,
+                    item_count: feed.entries.len(),
+                },
+            ))
+        });
+    }
+    let mut feeds = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        if let Ok(Some(feed)) = result {
+            feeds.push(feed);
+        }
+    }
+    feeds.sort_by_key(|(index, _)| *index);
+    feeds.into_iter().map(|(_, feed)| feed).collect()
+}
+
+// 拉取并解析候选地址, 只尝试一次
+async fn fetch_candidate(url: &str, allow_private: bool) -> Result<ParsedFeed, FetchError> {
+    let req = RequestOptionBuilder::default()
+        .url(url.to_string())
+        .timeout(10)
+        .retry_times(1)
+        .public_only(!allow_private)
+        .accept_content_types(FEED_CONTENT_TYPES.iter().map(|t| t.to_string()).collect())
+        .build()
+        .map_err(|e| FetchError::Request(e.to_string()))?;
+    let resp = get_response_from_url(req).await?;
+    parse_feed_from_content(resp.body).map_err(|e| FetchError::Parse(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_server::{response, serve};
+
+    // 测试服务运行在本机
+    fn local_option() -> DiscoveryOption {
+        DiscoveryOptionBuilder::default()
+            .allow_private(true)
+            .build()
+            .unwrap()
+    }
+
+    const RSS: &str = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>rss</title><link>http://example.com</link><description>d</description><item><title>a</title><link>http://example.com/a</link></item><item><title>b</title><link>http://example.com/b</link></item></channel></rss>"#;
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?><feed xmlns="http://www.w3.org/2005/Atom"><title>atom</title><id>urn:a</id><updated>2024-01-01T00:00:00Z</updated><entry><title>a</title><id>urn:a:1</id><link href="http://example.com/a"/><updated>2024-01-01T00:00:00Z</updated></entry></feed>"#;
+
+    #[test]
+    fn test_find_feed_links_in_html() {
+        let html = r#"
+        <html><head>
+        <link rel="alternate" type="application/rss+xml" title="RSS" href="/feed.xml">
+        <link rel="alternate" type="application/atom+xml" href="https://example.com/atom.xml">
+        <link rel="alternate" type="application/rss+xml" href="feed.xml">
+        <link rel="alternate" hreflang="en" href="/en">
+        <link rel="stylesheet" type="text/css" href="/a.css">
+        </head></html>
+        "#;
+        let links = find_feed_links_in_html("https://example.com/blog/", html);
+        assert_eq!(
+            links,
+            vec![
+                FeedLink {
+                    url: "https://example.com/feed.xml".to_string(),
+                    title: Some("RSS".to_string()),
+                },
+                FeedLink {
+                    url: "https://example.com/atom.xml".to_string(),
+                    title: None,
+                },
+                FeedLink {
+                    url: "https://example.com/blog/feed.xml".to_string(),
+                    title: None,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_discover_feeds() {
+        let host = serve(|request| {
+            if request.starts_with("GET /feed.xml") {
+                return response("200 OK", &[("Content-Type", "application/rss+xml")], RSS);
+            }
+            if request.starts_with("GET /atom.xml") {
+                return response("200 OK", &[("Content-Type", "application/atom+xml")], ATOM);
+            }
+            if request.starts_with("GET / ") {
+                return response(
+                    "200 OK",
+                    &[("Content-Type", "text/html")],
+                    r#"<html><head><title>site</title>
+                    <link rel="alternate" type="application/rss+xml" href="/feed.xml">
+                    <link rel="alternate" type="application/atom+xml" href="/atom.xml">
+                    <link rel="alternate" type="application/rss+xml" href="/missing.xml">
+                    </head></html>"#,
+                );
+            }
+            response("404 Not Found", &[], "")
+        })
+        .await;
+
+        let discovery = discover_feeds(format!("{}/", host), &local_option())
+            .await
+            .unwrap();
+        assert_eq!(discovery.site_title.as_deref(), Some("site"));
+        assert_eq!(
+            discovery.feeds,
+            vec![
+                DiscoveredFeed {
+                    url: format!("{}/feed.xml", host),
+                    title: "rss".to_string(),
+                    format: FeedFormat::Rss,
+                    item_count: 2,
+                },
+                DiscoveredFeed {
+                    url: format!("{}/atom.xml", host),
+                    title: "atom".to_string(),
+                    format: FeedFormat::Atom,
+                    item_count: 1,
+                },
+            ]
+        );
+
+        // 直接传入订阅源地址
+        let discovery = discover_feeds(format!("{}/feed.xml", host), &local_option())
+            .await
+            .unwrap();
+        assert_eq!(discovery.feeds.len(), 1);
+        assert_eq!(discovery.feeds[0].url, format!("{}/feed.xml", host));
+
+        // 只验证前几个候选地址
+        let option = DiscoveryOptionBuilder::default()
+            .allow_private(true)
+            .max_candidates(1usize)
+            .build()
+            .unwrap();
+        let discovery = discover_feeds(format!("{}/", host), &option).await.unwrap();
+        assert_eq!(discovery.feeds.len(), 1);
+
+        // 默认拒绝本机地址
+        let res = discover_feeds(format!("{}/", host), &DiscoveryOption::default()).await;
+        assert!(matches!(res, Err(FetchError::PrivateAddress(_))));
+    }
+
+    #[tokio::test]
+    async fn test_discover_feeds_by_common_paths() {
+        let host = serve(|request| {
+            if request.starts_with("GET /rss.xml") {
+                return response("200 OK", &[("Content-Type", "text/xml")], RSS);
+            }
+            if request.starts_with("GET / ") {
+                return response(
+                    "200 OK",
+                    &[("Content-Type", "text/html")],
+                    "<html><head><title>no feed</title></head></html>",
+                );
+            }
+            response("404 Not Found", &[], "")
+        })
+        .await;
+
+        let discovery = discover_feeds(format!("{}/", host), &local_option())
+            .await
+            .unwrap();
+        assert_eq!(discovery.feeds.len(), 1);
+        assert_eq!(discovery.feeds[0].url, format!("{}/rss.xml", host));
+        assert_eq!(discovery.feeds[0].format, FeedFormat::Rss);
+    }
+}
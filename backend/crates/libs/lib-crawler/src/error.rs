@@ -0,0 +1,97 @@
+use thiserror::Error;
+
+// 拉取远程内容时的错误, 会被记录到订阅源的更新记录中
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum FetchError {
+    #[error("dns lookup failed: {0}")]
+    Dns(String),
+    #[error("request timed out")]
+    Timeout,
+    #[error("tls handshake failed: {0}")]
+    Tls(String),
+    #[error("connect failed: {0}")]
+    Connect(String),
+    #[error("unexpected http status {0}")]
+    HttpStatus(u16),
+    // 429 / 503, retry_after 为服务端要求等待的秒数
+    #[error("throttled with http status {status}")]
+    Throttled {
+        status: u16,
+        retry_after: Option<u64>,
+    },
+    #[error("body exceeds {limit} bytes")]
+    BodyTooLarge { limit: u64 },
+    #[error("unexpected content type `{0}`")]
+    ContentType(String),
+    #[error("response body is empty")]
+    EmptyBody,
+    #[error("invalid request: {0}")]
+    Request(String),
+    #[error("parse failed: {0}")]
+    Parse(String),
+    // 目标是本机、内网或链路本地地址
+    #[error("refused to fetch non-public address `{0}`")]
+    PrivateAddress(String),
+}
+
+impl FetchError {
+    // 错误类型的简短标识, 用于存储和查询
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Dns(_) => "dns",
+            Self::Timeout => "timeout",
+            Self::Tls(_) => "tls",
+            Self::Connect(_) => "connect",
+            Self::HttpStatus(_) => "http_status",
+            Self::Throttled { .. } => "throttled",
+            Self::BodyTooLarge { .. } => "body_too_large",
+            Self::ContentType(_) => "content_type",
+            Self::EmptyBody => "empty_body",
+            Self::Request(_) => "request",
+            Self::Parse(_) => "parse",
+            Self::PrivateAddress(_) => "private_address",
+        }
+    }
+
+    // 是否值得立即重试: 网络抖动 / 服务端错误
+    // 被限流时不在这里重试, 交给调度器按 Retry-After 退避
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Dns(_) | Self::Timeout | Self::Connect(_) | Self::EmptyBody => true,
+            Self::HttpStatus(status) => *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            return Self::Timeout;
+        }
+        if let Some(status) = e.status() {
+            return Self::HttpStatus(status.as_u16());
+        }
+        // reqwest 没有区分 dns / tls 错误, 只能从错误链中判断
+        let mut detail = e.to_string();
+        let mut source = std::error::Error::source(&e);
+        while let Some(s) = source {
+            detail = format!("{}: {}", detail, s);
+            source = s.source();
+        }
+        let lower = detail.to_lowercase();
+        if lower.contains("dns error") || lower.contains("failed to lookup address") {
+            return Self::Dns(detail);
+        }
+        if lower.contains("certificate") || lower.contains("tls") || lower.contains("ssl") {
+            return Self::Tls(detail);
+        }
+        if e.is_connect() {
+            return Self::Connect(detail);
+        }
+        if e.is_builder() {
+            return Self::Request(detail);
+        }
+        Self::Connect(detail)
+    }
+}
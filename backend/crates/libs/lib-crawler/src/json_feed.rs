@@ -0,0 +1,299 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+
+use crate::model::{
+    parse_datetime, FeedFormat, ParsedCategory, ParsedEnclosure, ParsedEntry, ParsedFeed,
+    ParsedLink, ParsedPerson,
+};
+
+// JSON Feed 1.0 / 1.1 https://www.jsonfeed.org/version/1.1/
+const JSON_FEED_VERSION_PREFIX: &str = "https://jsonfeed.org/version/";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JsonFeed {
+    pub version: String,
+    pub title: String,
+    pub home_page_url: Option<String>,
+    pub feed_url: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub favicon: Option<String>,
+    pub language: Option<String>,
+    // 1.1 使用 authors, 1.0 使用 author
+    #[serde(default)]
+    pub authors: Vec<JsonFeedAuthor>,
+    pub author: Option<JsonFeedAuthor>,
+    #[serde(default)]
+    pub items: Vec<JsonFeedItem>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JsonFeedAuthor {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    pub avatar: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JsonFeedAttachment {
+    pub url: String,
+    pub mime_type: String,
+    pub title: Option<String>,
+    pub size_in_bytes: Option<u64>,
+    pub duration_in_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JsonFeedItem {
+    // id 可能是字符串也可能是数字
+    pub id: serde_json::Value,
+    pub url: Option<String>,
+    pub external_url: Option<String>,
+    pub title: Option<String>,
+    pub content_html: Option<String>,
+    pub content_text: Option<String>,
+    pub summary: Option<String>,
+    pub image: Option<String>,
+    pub banner_image: Option<String>,
+    pub date_published: Option<String>,
+    pub date_modified: Option<String>,
+    #[serde(default)]
+    pub authors: Vec<JsonFeedAuthor>,
+    pub author: Option<JsonFeedAuthor>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub language: Option<String>,
+    #[serde(default)]
+    pub attachments: Vec<JsonFeedAttachment>,
+    // 以 `_` 开头的自定义扩展
+    #[serde(flatten)]
+    pub others: BTreeMap<String, serde_json::Value>,
+}
+
+impl JsonFeedItem {
+    fn guid(&self) -> Option<String> {
+        match &self.id {
+            serde_json::Value::String(id) => Some(id.clone()),
+            serde_json::Value::Number(id) => Some(id.to_string()),
+            _ => None,
+        }
+    }
+
+    fn extensions(&self) -> BTreeMap<&String, &serde_json::Value> {
+        self.others
+            .iter()
+            .filter(|(k, _)| k.starts_with('_'))
+            .collect()
+    }
+}
+
+pub fn parse_json_feed(content: &str) -> anyhow::Result<JsonFeed> {
+    let feed: JsonFeed = serde_json::from_str(content)?;
+    if !feed.version.starts_with(JSON_FEED_VERSION_PREFIX) {
+        return Err(anyhow::anyhow!("不支持的 JSON Feed 版本:{}", feed.version));
+    }
+    Ok(feed)
+}
+
+// 没有名字的作者忽略
+fn parsed_person(author: &JsonFeedAuthor) -> Option<ParsedPerson> {
+    Some(ParsedPerson {
+        name: author.name.clone()?,
+        email: None,
+        uri: author.url.clone(),
+    })
+}
+
+impl From<JsonFeedAttachment> for ParsedEnclosure {
+    fn from(value: JsonFeedAttachment) -> Self {
+        Self {
+            url: value.url,
+            mime_type: Some(value.mime_type),
+            length: value.size_in_bytes,
+            duration: value
+                .duration_in_seconds
+                .filter(|d| d.is_finite() && *d >= 0.0)
+                .map(|d| d as u64),
+            title: value.title,
+            ..Default::default()
+        }
+    }
+}
+
+// 把 JSON Feed 转换为统一的 `ParsedFeed`
+impl From<JsonFeed> for ParsedFeed {
+    fn from(feed: JsonFeed) -> Self {
+        let feed_authors: Vec<JsonFeedAuthor> = match feed.authors.is_empty() {
+            true => feed.author.into_iter().collect(),
+            false => feed.authors,
+        };
+        let mut links = Vec::new();
+        if let Some(href) = &feed.home_page_url {
+            links.push(ParsedLink {
+                href: href.clone(),
+                ..Default::default()
+            });
+        }
+        if let Some(href) = &feed.feed_url {
+            links.push(ParsedLink {
+                href: href.clone(),
+                rel: Some("self".to_string()),
+                ..Default::default()
+            });
+        }
+
+        let entries = feed
+            .items
+            .into_iter()
+            .map(|element| {
+                let guid = element.guid();
+                let extensions = element.extensions();
+                let extensions = match extensions.is_empty() {
+                    true => None,
+                    false => serde_json::to_value(extensions).ok(),
+                };
+
+                let mut links = Vec::new();
+                if let Some(href) = &element.url {
+                    links.push(ParsedLink {
+                        href: href.clone(),
+                        ..Default::default()
+                    });
+                }
+                if let Some(href) = &element.external_url {
+                    links.push(ParsedLink {
+                        href: href.clone(),
+                        rel: Some("related".to_string()),
+                        ..Default::default()
+                    });
+                }
+
+                // 条目没有作者时使用订阅源的作者
+                let authors: Vec<&JsonFeedAuthor> =
+                    match (&element.author, element.authors.is_empty()) {
+                        (_, false) => element.authors.iter().collect(),
+                        (Some(author), true) => vec![author],
+                        (None, true) => feed_authors.iter().collect(),
+                    };
+                let authors = authors.into_iter().filter_map(parsed_person).collect();
+
+                let mut entry = ParsedEntry {
+                    guid,
+                    title: element.title,
+                    link: element.url.or(element.external_url),
+                    links,
+                    summary: element.summary,
+                    // 优先使用完整的 html 内容, 其次是纯文本
+                    content: element.content_html.or(element.content_text),
+                    published_at: element.date_published.as_deref().and_then(parse_datetime),
+                    updated_at: element.date_modified.as_deref().and_then(parse_datetime),
+                    authors,
+                    categories: element
+                        .tags
+                        .into_iter()
+                        .map(|term| ParsedCategory {
+                            term,
+                            ..Default::default()
+                        })
+                        .collect(),
+                    enclosures: element
+                        .attachments
+                        .into_iter()
+                        .map(ParsedEnclosure::from)
+                        .collect(),
+                    images: [element.image, element.banner_image]
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                    language: element.language,
+                    extensions,
+                };
+                entry.collect_images();
+                entry
+            })
+            .collect();
+
+        ParsedFeed {
+            format: FeedFormat::Json,
+            title: feed.title,
+            description: feed.description,
+            site_link: feed.home_page_url,
+            links,
+            language: feed.language,
+            icon: feed.icon.or(feed.favicon),
+            published_at: None,
+            updated_at: None,
+            authors: feed_authors.iter().filter_map(parsed_person).collect(),
+            entries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json_feed_1_1() {
+        let content = include_str!("../fixture/feeds/json_feed_1_1.json");
+        let feed = ParsedFeed::from(parse_json_feed(content).unwrap());
+        assert_eq!(feed.title, "JSON Feed Example");
+        assert_eq!(feed.site_link.as_deref(), Some("https://example.org/"));
+        assert_eq!(feed.icon.as_deref(), Some("https://example.org/icon.png"));
+        assert_eq!(feed.entries.len(), 2);
+
+        let first = &feed.entries[0];
+        assert_eq!(first.title.as_deref(), Some("First post"));
+        assert_eq!(first.link.as_deref(), Some("https://example.org/first"));
+        assert_eq!(first.guid.as_deref(), Some("1"));
+        assert_eq!(first.content.as_deref(), Some("<p>Hello <b>world</b></p>"));
+        assert_eq!(
+            first.published_at.map(|d| d.to_rfc3339()),
+            Some("2024-01-02T10:00:00+00:00".to_string())
+        );
+        assert_eq!(first.authors[0].name, "Alice");
+        assert_eq!(
+            first.images,
+            vec![
+                "https://example.org/first.png",
+                "https://example.org/first-banner.png",
+                "https://example.org/photo.jpg",
+            ]
+        );
+        assert_eq!(first.tags(), vec!["rust", "feeds"]);
+        assert_eq!(first.enclosures.len(), 2);
+        assert_eq!(first.enclosures[0].mime_type.as_deref(), Some("audio/mpeg"));
+        assert!(first
+            .extensions
+            .as_ref()
+            .unwrap()
+            .get("_blue_shed")
+            .is_some());
+
+        // 没有 html 时使用纯文本, 没有作者时使用订阅源的作者
+        let second = &feed.entries[1];
+        assert_eq!(second.content.as_deref(), Some("Plain text only"));
+        assert_eq!(
+            second.link.as_deref(),
+            Some("https://other.example.org/second")
+        );
+        assert_eq!(second.authors[0].name, "Feed Author");
+    }
+
+    #[test]
+    fn test_parse_json_feed_1_0() {
+        let content = include_str!("../fixture/feeds/json_feed_1_0.json");
+        let feed = ParsedFeed::from(parse_json_feed(content).unwrap());
+        assert_eq!(feed.entries.len(), 1);
+        let entry = &feed.entries[0];
+        assert_eq!(entry.authors[0].name, "Legacy Author");
+        assert_eq!(entry.content.as_deref(), Some("<p>legacy</p>"));
+    }
+
+    #[test]
+    fn test_parse_json_feed_invalid_version() {
+        assert!(parse_json_feed(r#"{"version": "1", "title": "x", "items": []}"#).is_err());
+        assert!(parse_json_feed("<rss></rss>").is_err());
+    }
+}
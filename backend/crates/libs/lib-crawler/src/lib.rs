@@ -1,7 +1,40 @@
 mod content;
+mod discovery;
+mod error;
+mod json_feed;
+mod model;
+mod readability;
+mod robots;
 mod rss;
+mod scheduler;
 mod url;
+mod websub;
+
+#[cfg(test)]
+mod test_server;
 pub use content::{try_get_all_image_from_html_content, try_get_all_text_from_html_content};
 pub use content::{try_get_metadata_from_content, HtmlMetadata};
-pub use rss::{fetch_rss_from_url, Channel};
-pub use url::get_content_from_url;
+pub use discovery::{
+    common_feed_urls, discover_feeds, find_feed_links_in_html, DiscoveredFeed, DiscoveryOption,
+    DiscoveryOptionBuilder, FeedDiscovery, FeedLink,
+};
+pub use error::FetchError;
+pub use json_feed::{parse_json_feed, JsonFeed, JsonFeedAttachment, JsonFeedAuthor, JsonFeedItem};
+pub use model::{
+    FeedFormat, ParsedCategory, ParsedEnclosure, ParsedEntry, ParsedFeed, ParsedLink, ParsedPerson,
+};
+pub use readability::{extract_article, fetch_article, html_to_markdown, ReadableArticle};
+pub use robots::{RobotsCache, RobotsRules, RobotsVerdict};
+pub use rss::{
+    fetch_rss_from_url, fetch_rss_from_url_if_modified, parse_feed, FeedFetchResult,
+    FeedValidators, FetchedFeed,
+};
+pub use scheduler::{HostPermit, HostScheduler, HostSchedulerOption, HostSchedulerOptionBuilder};
+pub use url::{
+    ensure_public_url, get_content_from_url, get_response_from_url, RequestOption,
+    RequestOptionBuilder, UrlContent,
+};
+pub use websub::{
+    request_websub, sign_websub_payload, verify_websub_signature, WebSubHub, WebSubMode,
+    WebSubRequest,
+};
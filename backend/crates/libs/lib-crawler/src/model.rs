@@ -0,0 +1,467 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+// 订阅源的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    Json,
+}
+
+impl From<feed_rs::model::FeedType> for FeedFormat {
+    fn from(value: feed_rs::model::FeedType) -> Self {
+        match value {
+            feed_rs::model::FeedType::Atom => Self::Atom,
+            feed_rs::model::FeedType::JSON => Self::Json,
+            _ => Self::Rss,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedLink {
+    pub href: String,
+    // alternate / self / enclosure 等, 为空时等同于 alternate
+    pub rel: Option<String>,
+    pub media_type: Option<String>,
+    pub title: Option<String>,
+    pub length: Option<u64>,
+}
+
+impl ParsedLink {
+    fn is_alternate(&self) -> bool {
+        self.rel.as_deref().is_none_or(|r| r == "alternate")
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedPerson {
+    pub name: String,
+    pub email: Option<String>,
+    pub uri: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParsedCategory {
+    pub term: String,
+    pub label: Option<String>,
+    pub scheme: Option<String>,
+}
+
+/// 条目的附件: rss 的 enclosure / media:content / atom 中 rel="enclosure" 的链接 / JSON Feed 的 attachments
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedEnclosure {
+    pub url: String,
+    pub mime_type: Option<String>,
+    // 字节数
+    pub length: Option<u64>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    // 时长(秒)
+    pub duration: Option<u64>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    // media:thumbnail
+    pub thumbnails: Vec<String>,
+}
+
+impl ParsedEnclosure {
+    pub fn is_image(&self) -> bool {
+        self.mime_type
+            .as_deref()
+            .is_some_and(|t| t.starts_with("image/"))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedEntry {
+    // rss 的 guid / atom 的 id / JSON Feed 的 id, 订阅源没有提供时为空
+    pub guid: Option<String>,
+    pub title: Option<String>,
+    // 文章地址
+    pub link: Option<String>,
+    pub links: Vec<ParsedLink>,
+    // rss 的 description / atom 的 summary
+    pub summary: Option<String>,
+    // rss 的 content:encoded / atom 的 content / JSON Feed 的 content_html
+    pub content: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub authors: Vec<ParsedPerson>,
+    pub categories: Vec<ParsedCategory>,
+    pub enclosures: Vec<ParsedEnclosure>,
+    // 条目的图片, 按出现的顺序去重
+    pub images: Vec<String>,
+    pub language: Option<String>,
+    // JSON Feed 中以 `_` 开头的扩展字段
+    pub extensions: Option<serde_json::Value>,
+}
+
+impl ParsedEntry {
+    // 分类的名称, 用作标签
+    pub fn tags(&self) -> Vec<String> {
+        self.categories
+            .iter()
+            .map(|c| c.label.clone().unwrap_or_else(|| c.term.clone()))
+            .filter(|t| !t.trim().is_empty())
+            .collect()
+    }
+
+    fn push_image(&mut self, url: String) {
+        if !url.is_empty() && !self.images.contains(&url) {
+            self.images.push(url);
+        }
+    }
+
+    // 从附件中收集图片, 并和已有的图片一起去重
+    pub(crate) fn collect_images(&mut self) {
+        let mut urls = std::mem::take(&mut self.images);
+        urls.extend(self.enclosures.iter().flat_map(|e| {
+            let mut urls = e.thumbnails.clone();
+            if e.is_image() {
+                urls.push(e.url.clone());
+            }
+            urls
+        }));
+        for url in urls {
+            self.push_image(url);
+        }
+    }
+}
+
+/// 解析后的订阅源, 各种格式统一转换为这个结构
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedFeed {
+    pub format: FeedFormat,
+    pub title: String,
+    pub description: Option<String>,
+    // 订阅源对应的网站
+    pub site_link: Option<String>,
+    pub links: Vec<ParsedLink>,
+    pub language: Option<String>,
+    pub icon: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub authors: Vec<ParsedPerson>,
+    pub entries: Vec<ParsedEntry>,
+}
+
+// 优先使用 alternate 链接
+fn primary_link(links: &[ParsedLink]) -> Option<String> {
+    links
+        .iter()
+        .find(|l| l.is_alternate())
+        .map(|l| l.href.clone())
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// 解析时间, 支持 rfc3339 / rfc2822 以及其他常见格式
+pub(crate) fn parse_datetime(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    if let Ok(d) = DateTime::parse_from_rfc3339(value) {
+        return Some(d.with_timezone(&Utc));
+    }
+    if let Ok(d) = DateTime::parse_from_rfc2822(value) {
+        return Some(d.with_timezone(&Utc));
+    }
+    dateparser::parse(value).ok()
+}
+
+impl From<feed_rs::model::Link> for ParsedLink {
+    fn from(value: feed_rs::model::Link) -> Self {
+        Self {
+            href: value.href,
+            rel: value.rel,
+            media_type: value.media_type,
+            title: value.title,
+            length: value.length,
+        }
+    }
+}
+
+impl From<feed_rs::model::Person> for ParsedPerson {
+    fn from(value: feed_rs::model::Person) -> Self {
+        Self {
+            name: value.name,
+            email: value.email,
+            uri: value.uri,
+        }
+    }
+}
+
+impl From<feed_rs::model::Category> for ParsedCategory {
+    fn from(value: feed_rs::model::Category) -> Self {
+        Self {
+            term: value.term,
+            label: value.label,
+            scheme: value.scheme,
+        }
+    }
+}
+
+impl From<feed_rs::model::Entry> for ParsedEntry {
+    fn from(value: feed_rs::model::Entry) -> Self {
+        let links: Vec<ParsedLink> = value.links.into_iter().map(ParsedLink::from).collect();
+
+        let mut enclosures: Vec<ParsedEnclosure> = Vec::new();
+        let mut images: Vec<String> = Vec::new();
+        for object in value.media {
+            let title = object.title.map(|t| t.content);
+            let description = object.description.map(|t| t.content);
+            let thumbnails: Vec<String> = object
+                .thumbnails
+                .iter()
+                .map(|t| t.image.uri.clone())
+                .collect();
+            let mut has_content = false;
+            for content in object.content {
+                let Some(url) = content.url else {
+                    continue;
+                };
+                has_content = true;
+                enclosures.push(ParsedEnclosure {
+                    url: url.to_string(),
+                    mime_type: content.content_type.map(|t| t.to_string()),
+                    length: content.size,
+                    width: content.width,
+                    height: content.height,
+                    duration: content.duration.or(object.duration).map(|d| d.as_secs()),
+                    title: title.clone(),
+                    description: description.clone(),
+                    thumbnails: thumbnails.clone(),
+                });
+            }
+            // 只有缩略图的 media 对象, 缩略图只作为图片
+            if !has_content {
+                images.extend(thumbnails);
+            }
+        }
+        // atom 的附件是 rel="enclosure" 的链接
+        for link in links
+            .iter()
+            .filter(|l| l.rel.as_deref() == Some("enclosure"))
+        {
+            if enclosures.iter().any(|e| e.url == link.href) {
+                continue;
+            }
+            enclosures.push(ParsedEnclosure {
+                url: link.href.clone(),
+                mime_type: link.media_type.clone(),
+                length: link.length,
+                title: link.title.clone(),
+                ..Default::default()
+            });
+        }
+
+        let mut entry = Self {
+            guid: non_empty(Some(value.id)),
+            title: value.title.map(|t| t.content),
+            link: primary_link(&links),
+            links,
+            summary: non_empty(value.summary.map(|t| t.content)),
+            content: non_empty(value.content.and_then(|c| c.body)),
+            published_at: value.published,
+            updated_at: value.updated,
+            authors: value.authors.into_iter().map(ParsedPerson::from).collect(),
+            categories: value
+                .categories
+                .into_iter()
+                .map(ParsedCategory::from)
+                .collect(),
+            enclosures,
+            images,
+            language: value.language,
+            extensions: None,
+        };
+        entry.collect_images();
+        entry
+    }
+}
+
+impl From<feed_rs::model::Feed> for ParsedFeed {
+    fn from(value: feed_rs::model::Feed) -> Self {
+        let links: Vec<ParsedLink> = value.links.into_iter().map(ParsedLink::from).collect();
+        Self {
+            format: FeedFormat::from(value.feed_type),
+            title: value.title.map(|t| t.content).unwrap_or_default(),
+            description: non_empty(value.description.map(|t| t.content)),
+            site_link: primary_link(&links),
+            links,
+            language: value.language,
+            icon: value.icon.or(value.logo).map(|i| i.uri),
+            published_at: value.published,
+            updated_at: value.updated,
+            authors: value.authors.into_iter().map(ParsedPerson::from).collect(),
+            entries: value.entries.into_iter().map(ParsedEntry::from).collect(),
+        }
+    }
+}
+
+impl From<rss::Item> for ParsedEntry {
+    fn from(value: rss::Item) -> Self {
+        let links: Vec<ParsedLink> = value
+            .link()
+            .map(|l| ParsedLink {
+                href: l.to_string(),
+                ..Default::default()
+            })
+            .into_iter()
+            .collect();
+        let enclosures: Vec<ParsedEnclosure> = value
+            .enclosure()
+            .map(|e| ParsedEnclosure {
+                url: e.url().to_string(),
+                mime_type: non_empty(Some(e.mime_type().to_string())),
+                length: e.length().parse().ok(),
+                ..Default::default()
+            })
+            .into_iter()
+            .collect();
+        let mut entry = Self {
+            guid: value.guid().map(|g| g.value().to_string()),
+            title: value.title().map(|t| t.to_string()),
+            link: primary_link(&links),
+            links,
+            summary: non_empty(value.description().map(|d| d.to_string())),
+            content: non_empty(value.content().map(|c| c.to_string())),
+            published_at: value.pub_date().and_then(parse_datetime),
+            updated_at: None,
+            authors: value
+                .author()
+                .map(|a| ParsedPerson {
+                    name: a.to_string(),
+                    ..Default::default()
+                })
+                .into_iter()
+                .collect(),
+            categories: value
+                .categories()
+                .iter()
+                .map(|c| ParsedCategory {
+                    term: c.name().to_string(),
+                    label: None,
+                    scheme: c.domain().map(|d| d.to_string()),
+                })
+                .collect(),
+            enclosures,
+            images: Vec::new(),
+            language: None,
+            extensions: None,
+        };
+        entry.collect_images();
+        entry
+    }
+}
+
+// feed_rs 无法解析时, 使用 rss 库解析的结果
+impl From<rss::Channel> for ParsedFeed {
+    fn from(value: rss::Channel) -> Self {
+        let links = vec![ParsedLink {
+            href: value.link().to_string(),
+            ..Default::default()
+        }];
+        Self {
+            format: FeedFormat::Rss,
+            title: value.title().to_string(),
+            description: non_empty(Some(value.description().to_string())),
+            site_link: non_empty(Some(value.link().to_string())),
+            links,
+            language: value.language().map(|l| l.to_string()),
+            icon: value.image().map(|i| i.url().to_string()),
+            published_at: value.pub_date().and_then(parse_datetime),
+            updated_at: value.last_build_date().and_then(parse_datetime),
+            authors: Vec::new(),
+            entries: value
+                .items()
+                .iter()
+                .cloned()
+                .map(ParsedEntry::from)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rss::parse_feed_from_content;
+
+    #[test]
+    fn test_parse_atom_entry() {
+        let content = include_str!("../fixture/feeds/atom.xml");
+        let feed = parse_feed_from_content(content.to_string()).unwrap();
+        assert_eq!(feed.format, FeedFormat::Atom);
+        assert_eq!(feed.title, "Atom Example");
+        assert_eq!(feed.site_link.as_deref(), Some("https://example.org/"));
+
+        let entry = &feed.entries[0];
+        assert_eq!(entry.guid.as_deref(), Some("urn:uuid:1225c695"));
+        assert_eq!(entry.link.as_deref(), Some("https://example.org/2024/atom"));
+        assert_eq!(entry.links.len(), 3);
+        // content 和 summary 分开保存
+        assert_eq!(entry.summary.as_deref(), Some("Short summary"));
+        assert!(entry.content.as_deref().unwrap().contains("Full content"));
+        assert_eq!(
+            entry.enclosures,
+            vec![ParsedEnclosure {
+                url: "https://example.org/audio.mp3".to_string(),
+                mime_type: Some("audio/mpeg".to_string()),
+                length: Some(1337),
+                ..Default::default()
+            }]
+        );
+        assert_eq!(entry.authors[0].email.as_deref(), Some("jane@example.org"));
+        assert_eq!(entry.tags(), vec!["Technology".to_string()]);
+        assert!(entry.published_at.is_some());
+        assert!(entry.updated_at > entry.published_at);
+    }
+
+    #[test]
+    fn test_parse_rss_entry() {
+        let content = include_str!("../fixture/feeds/rss_media.xml");
+        let feed = parse_feed_from_content(content.to_string()).unwrap();
+        assert_eq!(feed.format, FeedFormat::Rss);
+        assert_eq!(feed.entries.len(), 2);
+
+        let entry = &feed.entries[0];
+        assert_eq!(entry.guid.as_deref(), Some("post-1"));
+        assert_eq!(entry.summary.as_deref(), Some("Description"));
+        assert_eq!(
+            entry.content.as_deref(),
+            Some("<p>Encoded <img src=\"https://example.com/inline.png\"></p>")
+        );
+        let episode = entry
+            .enclosures
+            .iter()
+            .find(|e| e.url == "https://example.com/episode.mp3")
+            .unwrap();
+        assert_eq!(episode.mime_type.as_deref(), Some("audio/mpeg"));
+        assert_eq!(episode.length, Some(2048));
+        assert_eq!(
+            entry.images,
+            vec![
+                "https://example.com/thumb.jpg".to_string(),
+                "https://example.com/photo.jpg".to_string(),
+            ]
+        );
+        assert_eq!(entry.tags(), vec!["news".to_string(), "tech".to_string()]);
+
+        // 没有 guid 时不生成
+        assert_eq!(feed.entries[1].guid, None);
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        assert!(parse_datetime("2024-01-02T10:00:00Z").is_some());
+        assert!(parse_datetime("Tue, 02 Jan 2024 10:00:00 GMT").is_some());
+        assert!(parse_datetime("").is_none());
+    }
+}
@@ -0,0 +1,755 @@
+// 不依赖外部解析服务的正文提取, 思路参考 Mozilla Readability:
+// 按段落给祖先节点打分, 取得分最高的节点及其相关的兄弟节点作为正文, 再去掉其中的模板内容
+use std::collections::HashMap;
+
+use ego_tree::NodeId;
+use reqwest::Url;
+use scraper::{ElementRef, Html, Node, Selector};
+use serde::Serialize;
+
+use crate::content::try_get_metadata_from_content;
+use crate::error::FetchError;
+use crate::url::{get_content_from_url, RequestOptionBuilder};
+
+// 直接丢弃的标签
+const REMOVE_TAGS: &[&str] = &[
+    "script", "style", "noscript", "iframe", "object", "embed", "form", "button", "input",
+    "select", "textarea", "nav", "aside", "footer", "header", "svg", "canvas", "template", "link",
+    "meta", "title",
+];
+
+// 输出时保留的标签, 其余标签只保留内容
+const KEEP_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "a",
+    "img",
+    "figure",
+    "figcaption",
+    "ul",
+    "ol",
+    "li",
+    "blockquote",
+    "pre",
+    "code",
+    "em",
+    "strong",
+    "b",
+    "i",
+    "u",
+    "s",
+    "del",
+    "sup",
+    "sub",
+    "br",
+    "hr",
+    "table",
+    "thead",
+    "tbody",
+    "tr",
+    "th",
+    "td",
+    "dl",
+    "dt",
+    "dd",
+];
+
+// 没有结束标签的元素
+const VOID_TAGS: &[&str] = &["img", "br", "hr"];
+
+// 块级元素, 包含这些元素的 div 不当作段落
+const BLOCK_TAGS: &[&str] = &[
+    "div",
+    "p",
+    "section",
+    "article",
+    "table",
+    "ul",
+    "ol",
+    "pre",
+    "blockquote",
+    "figure",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+];
+
+// class / id 中出现时降低得分的词
+const NEGATIVE_HINTS: &[&str] = &[
+    "comment",
+    "footer",
+    "nav",
+    "sidebar",
+    "sponsor",
+    "advert",
+    "ad-",
+    "banner",
+    "share",
+    "social",
+    "related",
+    "popup",
+    "subscribe",
+    "newsletter",
+    "menu",
+    "breadcrumb",
+    "cookie",
+    "masthead",
+    "promo",
+    "pagination",
+    "widget",
+];
+
+// class / id 中出现时提高得分的词
+const POSITIVE_HINTS: &[&str] = &[
+    "article", "body", "content", "entry", "main", "post", "text", "blog", "story",
+];
+
+// 参与打分的段落的最少字数
+const MIN_PARAGRAPH_LEN: usize = 25;
+
+/// 从网页中提取的正文
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReadableArticle {
+    pub title: Option<String>,
+    pub excerpt: Option<String>,
+    // 清理后的正文 html
+    pub content: String,
+    pub markdown: String,
+    // 正文的纯文本
+    pub text: String,
+    pub lead_image_url: Option<String>,
+}
+
+/// 拉取网页并提取正文
+pub async fn fetch_article<T: AsRef<str>>(
+    url: T,
+    user_agent: Option<String>,
+) -> Result<ReadableArticle, FetchError> {
+    let url = url.as_ref();
+    let mut req = RequestOptionBuilder::default();
+    req.url(url.to_string())
+        .timeout(15)
+        .retry_times(2)
+        .public_only(true)
+        .accept_content_types(vec!["html".to_string()]);
+    if let Some(user_agent) = user_agent {
+        req.user_agent(user_agent);
+    }
+    let req = req
+        .build()
+        .map_err(|e| FetchError::Request(e.to_string()))?;
+    let body = get_content_from_url(req).await?;
+    extract_article(body, Some(url)).await
+}
+
+/// 从网页内容中提取正文, `url` 用于补全相对地址
+pub async fn extract_article(
+    content: String,
+    url: Option<&str>,
+) -> Result<ReadableArticle, FetchError> {
+    let metadata = try_get_metadata_from_content(content.clone())
+        .await
+        .unwrap_or_default();
+    let base = url.and_then(|u| Url::parse(u).ok());
+
+    let document = Html::parse_document(&content);
+    let html = extract_content_html(&document, base.as_ref());
+    let fragment = Html::parse_fragment(&html);
+    let text = normalize_text(&fragment.root_element());
+    if text.is_empty() {
+        return Err(FetchError::Parse("no readable content".to_string()));
+    }
+
+    let non_empty = |v: Option<String>| v.map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+    // 优先使用页面声明的图片, 没有时使用正文中的第一张图片
+    let lead_image_url = non_empty(metadata.image())
+        .map(|image| resolve_url(base.as_ref(), &image))
+        .or_else(|| {
+            fragment
+                .select(&Selector::parse("img[src]").unwrap())
+                .find_map(|img| img.value().attr("src").map(|src| src.to_string()))
+        });
+
+    Ok(ReadableArticle {
+        title: non_empty(metadata.title()),
+        excerpt: non_empty(metadata.description()),
+        markdown: html_to_markdown(&html),
+        content: html,
+        text,
+        lead_image_url,
+    })
+}
+
+// 选出正文所在的节点, 输出清理后的 html
+fn extract_content_html(document: &Html, base: Option<&Url>) -> String {
+    let body = document
+        .select(&Selector::parse("body").unwrap())
+        .next()
+        .unwrap_or_else(|| document.root_element());
+
+    let mut scores: HashMap<NodeId, f64> = HashMap::new();
+    for el in body.descendants().filter_map(ElementRef::wrap) {
+        if !is_paragraph(&el) || is_excluded(&el) {
+            continue;
+        }
+        let text = normalize_text(&el);
+        let len = text.chars().count();
+        if len < MIN_PARAGRAPH_LEN {
+            continue;
+        }
+        // 逗号越多、内容越长, 越像正文
+        let commas = text
+            .chars()
+            .filter(|c| matches!(c, ',' | '，' | '。'))
+            .count();
+        let score = 1.0 + commas as f64 + (len as f64 / 100.0).min(3.0);
+        for (level, ancestor) in el
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .take(3)
+            .enumerate()
+        {
+            let divider = match level {
+                0 => 1.0,
+                1 => 2.0,
+                _ => level as f64 * 3.0,
+            };
+            *scores
+                .entry(ancestor.id())
+                .or_insert_with(|| initial_score(&ancestor)) += score / divider;
+        }
+    }
+
+    // 得分按链接密度折算, 导航类的节点链接多、正文少
+    let top = scores
+        .iter()
+        .filter_map(|(id, score)| {
+            let el = document.tree.get(*id).and_then(ElementRef::wrap)?;
+            Some((el, score * (1.0 - link_density(&el))))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+
+    let mut out = String::new();
+    let Some((top, top_score)) = top else {
+        write_clean(&body, base, &mut out);
+        return out;
+    };
+
+    // 正文可能被拆分到多个兄弟节点中
+    let threshold = (top_score * 0.2).max(10.0);
+    let siblings: Vec<ElementRef> = match top.parent().and_then(ElementRef::wrap) {
+        Some(parent) => parent.children().filter_map(ElementRef::wrap).collect(),
+        None => vec![top],
+    };
+    for sibling in siblings {
+        let include = sibling.id() == top.id()
+            || scores
+                .get(&sibling.id())
+                .is_some_and(|score| *score >= threshold)
+            || (sibling.value().name() == "p"
+                && normalize_text(&sibling).chars().count() > 80
+                && link_density(&sibling) < 0.25);
+        if include && !is_excluded(&sibling) {
+            write_element(&sibling, base, &mut out);
+        }
+    }
+    out
+}
+
+fn is_paragraph(el: &ElementRef) -> bool {
+    match el.value().name() {
+        "p" | "pre" | "td" | "blockquote" => true,
+        // 没有块级子元素的 div 也当作段落
+        "div" => !el
+            .children()
+            .filter_map(ElementRef::wrap)
+            .any(|child| BLOCK_TAGS.contains(&child.value().name())),
+        _ => false,
+    }
+}
+
+// 自身或者祖先是需要丢弃的节点
+fn is_excluded(el: &ElementRef) -> bool {
+    std::iter::once(*el)
+        .chain(el.ancestors().filter_map(ElementRef::wrap))
+        .any(|node| REMOVE_TAGS.contains(&node.value().name()) || is_unlikely(&node))
+}
+
+fn hints(el: &ElementRef) -> String {
+    let value = el.value();
+    format!(
+        "{} {}",
+        value.attr("class").unwrap_or_default(),
+        value.id().unwrap_or_default()
+    )
+    .to_lowercase()
+}
+
+fn is_unlikely(el: &ElementRef) -> bool {
+    if matches!(el.value().name(), "html" | "body" | "article" | "main") {
+        return false;
+    }
+    let hints = hints(el);
+    NEGATIVE_HINTS.iter().any(|h| hints.contains(h))
+        && !POSITIVE_HINTS.iter().any(|h| hints.contains(h))
+}
+
+fn class_weight(el: &ElementRef) -> f64 {
+    let hints = hints(el);
+    let mut weight = 0.0;
+    if NEGATIVE_HINTS.iter().any(|h| hints.contains(h)) {
+        weight -= 25.0;
+    }
+    if POSITIVE_HINTS.iter().any(|h| hints.contains(h)) {
+        weight += 25.0;
+    }
+    weight
+}
+
+fn initial_score(el: &ElementRef) -> f64 {
+    let score = match el.value().name() {
+        "div" | "article" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    score + class_weight(el)
+}
+
+fn normalize_text(el: &ElementRef) -> String {
+    el.text()
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 链接文字占全部文字的比例
+fn link_density(el: &ElementRef) -> f64 {
+    let total = normalize_text(el).chars().count();
+    if total == 0 {
+        return 0.0;
+    }
+    let links: usize = el
+        .select(&Selector::parse("a").unwrap())
+        .map(|a| normalize_text(&a).chars().count())
+        .sum();
+    links as f64 / total as f64
+}
+
+// 模板类的容器: 链接为主或者没有内容
+fn is_boilerplate(el: &ElementRef) -> bool {
+    if !matches!(
+        el.value().name(),
+        "div" | "section" | "ul" | "ol" | "table" | "dl"
+    ) {
+        return false;
+    }
+    let images = el.select(&Selector::parse("img").unwrap()).count();
+    if images > 0 {
+        return false;
+    }
+    let text_len = normalize_text(el).chars().count();
+    class_weight(el) < 0.0 || text_len == 0 || (link_density(el) > 0.5 && text_len < 200)
+}
+
+fn resolve_url(base: Option<&Url>, url: &str) -> String {
+    base.and_then(|base| base.join(url).ok())
+        .map(|u| u.to_string())
+        .unwrap_or_else(|| url.to_string())
+}
+
+// 链接只保留 http/https/mailto, 去掉 javascript: / data: 等; 没有 base 的相对地址按 http 处理
+fn is_safe_href(href: &str) -> bool {
+    Url::parse("http://localhost/")
+        .and_then(|placeholder| placeholder.join(href))
+        .is_ok_and(|url| matches!(url.scheme(), "http" | "https" | "mailto"))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn write_element(el: &ElementRef, base: Option<&Url>, out: &mut String) {
+    let name = el.value().name();
+    if REMOVE_TAGS.contains(&name) || is_unlikely(el) || is_boilerplate(el) {
+        return;
+    }
+    if !KEEP_TAGS.contains(&name) {
+        write_clean(el, base, out);
+        return;
+    }
+    let value = el.value();
+    let mut attrs = Vec::new();
+    match name {
+        "a" => {
+            if let Some(href) = value.attr("href") {
+                let href = resolve_url(base, href.trim());
+                if is_safe_href(&href) {
+                    attrs.push(("href", href));
+                }
+            }
+        }
+        "img" => {
+            // 懒加载的图片把地址放在 data-* 属性中
+            let src = ["data-src", "data-original", "data-lazy-src", "src"]
+                .iter()
+                .filter_map(|attr| value.attr(attr))
+                .find(|src| !src.trim().is_empty() && !src.starts_with("data:"));
+            let Some(src) = src else {
+                return;
+            };
+            attrs.push(("src", resolve_url(base, src.trim())));
+            if let Some(alt) = value.attr("alt") {
+                attrs.push(("alt", alt.to_string()));
+            }
+        }
+        "td" | "th" => {
+            for attr in ["colspan", "rowspan"] {
+                if let Some(v) = value.attr(attr) {
+                    attrs.push((attr, v.to_string()));
+                }
+            }
+        }
+        _ => {}
+    }
+    out.push('<');
+    out.push_str(name);
+    for (k, v) in attrs {
+        out.push_str(&format!(" {}=\"{}\"", k, escape(&v)));
+    }
+    out.push('>');
+    if VOID_TAGS.contains(&name) {
+        return;
+    }
+    write_clean(el, base, out);
+    out.push_str(&format!("</{}>", name));
+}
+
+// 输出子节点, 丢弃注释和模板内容
+fn write_clean(el: &ElementRef, base: Option<&Url>, out: &mut String) {
+    for child in el.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape(text)),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_element(&child, base, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// 把 html 转换为 Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+    markdown_children(&fragment.root_element(), &mut out);
+    // 合并多余的空行
+    let mut markdown = String::new();
+    let mut blank = 0;
+    for line in out.lines() {
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            blank += 1;
+            if blank > 1 {
+                continue;
+            }
+        } else {
+            blank = 0;
+        }
+        markdown.push_str(line);
+        markdown.push('\n');
+    }
+    markdown.trim().to_string()
+}
+
+fn markdown_children(el: &ElementRef, out: &mut String) {
+    for child in el.children() {
+        match child.value() {
+            Node::Text(text) => {
+                // 连续的空白只保留一个空格
+                let mut collapsed = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                if text.starts_with(char::is_whitespace) && !collapsed.is_empty() {
+                    collapsed.insert(0, ' ');
+                }
+                if text.ends_with(char::is_whitespace) && !collapsed.is_empty() {
+                    collapsed.push(' ');
+                }
+                out.push_str(&collapsed);
+            }
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    markdown_element(&child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn markdown_inline(el: &ElementRef) -> String {
+    let mut out = String::new();
+    markdown_children(el, &mut out);
+    out.trim().to_string()
+}
+
+fn push_block(out: &mut String, block: &str) {
+    if block.is_empty() {
+        return;
+    }
+    out.push_str("\n\n");
+    out.push_str(block);
+    out.push_str("\n\n");
+}
+
+fn markdown_element(el: &ElementRef, out: &mut String) {
+    let name = el.value().name();
+    match name {
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+            let level = name[1..].parse::<usize>().unwrap_or(1);
+            let text = markdown_inline(el);
+            if !text.is_empty() {
+                push_block(out, &format!("{} {}", "#".repeat(level), text));
+            }
+        }
+        "p" | "div" | "section" | "article" | "figure" | "figcaption" | "dl" | "dd" | "dt" => {
+            push_block(out, &markdown_inline(el));
+        }
+        "br" => out.push('\n'),
+        "hr" => push_block(out, "---"),
+        "a" => {
+            let text = markdown_inline(el);
+            match el.value().attr("href") {
+                Some(href) if !text.is_empty() => out.push_str(&format!("[{}]({})", text, href)),
+                _ => out.push_str(&text),
+            }
+        }
+        "img" => {
+            if let Some(src) = el.value().attr("src") {
+                let alt = el.value().attr("alt").unwrap_or_default();
+                out.push_str(&format!("![{}]({})", alt, src));
+            }
+        }
+        "strong" | "b" => wrap_inline(el, "**", out),
+        "em" | "i" => wrap_inline(el, "*", out),
+        "del" | "s" => wrap_inline(el, "~~", out),
+        "code" => wrap_inline(el, "`", out),
+        "pre" => {
+            let code = el.text().collect::<String>();
+            push_block(out, &format!("```\n{}\n```", code.trim_matches('\n')));
+        }
+        "blockquote" => {
+            let mut inner = String::new();
+            markdown_children(el, &mut inner);
+            let quoted = inner
+                .trim()
+                .lines()
+                .map(|line| format!("> {}", line.trim()))
+                .collect::<Vec<_>>()
+                .join("\n");
+            push_block(out, &quoted);
+        }
+        "ul" | "ol" => {
+            let ordered = name == "ol";
+            let items = el
+                .children()
+                .filter_map(ElementRef::wrap)
+                .filter(|child| child.value().name() == "li")
+                .enumerate()
+                .map(|(i, li)| {
+                    let marker = match ordered {
+                        true => format!("{}. ", i + 1),
+                        false => "- ".to_string(),
+                    };
+                    let mut inner = String::new();
+                    markdown_children(&li, &mut inner);
+                    // 多行的列表项后续行需要缩进
+                    let indent = " ".repeat(marker.len());
+                    let body = inner
+                        .trim()
+                        .lines()
+                        .filter(|line| !line.trim().is_empty())
+                        .collect::<Vec<_>>()
+                        .join(&format!("\n{}", indent));
+                    format!("{}{}", marker, body)
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            push_block(out, &items);
+        }
+        "table" => {
+            let rows = el
+                .select(&Selector::parse("tr").unwrap())
+                .map(|tr| {
+                    tr.children()
+                        .filter_map(ElementRef::wrap)
+                        .filter(|cell| matches!(cell.value().name(), "td" | "th"))
+                        .map(|cell| markdown_inline(&cell).replace('|', "\\|"))
+                        .collect::<Vec<_>>()
+                })
+                .filter(|cells| !cells.is_empty())
+                .collect::<Vec<_>>();
+            if let Some(first) = rows.first() {
+                let mut lines = vec![format!("| {} |", first.join(" | "))];
+                lines.push(format!("|{}", " --- |".repeat(first.len())));
+                for row in rows.iter().skip(1) {
+                    lines.push(format!("| {} |", row.join(" | ")));
+                }
+                push_block(out, &lines.join("\n"));
+            }
+        }
+        _ => markdown_children(el, out),
+    }
+}
+
+fn wrap_inline(el: &ElementRef, mark: &str, out: &mut String) {
+    let text = markdown_inline(el);
+    if !text.is_empty() {
+        out.push_str(&format!("{}{}{}", mark, text, mark));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = r#"
+    <html>
+    <head>
+    <title>Rust 1.80 发布</title>
+    <meta property="og:image" content="/images/cover.png">
+    <meta name="description" content="Rust 1.80 的新特性">
+    <script>var tracking = 1;</script>
+    </head>
+    <body>
+    <header class="masthead"><a href="/">首页</a> <a href="/blog">博客</a></header>
+    <nav><ul><li><a href="/a">导航一</a></li><li><a href="/b">导航二</a></li></ul></nav>
+    <div id="main" class="post-content">
+        <h1>Rust 1.80 发布</h1>
+        <p>Rust 团队很高兴地宣布发布新版本 Rust 1.80, Rust 是一门让每个人都能构建可靠、高效软件的语言。</p>
+        <figure>
+            <img data-src="/images/lazy.png" src="data:image/gif;base64,R0lGOD">
+            <figcaption>新版本的 <em>特性</em> 概览</figcaption>
+        </figure>
+        <p>这个版本稳定了 LazyCell 和 LazyLock, 它们可以在第一次访问时初始化数据, 替代很多 lazy_static 的用法。更多细节请查看 <a href="/notes">发布说明</a>。</p>
+        <ul><li>稳定了排他范围模式</li><li>改进了 cfg 检查</li></ul>
+        <pre><code>let x = LazyLock::new(|| 1);</code></pre>
+        <div class="share-widget"><a href="/share">分享到微博</a> <a href="/share2">分享到微信</a></div>
+    </div>
+    <aside class="sidebar"><p>热门文章, 热门文章, 热门文章, 热门文章, 热门文章, 热门文章。</p></aside>
+    <div class="comments"><p>评论一: 写得很好, 学到了很多东西, 期待下一个版本的发布。</p></div>
+    <footer>版权所有</footer>
+    </body>
+    </html>
+    "#;
+
+    #[tokio::test]
+    async fn test_extract_article() {
+        let article = extract_article(
+            ARTICLE.to_string(),
+            Some("https://blog.example.com/2024/rust-1-80"),
+        )
+        .await
+        .unwrap();
+        assert_eq!(article.title.as_deref(), Some("Rust 1.80 发布"));
+        assert_eq!(article.excerpt.as_deref(), Some("Rust 1.80 的新特性"));
+        assert_eq!(
+            article.lead_image_url.as_deref(),
+            Some("https://blog.example.com/images/cover.png")
+        );
+
+        // 保留正文、图片和图注, 去掉导航、评论、侧边栏和分享按钮
+        assert!(article.text.contains("LazyCell"));
+        assert!(article
+            .content
+            .contains(r#"<img src="https://blog.example.com/images/lazy.png">"#));
+        assert!(article.content.contains("<figcaption>"));
+        assert!(article
+            .content
+            .contains(r#"<a href="https://blog.example.com/notes">"#));
+        for boilerplate in [
+            "导航一",
+            "评论一",
+            "热门文章",
+            "分享到微博",
+            "版权所有",
+            "tracking",
+        ] {
+            assert!(!article.content.contains(boilerplate), "{}", boilerplate);
+        }
+
+        assert!(article.markdown.starts_with("# Rust 1.80 发布"));
+        assert!(article
+            .markdown
+            .contains("![](https://blog.example.com/images/lazy.png)"));
+        assert!(article
+            .markdown
+            .contains("[发布说明](https://blog.example.com/notes)"));
+        assert!(article
+            .markdown
+            .contains("- 稳定了排他范围模式\n- 改进了 cfg 检查"));
+        assert!(article
+            .markdown
+            .contains("```\nlet x = LazyLock::new(|| 1);\n```"));
+    }
+
+    #[tokio::test]
+    async fn test_extract_article_without_content() {
+        let html = "<html><head><title>空页面</title></head><body><nav><a href='/'>首页</a></nav></body></html>";
+        let res = extract_article(html.to_string(), None).await;
+        assert!(matches!(res, Err(FetchError::Parse(_))));
+    }
+
+    #[tokio::test]
+    async fn test_extract_article_drops_unsafe_links() {
+        let html = r#"<html><body><article>
+        <p>这是一段足够长的正文, 用来测试链接的过滤, 只保留可以安全打开的链接。</p>
+        <p><a href="javascript:alert(1)">脚本</a> <a href="data:text/html,hi">数据</a>
+        <a href="mailto:me@example.com">邮件</a> <a href="/notes">笔记</a></p>
+        </article></body></html>"#;
+        let article = extract_article(html.to_string(), Some("https://example.com/post"))
+            .await
+            .unwrap();
+        assert!(!article.content.contains("javascript:"));
+        assert!(!article.content.contains("data:"));
+        assert!(article
+            .content
+            .contains(r#"<a href="mailto:me@example.com">"#));
+        assert!(article
+            .content
+            .contains(r#"<a href="https://example.com/notes">"#));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_article_rejects_private_address() {
+        let res = fetch_article("http://127.0.0.1:1/", None).await;
+        assert!(matches!(res, Err(FetchError::PrivateAddress(_))));
+    }
+
+    #[test]
+    fn test_html_to_markdown() {
+        let html = r#"<h2>标题</h2><p>一段 <strong>加粗</strong> 和 <em>斜体</em> 的文字</p>
+        <blockquote><p>引用</p></blockquote>
+        <ol><li>第一</li><li>第二</li></ol>
+        <table><tr><th>名称</th><th>值</th></tr><tr><td>a</td><td>1</td></tr></table>"#;
+        assert_eq!(
+            html_to_markdown(html),
+            "## 标题\n\n一段 **加粗** 和 *斜体* 的文字\n\n> 引用\n\n1. 第一\n2. 第二\n\n| 名称 | 值 |\n| --- | --- |\n| a | 1 |"
+        );
+    }
+}
@@ -0,0 +1,412 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::error::FetchError;
+use crate::url::{get_response_from_url, RequestOptionBuilder};
+
+// robots.txt 的缓存时间
+const ROBOTS_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+// robots.txt 暂时无法访问时, 按照全部禁止处理, 缓存时间较短
+const ROBOTS_UNREACHABLE_TTL: Duration = Duration::from_secs(10 * 60);
+// robots.txt 最大 500KB, 超出的部分截断不解析
+const ROBOTS_MAX_SIZE: u64 = 500 * 1024;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RobotsVerdict {
+    Allowed {
+        // Crawl-delay 指定的请求间隔
+        crawl_delay: Option<Duration>,
+    },
+    Disallowed {
+        // 命中的规则, 用于记录跳过的原因
+        rule: String,
+    },
+}
+
+impl RobotsVerdict {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Self::Allowed { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RobotsRule {
+    allow: bool,
+    pattern: String,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RobotsGroup {
+    agents: Vec<String>,
+    rules: Vec<RobotsRule>,
+    crawl_delay: Option<f64>,
+}
+
+/// 解析后的 robots.txt
+#[derive(Debug, Clone, Default)]
+pub struct RobotsRules {
+    groups: Vec<RobotsGroup>,
+    // 无法访问 robots.txt 时全部禁止
+    disallow_all: bool,
+}
+
+impl RobotsRules {
+    pub fn parse(content: &str) -> Self {
+        let mut groups: Vec<RobotsGroup> = Vec::new();
+        let mut current = RobotsGroup::default();
+        // 连续的 User-agent 行属于同一组
+        let mut collecting_agents = false;
+
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_lowercase();
+            let value = value.trim();
+            match key.as_str() {
+                "user-agent" => {
+                    if !collecting_agents && !current.agents.is_empty() {
+                        groups.push(std::mem::take(&mut current));
+                    }
+                    current.agents.push(value.to_lowercase());
+                    collecting_agents = true;
+                }
+                "allow" | "disallow" => {
+                    collecting_agents = false;
+                    if current.agents.is_empty() {
+                        continue;
+                    }
+                    // 空的 Disallow 表示不限制
+                    if value.is_empty() {
+                        continue;
+                    }
+                    current.rules.push(RobotsRule {
+                        allow: key == "allow",
+                        pattern: value.to_string(),
+                    });
+                }
+                "crawl-delay" => {
+                    collecting_agents = false;
+                    if let Ok(delay) = value.parse::<f64>() {
+                        current.crawl_delay = Some(delay);
+                    }
+                }
+                _ => {}
+            }
+        }
+        if !current.agents.is_empty() {
+            groups.push(current);
+        }
+        Self {
+            groups,
+            disallow_all: false,
+        }
+    }
+
+    fn unreachable() -> Self {
+        Self {
+            groups: Vec::new(),
+            disallow_all: true,
+        }
+    }
+
+    // 找到和 user agent 最匹配的组, 没有时使用 `*`
+    fn group_for(&self, user_agent: &str) -> Option<&RobotsGroup> {
+        let token = product_token(user_agent);
+        let specific = self
+            .groups
+            .iter()
+            .filter_map(|g| {
+                g.agents
+                    .iter()
+                    .filter(|a| a.as_str() != "*" && token.contains(a.as_str()))
+                    .map(|a| a.len())
+                    .max()
+                    .map(|len| (len, g))
+            })
+            .max_by_key(|(len, _)| *len)
+            .map(|(_, g)| g);
+        specific.or_else(|| {
+            self.groups
+                .iter()
+                .find(|g| g.agents.iter().any(|a| a == "*"))
+        })
+    }
+
+    /// 判断 user agent 是否可以访问 path (包含 query)
+    pub fn check(&self, user_agent: &str, path: &str) -> RobotsVerdict {
+        if self.disallow_all {
+            return RobotsVerdict::Disallowed {
+                rule: "robots.txt unreachable".to_string(),
+            };
+        }
+        let Some(group) = self.group_for(user_agent) else {
+            return RobotsVerdict::Allowed { crawl_delay: None };
+        };
+        // 最长匹配的规则生效, 长度相同时 Allow 优先
+        let matched = group
+            .rules
+            .iter()
+            .filter(|r| pattern_matches(&r.pattern, path))
+            .max_by_key(|r| (r.pattern.len(), r.allow));
+        match matched {
+            Some(rule) if !rule.allow => RobotsVerdict::Disallowed {
+                rule: format!("Disallow: {}", rule.pattern),
+            },
+            _ => RobotsVerdict::Allowed {
+                crawl_delay: group
+                    .crawl_delay
+                    .filter(|d| d.is_finite() && *d > 0.0)
+                    .map(Duration::from_secs_f64),
+            },
+        }
+    }
+}
+
+// `Mozilla/5.0 (compatible; ArticleCrawler/1.0)` 这种形式时, 取出爬虫名称部分; 否则取第一个 `/` 之前的部分
+fn product_token(user_agent: &str) -> String {
+    let ua = user_agent.to_lowercase();
+    if let Some(compatible) = ua.split("compatible;").nth(1) {
+        if let Some(name) = compatible.split(['/', ';', ')']).next() {
+            return name.trim().to_string();
+        }
+    }
+    ua.split(['/', ' ']).next().unwrap_or("").to_string()
+}
+
+// 支持 `*` 通配符和 `$` 结尾
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(p) => (p, true),
+        None => (pattern, false),
+    };
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let mut rest = path;
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            match rest.strip_prefix(part) {
+                Some(r) => rest = r,
+                None => return false,
+            }
+            continue;
+        }
+        // 最后一段在需要锚定结尾时, 必须匹配到末尾
+        if i == parts.len() - 1 && anchored {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+// 过期时间 + 规则
+type RobotsEntry = (Instant, Arc<RobotsRules>);
+
+/// 按站点缓存 robots.txt
+#[derive(Debug, Clone)]
+pub struct RobotsCache {
+    user_agent: String,
+    entries: Arc<Mutex<HashMap<String, RobotsEntry>>>,
+}
+
+impl RobotsCache {
+    pub fn new<T: Into<String>>(user_agent: T) -> Self {
+        Self {
+            user_agent: user_agent.into(),
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn user_agent(&self) -> &str {
+        &self.user_agent
+    }
+
+    /// 检查 url 是否允许抓取, 会按需拉取并缓存 robots.txt
+    pub async fn check(&self, url: &str) -> RobotsVerdict {
+        let parsed = match reqwest::Url::parse(url) {
+            Ok(u) => u,
+            Err(e) => {
+                return RobotsVerdict::Disallowed {
+                    rule: format!("invalid url: {}", e),
+                }
+            }
+        };
+        let origin = parsed.origin().ascii_serialization();
+        let mut path = parsed.path().to_string();
+        if let Some(query) = parsed.query() {
+            path = format!("{}?{}", path, query);
+        }
+        let rules = self.rules_for(&origin).await;
+        rules.check(&self.user_agent, &path)
+    }
+
+    async fn rules_for(&self, origin: &str) -> Arc<RobotsRules> {
+        if let Some((expire_at, rules)) = self.entries.lock().unwrap().get(origin) {
+            if *expire_at > Instant::now() {
+                return Arc::clone(rules);
+            }
+        }
+        let (rules, ttl) = self.fetch(origin).await;
+        let rules = Arc::new(rules);
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        // 顺便清理已经过期的站点, 避免缓存无限增长
+        entries.retain(|_, (expire_at, _)| *expire_at > now);
+        entries.insert(origin.to_string(), (now + ttl, Arc::clone(&rules)));
+        rules
+    }
+
+    async fn fetch(&self, origin: &str) -> (RobotsRules, Duration) {
+        let req = RequestOptionBuilder::default()
+            .url(format!("{}/robots.txt", origin))
+            .timeout(10)
+            .retry_times(1)
+            .user_agent(self.user_agent.clone())
+            .max_body_size(ROBOTS_MAX_SIZE)
+            .truncate_body(true)
+            .build();
+        let req = match req {
+            Ok(req) => req,
+            Err(_) => return (RobotsRules::unreachable(), ROBOTS_UNREACHABLE_TTL),
+        };
+        match get_response_from_url(req).await {
+            Ok(resp) => (RobotsRules::parse(&resp.body), ROBOTS_TTL),
+            // 4xx 表示站点没有 robots.txt, 不做限制
+            Err(FetchError::HttpStatus(status)) if (400..500).contains(&status) => {
+                (RobotsRules::default(), ROBOTS_TTL)
+            }
+            Err(FetchError::EmptyBody) => (RobotsRules::default(), ROBOTS_TTL),
+            Err(e) => {
+                tracing::warn!("robots.txt 无法访问 {}: {}", origin, e);
+                (RobotsRules::unreachable(), ROBOTS_UNREACHABLE_TTL)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UA: &str = "Mozilla/5.0 (compatible; ArticleCrawler/0.1; +https://example.com/bot)";
+
+    #[test]
+    fn test_product_token() {
+        assert_eq!(product_token(UA), "articlecrawler");
+        assert_eq!(product_token("ArticleCrawler/0.1"), "articlecrawler");
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("/private", "/private/a"));
+        assert!(pattern_matches("/*.pdf$", "/files/a.pdf"));
+        assert!(!pattern_matches("/*.pdf$", "/files/a.pdf?x=1"));
+        assert!(pattern_matches("/a*b", "/a/x/b/c"));
+        assert!(!pattern_matches("/a", "/b"));
+    }
+
+    #[test]
+    fn test_parse_and_check() {
+        let rules = RobotsRules::parse(
+            r#"
+# comment
+User-agent: *
+Disallow: /admin
+Allow: /admin/public
+Crawl-delay: 2
+
+User-agent: BadBot
+User-agent: ArticleCrawler
+Disallow: /private
+Crawl-delay: 5
+"#,
+        );
+        assert_eq!(
+            rules.check(UA, "/private/1"),
+            RobotsVerdict::Disallowed {
+                rule: "Disallow: /private".to_string()
+            }
+        );
+        // 匹配到专属的组后, `*` 组的规则不再生效
+        assert_eq!(
+            rules.check(UA, "/admin"),
+            RobotsVerdict::Allowed {
+                crawl_delay: Some(Duration::from_secs(5))
+            }
+        );
+
+        let other = "OtherBot/1.0";
+        assert!(!rules.check(other, "/admin/x").is_allowed());
+        assert!(rules.check(other, "/admin/public/x").is_allowed());
+        assert_eq!(
+            rules.check(other, "/"),
+            RobotsVerdict::Allowed {
+                crawl_delay: Some(Duration::from_secs(2))
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_robots_cache() {
+        use crate::test_server::{response, serve};
+
+        let host = serve(|request| {
+            if request.starts_with("GET /robots.txt") {
+                return response(
+                    "200 OK",
+                    &[("Content-Type", "text/plain")],
+                    "User-agent: *\nDisallow: /private\n",
+                );
+            }
+            response("404 Not Found", &[], "")
+        })
+        .await;
+        let cache = RobotsCache::new(UA);
+        assert!(cache.check(&format!("{}/post/1", host)).await.is_allowed());
+        assert!(!cache
+            .check(&format!("{}/private/1", host))
+            .await
+            .is_allowed());
+
+        let missing = serve(|_| response("404 Not Found", &[], "")).await;
+        assert!(cache.check(&format!("{}/any", missing)).await.is_allowed());
+
+        // 过期的站点在写入新站点时被清理
+        cache.entries.lock().unwrap().insert(
+            "http://expired.example.com".to_string(),
+            (Instant::now(), Arc::default()),
+        );
+        let other = serve(|_| response("404 Not Found", &[], "")).await;
+        assert!(cache.check(&format!("{}/any", other)).await.is_allowed());
+        let entries = cache.entries.lock().unwrap();
+        assert!(!entries.contains_key("http://expired.example.com"));
+        assert_eq!(entries.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_robots_truncated() {
+        use crate::test_server::{response, serve};
+
+        // 超过 500KB 的 robots.txt 只解析前面的部分
+        let host = serve(|_| {
+            let mut body = "User-agent: *\nDisallow: /private\n".to_string();
+            body.push_str(&"# padding\n".repeat(60 * 1024));
+            body.push_str("Disallow: /\n");
+            response("200 OK", &[("Content-Type", "text/plain")], &body)
+        })
+        .await;
+        let cache = RobotsCache::new(UA);
+        assert!(cache.check(&format!("{}/post/1", host)).await.is_allowed());
+        assert!(!cache
+            .check(&format!("{}/private/1", host))
+            .await
+            .is_allowed());
+    }
+}
@@ -1,49 +1,160 @@
-pub use rss::{extension::ExtensionBuilder, extension::ExtensionMap, Channel};
-use std::collections::BTreeMap;
+use rss::Channel;
 use std::path::Path;
 
-use crate::get_content_from_url;
-use crate::url::RequestOptionBuilder;
+use serde::{Deserialize, Serialize};
 
-pub async fn fetch_rss_from_url<T: AsRef<str>>(url: T) -> anyhow::Result<Channel> {
+use crate::error::FetchError;
+use crate::json_feed::parse_json_feed;
+use crate::model::{FeedFormat, ParsedFeed};
+use crate::url::{get_response_from_url, RequestOptionBuilder};
+
+// 条件请求所需的缓存校验信息, 来自上一次成功拉取的响应头
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedValidators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl FeedValidators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+// 拉取并解析成功的订阅源
+#[derive(Debug, Clone)]
+pub struct FetchedFeed {
+    pub feed: ParsedFeed,
+    // 本次响应的缓存校验信息, 需要保存下来用于下次请求
+    pub validators: FeedValidators,
+}
+
+#[derive(Debug, Clone)]
+pub enum FeedFetchResult {
+    Modified(Box<FetchedFeed>),
+    // 服务端返回 304, 订阅源自上次拉取后没有变化
+    NotModified,
+}
+
+// 订阅源可接受的 Content-Type, 很多站点会把订阅源标记为 text/html, 这里也允许
+pub(crate) const FEED_CONTENT_TYPES: &[&str] =
+    &["xml", "rss", "atom", "json", "text/plain", "text/html"];
+// 这些 Content-Type 需要检查内容是否像订阅源, 不像时按 Content-Type 错误处理
+const SNIFFED_CONTENT_TYPES: &[&str] = &["text/plain", "text/html"];
+// 检查内容开头的字符数
+const SNIFF_CHARS: usize = 4096;
+
+// 内容开头是否有订阅源的根元素, 或者是 JSON Feed
+fn looks_like_feed(body: &str) -> bool {
+    let head: String = body
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .chars()
+        .take(SNIFF_CHARS)
+        .collect::<String>()
+        .to_lowercase();
+    head.starts_with('{')
+        || ["<rss", "<feed", "<rdf:rdf"]
+            .iter()
+            .any(|tag| head.contains(tag))
+}
+
+pub async fn fetch_rss_from_url<T: AsRef<str>>(url: T) -> Result<ParsedFeed, FetchError> {
+    match fetch_rss_from_url_if_modified(url, &FeedValidators::default()).await? {
+        FeedFetchResult::Modified(fetched) => Ok(fetched.feed),
+        // 没有携带校验信息时不会出现
+        FeedFetchResult::NotModified => Err(FetchError::HttpStatus(304)),
+    }
+}
+
+// 携带上次的 ETag / Last-Modified 发起条件请求, 没有变化时返回 `NotModified`
+pub async fn fetch_rss_from_url_if_modified<T: AsRef<str>>(
+    url: T,
+    validators: &FeedValidators,
+) -> Result<FeedFetchResult, FetchError> {
     let path = format!("{}.xml", url.as_ref().replace('/', "_").replace(':', ""));
-    let content = match Path::new(&path).exists() {
-        true => std::fs::read_to_string(&path)?,
+    let (content, validators) = match Path::new(&path).exists() {
+        true => (
+            std::fs::read_to_string(&path).map_err(|e| FetchError::Request(e.to_string()))?,
+            FeedValidators::default(),
+        ),
         false => {
             // 重试3次
-            let req = RequestOptionBuilder::default()
-                .url(url.as_ref().to_string())
+            let mut req = RequestOptionBuilder::default();
+            req.url(url.as_ref().to_string())
                 .timeout(15)
                 .retry_times(3)
+                .accept_content_types(FEED_CONTENT_TYPES.iter().map(|t| t.to_string()).collect());
+            if let Some(etag) = &validators.etag {
+                req.if_none_match(etag.clone());
+            }
+            if let Some(last_modified) = &validators.last_modified {
+                req.if_modified_since(last_modified.clone());
+            }
+            let req = req
                 .build()
-                .map_err(|e| anyhow::anyhow!(e))?;
-            get_content_from_url(req).await?
-            // 根据url 写到当前目录的文件中
-            // 如果本地已经存在，就不写了
-            // if !Path::new(&path).exists() {
-            //     // 写到文件中
-            //     _ = std::fs::write(path, content.clone());
-            // }
+                .map_err(|e| FetchError::Request(e.to_string()))?;
+            let resp = get_response_from_url(req).await?;
+            if resp.is_not_modified() {
+                return Ok(FeedFetchResult::NotModified);
+            }
+            if let Some(content_type) = resp.content_type.as_deref().filter(|ct| {
+                let lower = ct.to_lowercase();
+                SNIFFED_CONTENT_TYPES.iter().any(|t| lower.contains(t))
+            }) {
+                if !looks_like_feed(&resp.body) {
+                    return Err(FetchError::ContentType(content_type.to_string()));
+                }
+            }
+            let validators = FeedValidators {
+                etag: resp.etag,
+                last_modified: resp.last_modified,
+            };
+            (resp.body, validators)
         }
     };
 
     // 如果content 是空的，就返回错误
     if content.is_empty() {
-        return Err(anyhow::anyhow!("rss content is empty"));
+        return Err(FetchError::EmptyBody);
+    }
+
+    let feed = parse_feed_from_content(content).map_err(|e| FetchError::Parse(e.to_string()))?;
+    Ok(FeedFetchResult::Modified(Box::new(FetchedFeed {
+        feed,
+        validators,
+    })))
+}
+
+/// 解析订阅源的内容, 例如 WebSub 推送的内容
+pub fn parse_feed(content: &str) -> Result<ParsedFeed, FetchError> {
+    if content.trim().is_empty() {
+        return Err(FetchError::EmptyBody);
     }
+    parse_feed_from_content(content.to_string()).map_err(|e| FetchError::Parse(e.to_string()))
+}
 
-    // 首先定义一系列的尝试解析的策略，每一个策略都是一个函数，返回一个Option<Channel>，如果解析成功，就返回Some(Channel)，否则返回None
+pub(crate) fn parse_feed_from_content(content: String) -> anyhow::Result<ParsedFeed> {
+    // 首先定义一系列的尝试解析的策略，每一个策略都是一个函数，返回一个Option<ParsedFeed>，如果解析成功，就返回Some(ParsedFeed)，否则返回None
     // 依次尝试每一个策略，如果有一个策略成功，就返回，否则返回错误
     // 这个判断的规则是 channel.validate() 返回的结果，如果是Err，就说明解析失败，如果是Ok，就说明解析成功
 
+    // JSON Feed 单独解析, feed_rs 会丢失 content_html / attachments / 扩展字段
+    if content.trim_start().starts_with('{') {
+        let feed = parse_json_feed(&content)?;
+        return Ok(ParsedFeed::from(feed));
+    }
+
     let try_ops = vec![
         // 尝试通过 feed_rs 解析
-        |content: String| -> anyhow::Result<Channel> {
-            return parse_rss_by_feed_rs(content.as_bytes()).map_err(|e| anyhow::anyhow!(e));
+        |content: String| -> anyhow::Result<ParsedFeed> {
+            parse_feed_by_feed_rs(content.as_bytes()).map_err(|e| anyhow::anyhow!(e))
         },
         // 尝试通过 rss 解析
-        |content: String| -> anyhow::Result<Channel> {
-            return Channel::read_from(content.as_bytes()).map_err(|e| anyhow::anyhow!(e));
+        |content: String| -> anyhow::Result<ParsedFeed> {
+            Channel::read_from(content.as_bytes())
+                .map(ParsedFeed::from)
+                .map_err(|e| anyhow::anyhow!(e))
         },
     ];
 
@@ -81,162 +192,150 @@ pub async fn fetch_rss_from_url<T: AsRef<str>>(url: T) -> anyhow::Result<Channel
     }
 }
 
-fn parse_rss_by_feed_rs<R: std::io::Read>(content: R) -> anyhow::Result<Channel> {
-    let feed = feed_rs::parser::parse(content).map_err(|e| anyhow::anyhow!(e))?;
-    let channel = map_feed_model_to_channel(feed)?;
-    Ok(channel)
+fn parse_feed_by_feed_rs<R: std::io::Read>(content: R) -> anyhow::Result<ParsedFeed> {
+    // 条目没有 id 时不自动生成, 便于区分订阅源是否提供了 guid
+    let parser = feed_rs::parser::Builder::new()
+        .id_generator(|_, _, _| String::new())
+        .build();
+    let feed = parser.parse(content).map_err(|e| anyhow::anyhow!(e))?;
+    if feed.title.is_none() {
+        return Err(anyhow::anyhow!("rss title 不存在"));
+    }
+    Ok(ParsedFeed::from(feed))
+}
+
+// test
+#[tokio::test]
+async fn test_fetch_rss_from_url() {
+    let url = "https://rss.uol.com.br/feed/noticias.xml";
+    let feed = fetch_rss_from_url(url).await;
+    assert!(feed.is_ok());
+    let feed_value = feed.unwrap();
+
+    println!("feed_value:title :{:?}", feed_value.title);
+    println!("feed_value:description :{:?}", feed_value.description);
+    println!("feed_value:link :{:?}", feed_value.site_link);
+    println!("feed_value:updated_at :{:?}", feed_value.updated_at);
+    println!("feed_value:published_at :{:?}", feed_value.published_at);
+    println!("feed_value:language :{:?}", feed_value.language);
+    println!("feed_value:entries :{:?}", feed_value.entries.len());
+    assert!(!feed_value.entries.is_empty());
+}
+
+#[test]
+fn test_parse_json_feed_content() {
+    let content = include_str!("../fixture/feeds/json_feed_1_1.json");
+    let feed = parse_feed_from_content(content.to_string()).unwrap();
+    assert_eq!(feed.format, FeedFormat::Json);
+    assert_eq!(feed.entries.len(), 2);
+    assert_eq!(
+        feed.entries[0].content.as_deref(),
+        Some("<p>Hello <b>world</b></p>")
+    );
 }
 
-fn map_feed_model_to_channel(feed: feed_rs::model::Feed) -> anyhow::Result<Channel> {
-    let title = match feed.title {
-        Some(title) => title.content,
-        None => {
-            return Err(anyhow::anyhow!("rss title 不存在"));
+#[tokio::test]
+async fn test_fetch_rss_with_validators() {
+    use crate::test_server::{header, response, serve};
+
+    const FEED: &str = r#"<?xml version="1.0"?><rss version="2.0"><channel><title>t</title><link>http://example.com</link><description>d</description><item><title>a</title><link>http://example.com/a</link></item></channel></rss>"#;
+    let host = serve(|request| {
+        if header(request, "if-none-match") == Some("\"v1\"") {
+            return response("304 Not Modified", &[("ETag", "\"v1\"")], "");
         }
+        response(
+            "200 OK",
+            &[
+                ("Content-Type", "application/rss+xml"),
+                ("ETag", "\"v1\""),
+                ("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+            ],
+            FEED,
+        )
+    })
+    .await;
+    let url = format!("{}/feed.xml", host);
+
+    let first = fetch_rss_from_url_if_modified(&url, &FeedValidators::default())
+        .await
+        .unwrap();
+    let validators = match first {
+        FeedFetchResult::Modified(fetched) => {
+            assert_eq!(fetched.feed.entries.len(), 1);
+            fetched.validators
+        }
+        FeedFetchResult::NotModified => panic!("first fetch should return content"),
     };
-    let description = match feed.description {
-        Some(description) => description.content,
-        None => "".to_string(),
-    };
-    let mut channel = Channel::default();
-    channel.set_title(title);
-    channel.set_description(description);
-    channel.set_link(match feed.links.first() {
-        Some(link) => link.href.clone(),
-        None => "".to_string(),
-    });
-    channel.set_language(feed.language);
-    channel.set_last_build_date(match feed.updated {
-        Some(updated) => updated.to_rfc3339(),
-        None => "".to_string(),
-    });
-    channel.set_pub_date(match feed.published {
-        Some(published) => published.to_rfc3339(),
-        None => "".to_string(),
-    });
-    let feed_items: Vec<rss::Item> = feed
-        .entries
-        .into_iter()
-        .map(|element| {
-            let mut item = rss::Item::default();
-
-            // 设置标题
-            item.set_title(match element.title {
-                Some(title) => Some(title.content),
-                None => Some("".to_string()),
-            });
-
-            // 设置描述
-            item.set_description(match element.summary {
-                Some(summary) => Some(summary.content),
-                None => Some("".to_string()),
-            });
-
-            // 设置链接
-            item.set_link(match element.links.first() {
-                Some(link) => Some(link.href.clone()),
-                None => Some("".to_string()),
-            });
-
-            // 设置作者
-            item.set_author(match element.authors.first() {
-                Some(author) => Some(author.name.clone()),
-                None => Some("".to_string()),
-            });
-
-            // 设置发布日期
-            let pub_date = match element.published {
-                Some(published) => Some(published),
-                None => element.updated,
-            };
+    assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+    assert_eq!(
+        validators.last_modified.as_deref(),
+        Some("Wed, 21 Oct 2015 07:28:00 GMT")
+    );
 
-            item.set_pub_date(pub_date.map(|pub_date| pub_date.to_rfc3339()));
-
-            let mut extension_map = BTreeMap::new();
-            // 设置图片, 从 media 中获取
-            let mut images = Vec::new();
-            for media in element.media {
-                if let Some(first) = media.thumbnails.first() {
-                    // get first url
-                    let ext = ExtensionBuilder::default()
-                        .value(Some(first.image.uri.clone()))
-                        .build();
-                    images.push(ext);
-                    // 设置 enclosure
-                    let mut enclosure = rss::Enclosure::default();
-                    enclosure.set_url(first.image.uri.clone());
-                    item.set_enclosure(enclosure);
-                }
-            }
-            extension_map.insert("images".to_string(), images);
-            // 设置作者的详细信息
-            let author_detail = element
-                .authors
-                .iter()
-                .map(|author| {
-                    let mut author_meta = BTreeMap::new();
-                    author_meta.insert("name".to_string(), author.name.clone());
-
-                    if let Some(uri) = author.uri.clone() {
-                        author_meta.insert("uri".to_string(), uri);
-                    }
-
-                    if let Some(email) = author.email.clone() {
-                        author_meta.insert("email".to_string(), email);
-                    }
-                    ExtensionBuilder::default().attrs(author_meta).build()
-                })
-                .collect::<Vec<_>>();
-            extension_map.insert("authors".to_string(), author_detail);
-            // category
-            extension_map.insert(
-                "category".to_string(),
-                element
-                    .categories
-                    .iter()
-                    .map(|c| {
-                        let mut category_meta = BTreeMap::new();
-                        category_meta.insert("term".to_string(), c.term.clone());
-                        category_meta
-                            .insert("scheme".to_string(), c.scheme.clone().unwrap_or_default());
-                        category_meta
-                            .insert("label".to_string(), c.label.clone().unwrap_or_default());
-                        ExtensionBuilder::default().attrs(category_meta).build()
-                    })
-                    .collect::<Vec<_>>(),
+    let second = fetch_rss_from_url_if_modified(&url, &validators)
+        .await
+        .unwrap();
+    assert!(matches!(second, FeedFetchResult::NotModified));
+}
+
+#[tokio::test]
+async fn test_fetch_rss_errors() {
+    use crate::test_server::{response, serve};
+
+    let host = serve(|request| {
+        if request.starts_with("GET /missing") {
+            return response(
+                "404 Not Found",
+                &[("Content-Type", "text/html")],
+                "<html>404</html>",
+            );
+        }
+        if request.starts_with("GET /image") {
+            return response("200 OK", &[("Content-Type", "image/png")], "png");
+        }
+        if request.starts_with("GET /page") {
+            return response(
+                "200 OK",
+                &[("Content-Type", "text/html; charset=utf-8")],
+                "<!DOCTYPE html><html><body>page</body></html>",
+            );
+        }
+        if request.starts_with("GET /html-feed") {
+            return response(
+                "200 OK",
+                &[("Content-Type", "text/html")],
+                r#"<?xml version="1.0"?><rss version="2.0"><channel><title>t</title><link>http://example.com</link><description>d</description></channel></rss>"#,
             );
+        }
+        response("200 OK", &[("Content-Type", "text/xml")], "not a feed")
+    })
+    .await;
 
-            let mut ext = ExtensionMap::default();
-            ext.insert("ext".to_string(), extension_map);
+    let err = fetch_rss_from_url(format!("{}/missing", host))
+        .await
+        .unwrap_err();
+    assert_eq!(err, FetchError::HttpStatus(404));
+    assert_eq!(err.kind(), "http_status");
 
-            item.set_extensions(ext);
-            item
-        })
-        .collect();
-    channel.set_items(feed_items);
-    Ok(channel)
-}
+    let err = fetch_rss_from_url(format!("{}/image", host))
+        .await
+        .unwrap_err();
+    assert_eq!(err, FetchError::ContentType("image/png".to_string()));
 
-// test
-#[tokio::test]
-async fn test_fetch_rss_from_url() {
-    let url = "https://rss.uol.com.br/feed/noticias.xml";
-    let channel = fetch_rss_from_url(url).await;
-    assert!(channel.is_ok());
-    let channel_value = channel.unwrap();
-
-    println!("channel_value:title :{:?}", channel_value.title());
-    println!(
-        "channel_value:description :{:?}",
-        channel_value.description()
+    // 标记为 text/html 的网页不是订阅源, 标记为 text/html 的订阅源可以正常解析
+    let err = fetch_rss_from_url(format!("{}/page", host))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        FetchError::ContentType("text/html; charset=utf-8".to_string())
     );
-    println!("channel_value:link :{:?}", channel_value.link());
-    println!(
-        "channel_value:last_build_date :{:?}",
-        channel_value.last_build_date()
-    );
-    println!("channel_value:pub_date :{:?}", channel_value.pub_date());
-    println!("channel_value:language :{:?}", channel_value.language());
-    println!("channel_value:items :{:?}", channel_value.items().len());
-    assert!(!channel_value.items().is_empty());
+    assert!(fetch_rss_from_url(format!("{}/html-feed", host))
+        .await
+        .is_ok());
+
+    let err = fetch_rss_from_url(format!("{}/broken", host))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), "parse");
 }
@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use derive_builder::Builder;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::error::FetchError;
+
+// 站点空闲超过这个时间后移除其状态
+const HOST_IDLE_TTL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Clone, Builder)]
+pub struct HostSchedulerOption {
+    // 全局最大并发数
+    #[builder(default = "8")]
+    pub max_concurrency: usize,
+    // 单个站点最大并发数
+    #[builder(default = "2")]
+    pub per_host_concurrency: usize,
+    // 同一站点两次请求之间的最小间隔
+    #[builder(default = "Duration::from_secs(1)")]
+    pub per_host_delay: Duration,
+    // 站点连续失败时的最大退避时间
+    #[builder(default = "Duration::from_secs(30 * 60)")]
+    pub max_backoff: Duration,
+}
+
+impl Default for HostSchedulerOption {
+    fn default() -> Self {
+        HostSchedulerOptionBuilder::default()
+            .build()
+            .expect("默认配置不会构建失败")
+    }
+}
+
+#[derive(Debug)]
+struct HostState {
+    // 正在进行的请求数量
+    active: usize,
+    // 下一次允许发起请求的时间
+    next_allowed_at: Instant,
+    // 连续失败次数
+    failures: u32,
+    // robots.txt 等指定的请求间隔, 会覆盖默认的间隔
+    delay: Option<Duration>,
+}
+
+/// 按站点调度请求, 限制单个站点的并发和频率, 避免被大型站点限流
+///
+/// 使用方式: 请求前 `acquire` 得到 `HostPermit`, 请求结束后通过 permit 反馈结果,
+/// permit 释放时归还站点的并发额度
+#[derive(Debug, Clone)]
+pub struct HostScheduler {
+    option: HostSchedulerOption,
+    global: Arc<Semaphore>,
+    hosts: Arc<Mutex<HashMap<String, HostState>>>,
+    notify: Arc<Notify>,
+}
+
+impl HostScheduler {
+    pub fn new(option: HostSchedulerOption) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(option.max_concurrency.max(1))),
+            option,
+            hosts: Arc::new(Mutex::new(HashMap::new())),
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// 为某个站点单独设置请求间隔(例如 robots.txt 的 Crawl-delay), 只会放大默认间隔
+    pub fn set_host_delay(&self, url: &str, delay: Duration) {
+        let host = host_of(url);
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = host_state(&mut hosts, host);
+        state.delay = Some(delay.max(self.option.per_host_delay));
+    }
+
+    /// 等待直到可以向 `url` 所在的站点发起请求
+    ///
+    /// 等待站点时不占用全局额度, 等待全局额度时也不占用站点额度,
+    /// 站点的请求间隔从真正发起请求时开始计算
+    pub async fn acquire(&self, url: &str) -> HostPermit {
+        let host = host_of(url);
+        loop {
+            let global = Arc::clone(&self.global)
+                .acquire_owned()
+                .await
+                .expect("semaphore 不会被关闭");
+
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            // 先注册等待, 避免检查之后、等待之前的通知丢失
+            notified.as_mut().enable();
+
+            let wait = {
+                let mut hosts = self.hosts.lock().unwrap();
+                let now = Instant::now();
+                let state = host_state(&mut hosts, host.clone());
+                if state.active >= self.option.per_host_concurrency.max(1) {
+                    None
+                } else if state.next_allowed_at > now {
+                    Some(state.next_allowed_at - now)
+                } else {
+                    state.active += 1;
+                    state.next_allowed_at = now + state.delay.unwrap_or(self.option.per_host_delay);
+                    return HostPermit {
+                        host,
+                        scheduler: self.clone(),
+                        _global: global,
+                    };
+                }
+            };
+            // 站点暂时不可用, 归还全局额度给其他站点
+            drop(global);
+            match wait {
+                Some(duration) => tokio::time::sleep(duration).await,
+                None => notified.await,
+            }
+        }
+    }
+
+    fn release(&self, host: &str) {
+        let mut hosts = self.hosts.lock().unwrap();
+        if let Some(state) = hosts.get_mut(host) {
+            state.active = state.active.saturating_sub(1);
+        }
+        drop(hosts);
+        self.notify.notify_waiters();
+    }
+
+    fn record_success(&self, host: &str) {
+        let mut hosts = self.hosts.lock().unwrap();
+        if let Some(state) = hosts.get_mut(host) {
+            state.failures = 0;
+        }
+    }
+
+    fn record_failure(&self, host: &str, retry_after: Option<Duration>) {
+        let mut hosts = self.hosts.lock().unwrap();
+        if let Some(state) = hosts.get_mut(host) {
+            state.failures = state.failures.saturating_add(1);
+            // 指数退避: 间隔 * 2^失败次数, 不超过 max_backoff
+            let base = state.delay.unwrap_or(self.option.per_host_delay);
+            let backoff = base
+                .saturating_mul(2u32.saturating_pow(state.failures.min(16)))
+                .min(self.option.max_backoff);
+            // 服务端要求的等待时间优先
+            let wait = match retry_after {
+                Some(retry_after) => retry_after.max(backoff),
+                None => backoff,
+            };
+            let next = Instant::now() + wait;
+            if next > state.next_allowed_at {
+                state.next_allowed_at = next;
+            }
+        }
+    }
+
+    #[cfg(test)]
+    fn failures(&self, url: &str) -> u32 {
+        let hosts = self.hosts.lock().unwrap();
+        hosts.get(&host_of(url)).map_or(0, |s| s.failures)
+    }
+
+    #[cfg(test)]
+    fn active(&self, url: &str) -> usize {
+        let hosts = self.hosts.lock().unwrap();
+        hosts.get(&host_of(url)).map_or(0, |s| s.active)
+    }
+
+    #[cfg(test)]
+    fn host_count(&self) -> usize {
+        self.hosts.lock().unwrap().len()
+    }
+}
+
+// 取出站点的状态, 新增站点时顺带清理长时间空闲的站点, 避免常驻进程中无限增长
+fn host_state(hosts: &mut HashMap<String, HostState>, host: String) -> &mut HostState {
+    let now = Instant::now();
+    if !hosts.contains_key(&host) {
+        hosts.retain(|_, state| state.active > 0 || state.next_allowed_at + HOST_IDLE_TTL > now);
+    }
+    hosts.entry(host).or_insert_with(|| HostState {
+        active: 0,
+        next_allowed_at: now,
+        failures: 0,
+        delay: None,
+    })
+}
+
+/// 站点的请求许可, 释放时归还并发额度
+#[derive(Debug)]
+pub struct HostPermit {
+    host: String,
+    scheduler: HostScheduler,
+    _global: OwnedSemaphorePermit,
+}
+
+impl HostPermit {
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    // 请求成功, 清空失败计数
+    pub fn succeeded(self) {
+        self.scheduler.record_success(&self.host);
+    }
+
+    // 请求失败, 站点进入退避
+    pub fn failed(self, retry_after: Option<Duration>) {
+        self.scheduler.record_failure(&self.host, retry_after);
+    }
+
+    // 根据拉取错误反馈结果, 429 / 503 会遵循服务端的 Retry-After
+    pub fn failed_with(self, error: &FetchError) {
+        let retry_after = match error {
+            FetchError::Throttled { retry_after, .. } => retry_after.map(Duration::from_secs),
+            _ => None,
+        };
+        self.failed(retry_after);
+    }
+}
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+        self.scheduler.release(&self.host);
+    }
+}
+
+// 以小写的 host 作为站点的标识, 解析失败时使用原始字符串
+fn host_of(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(u) => u.host_str().unwrap_or(url).to_lowercase(),
+        Err(_) => url.to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scheduler(per_host_concurrency: usize, delay_ms: u64) -> HostScheduler {
+        HostScheduler::new(
+            HostSchedulerOptionBuilder::default()
+                .max_concurrency(8usize)
+                .per_host_concurrency(per_host_concurrency)
+                .per_host_delay(Duration::from_millis(delay_ms))
+                .max_backoff(Duration::from_secs(5))
+                .build()
+                .unwrap(),
+        )
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_min_delay_per_host() {
+        let scheduler = scheduler(4, 500);
+        let start = Instant::now();
+        for _ in 0..3 {
+            scheduler.acquire("https://a.com/1").await.succeeded();
+        }
+        // 第一次立即执行, 后两次各等待 500ms
+        assert!(start.elapsed() >= Duration::from_millis(1000));
+
+        // 其他站点不受影响
+        let start = Instant::now();
+        scheduler.acquire("https://b.com/1").await.succeeded();
+        assert!(start.elapsed() < Duration::from_millis(10));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_concurrency_per_host() {
+        let scheduler = scheduler(1, 0);
+        let first = scheduler.acquire("https://a.com/1").await;
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move {
+                scheduler.acquire("https://a.com/2").await.succeeded();
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!waiting.is_finished());
+        drop(first);
+        waiting.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry_after_and_backoff() {
+        let scheduler = scheduler(2, 100);
+        let permit = scheduler.acquire("https://a.com/1").await;
+        permit.failed_with(&FetchError::Throttled {
+            status: 429,
+            retry_after: Some(3),
+        });
+        assert_eq!(scheduler.failures("https://a.com"), 1);
+
+        let start = Instant::now();
+        scheduler.acquire("https://a.com/2").await.succeeded();
+        assert!(start.elapsed() >= Duration::from_secs(3));
+        assert_eq!(scheduler.failures("https://a.com"), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_wait_global_without_holding_host() {
+        let scheduler = HostScheduler::new(
+            HostSchedulerOptionBuilder::default()
+                .max_concurrency(1usize)
+                .per_host_concurrency(1usize)
+                .per_host_delay(Duration::from_millis(0))
+                .build()
+                .unwrap(),
+        );
+        let busy = scheduler.acquire("https://b.com/1").await;
+        let waiting = {
+            let scheduler = scheduler.clone();
+            tokio::spawn(async move {
+                scheduler.acquire("https://a.com/1").await.succeeded();
+            })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        // 等待全局额度时不占用站点额度
+        assert!(!waiting.is_finished());
+        assert_eq!(scheduler.active("https://a.com"), 0);
+        drop(busy);
+        waiting.await.unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_evict_idle_hosts() {
+        let scheduler = scheduler(2, 100);
+        for host in ["a.com", "b.com", "c.com"] {
+            scheduler
+                .acquire(&format!("https://{}/1", host))
+                .await
+                .succeeded();
+        }
+        assert_eq!(scheduler.host_count(), 3);
+        let held = scheduler.acquire("https://a.com/2").await;
+
+        tokio::time::sleep(HOST_IDLE_TTL + Duration::from_secs(1)).await;
+        scheduler.acquire("https://d.com/1").await.succeeded();
+        // 空闲的 b.com / c.com 被移除, 正在请求的 a.com 保留
+        assert_eq!(scheduler.host_count(), 2);
+        assert_eq!(scheduler.active("https://a.com"), 1);
+        drop(held);
+    }
+}
@@ -0,0 +1,46 @@
+// 测试用的本地 http 服务, 避免测试依赖外部网络
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// 原始请求报文 -> 原始响应报文
+pub(crate) type Responder = fn(&str) -> String;
+
+// 启动服务并返回 `http://127.0.0.1:port`
+pub(crate) async fn serve(responder: Responder) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 16 * 1024];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let response = responder(&request);
+                _ = stream.write_all(response.as_bytes()).await;
+                _ = stream.shutdown().await;
+            });
+        }
+    });
+    format!("http://{}", addr)
+}
+
+pub(crate) fn response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+    let mut resp = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\n", status, body.len());
+    for (name, value) in headers {
+        resp.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    resp.push_str("Connection: close\r\n\r\n");
+    resp.push_str(body);
+    resp
+}
+
+// 取出请求头的值(忽略大小写)
+pub(crate) fn header<'a>(request: &'a str, name: &str) -> Option<&'a str> {
+    request.lines().find_map(|line| {
+        let (k, v) = line.split_once(':')?;
+        match k.trim().eq_ignore_ascii_case(name) {
+            true => Some(v.trim()),
+            false => None,
+        }
+    })
+}
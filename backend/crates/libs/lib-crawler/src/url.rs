@@ -1,10 +1,13 @@
 use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 
 use derive_builder::Builder;
 use rand::seq::SliceRandom;
 use rand::Rng;
 use scraper::Html;
 
+use crate::error::FetchError;
+
 static FAKE_UAS: &[&str] = &[
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/112.0.0.0 Safari/537.36 Edg/112.0.1722.64",
     "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/119.0.0.0 Safari/537.36 Edg/119.0.0.0",
@@ -17,6 +20,11 @@ static FAKE_UAS: &[&str] = &[
     "Mozilla/5.0 (Windows; U; Windows NT 6.1; zh-HK) AppleWebKit/533.18.1 (KHTML, like Gecko) Version/5.0.2 Safari/533.18.5",
 ];
 
+// 默认最大响应体 10MB
+const DEFAULT_MAX_BODY_SIZE: u64 = 10 * 1024 * 1024;
+// 只允许公网地址时, 手动跟随跳转的最大次数
+const MAX_REDIRECTS: usize = 10;
+
 #[derive(Debug, Clone, Builder)]
 pub struct RequestOption {
     pub url: String,
@@ -31,43 +39,191 @@ pub struct RequestOption {
     // Referer
     #[builder(setter(strip_option), default = "true")]
     pub referer: bool,
+    // 条件请求: 上次响应的 ETag
+    #[builder(setter(strip_option), default)]
+    pub if_none_match: Option<String>,
+    // 条件请求: 上次响应的 Last-Modified
+    #[builder(setter(strip_option), default)]
+    pub if_modified_since: Option<String>,
+    // 响应体的最大字节数
+    #[builder(setter(strip_option), default = "Some(DEFAULT_MAX_BODY_SIZE)")]
+    pub max_body_size: Option<u64>,
+    // 响应体超出 max_body_size 时截断, 而不是报错
+    #[builder(default = "false")]
+    pub truncate_body: bool,
+    // 允许的 Content-Type, 只要包含其中任意一个即可, 为空时不检查
+    #[builder(setter(strip_option), default)]
+    pub accept_content_types: Option<Vec<String>>,
+    // 只允许请求公网地址, 每次跳转都会重新检查, 用于请求用户或订阅源提供的地址
+    #[builder(default = "false")]
+    pub public_only: bool,
+}
+
+// 一次请求的结果, 包含用于下次条件请求的缓存校验信息
+#[derive(Debug, Clone, Default)]
+pub struct UrlContent {
+    pub status: u16,
+    pub body: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub content_type: Option<String>,
+}
+
+impl UrlContent {
+    // 服务端返回 304, 内容自上次请求后没有变化
+    pub fn is_not_modified(&self) -> bool {
+        self.status == reqwest::StatusCode::NOT_MODIFIED.as_u16()
+    }
+}
+
+/// 确认地址指向公网, 拒绝本机、内网和链路本地地址, 避免被用来访问内部服务
+///
+/// 域名会先解析, 任意一个解析结果不是公网地址都会被拒绝
+pub async fn ensure_public_url(url: &str) -> Result<(), FetchError> {
+    resolve_public_url(url).await.map(|_| ())
+}
+
+// 解析地址并确认都是公网地址, 返回域名(ip 地址为空)和解析结果
+async fn resolve_public_url(url: &str) -> Result<(Option<String>, Vec<SocketAddr>), FetchError> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| FetchError::Request(e.to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(FetchError::Request(format!(
+            "unsupported scheme `{}`",
+            parsed.scheme()
+        )));
+    }
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| FetchError::Request("missing host".to_string()))?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let (domain, addrs) = match host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        Ok(ip) => (None, vec![SocketAddr::new(ip, port)]),
+        Err(_) => (
+            Some(host.to_string()),
+            tokio::net::lookup_host((host, port))
+                .await
+                .map_err(|e| FetchError::Dns(e.to_string()))?
+                .collect(),
+        ),
+    };
+    if addrs.is_empty()
+        || !addrs
+            .iter()
+            .all(|addr| is_public_ip(&addr.ip()) || loopback_allowed(&addr.ip()))
+    {
+        return Err(FetchError::PrivateAddress(host.to_string()));
+    }
+    Ok((domain, addrs))
+}
+
+/// 请求公网地址的 client: 连接固定到检查过的解析结果, 避免检查之后 DNS 被重新绑定到内网;
+/// 不自动跟随跳转, 由调用方检查跳转的地址
+pub(crate) async fn pin_public_url(
+    builder: reqwest::ClientBuilder,
+    url: &str,
+) -> Result<reqwest::ClientBuilder, FetchError> {
+    let (domain, addrs) = resolve_public_url(url).await?;
+    let builder = builder.redirect(reqwest::redirect::Policy::none());
+    Ok(match domain {
+        Some(domain) => builder.resolve_to_addrs(&domain, &addrs),
+        None => builder,
+    })
+}
+
+#[cfg(test)]
+thread_local! {
+    // 测试服务运行在本机, 测试中可以把本机地址当作公网地址
+    static LOOPBACK_IS_PUBLIC: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
 }
 
-pub async fn get_content_from_url(req: RequestOption) -> Result<String, anyhow::Error> {
-    let mut retry = req.retry_times.unwrap_or(5);
-    let mut content = "".to_string();
+#[cfg(test)]
+pub(crate) fn treat_loopback_as_public() {
+    LOOPBACK_IS_PUBLIC.with(|allowed| allowed.set(true));
+}
+
+#[cfg(test)]
+fn loopback_allowed(ip: &IpAddr) -> bool {
+    ip.is_loopback() && LOOPBACK_IS_PUBLIC.with(|allowed| allowed.get())
+}
+
+#[cfg(not(test))]
+fn loopback_allowed(_ip: &IpAddr) -> bool {
+    false
+}
+
+fn is_public_ip(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                // 0.0.0.0/8 和运营商级 NAT 100.64.0.0/10
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(v6) => {
+            if let Some(v4) = v6.to_ipv4_mapped() {
+                return is_public_ip(&IpAddr::V4(v4));
+            }
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // 唯一本地地址 fc00::/7 和链路本地地址 fe80::/10
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+pub async fn get_content_from_url(req: RequestOption) -> Result<String, FetchError> {
+    let content = get_response_from_url(req).await?;
+    Ok(content.body)
+}
+
+pub async fn get_response_from_url(req: RequestOption) -> Result<UrlContent, FetchError> {
+    let mut retry = req.retry_times.unwrap_or(5).max(1);
     // 默认超时1s，每重试一次，超时时间为 上次的2倍
     let mut delay_secs = 1;
-    while retry > 0 {
-        match try_get_content_from_url_once(req.clone()).await {
-            Ok(body) => {
-                content = body;
-                break;
-            }
-            Err(_) => {
-                retry -= 1;
-                delay_secs *= 2;
-                // set max delay time to 30s
-                if delay_secs > 20 {
-                    // 使用 随机数
-                    delay_secs = rand::thread_rng().gen_range(2..10);
-                }
-                tokio::time::sleep(tokio::time::Duration::from_secs(delay_secs)).await;
-            }
+    loop {
+        let err = match try_get_content_from_url_once(req.clone()).await {
+            Ok(resp) => return Ok(resp),
+            Err(e) => e,
+        };
+        retry -= 1;
+        // 不可恢复的错误(404 / 内容类型错误等)不需要重试
+        if retry == 0 || !err.is_retryable() {
+            return Err(err);
+        }
+        delay_secs *= 2;
+        // set max delay time to 30s
+        if delay_secs > 20 {
+            // 使用 随机数
+            delay_secs = rand::thread_rng().gen_range(2..10);
         }
+        tokio::time::sleep(tokio::time::Duration::from_secs(delay_secs)).await;
     }
-    Ok(content)
 }
 
-async fn try_get_content_from_url_once(req: RequestOption) -> Result<String, anyhow::Error> {
+// 根据请求的配置创建 client
+fn client_builder(req: &RequestOption) -> Result<reqwest::ClientBuilder, FetchError> {
     let mut client_builder = reqwest::Client::builder();
     if let Some(timeout) = req.timeout {
         client_builder = client_builder.timeout(std::time::Duration::from_secs(timeout));
     }
-    if let Some(ip_proxy) = req.ip_proxy {
+    if let Some(ip_proxy) = &req.ip_proxy {
         client_builder = client_builder.proxy(reqwest::Proxy::all(ip_proxy)?);
     }
-    if let Some(user_agent) = req.user_agent {
+    if let Some(user_agent) = &req.user_agent {
         client_builder = client_builder.user_agent(user_agent);
     } else {
         // 随机选择一个ua
@@ -78,18 +234,239 @@ async fn try_get_content_from_url_once(req: RequestOption) -> Result<String, any
     }
     // 配置referer
     client_builder = client_builder.referer(req.referer);
+    Ok(client_builder)
+}
 
-    let client = match client_builder.build() {
-        Ok(client) => client,
-        Err(e) => {
-            return Err(anyhow::anyhow!("构建client失败:{}", e));
+// 跳转响应的目标地址
+fn redirect_location(url: &str, response: &reqwest::Response) -> Option<String> {
+    if !matches!(response.status().as_u16(), 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = response
+        .headers()
+        .get(reqwest::header::LOCATION)?
+        .to_str()
+        .ok()?;
+    let next = reqwest::Url::parse(url).ok()?.join(location).ok()?;
+    Some(next.to_string())
+}
+
+async fn try_get_content_from_url_once(req: RequestOption) -> Result<UrlContent, FetchError> {
+    let mut url = req.url.clone();
+    let mut redirects = 0;
+    let mut response = loop {
+        let mut builder = client_builder(&req)?;
+        if req.public_only {
+            builder = pin_public_url(builder, &url).await?;
+        }
+        let client = match builder.build() {
+            Ok(client) => client,
+            Err(e) => {
+                return Err(FetchError::Request(format!("构建client失败:{}", e)));
+            }
+        };
+        let mut request = client.get(&url);
+        if let Some(etag) = &req.if_none_match {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &req.if_modified_since {
+            request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
         }
+        let response = request.send().await?;
+        // 只允许公网地址时手动跟随跳转, 每一跳都重新检查
+        match redirect_location(&url, &response) {
+            Some(next) if req.public_only => {
+                redirects += 1;
+                if redirects > MAX_REDIRECTS {
+                    return Err(FetchError::Request("too many redirects".to_string()));
+                }
+                url = next;
+            }
+            _ => break response,
+        }
+    };
+    let header_value = |name: reqwest::header::HeaderName| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(|v| v.to_string())
     };
-    let url = req.url;
-    let response = client.get(&url).send().await?;
-    let body = response.text().await?;
-    if body.is_empty() {
-        return Err(anyhow::anyhow!("rss内容为空"));
+    let content_type = header_value(reqwest::header::CONTENT_TYPE);
+    let mut content = UrlContent {
+        status: response.status().as_u16(),
+        etag: header_value(reqwest::header::ETAG),
+        last_modified: header_value(reqwest::header::LAST_MODIFIED),
+        content_type: content_type.clone(),
+        ..Default::default()
+    };
+    // 304 没有响应体
+    if content.is_not_modified() {
+        return Ok(content);
+    }
+    if content.status == 429 || content.status == 503 {
+        let retry_after =
+            header_value(reqwest::header::RETRY_AFTER).and_then(|value| parse_retry_after(&value));
+        return Err(FetchError::Throttled {
+            status: content.status,
+            retry_after,
+        });
+    }
+    if !response.status().is_success() {
+        return Err(FetchError::HttpStatus(content.status));
+    }
+    if let (Some(accepts), Some(content_type)) = (&req.accept_content_types, &content_type) {
+        let lower = content_type.to_lowercase();
+        if !accepts.iter().any(|a| lower.contains(a.as_str())) {
+            return Err(FetchError::ContentType(content_type.to_string()));
+        }
+    }
+
+    // 边读边检查大小, 避免把超大的响应读进内存
+    let limit = req.max_body_size.unwrap_or(u64::MAX);
+    if !req.truncate_body && response.content_length().is_some_and(|len| len > limit) {
+        return Err(FetchError::BodyTooLarge { limit });
+    }
+    let mut bytes: Vec<u8> = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if (bytes.len() + chunk.len()) as u64 > limit {
+            if !req.truncate_body {
+                return Err(FetchError::BodyTooLarge { limit });
+            }
+            let remaining = limit as usize - bytes.len();
+            bytes.extend_from_slice(&chunk[..remaining]);
+            break;
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    content.body = decode_body(&bytes, content_type.as_deref());
+    if content.body.is_empty() {
+        return Err(FetchError::EmptyBody);
+    }
+    Ok(content)
+}
+
+// Retry-After 可以是秒数, 也可以是 http 时间
+fn parse_retry_after(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = at.timestamp() - chrono::Utc::now().timestamp();
+    Some(secs.max(0) as u64)
+}
+
+// 根据 Content-Type 中的 charset 解码, 默认 utf-8
+fn decode_body(bytes: &[u8], content_type: Option<&str>) -> String {
+    let encoding = content_type
+        .and_then(|ct| {
+            ct.split(';')
+                .filter_map(|part| part.trim().split_once('='))
+                .find(|(k, _)| k.trim().eq_ignore_ascii_case("charset"))
+                .map(|(_, v)| v.trim().trim_matches('"').to_string())
+        })
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ensure_public_url() {
+        for url in [
+            "http://127.0.0.1/feed",
+            "http://10.0.0.8/",
+            "http://192.168.1.1:8080/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://localhost:9000/",
+        ] {
+            assert!(
+                matches!(
+                    ensure_public_url(url).await,
+                    Err(FetchError::PrivateAddress(_))
+                ),
+                "{}",
+                url
+            );
+        }
+        assert!(ensure_public_url("file:///etc/passwd").await.is_err());
+        assert!(ensure_public_url("http://93.184.216.34/").await.is_ok());
+        assert!(ensure_public_url("https://[2606:2800:220:1::]/")
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_public_only_checks_redirects() {
+        use crate::test_server::{response, serve};
+
+        treat_loopback_as_public();
+        let host = serve(|request| match request.starts_with("GET /start") {
+            true => response("302 Found", &[("Location", "/final")], ""),
+            false => response("200 OK", &[], "final"),
+        })
+        .await;
+        let req = RequestOptionBuilder::default()
+            .url(format!("{}/start", host))
+            .retry_times(1)
+            .public_only(true)
+            .build()
+            .unwrap();
+        assert_eq!(get_content_from_url(req).await.unwrap(), "final");
+
+        // 跳转到内网地址时拒绝
+        let host = serve(|_| {
+            response(
+                "302 Found",
+                &[("Location", "http://169.254.169.254/latest/meta-data")],
+                "",
+            )
+        })
+        .await;
+        let req = RequestOptionBuilder::default()
+            .url(format!("{}/start", host))
+            .retry_times(1)
+            .public_only(true)
+            .build()
+            .unwrap();
+        assert!(matches!(
+            get_content_from_url(req).await,
+            Err(FetchError::PrivateAddress(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(120));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    async fn test_throttled_response() {
+        use crate::test_server::{response, serve};
+
+        let host = serve(|_| response("429 Too Many Requests", &[("Retry-After", "30")], "")).await;
+        let req = RequestOptionBuilder::default()
+            .url(format!("{}/feed", host))
+            .retry_times(3)
+            .build()
+            .unwrap();
+        let err = get_response_from_url(req).await.unwrap_err();
+        assert_eq!(
+            err,
+            FetchError::Throttled {
+                status: 429,
+                retry_after: Some(30)
+            }
+        );
     }
-    Ok(body)
 }
@@ -0,0 +1,203 @@
+use ring::hmac;
+
+use crate::error::FetchError;
+use crate::model::ParsedFeed;
+use crate::url::pin_public_url;
+
+/// 订阅源声明的 WebSub hub, topic 为订阅源的 rel="self" 地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebSubHub {
+    pub hub: String,
+    pub topic: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebSubMode {
+    Subscribe,
+    Unsubscribe,
+}
+
+impl WebSubMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Subscribe => "subscribe",
+            Self::Unsubscribe => "unsubscribe",
+        }
+    }
+}
+
+/// 向 hub 发起订阅或取消订阅的请求
+#[derive(Debug, Clone)]
+pub struct WebSubRequest {
+    pub hub: WebSubHub,
+    pub mode: WebSubMode,
+    // hub 验证订阅意图和推送内容的地址
+    pub callback: String,
+    // 推送内容签名使用的密钥
+    pub secret: String,
+    // 期望的租期, 单位秒, hub 可以自行调整
+    pub lease_seconds: i64,
+    // 是否允许请求本机和内网地址的 hub, 只应在测试中打开
+    pub allow_private: bool,
+}
+
+impl ParsedFeed {
+    /// 订阅源中 rel="hub" 的链接, 没有 rel="self" 时使用拉取订阅源的地址作为 topic
+    pub fn websub_hub(&self, feed_url: &str) -> Option<WebSubHub> {
+        let rel = |name: &str| {
+            self.links
+                .iter()
+                .find(|l| l.rel.as_deref() == Some(name) && !l.href.trim().is_empty())
+                .map(|l| l.href.trim().to_string())
+        };
+        Some(WebSubHub {
+            hub: rel("hub")?,
+            topic: rel("self").unwrap_or_else(|| feed_url.to_string()),
+        })
+    }
+}
+
+/// 发送订阅请求, hub 返回 2xx 表示已接受, 之后会异步请求 callback 验证订阅意图
+pub async fn request_websub(req: &WebSubRequest) -> Result<(), FetchError> {
+    let mut form = vec![
+        ("hub.mode", req.mode.as_str().to_string()),
+        ("hub.topic", req.hub.topic.clone()),
+        ("hub.callback", req.callback.clone()),
+    ];
+    if req.mode == WebSubMode::Subscribe {
+        form.push(("hub.secret", req.secret.clone()));
+        form.push(("hub.lease_seconds", req.lease_seconds.to_string()));
+    }
+    // hub 地址来自订阅源, 只允许公网地址, 并且不跟随跳转
+    let builder = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .redirect(reqwest::redirect::Policy::none());
+    let client = match req.allow_private {
+        true => builder.build()?,
+        false => pin_public_url(builder, &req.hub.hub).await?.build()?,
+    };
+    let resp = client.post(&req.hub.hub).form(&form).send().await?;
+    if !resp.status().is_success() {
+        return Err(FetchError::HttpStatus(resp.status().as_u16()));
+    }
+    Ok(())
+}
+
+fn algorithm(name: &str) -> Option<hmac::Algorithm> {
+    match name {
+        "sha1" => Some(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY),
+        "sha256" => Some(hmac::HMAC_SHA256),
+        "sha384" => Some(hmac::HMAC_SHA384),
+        "sha512" => Some(hmac::HMAC_SHA512),
+        _ => None,
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// 推送内容的签名, 即 `X-Hub-Signature` 的值, 格式为 `sha256=<hex>`
+pub fn sign_websub_payload(secret: &str, body: &[u8]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, body);
+    let hex: String = tag.as_ref().iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+/// 校验推送内容的签名, 支持 sha1 / sha256 / sha384 / sha512
+pub fn verify_websub_signature(secret: &str, signature: &str, body: &[u8]) -> bool {
+    let Some((method, value)) = signature.trim().split_once('=') else {
+        return false;
+    };
+    let (Some(algorithm), Some(tag)) = (
+        algorithm(&method.to_lowercase()),
+        decode_hex(&value.to_lowercase()),
+    ) else {
+        return false;
+    };
+    let key = hmac::Key::new(algorithm, secret.as_bytes());
+    hmac::verify(&key, body, &tag).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rss::parse_feed_from_content;
+    use crate::test_server::{response, serve};
+
+    #[test]
+    fn test_detect_hub() {
+        let content = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>
+<title>t</title><link>http://example.com</link><description>d</description>
+<atom:link rel="hub" href="https://hub.example.com/"/>
+<atom:link rel="self" href="http://example.com/feed.xml"/>
+</channel></rss>"#;
+        let feed = parse_feed_from_content(content.to_string()).unwrap();
+        assert_eq!(
+            feed.websub_hub("http://example.com/rss"),
+            Some(WebSubHub {
+                hub: "https://hub.example.com/".to_string(),
+                topic: "http://example.com/feed.xml".to_string(),
+            })
+        );
+
+        let content = include_str!("../fixture/feeds/atom.xml");
+        let feed = parse_feed_from_content(content.to_string()).unwrap();
+        assert_eq!(feed.websub_hub("https://example.org/feed.atom"), None);
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let signature = sign_websub_payload("secret", b"payload");
+        assert!(verify_websub_signature("secret", &signature, b"payload"));
+        assert!(!verify_websub_signature("secret", &signature, b"changed"));
+        assert!(!verify_websub_signature("other", &signature, b"payload"));
+        assert!(!verify_websub_signature("secret", "md5=abcd", b"payload"));
+        assert!(!verify_websub_signature("secret", "sha256=zz", b"payload"));
+    }
+
+    #[tokio::test]
+    async fn test_request_websub() {
+        // 本地的 hub, 只接受带有 secret 的订阅请求
+        let host = serve(|request| {
+            match request.contains("hub.mode=subscribe") && request.contains("hub.secret=s3cret") {
+                true => response("202 Accepted", &[], ""),
+                false => response("400 Bad Request", &[], "bad"),
+            }
+        })
+        .await;
+        let mut req = WebSubRequest {
+            hub: WebSubHub {
+                hub: format!("{}/hub", host),
+                topic: "http://example.com/feed.xml".to_string(),
+            },
+            mode: WebSubMode::Subscribe,
+            callback: "http://localhost/websub/callback/1".to_string(),
+            secret: "s3cret".to_string(),
+            lease_seconds: 3600,
+            allow_private: true,
+        };
+        assert!(request_websub(&req).await.is_ok());
+        req.mode = WebSubMode::Unsubscribe;
+        assert_eq!(
+            request_websub(&req).await.unwrap_err(),
+            FetchError::HttpStatus(400)
+        );
+
+        // 内网的 hub 不会被请求
+        req.allow_private = false;
+        req.hub.hub = "http://169.254.169.254/hub".to_string();
+        assert!(matches!(
+            request_websub(&req).await,
+            Err(FetchError::PrivateAddress(_))
+        ));
+    }
+}
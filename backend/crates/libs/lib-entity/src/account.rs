@@ -13,6 +13,40 @@ pub enum Gender {
     FEMALE,
 }
 
+// 用户角色, 数值越大权限越多, 新用户默认为 Editor
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Serialize,
+)]
+#[sea_orm(rs_type = "i32", db_type = "Integer")]
+#[serde(rename_all = "lowercase")]
+pub enum AccountRole {
+    // 只能阅读
+    #[sea_orm(num_value = 0)]
+    Reader,
+    // 可以管理订阅源和分类
+    #[default]
+    #[sea_orm(num_value = 1)]
+    Editor,
+    // 可以管理用户和全局数据
+    #[sea_orm(num_value = 2)]
+    Admin,
+}
+
+impl AccountRole {
+    /// 是否拥有 `required` 角色的权限
+    pub fn allows(&self, required: AccountRole) -> bool {
+        self.level() >= required.level()
+    }
+
+    fn level(&self) -> i32 {
+        match self {
+            Self::Reader => 0,
+            Self::Editor => 1,
+            Self::Admin => 2,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Default, Debug, DeriveEntity)]
 pub struct Entity;
 
@@ -36,6 +70,11 @@ pub struct Model {
     pub avatar: Option<String>,       // 头像
     pub birth: Option<NaiveDateTime>, // 出生日期
     pub gender: Option<Gender>,       // 性别 1 男 2 女
+    #[serde(default)]
+    pub role: AccountRole, // 角色
+    // Fever API 的 api_key, 即 md5(email:password), 协议规定使用 md5
+    #[serde(skip)]
+    pub fever_api_key: Option<String>,
     #[serde(skip)]
     #[serde(serialize_with = "to_milli_ts")]
     pub create_time: NaiveDateTime, // 创建时间（注册时间）
@@ -53,6 +92,8 @@ pub enum Column {
     Avatar,
     Birth,
     Gender,
+    Role,
+    FeverApiKey,
     LastLoginTime,
     CreateTime,
     UpdateTime,
@@ -77,10 +118,12 @@ impl ColumnTrait for Column {
             Self::Id => ColumnType::Integer.def(),
             Self::NickName => ColumnType::String(Some(100)).def().nullable(),
             Self::Email => ColumnType::String(Some(100)).def().nullable(),
-            Self::Password => ColumnType::String(Some(40)).def().nullable(),
+            Self::Password => ColumnType::String(Some(255)).def().nullable(),
             Self::Avatar => ColumnType::Binary(BlobSize::Medium).def().nullable(),
             Self::Birth => ColumnType::Date.def().nullable(),
             Self::Gender => ColumnType::SmallInteger.def().nullable(),
+            Self::Role => ColumnType::Integer.def().default(1),
+            Self::FeverApiKey => ColumnType::String(Some(32)).def().nullable(),
             Self::LastLoginTime => ColumnType::DateTime.def().nullable(),
 
             Self::CreateTime => ColumnType::DateTime
@@ -101,7 +144,7 @@ pub enum Relation {
 impl RelationTrait for Relation {
     fn def(&self) -> RelationDef {
         match self {
-            Self::Token => Entity::has_one(super::account_token::Entity).into(),
+            Self::Token => Entity::has_many(super::account_token::Entity).into(),
         }
     }
 }
@@ -0,0 +1,129 @@
+use chrono::naive::serde::ts_milliseconds::serialize as to_milli_ts;
+use chrono::NaiveDateTime;
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "account_subscription"
+    }
+    fn schema_name(&self) -> Option<&str> {
+        // Some("dasv")
+        None
+    }
+}
+
+// 用户订阅的订阅源, 订阅源本身是全局共享的, 每个地址只抓取一次
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel, Eq, Serialize)]
+pub struct Model {
+    #[serde(skip)]
+    pub id: i64,
+    // 用户 id
+    pub account_id: i64,
+    // 订阅源 id
+    pub subscription_id: i64,
+    // 用户自己的分类
+    pub category_id: i64,
+    // 用户自定义的标题
+    pub custom_title: Option<String>,
+    // 排序序列
+    pub sort_order: Option<i32>,
+    // 订阅时间
+    #[serde(serialize_with = "to_milli_ts")]
+    pub created_at: NaiveDateTime,
+    #[serde(serialize_with = "to_milli_ts")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    AccountId,
+    SubscriptionId,
+    CategoryId,
+    CustomTitle,
+    SortOrder,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i64;
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::AccountId => ColumnType::Integer.def(),
+            Self::SubscriptionId => ColumnType::Integer.def(),
+            Self::CategoryId => ColumnType::Integer.def(),
+            Self::CustomTitle => ColumnType::String(Some(255u32)).def().null(),
+            Self::SortOrder => ColumnType::Integer.def().null(),
+            Self::CreatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+            Self::UpdatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Account,
+    Subscription,
+    Category,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Account => Entity::belongs_to(super::account::Entity)
+                .from(Column::AccountId)
+                .to(super::account::Column::Id)
+                .into(),
+            Self::Subscription => Entity::belongs_to(super::feed_subscription::Entity)
+                .from(Column::SubscriptionId)
+                .to(super::feed_subscription::Column::Id)
+                .into(),
+            Self::Category => Entity::belongs_to(super::feed_category::Entity)
+                .from(Column::CategoryId)
+                .to(super::feed_category::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<super::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl Related<super::feed_subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Subscription.def()
+    }
+}
+
+impl Related<super::feed_category::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Category.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
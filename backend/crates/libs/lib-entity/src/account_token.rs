@@ -1,7 +1,5 @@
-use chrono::naive::serde::ts_milliseconds_option::serialize as to_milli_tsopt;
 use chrono::NaiveDateTime;
 use sea_orm::entity::prelude::*;
-use serde::{Deserialize, Serialize};
 
 #[derive(Copy, Clone, Default, Debug, DeriveEntity)]
 pub struct Entity;
@@ -16,28 +14,51 @@ impl EntityName for Entity {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel, Deserialize)]
+// 登录会话, 每次登录一条记录, 保存刷新令牌
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel, Eq)]
 pub struct Model {
-    #[serde(skip)]
-    pub account_id: i64, // 主键
-    pub token: String, // token
+    pub id: i64, // 主键
+    // 用户 id
+    pub account_id: i64,
+    // 刷新令牌的 sha256, 不保存明文
+    pub token: String,
+    // 轮换前的刷新令牌的 sha256, 再次出现时说明令牌可能泄露
+    pub previous_token: Option<String>,
+    // 当前访问令牌的 jti
+    pub access_jti: String,
+    // 当前访问令牌的过期时间
+    pub access_expired_at: NaiveDateTime,
+    // 刷新令牌的过期时间
+    pub expired_at: NaiveDateTime,
+    // 注销时间, 不为空表示会话已失效
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
 pub enum Column {
+    Id,
     AccountId,
     Token,
+    PreviousToken,
+    AccessJti,
+    AccessExpiredAt,
+    ExpiredAt,
+    RevokedAt,
+    CreatedAt,
+    UpdatedAt,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
 pub enum PrimaryKey {
-    AccountId,
+    Id,
 }
 
 impl PrimaryKeyTrait for PrimaryKey {
     type ValueType = i64;
     fn auto_increment() -> bool {
-        false
+        true
     }
 }
 
@@ -45,8 +66,20 @@ impl ColumnTrait for Column {
     type EntityName = Entity;
     fn def(&self) -> ColumnDef {
         match self {
+            Column::Id => ColumnType::Integer.def(),
             Column::AccountId => ColumnType::Integer.def(),
-            Column::Token => ColumnType::String(Some(64u32)).def(),
+            Column::Token => ColumnType::String(Some(64u32)).def().unique(),
+            Column::PreviousToken => ColumnType::String(Some(64u32)).def().null(),
+            Column::AccessJti => ColumnType::String(Some(64u32)).def(),
+            Column::AccessExpiredAt => ColumnType::DateTime.def(),
+            Column::ExpiredAt => ColumnType::DateTime.def(),
+            Column::RevokedAt => ColumnType::DateTime.def().null(),
+            Column::CreatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+            Column::UpdatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
         }
     }
 }
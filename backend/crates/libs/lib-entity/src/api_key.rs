@@ -0,0 +1,108 @@
+use chrono::NaiveDateTime;
+use sea_orm::entity::prelude::*;
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "api_key"
+    }
+    fn schema_name(&self) -> Option<&str> {
+        // Some("dasv")
+        None
+    }
+}
+
+// 用户的 API Key, 供脚本和第三方客户端使用
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel)]
+pub struct Model {
+    pub id: i64,
+    // 用户 id
+    pub account_id: i64,
+    // 名称
+    pub name: String,
+    // Key 的前几位, 用于在列表中区分
+    pub prefix: String,
+    // Key 的 sha256, 不保存明文
+    pub key_hash: String,
+    // 权限范围列表
+    pub scopes: Option<Json>,
+    // 最后使用时间
+    pub last_used_at: Option<NaiveDateTime>,
+    // 过期时间, 为空表示不过期
+    pub expired_at: Option<NaiveDateTime>,
+    // 吊销时间, 不为空表示已吊销
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    AccountId,
+    Name,
+    Prefix,
+    KeyHash,
+    Scopes,
+    LastUsedAt,
+    ExpiredAt,
+    RevokedAt,
+    CreatedAt,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i64;
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::AccountId => ColumnType::Integer.def(),
+            Self::Name => ColumnType::String(Some(100)).def(),
+            Self::Prefix => ColumnType::String(Some(16)).def(),
+            Self::KeyHash => ColumnType::String(Some(64)).def().unique(),
+            Self::Scopes => ColumnType::Json.def().null(),
+            Self::LastUsedAt => ColumnType::DateTime.def().null(),
+            Self::ExpiredAt => ColumnType::DateTime.def().null(),
+            Self::RevokedAt => ColumnType::DateTime.def().null(),
+            Self::CreatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Account,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Account => Entity::belongs_to(super::account::Entity)
+                .from(Column::AccountId)
+                .to(super::account::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<super::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -43,6 +43,10 @@ pub struct Model {
     // 最近一次更新时间
     #[serde(serialize_with = "to_milli_tsopt")]
     pub last_build_at: Option<NaiveDateTime>,
+    // 上次响应的 ETag, 用于条件请求
+    pub etag: Option<String>,
+    // 上次响应的 Last-Modified, 用于条件请求
+    pub last_modified: Option<String>,
 }
 
 impl Model {
@@ -59,6 +63,8 @@ pub enum Column {
     FittedAdaptive,
     SourceType,
     LastBuildAt,
+    Etag,
+    LastModified,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
@@ -83,6 +89,8 @@ impl ColumnTrait for Column {
             Self::FittedAdaptive => ColumnType::Boolean.def().nullable(),
             Self::SourceType => ColumnType::SmallInteger.def().nullable(),
             Self::LastBuildAt => ColumnType::DateTime.def().nullable(),
+            Self::Etag => ColumnType::String(Some(255)).def().nullable(),
+            Self::LastModified => ColumnType::String(Some(64)).def().nullable(),
         }
     }
 }
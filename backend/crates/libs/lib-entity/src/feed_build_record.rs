@@ -4,9 +4,12 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 // define a enum Entity
-#[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Serialize)]
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Serialize,
+)]
 #[sea_orm(rs_type = "i32", db_type = "Integer")]
 pub enum Status {
+    #[default]
     #[sea_orm(num_value = 0)]
     Unknow,
     #[sea_orm(num_value = 1)]
@@ -19,13 +22,6 @@ pub enum Status {
     Success,
 }
 
-// impl for default
-impl Default for Status {
-    fn default() -> Self {
-        Self::Unknow
-    }
-}
-
 #[derive(Copy, Clone, Default, Debug, DeriveEntity)]
 pub struct Entity;
 
@@ -49,6 +45,8 @@ pub struct Model {
     pub status: Status,
     // 备注 失败原因
     pub remark: String,
+    // 失败原因的分类, 例如 timeout / http_status / parse
+    pub reason: Option<String>,
     #[serde(serialize_with = "to_milli_ts")]
     pub created_at: NaiveDateTime,
 }
@@ -81,6 +79,7 @@ pub enum Column {
     SubscriptionId,
     Status,
     Remark,
+    Reason,
     CreatedAt,
 }
 
@@ -104,6 +103,7 @@ impl ColumnTrait for Column {
             Self::SubscriptionId => ColumnType::Integer.def(),
             Self::Status => ColumnType::SmallInteger.def(),
             Self::Remark => ColumnType::String(Some(255u32)).def().nullable(),
+            Self::Reason => ColumnType::String(Some(32u32)).def().nullable(),
             Self::CreatedAt => ColumnType::Timestamp.def(),
         }
     }
@@ -27,6 +27,8 @@ pub struct Model {
     pub icon: Option<String>,
     // 排序序列
     pub sort_order: Option<i64>,
+    // 所属用户, 为空表示共享的分类
+    pub account_id: Option<i64>,
     #[serde(serialize_with = "to_milli_ts")]
     pub created_at: NaiveDateTime,
     #[serde(serialize_with = "to_milli_ts")]
@@ -41,6 +43,7 @@ pub enum Column {
     ParentId,
     Icon,
     SortOrder,
+    AccountId,
     CreatedAt,
     UpdatedAt,
 }
@@ -67,6 +70,7 @@ impl ColumnTrait for Column {
             Self::ParentId => ColumnType::Integer.def().null(),
             Self::Icon => ColumnType::String(Some(255u32)).def().null(),
             Self::SortOrder => ColumnType::Integer.def().null(),
+            Self::AccountId => ColumnType::Integer.def().null(),
             Self::CreatedAt => ColumnType::DateTime
                 .def()
                 .default(Expr::current_timestamp()),
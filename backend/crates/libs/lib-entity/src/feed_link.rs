@@ -24,6 +24,10 @@ pub struct Model {
     pub title: String,
     // 链接
     pub link: String,
+    // 订阅源提供的唯一标识 (rss guid / atom id)
+    pub guid: Option<String>,
+    // 规范化后的链接, 用于去重
+    pub canonical_link: Option<String>,
     // subscription_id
     pub subscription_id: i64,
     // 描述(可能包含 html)
@@ -36,6 +40,11 @@ pub struct Model {
     pub authors: Option<Json>,
     // tags array of string
     pub tags: Option<Json>,
+    // 标题和内容的 SimHash 指纹
+    #[serde(skip)]
+    pub simhash: Option<i64>,
+    // 近似重复文章的分组
+    pub cluster_id: Option<i64>,
     // 发布时间
     #[serde(serialize_with = "to_milli_tsopt")]
     pub published_at: Option<NaiveDateTime>,
@@ -54,12 +63,16 @@ pub enum Column {
     Id,
     Title,
     Link,
+    Guid,
+    CanonicalLink,
     SubscriptionId,
     Description,
     DescPureTxt,
     Images,
     Authors,
     Tags,
+    Simhash,
+    ClusterId,
     PublishedAt,
     CreatedAt,
     UpdatedAt,
@@ -93,7 +106,11 @@ impl ColumnTrait for Column {
             Self::Tags => ColumnType::Array(RcOrArc::new(ColumnType::String(Some(64u32))))
                 .def()
                 .nullable(),
+            Self::Simhash => ColumnType::BigInteger.def().nullable(),
+            Self::ClusterId => ColumnType::BigInteger.def().nullable(),
             Self::Link => ColumnType::Text.def(),
+            Self::Guid => ColumnType::String(Some(512u32)).def().nullable(),
+            Self::CanonicalLink => ColumnType::Text.def().nullable(),
             Self::PublishedAt => ColumnType::DateTime.def().nullable(),
             Self::CreatedAt => ColumnType::DateTime
                 .def()
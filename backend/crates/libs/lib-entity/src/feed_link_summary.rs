@@ -7,7 +7,7 @@ pub struct Entity;
 
 impl EntityName for Entity {
     fn table_name(&self) -> &str {
-        "rss_link_summary"
+        "feed_link_summary"
     }
     fn schema_name(&self) -> Option<&str> {
         // Some("dasv")
@@ -0,0 +1,150 @@
+use chrono::NaiveDateTime;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// 任务类型
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Deserialize, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(32))")]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    // 拉取订阅源
+    #[sea_orm(string_value = "feed_refresh")]
+    FeedRefresh,
+    // 补全文章的正文和图片
+    #[sea_orm(string_value = "link_meta")]
+    LinkMeta,
+    // 生成文章的总结
+    #[sea_orm(string_value = "link_summary")]
+    LinkSummary,
+    // 清理过期的文章和任务
+    #[sea_orm(string_value = "prune")]
+    Prune,
+}
+
+// 任务状态
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Deserialize, Serialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    // 等待执行, 包括等待重试
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    // 已被 worker 租用, 租期过后没有完成的任务会被重新执行
+    #[sea_orm(string_value = "running")]
+    Running,
+    #[sea_orm(string_value = "succeeded")]
+    Succeeded,
+    // 重试次数用完, 需要人工处理
+    #[sea_orm(string_value = "dead")]
+    Dead,
+}
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "job_queue"
+    }
+    fn schema_name(&self) -> Option<&str> {
+        // Some("dasv")
+        None
+    }
+}
+
+// 持久化的任务队列
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel)]
+pub struct Model {
+    pub id: i64,
+    pub kind: Kind,
+    // 任务参数, json
+    pub payload: String,
+    // 去重键, 同一个键同时只有一个未完成的任务
+    pub dedup_key: Option<String>,
+    // 优先级, 越大越先执行
+    pub priority: i32,
+    pub status: Status,
+    // 已经执行的次数
+    pub attempts: i32,
+    // 最多执行的次数
+    pub max_attempts: i32,
+    // 最早的执行时间
+    pub run_at: NaiveDateTime,
+    // 租用任务的 worker
+    pub locked_by: Option<String>,
+    // 租期的到期时间
+    pub lease_until: Option<NaiveDateTime>,
+    // 最近一次失败的原因
+    pub last_error: Option<String>,
+    pub finished_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    Kind,
+    Payload,
+    DedupKey,
+    Priority,
+    Status,
+    Attempts,
+    MaxAttempts,
+    RunAt,
+    LockedBy,
+    LeaseUntil,
+    LastError,
+    FinishedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i64;
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::Kind => ColumnType::String(Some(32)).def(),
+            Self::Payload => ColumnType::Text.def(),
+            Self::DedupKey => ColumnType::String(Some(128)).def().null(),
+            Self::Priority => ColumnType::Integer.def(),
+            Self::Status => ColumnType::String(Some(16)).def(),
+            Self::Attempts => ColumnType::Integer.def(),
+            Self::MaxAttempts => ColumnType::Integer.def(),
+            Self::RunAt => ColumnType::DateTime.def(),
+            Self::LockedBy => ColumnType::String(Some(64)).def().null(),
+            Self::LeaseUntil => ColumnType::DateTime.def().null(),
+            Self::LastError => ColumnType::Text.def().null(),
+            Self::FinishedAt => ColumnType::DateTime.def().null(),
+            Self::CreatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+            Self::UpdatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+        }
+    }
+}
+
+// 任务之间没有关联, 参数中的 id 可能已经被删除
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,5 +1,7 @@
 pub mod account;
+pub mod account_subscription;
 pub mod account_token;
+pub mod api_key;
 
 pub mod feed_build_config;
 pub mod feed_build_record;
@@ -7,3 +9,8 @@ pub mod feed_category;
 pub mod feed_link;
 pub mod feed_link_summary;
 pub mod feed_subscription;
+pub mod job_queue;
+pub mod link_state;
+pub mod revoked_token;
+pub mod saved_link;
+pub mod websub_subscription;
@@ -0,0 +1,107 @@
+use chrono::naive::serde::ts_milliseconds_option::serialize as to_milli_tsopt;
+use chrono::NaiveDateTime;
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "link_state"
+    }
+    fn schema_name(&self) -> Option<&str> {
+        // Some("dasv")
+        None
+    }
+}
+
+// 用户对文章的阅读状态, 没有记录表示未读
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel, Eq, Serialize)]
+pub struct Model {
+    #[serde(skip)]
+    pub id: i64,
+    // 用户 id
+    pub account_id: i64,
+    // 文章 id
+    pub link_id: i64,
+    // 阅读时间, 为空表示未读
+    #[serde(serialize_with = "to_milli_tsopt")]
+    pub read_at: Option<NaiveDateTime>,
+    // 收藏时间, 为空表示未收藏
+    #[serde(serialize_with = "to_milli_tsopt")]
+    pub starred_at: Option<NaiveDateTime>,
+    // 是否归档
+    pub archived: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    AccountId,
+    LinkId,
+    ReadAt,
+    StarredAt,
+    Archived,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i64;
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::AccountId => ColumnType::Integer.def(),
+            Self::LinkId => ColumnType::Integer.def(),
+            Self::ReadAt => ColumnType::DateTime.def().null(),
+            Self::StarredAt => ColumnType::DateTime.def().null(),
+            Self::Archived => ColumnType::Boolean.def().default(false),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Account,
+    Link,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Account => Entity::belongs_to(super::account::Entity)
+                .from(Column::AccountId)
+                .to(super::account::Column::Id)
+                .into(),
+            Self::Link => Entity::belongs_to(super::feed_link::Entity)
+                .from(Column::LinkId)
+                .to(super::feed_link::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<super::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl Related<super::feed_link::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Link.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,88 @@
+use chrono::NaiveDateTime;
+use sea_orm::entity::prelude::*;
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "revoked_token"
+    }
+    fn schema_name(&self) -> Option<&str> {
+        // Some("dasv")
+        None
+    }
+}
+
+// 已注销但还没过期的访问令牌
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel, Eq)]
+pub struct Model {
+    pub id: i64,
+    // 访问令牌的 jti
+    pub jti: String,
+    // 用户 id
+    pub account_id: i64,
+    // 访问令牌原本的过期时间, 之后可以清理
+    pub expired_at: NaiveDateTime,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    Jti,
+    AccountId,
+    ExpiredAt,
+    CreatedAt,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i64;
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::Jti => ColumnType::String(Some(64u32)).def().unique(),
+            Self::AccountId => ColumnType::Integer.def(),
+            Self::ExpiredAt => ColumnType::DateTime.def(),
+            Self::CreatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Account,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Account => Entity::belongs_to(super::account::Entity)
+                .from(Column::AccountId)
+                .to(super::account::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<super::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
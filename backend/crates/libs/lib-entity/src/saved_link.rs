@@ -0,0 +1,128 @@
+use chrono::naive::serde::ts_milliseconds::serialize as to_milli_ts;
+use chrono::NaiveDateTime;
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "saved_link"
+    }
+    fn schema_name(&self) -> Option<&str> {
+        // Some("dasv")
+        None
+    }
+}
+
+// 用户保存的文章快照, 原文章过期删除后仍然保留
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel, Serialize)]
+pub struct Model {
+    pub id: i64,
+    // 用户 id
+    pub account_id: i64,
+    // 对应的文章 id, 保存的文章不会被过期清理
+    pub link_id: Option<i64>,
+    // 文章地址
+    pub link: String,
+    // 标题
+    pub title: String,
+    // 正文的 Markdown 快照
+    pub content: Option<String>,
+    // 图片地址列表
+    pub images: Option<Json>,
+    // 用户的标签
+    pub tags: Option<Json>,
+    // 用户的笔记
+    pub notes: Option<String>,
+    #[serde(serialize_with = "to_milli_ts")]
+    pub created_at: NaiveDateTime,
+    #[serde(serialize_with = "to_milli_ts")]
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    AccountId,
+    LinkId,
+    Link,
+    Title,
+    Content,
+    Images,
+    Tags,
+    Notes,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i64;
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::AccountId => ColumnType::Integer.def(),
+            Self::LinkId => ColumnType::Integer.def().null(),
+            Self::Link => ColumnType::Text.def(),
+            Self::Title => ColumnType::String(Some(255u32)).def(),
+            Self::Content => ColumnType::Text.def().null(),
+            Self::Images => ColumnType::Json.def().null(),
+            Self::Tags => ColumnType::Json.def().null(),
+            Self::Notes => ColumnType::Text.def().null(),
+            Self::CreatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+            Self::UpdatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Account,
+    Link,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Account => Entity::belongs_to(super::account::Entity)
+                .from(Column::AccountId)
+                .to(super::account::Column::Id)
+                .into(),
+            Self::Link => Entity::belongs_to(super::feed_link::Entity)
+                .from(Column::LinkId)
+                .to(super::feed_link::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<super::account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Account.def()
+    }
+}
+
+impl Related<super::feed_link::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Link.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
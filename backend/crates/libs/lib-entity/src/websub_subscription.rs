@@ -0,0 +1,132 @@
+use chrono::NaiveDateTime;
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+// WebSub 订阅的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Deserialize, Serialize)]
+#[sea_orm(rs_type = "String", db_type = "String(Some(16))")]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    // 已向 hub 发送订阅请求, 等待 hub 验证
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    // hub 已验证, 在租期内会推送更新
+    #[sea_orm(string_value = "active")]
+    Active,
+    // hub 拒绝了订阅
+    #[sea_orm(string_value = "denied")]
+    Denied,
+    // 已取消订阅
+    #[sea_orm(string_value = "unsubscribed")]
+    Unsubscribed,
+}
+
+#[derive(Copy, Clone, Default, Debug, DeriveEntity)]
+pub struct Entity;
+
+impl EntityName for Entity {
+    fn table_name(&self) -> &str {
+        "websub_subscription"
+    }
+    fn schema_name(&self) -> Option<&str> {
+        // Some("dasv")
+        None
+    }
+}
+
+// 订阅源在 WebSub hub 上的推送订阅, 每个订阅源最多一条
+#[derive(Clone, Debug, PartialEq, DeriveModel, DeriveActiveModel)]
+pub struct Model {
+    pub id: i64,
+    // 订阅源 id
+    pub subscription_id: i64,
+    // hub 地址
+    pub hub: String,
+    // 订阅源在 hub 上的 topic
+    pub topic: String,
+    // 推送内容签名使用的密钥
+    pub secret: String,
+    pub state: State,
+    // hub 确认的租期, 单位秒
+    pub lease_seconds: Option<i64>,
+    // 租期的到期时间
+    pub expired_at: Option<NaiveDateTime>,
+    // 最近一次收到推送的时间
+    pub last_pushed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveColumn)]
+pub enum Column {
+    Id,
+    SubscriptionId,
+    Hub,
+    Topic,
+    Secret,
+    State,
+    LeaseSeconds,
+    ExpiredAt,
+    LastPushedAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DerivePrimaryKey)]
+pub enum PrimaryKey {
+    Id,
+}
+
+impl PrimaryKeyTrait for PrimaryKey {
+    type ValueType = i64;
+    fn auto_increment() -> bool {
+        true
+    }
+}
+
+impl ColumnTrait for Column {
+    type EntityName = Entity;
+    fn def(&self) -> ColumnDef {
+        match self {
+            Self::Id => ColumnType::Integer.def(),
+            Self::SubscriptionId => ColumnType::Integer.def().unique(),
+            Self::Hub => ColumnType::String(Some(512)).def(),
+            Self::Topic => ColumnType::String(Some(512)).def(),
+            Self::Secret => ColumnType::String(Some(64)).def(),
+            Self::State => ColumnType::String(Some(16)).def(),
+            Self::LeaseSeconds => ColumnType::Integer.def().null(),
+            Self::ExpiredAt => ColumnType::DateTime.def().null(),
+            Self::LastPushedAt => ColumnType::DateTime.def().null(),
+            Self::CreatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+            Self::UpdatedAt => ColumnType::DateTime
+                .def()
+                .default(Expr::current_timestamp()),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Subscription,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Subscription => Entity::belongs_to(super::feed_subscription::Entity)
+                .from(Column::SubscriptionId)
+                .to(super::feed_subscription::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<super::feed_subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Subscription.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,4 +1,10 @@
+pub mod markdown;
 pub mod math;
+pub mod password;
 mod settings;
+pub mod simhash;
+pub mod text;
+mod url;
 
 pub use settings::*;
+pub use url::canonicalize_url;
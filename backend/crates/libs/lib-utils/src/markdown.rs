@@ -0,0 +1,285 @@
+// 简单的 Markdown 转 HTML, 只覆盖解析服务输出的常见语法:
+// 标题、段落、引用、列表、代码块、分隔线、图片、链接、行内代码、加粗和斜体
+
+/// 转义 HTML 特殊字符
+pub fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+/// 提取 Markdown 中所有图片的地址
+pub fn markdown_images(markdown: &str) -> Vec<String> {
+    let mut images = Vec::new();
+    let mut rest = markdown;
+    while let Some(start) = rest.find("![") {
+        rest = &rest[start + 2..];
+        if let Some((_, url, consumed)) = parse_link_tail(rest) {
+            if !url.is_empty() && !images.contains(&url) {
+                images.push(url);
+            }
+            rest = &rest[consumed..];
+        }
+    }
+    images
+}
+
+#[derive(PartialEq)]
+enum ListKind {
+    Ordered,
+    Unordered,
+}
+
+/// Markdown 转 HTML
+pub fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut quote: Vec<&str> = Vec::new();
+    let mut list: Option<ListKind> = None;
+    let mut code: Option<Vec<&str>> = None;
+
+    fn flush_paragraph(html: &mut String, paragraph: &mut Vec<&str>) {
+        if !paragraph.is_empty() {
+            html.push_str(&format!("<p>{}</p>\n", render_inline(&paragraph.join(" "))));
+            paragraph.clear();
+        }
+    }
+    fn flush_quote(html: &mut String, quote: &mut Vec<&str>) {
+        if !quote.is_empty() {
+            html.push_str(&format!(
+                "<blockquote>\n{}</blockquote>\n",
+                markdown_to_html(&quote.join("\n"))
+            ));
+            quote.clear();
+        }
+    }
+    fn close_list(html: &mut String, list: &mut Option<ListKind>) {
+        match list.take() {
+            Some(ListKind::Ordered) => html.push_str("</ol>\n"),
+            Some(ListKind::Unordered) => html.push_str("</ul>\n"),
+            None => {}
+        }
+    }
+
+    for line in markdown.lines() {
+        // 代码块内原样输出
+        if let Some(lines) = code.as_mut() {
+            if line.trim_start().starts_with("```") {
+                html.push_str(&format!(
+                    "<pre><code>{}</code></pre>\n",
+                    escape_html(&lines.join("\n"))
+                ));
+                code = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix('>') {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            quote.push(rest.strip_prefix(' ').unwrap_or(rest));
+            continue;
+        }
+        flush_quote(&mut html, &mut quote);
+
+        if trimmed.is_empty() {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            continue;
+        }
+        if trimmed.starts_with("```") {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            code = Some(Vec::new());
+            continue;
+        }
+        if is_rule(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            html.push_str("<hr />\n");
+            continue;
+        }
+        let level = trimmed.chars().take_while(|c| *c == '#').count();
+        if (1..=6).contains(&level) && trimmed[level..].starts_with(' ') {
+            flush_paragraph(&mut html, &mut paragraph);
+            close_list(&mut html, &mut list);
+            html.push_str(&format!(
+                "<h{level}>{}</h{level}>\n",
+                render_inline(trimmed[level..].trim().trim_end_matches('#').trim())
+            ));
+            continue;
+        }
+        if let Some((kind, item)) = list_item(trimmed) {
+            flush_paragraph(&mut html, &mut paragraph);
+            if list.as_ref() != Some(&kind) {
+                close_list(&mut html, &mut list);
+                html.push_str(match kind {
+                    ListKind::Ordered => "<ol>\n",
+                    ListKind::Unordered => "<ul>\n",
+                });
+                list = Some(kind);
+            }
+            html.push_str(&format!("<li>{}</li>\n", render_inline(item)));
+            continue;
+        }
+        close_list(&mut html, &mut list);
+        paragraph.push(trimmed);
+    }
+
+    if let Some(lines) = code {
+        html.push_str(&format!(
+            "<pre><code>{}</code></pre>\n",
+            escape_html(&lines.join("\n"))
+        ));
+    }
+    flush_quote(&mut html, &mut quote);
+    flush_paragraph(&mut html, &mut paragraph);
+    close_list(&mut html, &mut list);
+    html
+}
+
+fn is_rule(line: &str) -> bool {
+    let chars: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    chars.len() >= 3 && ['-', '*', '_'].iter().any(|m| chars.iter().all(|c| c == m))
+}
+
+fn list_item(line: &str) -> Option<(ListKind, &str)> {
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(item) = line.strip_prefix(marker) {
+            return Some((ListKind::Unordered, item));
+        }
+    }
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits > 0 {
+        if let Some(item) = line[digits..].strip_prefix(". ") {
+            return Some((ListKind::Ordered, item));
+        }
+    }
+    None
+}
+
+// 解析 `[text](url)` 中 `[` 之后的部分, 返回 (text, url, 消耗的字节数)
+fn parse_link_tail(rest: &str) -> Option<(String, String, usize)> {
+    let close = rest.find("](")?;
+    let text = &rest[..close];
+    if text.contains('\n') {
+        return None;
+    }
+    let after = &rest[close + 2..];
+    let end = after.find(')')?;
+    // 去掉可选的标题 `(url "title")`
+    let url = after[..end].split_whitespace().next().unwrap_or("");
+    Some((text.to_string(), url.to_string(), close + 2 + end + 1))
+}
+
+// 只保留普通链接, 避免导出的页面中执行脚本
+fn safe_url(url: &str) -> String {
+    let lower = url.trim().to_ascii_lowercase();
+    match lower.starts_with("javascript:")
+        || lower.starts_with("vbscript:")
+        || lower.starts_with("data:text")
+    {
+        true => "#".to_string(),
+        false => escape_html(url),
+    }
+}
+
+// 行内语法
+fn render_inline(text: &str) -> String {
+    let mut html = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                html.push_str(&format!("<code>{}</code>", escape_html(&rest[1..1 + end])));
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+        if rest.starts_with("![") {
+            if let Some((alt, url, consumed)) = parse_link_tail(&rest[2..]) {
+                html.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\" />",
+                    safe_url(&url),
+                    escape_html(&alt)
+                ));
+                rest = &rest[2 + consumed..];
+                continue;
+            }
+        }
+        if c == '[' {
+            if let Some((label, url, consumed)) = parse_link_tail(&rest[1..]) {
+                html.push_str(&format!(
+                    "<a href=\"{}\">{}</a>",
+                    safe_url(&url),
+                    render_inline(&label)
+                ));
+                rest = &rest[1 + consumed..];
+                continue;
+            }
+        }
+        let mut emphasized = false;
+        for (marker, tag) in [("**", "strong"), ("*", "em")] {
+            if let Some(inner) = rest.strip_prefix(marker) {
+                if let Some(end) = inner.find(marker).filter(|end| *end > 0) {
+                    html.push_str(&format!("<{tag}>{}</{tag}>", render_inline(&inner[..end])));
+                    rest = &inner[end + marker.len()..];
+                    emphasized = true;
+                    break;
+                }
+            }
+        }
+        if emphasized {
+            continue;
+        }
+        html.push_str(&escape_html(&c.to_string()));
+        rest = &rest[c.len_utf8()..];
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_to_html() {
+        let markdown = "# 标题\n\n第一段 **加粗** 和 `a<b`\n继续\n\n- 一\n- [链接](https://example.com)\n\n1. 有序\n\n> 引用\n\n```\nfn main() {}\n```\n\n![图](https://example.com/a.png \"t\")\n\n---";
+        let html = markdown_to_html(markdown);
+        assert_eq!(
+            html,
+            "<h1>标题</h1>\n\
+            <p>第一段 <strong>加粗</strong> 和 <code>a&lt;b</code> 继续</p>\n\
+            <ul>\n<li>一</li>\n<li><a href=\"https://example.com\">链接</a></li>\n</ul>\n\
+            <ol>\n<li>有序</li>\n</ol>\n\
+            <blockquote>\n<p>引用</p>\n</blockquote>\n\
+            <pre><code>fn main() {}</code></pre>\n\
+            <p><img src=\"https://example.com/a.png\" alt=\"图\" /></p>\n\
+            <hr />\n"
+        );
+    }
+
+    #[test]
+    fn test_markdown_images() {
+        let markdown = "![a](https://example.com/a.png) text ![b](https://example.com/b.png \"t\") ![a](https://example.com/a.png)";
+        assert_eq!(
+            markdown_images(markdown),
+            vec![
+                "https://example.com/a.png".to_string(),
+                "https://example.com/b.png".to_string()
+            ]
+        );
+    }
+}
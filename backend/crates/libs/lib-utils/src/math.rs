@@ -1,6 +1,6 @@
 pub fn get_page_count(all_count: u64, page_size: u64) -> u64 {
     let mut page_count = all_count / page_size;
-    if all_count % page_size != 0 {
+    if !all_count.is_multiple_of(page_size) {
         page_count += 1;
     }
     page_count
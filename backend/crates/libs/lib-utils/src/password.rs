@@ -0,0 +1,226 @@
+// 密码哈希: Argon2id, 每个用户独立的随机盐
+// 存储为 PHC 字符串 `$argon2id$v=19$m=..,t=..,p=..$<盐>$<哈希>`, 参数随哈希保存, 以后可以平滑提高
+// 旧数据的 `pbkdf2_sha256$<迭代次数>$<盐 hex>$<哈希 hex>` 和无盐 md5 仍然可以校验, 登录后重新哈希
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::{Algorithm, Argon2, Params, Version};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{digest, pbkdf2};
+use std::num::NonZeroU32;
+
+const PBKDF2_ALGORITHM_NAME: &str = "pbkdf2_sha256";
+const SALT_LEN: usize = 16;
+
+// OWASP 推荐的 Argon2id 参数: 19 MiB 内存, 2 次迭代, 1 个并行度
+fn current_params() -> Params {
+    Params::new(19 * 1024, 2, 1, None).expect("Argon2 参数合法")
+}
+
+/// 校验密码的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordVerification {
+    // 密码错误
+    Invalid,
+    // 密码正确, `needs_rehash` 表示应该用当前的算法重新哈希后保存
+    Valid { needs_rehash: bool },
+}
+
+/// 生成 `len` 字节的安全随机数, 以 hex 表示
+pub fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("系统随机数生成失败");
+    to_hex(&bytes)
+}
+
+/// 令牌的 sha256, 用于保存刷新令牌等随机生成的高熵令牌
+pub fn hash_token(token: &str) -> String {
+    to_hex(digest::digest(&digest::SHA256, token.as_bytes()).as_ref())
+}
+
+/// 哈希密码
+///
+/// 计算量很大, 在异步代码中需要放到 `spawn_blocking` 中执行
+pub fn hash_password(password: &str) -> String {
+    hash_password_with_params(password, current_params())
+}
+
+fn hash_password_with_params(password: &str, params: Params) -> String {
+    let mut salt = [0u8; SALT_LEN];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .expect("系统随机数生成失败");
+    let salt = SaltString::encode_b64(&salt).expect("盐的长度合法");
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.as_bytes(), &salt)
+        .expect("Argon2 哈希失败")
+        .to_string()
+}
+
+/// 校验密码, 兼容旧的 PBKDF2 和无盐 md5 哈希
+///
+/// 计算量很大, 在异步代码中需要放到 `spawn_blocking` 中执行
+pub fn verify_password(password: &str, stored: &str) -> PasswordVerification {
+    if stored.starts_with("$argon2") {
+        let Ok(hash) = PasswordHash::new(stored) else {
+            return PasswordVerification::Invalid;
+        };
+        return match Argon2::default().verify_password(password.as_bytes(), &hash) {
+            Ok(()) => PasswordVerification::Valid {
+                needs_rehash: needs_rehash(&hash),
+            },
+            Err(_) => PasswordVerification::Invalid,
+        };
+    }
+    let parts: Vec<&str> = stored.split('$').collect();
+    if let [PBKDF2_ALGORITHM_NAME, iterations, salt, hash] = parts[..] {
+        let (Some(iterations), Some(salt), Some(hash)) = (
+            iterations.parse::<u32>().ok().and_then(NonZeroU32::new),
+            from_hex(salt),
+            from_hex(hash),
+        ) else {
+            return PasswordVerification::Invalid;
+        };
+        return match pbkdf2::verify(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            &salt,
+            password.as_bytes(),
+            &hash,
+        ) {
+            Ok(()) => PasswordVerification::Valid { needs_rehash: true },
+            Err(_) => PasswordVerification::Invalid,
+        };
+    }
+    // 旧数据: 32 位 hex 的 md5
+    if stored.len() == 32 && stored.chars().all(|c| c.is_ascii_hexdigit()) {
+        let legacy = format!("{:x}", md5::compute(password.as_bytes()));
+        if constant_time_eq(legacy.as_bytes(), stored.to_ascii_lowercase().as_bytes()) {
+            return PasswordVerification::Valid { needs_rehash: true };
+        }
+    }
+    PasswordVerification::Invalid
+}
+
+// 算法或者参数和当前的不一致时需要重新哈希
+fn needs_rehash(hash: &PasswordHash) -> bool {
+    let current = current_params();
+    let Ok(params) = Params::try_from(hash) else {
+        return true;
+    };
+    hash.algorithm != Algorithm::Argon2id.ident()
+        || params.m_cost() < current.m_cost()
+        || params.t_cost() < current.t_cost()
+        || params.p_cost() < current.p_cost()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 测试中使用较小的参数
+    fn test_params() -> Params {
+        Params::new(8, 1, 1, None).unwrap()
+    }
+
+    // 旧版本保存的 PBKDF2 哈希
+    fn pbkdf2_hash(password: &str, iterations: u32) -> String {
+        let salt = [7u8; SALT_LEN];
+        let mut hash = [0u8; digest::SHA256_OUTPUT_LEN];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(iterations).unwrap(),
+            &salt,
+            password.as_bytes(),
+            &mut hash,
+        );
+        format!(
+            "{}${}${}${}",
+            PBKDF2_ALGORITHM_NAME,
+            iterations,
+            to_hex(&salt),
+            to_hex(&hash)
+        )
+    }
+
+    #[test]
+    fn test_hash_and_verify_password() {
+        let hashed = hash_password_with_params("secret", test_params());
+        assert!(hashed.starts_with("$argon2id$v=19$m=8,t=1,p=1$"));
+        // 每次的盐都不同
+        assert_ne!(hashed, hash_password_with_params("secret", test_params()));
+        // 参数低于当前要求, 需要重新哈希
+        assert_eq!(
+            verify_password("secret", &hashed),
+            PasswordVerification::Valid { needs_rehash: true }
+        );
+        assert_eq!(
+            verify_password("wrong", &hashed),
+            PasswordVerification::Invalid
+        );
+
+        let hashed = hash_password("secret");
+        assert_eq!(
+            verify_password("secret", &hashed),
+            PasswordVerification::Valid {
+                needs_rehash: false
+            }
+        );
+
+        let legacy = pbkdf2_hash("secret", 1000);
+        assert_eq!(
+            verify_password("secret", &legacy),
+            PasswordVerification::Valid { needs_rehash: true }
+        );
+        assert_eq!(
+            verify_password("wrong", &legacy),
+            PasswordVerification::Invalid
+        );
+
+        let legacy = format!("{:x}", md5::compute("secret".as_bytes()));
+        assert_eq!(
+            verify_password("secret", &legacy),
+            PasswordVerification::Valid { needs_rehash: true }
+        );
+        assert_eq!(
+            verify_password("wrong", &legacy),
+            PasswordVerification::Invalid
+        );
+        assert_eq!(
+            verify_password("secret", "pbkdf2_sha256$x$00$00"),
+            PasswordVerification::Invalid
+        );
+        assert_eq!(
+            verify_password("secret", "$argon2id$broken"),
+            PasswordVerification::Invalid
+        );
+    }
+
+    #[test]
+    fn test_hash_token() {
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(random_hex(16).len(), 32);
+    }
+}
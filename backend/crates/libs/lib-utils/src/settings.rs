@@ -14,14 +14,18 @@ pub struct Database {
 #[allow(unused)]
 pub struct Jwt {
     pub secret: String,
+    // 访问令牌有效期, 单位秒
     pub exp: i64,
+    // 刷新令牌有效期, 单位秒
+    pub refresh_exp: i64,
 }
 
 impl Default for Jwt {
     fn default() -> Self {
         Self {
             secret: "im_fake_secret".to_string(),
-            exp: 60 * 60 * 24 * 90,
+            exp: 60 * 15,
+            refresh_exp: 60 * 60 * 24 * 30,
         }
     }
 }
@@ -31,6 +35,10 @@ impl Default for Jwt {
 pub struct Web {
     pub address: String,
     pub compression: Option<bool>,
+    // 管理员账户的邮箱, 启动时自动创建, 已有账户时密码匹配才提升为管理员
+    pub admin_name: Option<String>,
+    // 管理员账户的密码, 不配置时不创建管理员
+    pub admin_password: Option<String>,
 }
 
 impl Default for Web {
@@ -38,6 +46,8 @@ impl Default for Web {
         Self {
             address: "0.0.0.0:9000".to_string(),
             compression: Some(true),
+            admin_name: None,
+            admin_password: None,
         }
     }
 }
@@ -67,14 +77,8 @@ pub struct OpenAI {
 impl Default for OpenAI {
     fn default() -> Self {
         Self {
-            api_key: match env::var("openai.api_key") {
-                Ok(api_key) => Some(api_key),
-                Err(_) => None,
-            },
-            api_base: match env::var("openai.api_base") {
-                Ok(api_base) => Some(api_base),
-                Err(_) => None,
-            },
+            api_key: env::var("openai.api_key").ok(),
+            api_base: env::var("openai.api_base").ok(),
         }
     }
 }
@@ -87,10 +91,8 @@ pub struct Services {
 impl Default for Services {
     fn default() -> Self {
         Self {
-            js_server_host: Some(
-                env::var("services.js_server_host")
-                    .unwrap_or_else(|_| "http://localhost:3000".to_string()),
-            ),
+            // 正文提取失败时使用的解析服务, 不配置时只使用内置的提取
+            js_server_host: env::var("services.js_server_host").ok(),
             web_api_host: Some(
                 env::var("services.web_api_host")
                     .unwrap_or_else(|_| "http://localhost:3000".to_string()),
@@ -99,6 +101,71 @@ impl Default for Services {
     }
 }
 
+// 爬虫的调度配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Crawler {
+    // 全局最大并发数, 为空时根据 cpu 核数决定
+    pub max_concurrency: Option<usize>,
+    // 单个站点最大并发数
+    pub per_host_concurrency: usize,
+    // 同一站点两次请求之间的最小间隔, 单位毫秒
+    pub per_host_delay_ms: u64,
+    // 站点连续失败时的最大退避时间, 单位秒
+    pub max_backoff_secs: u64,
+    // 抓取文章页面时使用的 user agent, 也用于匹配 robots.txt 的规则
+    pub user_agent: String,
+    // 是否遵守 robots.txt
+    pub respect_robots: bool,
+    // worker 租用任务的租期, 超过租期没有完成的任务会被重新执行, 单位秒
+    pub job_lease_secs: i64,
+    // 是否为新文章自动生成总结, 需要配置 openai
+    pub auto_summary: bool,
+}
+
+impl Default for Crawler {
+    fn default() -> Self {
+        Self {
+            max_concurrency: None,
+            per_host_concurrency: 2,
+            per_host_delay_ms: 1000,
+            max_backoff_secs: 30 * 60,
+            user_agent: "Mozilla/5.0 (compatible; ArticleCrawler/0.1; +https://github.com/jiazifa/article-crawler)".to_string(),
+            respect_robots: true,
+            job_lease_secs: 10 * 60,
+            auto_summary: false,
+        }
+    }
+}
+
+// WebSub 推送订阅配置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebSub {
+    // 是否向订阅源声明的 hub 订阅推送
+    pub enabled: bool,
+    // hub 回调使用的外部地址, 例如 `https://example.com/api`, 为空时不订阅
+    pub callback_base: Option<String>,
+    // 期望的租期, 单位秒
+    pub lease_seconds: i64,
+    // 租期到期前多久续订, 单位秒
+    pub renew_before_secs: i64,
+    // 推送生效时的兜底拉取频率, 单位分钟
+    pub poll_frequency: i32,
+}
+
+impl Default for WebSub {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            callback_base: None,
+            lease_seconds: 60 * 60 * 24 * 7,
+            renew_before_secs: 60 * 60 * 24,
+            poll_frequency: 60 * 24,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 #[allow(unused)]
 pub struct Setting {
@@ -109,6 +176,10 @@ pub struct Setting {
     pub log: Log,
     pub openai: OpenAI,
     pub services: Services,
+    #[serde(default)]
+    pub crawler: Crawler,
+    #[serde(default)]
+    pub websub: WebSub,
 }
 
 impl Default for Setting {
@@ -123,6 +194,8 @@ impl Default for Setting {
             log: Log::default(),
             openai: OpenAI::default(),
             services: Services::default(),
+            crawler: Crawler::default(),
+            websub: WebSub::default(),
         }
     }
 }
@@ -167,7 +240,10 @@ fn load_configure(file: Option<String>) -> Config {
     builder = builder.set_default("log.dir", "logs").unwrap();
 
     builder = builder.set_default("jwt.secret", "secret").unwrap();
-    builder = builder.set_default("jwt.exp", 3600 * 24 * 30).unwrap();
+    builder = builder.set_default("jwt.exp", 60 * 15).unwrap();
+    builder = builder
+        .set_default("jwt.refresh_exp", 3600 * 24 * 30)
+        .unwrap();
 
     builder = builder
         .set_default("database.uri", "sqlite://./data.db?mode=rwc")
@@ -0,0 +1,107 @@
+use crate::text::is_cjk;
+
+// 特征太少时指纹不可靠, 不参与去重
+const MIN_FEATURES: usize = 8;
+// 单个词和连续 2 个词都作为特征
+const SHINGLE_SIZE: usize = 2;
+
+// FNV-1a, 指纹需要保存到数据库, 不能使用每次运行都会变化的 hasher
+fn fnv1a(value: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in value.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// 中日韩文字没有空格分词, 每个字作为一个词
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in text.chars().flat_map(|c| c.to_lowercase()) {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.push(c);
+        } else if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+/// 计算文本的 SimHash 指纹, 内容相近的文本指纹的汉明距离也小
+///
+/// 文本太短时返回 `None`
+pub fn simhash(text: &str) -> Option<u64> {
+    let tokens = tokenize(text);
+    if tokens.len() < MIN_FEATURES {
+        return None;
+    }
+    let mut weights = [0i64; 64];
+    let features = tokens
+        .iter()
+        .cloned()
+        .chain(tokens.windows(SHINGLE_SIZE).map(|w| w.join(" ")));
+    for feature in features {
+        let hash = fnv1a(&feature);
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            if hash & (1 << bit) != 0 {
+                *weight += 1;
+            } else {
+                *weight -= 1;
+            }
+        }
+    }
+    let mut fingerprint = 0u64;
+    for (bit, weight) in weights.iter().enumerate() {
+        if *weight > 0 {
+            fingerprint |= 1 << bit;
+        }
+    }
+    Some(fingerprint)
+}
+
+/// 两个指纹不同的位数
+pub fn hamming_distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// 汉明距离不超过 `NEAR_DUPLICATE_DISTANCE` 时认为是近似重复的内容
+pub const NEAR_DUPLICATE_DISTANCE: u32 = 10;
+
+pub fn is_near_duplicate(a: u64, b: u64) -> bool {
+    hamming_distance(a, b) <= NEAR_DUPLICATE_DISTANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simhash() {
+        let origin = "The central bank raised interest rates by a quarter point on Wednesday, \
+            citing persistent inflation and a strong labor market across the country.";
+        let syndicated = "The central bank raised interest rates by a quarter point on Wednesday, \
+            citing persistent inflation and a strong labor market across the nation. (Reuters)";
+        let other = "A new species of frog was discovered in the rainforest by a team of \
+            biologists who spent three months surveying remote river valleys.";
+
+        let a = simhash(origin).unwrap();
+        let b = simhash(syndicated).unwrap();
+        let c = simhash(other).unwrap();
+        assert!(is_near_duplicate(a, b));
+        assert!(!is_near_duplicate(a, c));
+        assert_eq!(simhash(origin), simhash(&origin.to_uppercase()));
+
+        assert!(simhash("too short").is_none());
+        assert!(simhash("央行周三宣布加息二十五个基点").is_some());
+    }
+}
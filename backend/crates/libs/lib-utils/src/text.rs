@@ -0,0 +1,96 @@
+// 全文索引的分词辅助: 中日韩文字没有空格分隔, 在每个字之间插入空格后交给 unicode61 分词
+
+/// 是否是中日韩文字
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30ff | 0x3400..=0x4dbf | 0x4e00..=0x9fff | 0xac00..=0xd7af | 0xf900..=0xfaff)
+}
+
+/// 在相邻的中日韩文字之间插入空格, 使每个字成为一个词
+pub fn segment_cjk(text: &str) -> String {
+    let mut result = String::with_capacity(text.len() * 2);
+    let mut prev: Option<char> = None;
+    for c in text.chars() {
+        if let Some(p) = prev {
+            if (is_cjk(c) && !p.is_whitespace()) || (is_cjk(p) && !c.is_whitespace()) {
+                result.push(' ');
+            }
+        }
+        result.push(c);
+        prev = Some(c);
+    }
+    result
+}
+
+/// 去掉 `segment_cjk` 插入的空格, `markers` 中的标记 (如高亮标签) 不影响判断
+pub fn desegment_cjk(text: &str, markers: &[&str]) -> String {
+    // 跳过标记后, 第一个可见字符
+    let visible_after = |rest: &str| -> Option<char> {
+        let mut rest = rest;
+        loop {
+            match markers
+                .iter()
+                .find(|m| !m.is_empty() && rest.starts_with(*m))
+            {
+                Some(m) => rest = &rest[m.len()..],
+                None => return rest.chars().next(),
+            }
+        }
+    };
+    let mut result = String::with_capacity(text.len());
+    // 最近一个可见字符, 标记不算
+    let mut last_visible: Option<char> = None;
+    let mut index = 0;
+    while index < text.len() {
+        let rest = &text[index..];
+        if let Some(m) = markers
+            .iter()
+            .find(|m| !m.is_empty() && rest.starts_with(*m))
+        {
+            result.push_str(m);
+            index += m.len();
+            continue;
+        }
+        let c = rest.chars().next().unwrap_or_default();
+        index += c.len_utf8();
+        if c == ' ' {
+            let next = visible_after(&text[index..]);
+            let joined = last_visible.is_some_and(is_cjk) || next.is_some_and(is_cjk);
+            if joined && last_visible.is_some_and(|p| !p.is_whitespace()) {
+                continue;
+            }
+        }
+        result.push(c);
+        last_visible = Some(c);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_cjk() {
+        assert_eq!(segment_cjk("全文搜索 rust"), "全 文 搜 索 rust");
+        assert_eq!(segment_cjk("使用SQLite的FTS5"), "使 用 SQLite 的 FTS5");
+        assert_eq!(segment_cjk("hello world"), "hello world");
+    }
+
+    #[test]
+    fn test_desegment_cjk() {
+        let markers = ["<mark>", "</mark>"];
+        assert_eq!(
+            desegment_cjk(
+                "使 用 <mark>SQLite</mark> 的 <mark>全 文</mark> 搜 索",
+                &markers
+            ),
+            "使用<mark>SQLite</mark>的<mark>全文</mark>搜索"
+        );
+        assert_eq!(desegment_cjk("hello world", &markers), "hello world");
+        assert_eq!(
+            desegment_cjk(&segment_cjk("搜索 rust 和 go"), &markers),
+            "搜索rust和go"
+        );
+    }
+}
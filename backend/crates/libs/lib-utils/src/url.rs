@@ -0,0 +1,84 @@
+use url::Url;
+
+// 常见的追踪参数, 不影响页面内容
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "yclid", "igshid", "mc_cid", "mc_eid", "_hsenc",
+    "_hsmi", "spm", "ref", "ref_src",
+];
+// 以这些前缀开头的参数都是追踪参数
+const TRACKING_PARAM_PREFIXES: &[&str] = &["utm_", "__twitter", "_ga"];
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_lowercase();
+    TRACKING_PARAMS.contains(&key.as_str())
+        || TRACKING_PARAM_PREFIXES.iter().any(|p| key.starts_with(p))
+}
+
+/// 规范化文章地址, 用于判断两个地址是否指向同一篇文章
+///
+/// - scheme 统一为 https, host 转小写, 去掉默认端口和 fragment
+/// - 去掉追踪参数, 其余参数按名称排序
+/// - 去掉路径末尾的 `/`
+///
+/// 无法解析的地址只去掉首尾空白
+pub fn canonicalize_url(raw: &str) -> String {
+    let raw = raw.trim();
+    let Ok(mut url) = Url::parse(raw) else {
+        return raw.to_string();
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return raw.to_string();
+    }
+    let _ = url.set_scheme("https");
+    // http 的 80 端口转换为 https 后不再是默认端口
+    if matches!(url.port(), Some(80) | Some(443)) {
+        let _ = url.set_port(None);
+    }
+    url.set_fragment(None);
+
+    let mut params: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(k, _)| !is_tracking_param(k))
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    params.sort();
+    if params.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(params);
+    }
+
+    let path = url.path().trim_end_matches('/').to_string();
+    if path.is_empty() {
+        url.set_path("/");
+    } else {
+        url.set_path(&path);
+    }
+    url.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonicalize_url() {
+        assert_eq!(
+            canonicalize_url("HTTP://Example.COM:80/post/1/?utm_source=rss&b=2&a=1#comments"),
+            "https://example.com/post/1?a=1&b=2"
+        );
+        assert_eq!(
+            canonicalize_url("https://example.com/?fbclid=abc"),
+            "https://example.com/"
+        );
+        assert_eq!(
+            canonicalize_url("https://example.com/a?id=1&utm_medium=feed"),
+            canonicalize_url("http://example.com/a/?id=1")
+        );
+        assert_eq!(canonicalize_url(" not a url "), "not a url");
+        assert_eq!(
+            canonicalize_url("mailto:a@example.com"),
+            "mailto:a@example.com"
+        );
+    }
+}
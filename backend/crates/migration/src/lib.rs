@@ -4,6 +4,23 @@ mod m20240110_065914_add_feed_link_summary;
 mod m20240221_025803_add_feed_update_record;
 mod m20240227_070206_add_feed_update_config;
 mod m20240402_033409_add_account_table;
+mod m20241018_021500_add_feed_build_validators;
+mod m20241018_064200_add_feed_build_record_reason;
+mod m20241019_083000_add_feed_link_guid;
+mod m20241019_101500_add_feed_link_cluster;
+mod m20241020_020000_add_feed_link_fts;
+mod m20241021_030000_add_account_subscription;
+mod m20241021_080000_add_link_state;
+mod m20241022_021000_add_saved_link;
+mod m20241023_020000_widen_account_password;
+mod m20241023_060000_add_account_session;
+mod m20241024_020000_add_api_key;
+mod m20241024_080000_add_account_role;
+mod m20241025_020000_add_account_fever_key;
+mod m20241026_020000_add_websub_subscription;
+mod m20241027_020000_add_job_queue;
+mod m20241028_020000_add_account_token_previous;
+mod m20241028_030000_add_feed_link_canonical_index;
 
 pub struct Migrator;
 
@@ -16,6 +33,23 @@ impl MigratorTrait for Migrator {
             Box::new(m20240221_025803_add_feed_update_record::Migration),
             Box::new(m20240227_070206_add_feed_update_config::Migration),
             Box::new(m20240402_033409_add_account_table::Migration),
+            Box::new(m20241018_021500_add_feed_build_validators::Migration),
+            Box::new(m20241018_064200_add_feed_build_record_reason::Migration),
+            Box::new(m20241019_083000_add_feed_link_guid::Migration),
+            Box::new(m20241019_101500_add_feed_link_cluster::Migration),
+            Box::new(m20241020_020000_add_feed_link_fts::Migration),
+            Box::new(m20241021_030000_add_account_subscription::Migration),
+            Box::new(m20241021_080000_add_link_state::Migration),
+            Box::new(m20241022_021000_add_saved_link::Migration),
+            Box::new(m20241023_020000_widen_account_password::Migration),
+            Box::new(m20241023_060000_add_account_session::Migration),
+            Box::new(m20241024_020000_add_api_key::Migration),
+            Box::new(m20241024_080000_add_account_role::Migration),
+            Box::new(m20241025_020000_add_account_fever_key::Migration),
+            Box::new(m20241026_020000_add_websub_subscription::Migration),
+            Box::new(m20241027_020000_add_job_queue::Migration),
+            Box::new(m20241028_020000_add_account_token_previous::Migration),
+            Box::new(m20241028_030000_add_feed_link_canonical_index::Migration),
         ]
     }
 }
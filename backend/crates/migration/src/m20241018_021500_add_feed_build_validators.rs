@@ -0,0 +1,52 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 订阅源的缓存校验信息, 用于条件请求 (sqlite 每次只能添加一列)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_build_config"))
+                    .add_column(ColumnDef::new(Alias::new("etag")).string_len(255u32).null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_build_config"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("last_modified"))
+                            .string_len(64u32)
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_build_config"))
+                    .drop_column(Alias::new("last_modified"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_build_config"))
+                    .drop_column(Alias::new("etag"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
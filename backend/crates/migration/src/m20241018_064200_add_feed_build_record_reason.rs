@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 更新失败的原因分类
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_build_record"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("reason"))
+                            .string_len(32u32)
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_feed_build_record_reason")
+                    .table(Alias::new("feed_build_record"))
+                    .col(Alias::new("reason"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_feed_build_record_reason")
+                    .table(Alias::new("feed_build_record"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_build_record"))
+                    .drop_column(Alias::new("reason"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
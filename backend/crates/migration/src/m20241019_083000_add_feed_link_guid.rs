@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 订阅源提供的条目唯一标识 (sqlite 每次只能添加一列)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_link"))
+                    .add_column(ColumnDef::new(Alias::new("guid")).string_len(512u32).null())
+                    .to_owned(),
+            )
+            .await?;
+        // 去掉追踪参数后的链接, 用于去重
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_link"))
+                    .add_column(ColumnDef::new(Alias::new("canonical_link")).text().null())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_feed_link_subscription_guid")
+                    .table(Alias::new("feed_link"))
+                    .col(Alias::new("subscription_id"))
+                    .col(Alias::new("guid"))
+                    .to_owned(),
+            )
+            .await?;
+
+        // 回填已有数据的规范化链接
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let select = Query::select()
+            .columns([Alias::new("id"), Alias::new("link")])
+            .from(Alias::new("feed_link"))
+            .to_owned();
+        let rows = db.query_all(backend.build(&select)).await?;
+        for row in rows {
+            let id: i64 = row.try_get("", "id")?;
+            let link: String = row.try_get("", "link")?;
+            let update = Query::update()
+                .table(Alias::new("feed_link"))
+                .value(
+                    Alias::new("canonical_link"),
+                    lib_utils::canonicalize_url(&link),
+                )
+                .and_where(Expr::col(Alias::new("id")).eq(id))
+                .to_owned();
+            db.execute(backend.build(&update)).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_feed_link_subscription_guid")
+                    .table(Alias::new("feed_link"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_link"))
+                    .drop_column(Alias::new("canonical_link"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_link"))
+                    .drop_column(Alias::new("guid"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
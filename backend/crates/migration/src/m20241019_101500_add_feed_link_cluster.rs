@@ -0,0 +1,70 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 标题和内容的 SimHash 指纹 (sqlite 每次只能添加一列)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_link"))
+                    .add_column(ColumnDef::new(Alias::new("simhash")).big_integer().null())
+                    .to_owned(),
+            )
+            .await?;
+        // 近似重复文章的分组, 值为组内第一篇文章的 id
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_link"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("cluster_id"))
+                            .big_integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_feed_link_cluster_id")
+                    .table(Alias::new("feed_link"))
+                    .col(Alias::new("cluster_id"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_feed_link_cluster_id")
+                    .table(Alias::new("feed_link"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_link"))
+                    .drop_column(Alias::new("cluster_id"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_link"))
+                    .drop_column(Alias::new("simhash"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
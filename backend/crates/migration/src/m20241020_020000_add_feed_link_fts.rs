@@ -0,0 +1,59 @@
+use lib_utils::text::segment_cjk;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 全文索引只在 sqlite 下使用 FTS5, 其他数据库使用 LIKE 查询
+        if manager.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+        // rowid 对应 feed_link.id
+        db.execute_unprepared(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS feed_link_fts USING fts5(title, content, keywords, tokenize = 'unicode61')",
+        )
+        .await?;
+
+        // 为已有的文章建立索引
+        let rows = db
+            .query_all(Statement::from_string(
+                DbBackend::Sqlite,
+                "SELECT feed_link.id AS id, feed_link.title AS title, feed_link.desc_pure_txt AS content, feed_link_summary.keywords AS keywords FROM feed_link LEFT JOIN feed_link_summary ON feed_link_summary.link_url = feed_link.link",
+            ))
+            .await?;
+        for row in rows {
+            let id: i64 = row.try_get("", "id")?;
+            let title: Option<String> = row.try_get("", "title")?;
+            let content: Option<String> = row.try_get("", "content")?;
+            let keywords: Option<String> = row.try_get("", "keywords")?;
+            db.execute(Statement::from_sql_and_values(
+                DbBackend::Sqlite,
+                "INSERT INTO feed_link_fts (rowid, title, content, keywords) VALUES (?, ?, ?, ?)",
+                [
+                    id.into(),
+                    segment_cjk(&title.unwrap_or_default()).into(),
+                    segment_cjk(&content.unwrap_or_default()).into(),
+                    segment_cjk(&keywords.unwrap_or_default()).into(),
+                ],
+            ))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() != DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TABLE IF EXISTS feed_link_fts")
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,331 @@
+use std::collections::HashMap;
+
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+// 没有分类的订阅源迁移到这个分类下
+const UNCATEGORIZED_TITLE: &str = "未分类";
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 用户和订阅源的关联, 订阅源本身全局唯一
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("account_subscription"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("id"))
+                            .integer()
+                            .auto_increment()
+                            .primary_key()
+                            .not_null()
+                            .comment("主键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("account_id"))
+                            .integer()
+                            .not_null()
+                            .comment("用户id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("subscription_id"))
+                            .integer()
+                            .not_null()
+                            .comment("订阅源id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("category_id"))
+                            .integer()
+                            .not_null()
+                            .comment("用户的分类id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("custom_title"))
+                            .string_len(255)
+                            .null()
+                            .comment("自定义标题".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("sort_order"))
+                            .integer()
+                            .null()
+                            .comment("排序序列".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("created_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("订阅时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("updated_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("更新时间".to_string()),
+                    )
+                    .comment("用户订阅表".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_account_subscription_unique")
+                    .table(Alias::new("account_subscription"))
+                    .col(Alias::new("account_id"))
+                    .col(Alias::new("subscription_id"))
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        // 分类归属于用户, 旧数据为空表示共享
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_category"))
+                    .add_column(ColumnDef::new(Alias::new("account_id")).integer().null())
+                    .to_owned(),
+            )
+            .await?;
+        attach_existing_subscriptions(manager).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("feed_category"))
+                    .drop_column(Alias::new("account_id"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("account_subscription"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+// 已有的分类
+struct Category {
+    id: i64,
+    title: String,
+    description: Option<String>,
+    parent_id: Option<i64>,
+    icon: Option<String>,
+    sort_order: Option<i64>,
+}
+
+// 把已有的分类和订阅源交给已有的用户, 否则升级后用户看不到之前的订阅
+//
+// 第一个用户直接接管已有的分类, 其他用户得到一份分类的副本
+async fn attach_existing_subscriptions(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    let db = manager.get_connection();
+    let backend = manager.get_database_backend();
+
+    let accounts = db
+        .query_all(
+            backend.build(
+                &Query::select()
+                    .column(Alias::new("id"))
+                    .from(Alias::new("account"))
+                    .order_by(Alias::new("id"), Order::Asc)
+                    .to_owned(),
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|row| row.try_get::<i64>("", "id"))
+        .collect::<Result<Vec<_>, _>>()?;
+    let Some((owner, others)) = accounts.split_first() else {
+        return Ok(());
+    };
+
+    let mut categories = vec![];
+    for row in db
+        .query_all(
+            backend.build(
+                &Query::select()
+                    .columns([
+                        Alias::new("id"),
+                        Alias::new("title"),
+                        Alias::new("description"),
+                        Alias::new("parent_id"),
+                        Alias::new("icon"),
+                        Alias::new("sort_order"),
+                    ])
+                    .from(Alias::new("feed_category"))
+                    .and_where(Expr::col(Alias::new("account_id")).is_null())
+                    .order_by(Alias::new("id"), Order::Asc)
+                    .to_owned(),
+            ),
+        )
+        .await?
+    {
+        categories.push(Category {
+            id: row.try_get("", "id")?,
+            title: row.try_get("", "title")?,
+            description: row.try_get("", "description")?,
+            parent_id: row.try_get("", "parent_id")?,
+            icon: row.try_get("", "icon")?,
+            sort_order: row.try_get("", "sort_order")?,
+        });
+    }
+    let subscriptions = db
+        .query_all(
+            backend.build(
+                &Query::select()
+                    .columns([Alias::new("id"), Alias::new("category_id")])
+                    .from(Alias::new("feed_subscription"))
+                    .to_owned(),
+            ),
+        )
+        .await?
+        .into_iter()
+        .map(|row| {
+            Ok((
+                row.try_get::<i64>("", "id")?,
+                row.try_get::<Option<i64>>("", "category_id")?,
+            ))
+        })
+        .collect::<Result<Vec<_>, DbErr>>()?;
+
+    // 第一个用户接管已有的分类
+    db.execute(
+        backend.build(
+            &Query::update()
+                .table(Alias::new("feed_category"))
+                .value(Alias::new("account_id"), *owner)
+                .and_where(Expr::col(Alias::new("account_id")).is_null())
+                .to_owned(),
+        ),
+    )
+    .await?;
+
+    for account_id in accounts.iter().copied() {
+        // 旧分类 id 到该用户分类 id 的映射
+        let mut category_map: HashMap<i64, i64> = HashMap::new();
+        if account_id == *owner {
+            category_map.extend(categories.iter().map(|c| (c.id, c.id)));
+        } else if others.contains(&account_id) {
+            for category in categories.iter() {
+                let id = insert_category(
+                    manager,
+                    account_id,
+                    &category.title,
+                    category.description.clone(),
+                    category.icon.clone(),
+                    category.sort_order,
+                )
+                .await?;
+                category_map.insert(category.id, id);
+            }
+            // 复制的分类指向同一用户下的父分类
+            for category in categories.iter() {
+                let Some(parent_id) = category.parent_id.and_then(|p| category_map.get(&p)) else {
+                    continue;
+                };
+                db.execute(
+                    backend.build(
+                        &Query::update()
+                            .table(Alias::new("feed_category"))
+                            .value(Alias::new("parent_id"), *parent_id)
+                            .and_where(Expr::col(Alias::new("id")).eq(category_map[&category.id]))
+                            .to_owned(),
+                    ),
+                )
+                .await?;
+            }
+        }
+
+        let mut uncategorized = None;
+        for (subscription_id, category_id) in subscriptions.iter() {
+            let category_id = match category_id.and_then(|c| category_map.get(&c)) {
+                Some(category_id) => *category_id,
+                None => match uncategorized {
+                    Some(id) => id,
+                    None => {
+                        let id = insert_category(
+                            manager,
+                            account_id,
+                            UNCATEGORIZED_TITLE,
+                            None,
+                            None,
+                            None,
+                        )
+                        .await?;
+                        uncategorized = Some(id);
+                        id
+                    }
+                },
+            };
+            db.execute(
+                backend.build(
+                    &Query::insert()
+                        .into_table(Alias::new("account_subscription"))
+                        .columns([
+                            Alias::new("account_id"),
+                            Alias::new("subscription_id"),
+                            Alias::new("category_id"),
+                        ])
+                        .values_panic([
+                            account_id.into(),
+                            (*subscription_id).into(),
+                            category_id.into(),
+                        ])
+                        .to_owned(),
+                ),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+// 为用户创建分类, 返回新分类的 id
+async fn insert_category(
+    manager: &SchemaManager<'_>,
+    account_id: i64,
+    title: &str,
+    description: Option<String>,
+    icon: Option<String>,
+    sort_order: Option<i64>,
+) -> Result<i64, DbErr> {
+    let backend = manager.get_database_backend();
+    let result = manager
+        .get_connection()
+        .execute(
+            backend.build(
+                &Query::insert()
+                    .into_table(Alias::new("feed_category"))
+                    .columns([
+                        Alias::new("title"),
+                        Alias::new("description"),
+                        Alias::new("icon"),
+                        Alias::new("sort_order"),
+                        Alias::new("account_id"),
+                    ])
+                    .values_panic([
+                        title.into(),
+                        description.into(),
+                        icon.into(),
+                        sort_order.into(),
+                        account_id.into(),
+                    ])
+                    .to_owned(),
+            ),
+        )
+        .await?;
+    Ok(result.last_insert_id() as i64)
+}
@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("link_state"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("id"))
+                            .integer()
+                            .auto_increment()
+                            .primary_key()
+                            .not_null()
+                            .comment("主键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("account_id"))
+                            .integer()
+                            .not_null()
+                            .comment("用户id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("link_id"))
+                            .integer()
+                            .not_null()
+                            .comment("文章id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("read_at"))
+                            .date_time()
+                            .null()
+                            .comment("阅读时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("starred_at"))
+                            .date_time()
+                            .null()
+                            .comment("收藏时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("archived"))
+                            .boolean()
+                            .not_null()
+                            .default(false)
+                            .comment("是否归档".to_string()),
+                    )
+                    .comment("用户的文章状态表".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_link_state_unique")
+                    .table(Alias::new("link_state"))
+                    .col(Alias::new("account_id"))
+                    .col(Alias::new("link_id"))
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("link_state"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("saved_link"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("id"))
+                            .integer()
+                            .auto_increment()
+                            .primary_key()
+                            .not_null()
+                            .comment("主键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("account_id"))
+                            .integer()
+                            .not_null()
+                            .comment("用户id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("link_id"))
+                            .integer()
+                            .null()
+                            .comment("文章id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("link"))
+                            .text()
+                            .not_null()
+                            .comment("文章地址".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("title"))
+                            .string_len(255)
+                            .not_null()
+                            .comment("标题".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("content"))
+                            .text()
+                            .null()
+                            .comment("正文的 Markdown 快照".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("images"))
+                            .json()
+                            .null()
+                            .comment("图片".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("tags"))
+                            .json()
+                            .null()
+                            .comment("标签".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("notes"))
+                            .text()
+                            .null()
+                            .comment("笔记".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("created_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("保存时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("updated_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("更新时间".to_string()),
+                    )
+                    .comment("用户保存的文章".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_saved_link_account_link")
+                    .table(Alias::new("saved_link"))
+                    .col(Alias::new("account_id"))
+                    .col(Alias::new("link_id"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("saved_link"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
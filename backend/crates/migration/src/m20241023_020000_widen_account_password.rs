@@ -0,0 +1,48 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // sqlite 不限制 varchar 长度, 也不支持修改列
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        // 加盐哈希后的密码比 md5 长
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("account"))
+                    .modify_column(
+                        ColumnDef::new(Alias::new("password"))
+                            .string_len(255)
+                            .null()
+                            .comment("密码".to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if manager.get_database_backend() == DbBackend::Sqlite {
+            return Ok(());
+        }
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("account"))
+                    .modify_column(
+                        ColumnDef::new(Alias::new("password"))
+                            .string_len(40)
+                            .null()
+                            .comment("密码".to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,180 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 旧的 account_token 以用户 id 为主键, 只能保存一个令牌, 且从未写入过数据, 直接重建
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("account_token"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("account_token"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("id"))
+                            .integer()
+                            .auto_increment()
+                            .primary_key()
+                            .not_null()
+                            .comment("主键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("account_id"))
+                            .integer()
+                            .not_null()
+                            .comment("用户id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("token"))
+                            .string_len(64)
+                            .not_null()
+                            .unique_key()
+                            .comment("刷新令牌的sha256".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("access_jti"))
+                            .string_len(64)
+                            .not_null()
+                            .comment("当前访问令牌的jti".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("access_expired_at"))
+                            .date_time()
+                            .not_null()
+                            .comment("当前访问令牌的过期时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("expired_at"))
+                            .date_time()
+                            .not_null()
+                            .comment("刷新令牌的过期时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("revoked_at"))
+                            .date_time()
+                            .null()
+                            .comment("注销时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("created_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("登录时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("updated_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("刷新时间".to_string()),
+                    )
+                    .comment("用户登录会话表".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_account_token_account")
+                    .table(Alias::new("account_token"))
+                    .col(Alias::new("account_id"))
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("revoked_token"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("id"))
+                            .integer()
+                            .auto_increment()
+                            .primary_key()
+                            .not_null()
+                            .comment("主键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("jti"))
+                            .string_len(64)
+                            .not_null()
+                            .unique_key()
+                            .comment("访问令牌的jti".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("account_id"))
+                            .integer()
+                            .not_null()
+                            .comment("用户id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("expired_at"))
+                            .date_time()
+                            .not_null()
+                            .comment("访问令牌的过期时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("created_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("注销时间".to_string()),
+                    )
+                    .comment("已注销的访问令牌表".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("revoked_token"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("account_token"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("account_token"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("account_id"))
+                            .primary_key()
+                            .integer()
+                            .not_null()
+                            .comment("用户id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("token"))
+                            .string_len(64)
+                            .null()
+                            .comment("token".to_string()),
+                    )
+                    .comment("用户token表".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
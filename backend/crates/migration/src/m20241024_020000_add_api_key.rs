@@ -0,0 +1,104 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("api_key"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("id"))
+                            .integer()
+                            .auto_increment()
+                            .primary_key()
+                            .not_null()
+                            .comment("主键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("account_id"))
+                            .integer()
+                            .not_null()
+                            .comment("用户id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("name"))
+                            .string_len(100)
+                            .not_null()
+                            .comment("名称".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("prefix"))
+                            .string_len(16)
+                            .not_null()
+                            .comment("Key的前几位".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("key_hash"))
+                            .string_len(64)
+                            .not_null()
+                            .unique_key()
+                            .comment("Key的sha256".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("scopes"))
+                            .json()
+                            .null()
+                            .comment("权限范围".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("last_used_at"))
+                            .date_time()
+                            .null()
+                            .comment("最后使用时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("expired_at"))
+                            .date_time()
+                            .null()
+                            .comment("过期时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("revoked_at"))
+                            .date_time()
+                            .null()
+                            .comment("吊销时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("created_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("创建时间".to_string()),
+                    )
+                    .comment("用户API Key表".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_api_key_account")
+                    .table(Alias::new("api_key"))
+                    .col(Alias::new("account_id"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("api_key"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
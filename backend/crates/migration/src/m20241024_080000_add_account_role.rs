@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 已有用户保持原来的权限, 默认为 editor
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("account"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("role"))
+                            .integer()
+                            .not_null()
+                            .default(1)
+                            .comment("角色 0 reader 1 editor 2 admin".to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("account"))
+                    .drop_column(Alias::new("role"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("account"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("fever_api_key"))
+                            .string_len(32)
+                            .null()
+                            .comment("Fever API 的 api_key".to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_account_fever_api_key")
+                    .table(Alias::new("account"))
+                    .col(Alias::new("fever_api_key"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_account_fever_api_key")
+                    .table(Alias::new("account"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("account"))
+                    .drop_column(Alias::new("fever_api_key"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
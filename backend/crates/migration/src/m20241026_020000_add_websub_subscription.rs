@@ -0,0 +1,110 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("websub_subscription"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("id"))
+                            .integer()
+                            .auto_increment()
+                            .primary_key()
+                            .not_null()
+                            .comment("主键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("subscription_id"))
+                            .integer()
+                            .not_null()
+                            .unique_key()
+                            .comment("订阅源id".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("hub"))
+                            .string_len(512)
+                            .not_null()
+                            .comment("hub地址".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("topic"))
+                            .string_len(512)
+                            .not_null()
+                            .comment("订阅源在hub上的topic".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("secret"))
+                            .string_len(64)
+                            .not_null()
+                            .comment("推送内容的签名密钥".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("state"))
+                            .string_len(16)
+                            .not_null()
+                            .comment("订阅状态".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("lease_seconds"))
+                            .integer()
+                            .null()
+                            .comment("租期(秒)".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("expired_at"))
+                            .date_time()
+                            .null()
+                            .comment("租期到期时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("last_pushed_at"))
+                            .date_time()
+                            .null()
+                            .comment("最近一次推送时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("created_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("创建时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("updated_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("更新时间".to_string()),
+                    )
+                    .comment("订阅源的WebSub推送订阅表".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_websub_subscription_expired_at")
+                    .table(Alias::new("websub_subscription"))
+                    .col(Alias::new("expired_at"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("websub_subscription"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
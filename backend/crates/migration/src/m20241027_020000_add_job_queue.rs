@@ -0,0 +1,145 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Alias::new("job_queue"))
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Alias::new("id"))
+                            .integer()
+                            .auto_increment()
+                            .primary_key()
+                            .not_null()
+                            .comment("主键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("kind"))
+                            .string_len(32)
+                            .not_null()
+                            .comment("任务类型".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("payload"))
+                            .text()
+                            .not_null()
+                            .comment("任务参数".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("dedup_key"))
+                            .string_len(128)
+                            .null()
+                            .comment("去重键".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("priority"))
+                            .integer()
+                            .not_null()
+                            .default(0)
+                            .comment("优先级".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("status"))
+                            .string_len(16)
+                            .not_null()
+                            .comment("任务状态".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("attempts"))
+                            .integer()
+                            .not_null()
+                            .default(0)
+                            .comment("已执行次数".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("max_attempts"))
+                            .integer()
+                            .not_null()
+                            .comment("最多执行次数".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("run_at"))
+                            .date_time()
+                            .not_null()
+                            .comment("最早执行时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("locked_by"))
+                            .string_len(64)
+                            .null()
+                            .comment("租用任务的worker".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("lease_until"))
+                            .date_time()
+                            .null()
+                            .comment("租期到期时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("last_error"))
+                            .text()
+                            .null()
+                            .comment("最近一次失败原因".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("finished_at"))
+                            .date_time()
+                            .null()
+                            .comment("完成时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("created_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("创建时间".to_string()),
+                    )
+                    .col(
+                        ColumnDef::new(Alias::new("updated_at"))
+                            .default(Expr::current_timestamp())
+                            .date_time()
+                            .comment("更新时间".to_string()),
+                    )
+                    .comment("任务队列表".to_string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_job_queue_status_run_at")
+                    .table(Alias::new("job_queue"))
+                    .col(Alias::new("status"))
+                    .col(Alias::new("run_at"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_job_queue_dedup_key")
+                    .table(Alias::new("job_queue"))
+                    .col(Alias::new("dedup_key"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(Alias::new("job_queue"))
+                    .if_exists()
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
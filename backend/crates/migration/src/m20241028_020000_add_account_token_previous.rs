@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("account_token"))
+                    .add_column(
+                        ColumnDef::new(Alias::new("previous_token"))
+                            .string_len(64)
+                            .null()
+                            .comment("上一个刷新令牌的 sha256, 用于发现令牌被重复使用".to_string()),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_account_token_previous_token")
+                    .table(Alias::new("account_token"))
+                    .col(Alias::new("previous_token"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_account_token_previous_token")
+                    .table(Alias::new("account_token"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("account_token"))
+                    .drop_column(Alias::new("previous_token"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::DbBackend;
+
+// mysql 的 text 列只能使用前缀索引
+const MYSQL_PREFIX_LEN: u32 = 255;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // 按规范化链接去重时使用
+        let mut index = Index::create()
+            .name("idx_feed_link_subscription_canonical")
+            .table(Alias::new("feed_link"))
+            .col(Alias::new("subscription_id"))
+            .to_owned();
+        match manager.get_database_backend() {
+            DbBackend::MySql => index.col((Alias::new("canonical_link"), MYSQL_PREFIX_LEN)),
+            _ => index.col(Alias::new("canonical_link")),
+        };
+        manager.create_index(index).await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_feed_link_subscription_canonical")
+                    .table(Alias::new("feed_link"))
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
 pub mod remove_links;
 pub mod schema;
 pub mod utils;
+pub mod worker;
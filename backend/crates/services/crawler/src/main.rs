@@ -1,374 +1,117 @@
-use crawler::remove_links::remove_expired_links;
 use crawler::utils::{load_categories_from_dir, load_subscriptions_from_dir};
+use crawler::worker::{Worker, KEEP_LINK_DAYS};
 
-use lib_core::error::ErrorInService;
-use lib_core::feed::schema::{
-    InsertSubscriptionRecordRequestBuilder, QueryPreferUpdateSubscriptionRequest,
-    SubscriptionWithLinksResp,
-};
-use lib_core::feed::{
-    CreateOrUpdateSubscriptionRequest, LinkController, SubscriptionBuildRecordStatus,
-    SubscriptionParseController,
-};
-use lib_core::feed::{SubscriptionController, SubscritionConfigController};
+use lib_core::auth::AccountController;
+use lib_core::feed::schema::SubscriptionParseResult;
+use lib_core::feed::{OpmlController, SubscriptionController, SubscriptionParseController};
+use lib_core::job::JobController;
+use lib_core::DBConnection;
 use lib_utils::Setting;
-use std::sync::{Arc, Mutex};
-use std::time::Duration;
-use tokio::time::{self, timeout};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing_subscriber::{
     filter::EnvFilter, layer::SubscriberExt, util::SubscriberInitExt, Registry,
 };
 
-use clap::Parser;
-use rand::Rng;
+use clap::{Args, Parser, Subcommand};
 
 #[derive(Parser, Clone)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
-    pub cfg_file: Option<String>,
+    /// 配置文件路径
+    #[arg(short, long, global = true, default_value = "fixture/config.toml")]
+    pub config: String,
+    /// 保存清理记录等数据的目录
+    #[arg(short, long, global = true, default_value = "data")]
+    pub workspace: PathBuf,
+    // 不指定子命令时, 等同于 run
+    #[command(subcommand)]
+    pub command: Option<Command>,
 }
 
-pub fn app() -> Cli {
-    Cli::parse()
+#[derive(Subcommand, Clone)]
+pub enum Command {
+    /// 常驻执行任务队列, 定时更新订阅源
+    Run {
+        /// 启动时不从目录加载分类和订阅源
+        #[arg(long)]
+        skip_seed: bool,
+        #[command(flatten)]
+        seed: SeedArgs,
+    },
+    /// 立即更新一个订阅源并保存新的文章
+    Refresh {
+        /// 订阅源的 id 或者地址
+        #[arg(short, long)]
+        subscription: String,
+    },
+    /// 拉取并解析订阅源, 只输出结果不保存
+    Fetch {
+        /// 订阅源地址
+        url: String,
+        /// 最多输出的文章数量
+        #[arg(short, long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// 从 OPML 文件为用户导入分类和订阅源
+    Import {
+        /// OPML 文件路径
+        file: String,
+        /// 导入到这个邮箱对应的用户
+        #[arg(short, long)]
+        account: String,
+    },
+    /// 导出用户的分类和订阅源到 OPML 文件
+    Export {
+        /// 输出的文件路径, 不指定时输出到标准输出
+        output: Option<String>,
+        /// 导出这个邮箱对应的用户的订阅
+        #[arg(short, long)]
+        account: String,
+    },
+    /// 清理过期的文章和已经完成的任务
+    Prune {
+        /// 清理多少天以前的文章
+        #[arg(long, default_value_t = KEEP_LINK_DAYS)]
+        older_than: i64,
+    },
+    /// 输出任务队列的统计
+    Stats,
+    /// 从目录加载分类和订阅源
+    Seed {
+        #[command(flatten)]
+        seed: SeedArgs,
+    },
 }
 
-pub struct Runner;
-
-impl Runner {
-    pub fn get_setting(&self, app: Cli) -> Setting {
-        match Setting::from_config(app.cfg_file) {
-            Ok(setting) => setting,
-            Err(e) => {
-                println!("配置文件解析失败:{}， 将使用默认配置运行", e);
-                Setting::default()
-            }
-        }
-    }
-
-    // 更新链接的元信息
-    pub async fn update_links_meta_job(
-        &self,
-        links: Vec<lib_core::feed::schema::LinkModel>,
-        setting: &Setting,
-    ) -> anyhow::Result<()> {
-        let js_server_host = match &setting.services.js_server_host {
-            Some(js_server_host) => js_server_host,
-            None => {
-                println!("summary_rss_link error:链接解析服务未就绪");
-                return Err(ErrorInService::Custom("链接解析服务未就绪".to_string()).into());
-            }
-        };
-        println!("链接解析服务:{}", js_server_host);
-        let ping_url = format!("{}/health/check", js_server_host);
-        let ping_result = reqwest::get(ping_url).await;
-        if ping_result.is_err() {
-            return Err(ErrorInService::Custom("链接解析服务未就就绪".to_string()).into());
-        }
-
-        let conn_origin = lib_core::get_db_conn(setting.database.uri.clone()).await;
-        let conn_origin_arc = Arc::new(tokio::sync::Mutex::new(conn_origin));
-
-        let links = links
-            .iter()
-            .map(|link| link.to_owned())
-            .collect::<Vec<lib_core::feed::schema::LinkModel>>();
-
-        let (link_update_tx, mut link_update_rx) = tokio::sync::mpsc::channel::<
-            Result<lib_core::feed::CreateOrUpdateRssLinkRequest, ErrorInService>,
-        >(2);
-
-        let conn_clone = Arc::clone(&conn_origin_arc);
-        let handler = tokio::spawn(async move {
-            let conn_temp = conn_clone.lock().await;
-            while let Some(recv) = link_update_rx.recv().await {
-                let result = recv;
-                match result {
-                    Ok(req) => {
-                        let _ = LinkController.insert_link(req, &conn_temp).await;
-                    }
-                    Err(e) => {
-                        tracing::error!("更新链接失败:{}", e);
-                    }
-                }
-            }
-        });
-
-        // define tasks for every subscription, which will send data to the channel, send subscription'title to the channel
-        let mut tasks = Vec::new();
-
-        let num_cpus = num_cpus::get();
-        let max_concurrent_tasks = num_cpus + 2;
-        let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrent_tasks));
-
-        for link in links {
-            let request_url = format!("{}/parse", js_server_host);
-            let tx = link_update_tx.clone();
-            let semaphore = Arc::clone(&semaphore);
-            let task = tokio::spawn(async move {
-                let _permit = semaphore.acquire().await;
-                let result =
-                    match crawler::utils::fetch_link_meta(link.link.clone(), request_url).await {
-                        Ok(meta) => {
-                            let mut update_link_req_builder =
-                            lib_core::feed::schema::CreateOrUpdateRssLinkRequestBuilder::default();
-                            update_link_req_builder.link(link.link);
-                            update_link_req_builder.title(link.title);
-                            if link.content.is_none() {
-                                if let Some(content) =
-                                    meta["content"].as_str().map(|content| content.to_string())
-                                {
-                                    update_link_req_builder.description(content.clone());
-
-                                    if let Ok(desc_pure_txt) =
-                                        lib_crawler::try_get_all_text_from_html_content(content)
-                                    {
-                                        update_link_req_builder.desc_pure_txt(desc_pure_txt);
-                                    }
-                                }
-                            }
-                            if let Some(lead_image_url) = meta["lead_image_url"]
-                                .as_str()
-                                .map(|lead_image_url| lead_image_url.to_string())
-                            {
-                                let image = lib_core::feed::schema::Image {
-                                    url: lead_image_url.clone(),
-                                    title: None,
-                                    link: None,
-                                    width: None,
-                                    height: None,
-                                    description: None,
-                                };
-                                update_link_req_builder.images(vec![image]);
-                            }
-                            // 如果没有 Description， 那么尝试使用 content
-
-                            update_link_req_builder.build()
-                        }
-                        Err(e) => Err(e),
-                    };
-                _ = tx.send(result).await;
-            });
-            tasks.push(task);
-        }
+#[derive(Args, Clone)]
+pub struct SeedArgs {
+    /// 分类定义所在的目录
+    #[arg(long, default_value = "fixture/feed/categories")]
+    pub categories: String,
+    /// 订阅源定义所在的目录
+    #[arg(long, default_value = "fixture/feed/subscriptions")]
+    pub subscriptions: String,
+}
 
-        // wait for all tasks to finish
-        for task in tasks {
-            task.await?;
+impl Default for SeedArgs {
+    fn default() -> Self {
+        Self {
+            categories: "fixture/feed/categories".to_string(),
+            subscriptions: "fixture/feed/subscriptions".to_string(),
         }
-        handler.abort_handle().abort();
-        Ok(())
     }
+}
 
-    /// Asynchronously updates the subscriptions based on the provided `Setting`.
-    ///
-    /// # Arguments
-    ///
-    /// * `setting` - The `Setting` configuration.
-    ///
-    /// # Returns
-    ///
-    /// A vector of `LinkModel` representing the updated links.
-    pub async fn update_subscriptions_job(
-        &self,
-        setting: &Setting,
-    ) -> anyhow::Result<Vec<lib_core::feed::schema::LinkModel>> {
-        let conn_origin = lib_core::get_db_conn(setting.database.uri.clone()).await;
-
-        // 查询需要更新的订阅源
-
-        let req = QueryPreferUpdateSubscriptionRequest::new(3u32);
-        let subs_config_controller = SubscritionConfigController;
-        let subscriptions = match subs_config_controller
-            .query_prefer_update_subscription(req, &conn_origin)
-            .await
-        {
-            Ok(subscriptions) => subscriptions,
-            Err(e) => {
-                anyhow::bail!("查询需要更新的订阅源失败:{}", e);
-            }
-        };
-
-        let update_subscription_ids = subscriptions
-            .iter()
-            .flat_map(|subscription| subscription.id)
-            .collect::<Vec<i64>>();
-
-        // define a safe variable to count the number of updated subscriptions and links
-        let inserted_links = Arc::new(tokio::sync::Mutex::new(
-            Vec::<lib_entity::feed_link::Model>::new(),
-        ));
-        let conn_origin_arc = Arc::new(tokio::sync::Mutex::new(conn_origin));
-        let updated_subscription_count = Arc::new(Mutex::new(0));
-
-        let update_subscription_failed_count = Arc::new(Mutex::new(0));
-        // define a mpsc channel
-        let (sub_update_tx, mut sub_update_rx) = tokio::sync::mpsc::channel::<(
-            CreateOrUpdateSubscriptionRequest,
-            Result<SubscriptionWithLinksResp, ErrorInService>,
-        )>(2);
-
-        // define a task to receive data from the channel
-        let updated_subscription_count_clone = Arc::clone(&updated_subscription_count);
-        let updated_subscription_failed_count_clone = Arc::clone(&update_subscription_failed_count);
-
-        let inserted_links_clone = Arc::clone(&inserted_links);
-        let conn_clone = Arc::clone(&conn_origin_arc);
-        let handler = tokio::spawn(async move {
-            let mut inserted_links_tmp = inserted_links_clone.lock().await;
-            let conn_temp = conn_clone.lock().await;
-            while let Some(recv) = sub_update_rx.recv().await {
-                let (subscription, sub) = recv;
-                // 如果订阅源更新失败，那么将更新状态记录到数据库
-                let sub = match sub {
-                    Ok(sub) => sub,
-                    Err(e) => {
-                        println!("更新订阅源失败:{}", e);
-                        if let Some(id) = subscription.id {
-                            if let Ok(req) = InsertSubscriptionRecordRequestBuilder::default()
-                                .subscription_id(id)
-                                .status(SubscriptionBuildRecordStatus::Faild)
-                                .build()
-                            {
-                                _ = subs_config_controller
-                                    .insert_subscription_update_record(req, &conn_temp)
-                                    .await;
-                            }
-                        }
-                        let lock = Arc::clone(&updated_subscription_failed_count_clone);
-                        let mut count = lock.lock().unwrap();
-                        *count += 1;
-                        continue;
-                    }
-                };
-                // 更新订阅源
-                let SubscriptionWithLinksResp {
-                    subscription: rss_subscription,
-                    links,
-                } = sub;
-                let language = match subscription.language {
-                    Some(language) => Some(language),
-                    None => rss_subscription.language.clone(),
-                };
-                // 首先更新订阅源部分，更新订阅源的最后更新时间
-                let mut update_subscription_req = rss_subscription;
-                update_subscription_req.last_build_date = Some(chrono::Utc::now().naive_utc());
-                // 如果没有 category_id，那么使用原来的 category_id
-
-                update_subscription_req.category_id = subscription.category_id;
-                update_subscription_req.language = language;
-                match SubscriptionController
-                    .insert_subscription(update_subscription_req, &conn_temp)
-                    .await
-                {
-                    Ok((_, _)) => {
-                        let lock = Arc::clone(&updated_subscription_count_clone);
-                        let mut count = lock.lock().unwrap();
-                        *count += 1;
-                    }
-                    Err(e) => {
-                        let lock = Arc::clone(&updated_subscription_failed_count_clone);
-                        let mut count = lock.lock().unwrap();
-                        *count += 1;
-                        tracing::error!("更新订阅源失败:{}", e);
-                        continue;
-                    }
-                }
-                // 尝试更新拉取到的每一条连接，这里的更新是指如果链接不存在，那么将链接插入到数据库中， 如果已经存在了，也不会有后续的操作
-                let all_link_count = links.len();
-
-                for link in links {
-                    let mut update_link_req = link;
-
-                    update_link_req.subscrption_id = subscription.id.expect("订阅源ID不能为空");
-                    match LinkController
-                        .insert_link(update_link_req, &conn_temp)
-                        .await
-                    {
-                        Ok((is_update, l)) => {
-                            if !is_update {
-                                inserted_links_tmp.push(l);
-                            }
-                        }
-                        Err(e) => {
-                            tracing::error!("更新链接失败:{}", e);
-                            continue;
-                        }
-                    }
-                }
-                // 如果新链接数量大于总链接数量的一半，那么认为更新订阅源成功
-                if inserted_links_tmp.len() > all_link_count / 3 {
-                    // 更新订阅源成功
-                    if let Some(id) = subscription.id {
-                        if let Ok(req) = InsertSubscriptionRecordRequestBuilder::default()
-                            .subscription_id(id)
-                            .status(SubscriptionBuildRecordStatus::Success)
-                            .build()
-                        {
-                            _ = subs_config_controller
-                                .insert_subscription_update_record(req, &conn_temp)
-                                .await;
-                        }
-                    }
-                }
-            }
-        });
-
-        // define tasks for every subscription, which will send data to the channel, send subscription'title to the channel
-        let mut tasks = Vec::new();
-
-        let num_cpus = num_cpus::get();
-        let max_concurrent_tasks = num_cpus + 2;
-        let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrent_tasks));
-
-        for subscription in subscriptions {
-            let tx = sub_update_tx.clone();
-            let semaphore = Arc::clone(&semaphore);
-            let task = tokio::spawn(async move {
-                let _permit = semaphore.acquire().await;
-                let result = SubscriptionParseController::parser_rss_from_url(
-                    subscription.clone().link.as_str(),
-                )
-                .await;
-                _ = tx.send((subscription.clone(), result)).await;
-            });
-            tasks.push(task);
-        }
-
-        // wait for all tasks to finish
-        for task in tasks {
-            task.await?;
-        }
-        handler.abort_handle().abort();
-        let inserted_link_ref = inserted_links.lock().await;
-        println!(
-            "更新订阅源成功:{} 更新订阅源失败:{} 更新链接成功:{}",
-            updated_subscription_count.lock().unwrap(),
-            update_subscription_failed_count.lock().unwrap(),
-            inserted_link_ref.len()
-        );
-        let conn_outside = conn_origin_arc.lock().await;
-        if let Err(err) = SubscritionConfigController
-            .update_subscription_config(update_subscription_ids.clone(), &conn_outside)
-            .await
-        {
-            println!("更新订阅源失败:{}", err);
-        }
-        // 返回所有新增的链接模型
-        let links = inserted_link_ref
-            .iter()
-            .map(|link| link.clone().into())
-            .collect();
-        Ok(links)
-    }
+pub fn app() -> Cli {
+    Cli::parse()
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let runner = Runner {};
-    let mut app = Cli::parse();
-    app.cfg_file = Some("fixture/config.toml".to_string());
+    let app = Cli::parse();
 
-    let setting = match Setting::from_config(app.clone().cfg_file) {
+    let setting = match Setting::from_config(Some(app.config.clone())) {
         Ok(setting) => setting,
         Err(e) => {
             println!("配置文件解析失败:{}， 将使用默认配置运行", e);
@@ -393,16 +136,126 @@ async fn main() -> anyhow::Result<()> {
         .with(formatting_layer)
         .init();
 
+    // 只解析订阅源时不需要连接数据库
+    if let Some(Command::Fetch { url, limit }) = &app.command {
+        return fetch(url, *limit).await;
+    }
+
     let conn = lib_core::get_db_conn(setting.database.uri.clone()).await;
+    let command = app.command.clone().unwrap_or(Command::Run {
+        skip_seed: false,
+        seed: SeedArgs::default(),
+    });
+
+    match command {
+        Command::Run { skip_seed, seed } => {
+            if !skip_seed {
+                load_seed(&seed, &conn).await;
+            }
+            // 持续执行任务队列中的任务, 退出后未完成的任务会在下次启动时继续执行
+            let worker = Arc::new(Worker::new(
+                &setting,
+                conn,
+                create_workspace(&app.workspace)?,
+            ));
+            worker.run().await
+        }
+        Command::Refresh { subscription } => {
+            let found = match subscription.parse::<i64>() {
+                Ok(id) => SubscriptionController.find_subscription(id, &conn).await?,
+                Err(_) => {
+                    SubscriptionController
+                        .find_subscription_by_link(&subscription, &conn)
+                        .await?
+                }
+            };
+            let Some(found) = found else {
+                anyhow::bail!("订阅源不存在:{}", subscription);
+            };
+            let worker = Worker::new(&setting, conn, create_workspace(&app.workspace)?);
+            let count = worker.refresh_feed(found.id).await?;
+            println!("更新完成, {} 新增文章:{}", found.title, count);
+            Ok(())
+        }
+        // 已经在连接数据库之前处理
+        Command::Fetch { .. } => Ok(()),
+        Command::Import { file, account } => {
+            let account_id = find_account_id(&account, &conn).await?;
+            let content = std::fs::read_to_string(&file)?;
+            let report = OpmlController
+                .import_opml(account_id, &content, &conn)
+                .await?;
+            println!(
+                "导入完成, 新建分类:{} 新建订阅源:{} 重复:{} 无效:{}",
+                report.created_categories,
+                report.created_subscriptions,
+                report.duplicates.len(),
+                report.invalid.len()
+            );
+            for invalid in report.invalid {
+                println!(
+                    "无效的条目: {} {} ({})",
+                    invalid.title,
+                    invalid.xml_url.unwrap_or_default(),
+                    invalid.reason
+                );
+            }
+            Ok(())
+        }
+        Command::Export { output, account } => {
+            let account_id = find_account_id(&account, &conn).await?;
+            let content = OpmlController.export_opml(account_id, &conn).await?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content)?;
+                    println!("已导出到:{}", path);
+                }
+                None => println!("{}", content),
+            }
+            Ok(())
+        }
+        Command::Prune { older_than } => {
+            let worker = Worker::new(&setting, conn, create_workspace(&app.workspace)?);
+            // 手动清理时忽略每月一次的限制
+            match worker.prune(older_than, true).await? {
+                Some(removed) => println!("已清理 {} 天以前的文章: {} 篇", older_than, removed),
+                None => println!("本月已经清理过文章, 没有执行清理"),
+            }
+            Ok(())
+        }
+        Command::Stats => {
+            let stats = JobController.stats(&conn).await?;
+            if stats.is_empty() {
+                println!("任务队列为空");
+            }
+            for item in stats {
+                println!(
+                    "{:<14} {:<10} {}",
+                    format!("{:?}", item.kind),
+                    format!("{:?}", item.status),
+                    item.count
+                );
+            }
+            Ok(())
+        }
+        Command::Seed { seed } => {
+            load_seed(&seed, &conn).await;
+            Ok(())
+        }
+    }
+}
 
-    let workspace = std::path::Path::new("data");
+fn create_workspace(workspace: &Path) -> anyhow::Result<PathBuf> {
     if !workspace.exists() {
-        std::fs::create_dir(workspace).unwrap();
+        std::fs::create_dir_all(workspace)?;
     }
-    println!("开始加载分类");
+    Ok(workspace.to_path_buf())
+}
 
-    let category_dir = "fixture/feed/categories";
-    let categories = match load_categories_from_dir(category_dir.to_string(), &conn).await {
+// 从目录加载分类和订阅源, 失败时只记录日志
+async fn load_seed(seed: &SeedArgs, conn: &DBConnection) {
+    tracing::info!("开始加载分类");
+    let categories = match load_categories_from_dir(seed.categories.clone(), conn).await {
         Ok(categories) => categories,
         Err(e) => {
             println!("加载分类失败:{}", e);
@@ -412,8 +265,7 @@ async fn main() -> anyhow::Result<()> {
     };
 
     tracing::info!("开始加载订阅源");
-    let subscription_dir = "fixture/feed/subscriptions";
-    match load_subscriptions_from_dir(subscription_dir.to_string(), categories, &conn).await {
+    match load_subscriptions_from_dir(seed.subscriptions.clone(), categories, conn).await {
         Ok(_) => {
             tracing::info!("加载订阅源成功");
         }
@@ -421,93 +273,111 @@ async fn main() -> anyhow::Result<()> {
             tracing::error!("加载订阅源失败:{}", e);
         }
     }
+}
 
-    let setting = runner.get_setting(app.clone());
-    // 开启一个定时任务，每隔一段时间更新一次订阅源
-    #[allow(clippy::manual_map)]
-    loop {
-        let min_sec = 60 * 10; // 最小秒数为1小时
-
-        let max_sec = 60 * 40; // 最大秒数为3小时
-
-        let sec = rand::thread_rng().gen_range(min_sec..=max_sec);
-
-        let before_jobs = chrono::Utc::now();
-        println!("现在是:{} 准备开始更新订阅源任务", before_jobs);
-
-        let six_month_ago = before_jobs - chrono::Duration::days(180);
-        _ = remove_expired_links(workspace, &six_month_ago.naive_utc(), &conn).await;
-
-        let links = runner.update_subscriptions_job(&setting).await?;
-        let afte_update_subscriptions_at = chrono::Utc::now();
-        let link_spent = afte_update_subscriptions_at - before_jobs;
-        let link_spent_sec = link_spent.num_seconds();
-        let local_date = chrono::Local::now();
-        println!(
-            "更新订阅源任务完成, 本次更新耗时:{}秒, 现在是:{}, 将在{}秒后再次更新订阅源",
-            link_spent_sec, local_date, sec
-        );
-        let interval = Duration::from_secs(sec); // 每隔一小时更新一次
-
-        // 过滤掉
-        let mut filted_links = links
-            .iter()
-            .filter(|link| {
-                link.images
-                    .as_ref()
-                    .map_or(true, |images| images.is_empty())
-            })
-            .cloned()
-            .collect::<Vec<lib_core::feed::schema::LinkModel>>();
-        // 根据 `published_at` 排序
-        filted_links.sort_by(|a, b| {
-            // compare published_at
-            let a = a
-                .published_at
-                .unwrap_or_else(|| chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc());
-            let b = b
-                .published_at
-                .unwrap_or_else(|| chrono::DateTime::from_timestamp(0, 0).unwrap().naive_utc());
-
-            a.partial_cmp(&b).unwrap_or(std::cmp::Ordering::Equal)
-        });
-        // 只根据 1s 一个链接的速度计算，空余时间可以接受的更新数量
-        let trimmed_links: Vec<lib_core::feed::schema::LinkModel> = filted_links;
-        println!(
-            "准备更新链接信息任务, 本次更新链接数量:{}",
-            trimmed_links.len()
-        );
-        // 更新链接信息，这里限定一个时间，如果超过这个时间，那么将会终止任务
-        _ = timeout(
-            interval.saturating_sub(Duration::from_secs(60)),
-            runner.update_links_meta_job(trimmed_links, &setting),
-        )
-        .await;
-
-        let after_update_links_at = chrono::Utc::now();
-        let update_links_spent_sec =
-            after_update_links_at.timestamp() - afte_update_subscriptions_at.timestamp();
-
-        // 这里睡眠的时间需要减少更新链接所消耗的时间了
-        let mut real_interval = interval.as_secs() as i64 - update_links_spent_sec;
-        if real_interval < 10 {
-            real_interval = 10;
+// 拉取并输出订阅源的内容, 用于排查单个订阅源的问题
+async fn fetch(url: &str, limit: usize) -> anyhow::Result<()> {
+    let channel = match SubscriptionParseController::parser_rss_from_url(url, None).await? {
+        SubscriptionParseResult::Modified(channel) => *channel,
+        SubscriptionParseResult::Unchanged => {
+            println!("订阅源没有变化");
+            return Ok(());
         }
+    };
+    let subscription = &channel.subscription;
+    println!("标题: {}", subscription.title);
+    println!(
+        "网站: {}",
+        subscription.site_link.clone().unwrap_or_default()
+    );
+    println!(
+        "语言: {}",
+        subscription.language.clone().unwrap_or_default()
+    );
+    println!("更新时间: {:?}", subscription.last_build_date);
+    if let Some(hub) = &channel.websub {
+        println!("WebSub: {} ({})", hub.hub, hub.topic);
+    }
+    println!("文章数量: {}", channel.links.len());
+    for link in channel.links.iter().take(limit) {
         println!(
-            "更新链接信息任务完成, 本次更新耗时:{}秒, 现在是:{}, 将休眠:{}秒后再次更新",
-            update_links_spent_sec,
-            chrono::Local::now(),
-            real_interval
+            "- {} {}\n  {}",
+            link.published_at
+                .map(|t| t.to_string())
+                .unwrap_or_else(|| "-".to_string()),
+            link.title,
+            link.link
         );
+    }
+    Ok(())
+}
 
-        time::sleep(Duration::from_secs(real_interval as u64)).await;
+// 根据邮箱查找用户 id
+async fn find_account_id(email: &str, conn: &DBConnection) -> anyhow::Result<i64> {
+    match AccountController.account_by_email(email, conn).await? {
+        Some(account) => Ok(account.id),
+        None => anyhow::bail!("用户不存在:{}", email),
     }
 }
 
 // Test
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use super::{Cli, Command};
+    use clap::Parser;
+    use lib_core::feed::schema::SubscriptionParseResult;
+    use lib_core::feed::SubscriptionParseController;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_parse_cli() {
+        let cli = Cli::try_parse_from(["crawler"]).unwrap();
+        assert_eq!(cli.config, "fixture/config.toml");
+        assert!(cli.command.is_none());
+
+        let cli = Cli::try_parse_from([
+            "crawler",
+            "refresh",
+            "--subscription",
+            "https://example.com/feed.xml",
+            "--config",
+            "config.toml",
+        ])
+        .unwrap();
+        assert_eq!(cli.config, "config.toml");
+        assert!(matches!(
+            cli.command,
+            Some(Command::Refresh { subscription }) if subscription == "https://example.com/feed.xml"
+        ));
+
+        let cli = Cli::try_parse_from(["crawler", "prune", "--older-than", "30"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Prune { older_than: 30 })
+        ));
+
+        let cli = Cli::try_parse_from(["crawler", "-w", "/tmp/data", "seed", "--categories", "c"])
+            .unwrap();
+        assert_eq!(cli.workspace.to_str(), Some("/tmp/data"));
+        match cli.command {
+            Some(Command::Seed { seed }) => {
+                assert_eq!(seed.categories, "c");
+                assert_eq!(seed.subscriptions, "fixture/feed/subscriptions");
+            }
+            _ => panic!("expected seed command"),
+        }
+
+        let cli = Cli::try_parse_from(["crawler", "import", "feeds.opml", "-a", "a@example.com"])
+            .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Import { file, account }) if file == "feeds.opml" && account == "a@example.com"
+        ));
+        // 导入导出需要指定用户
+        assert!(Cli::try_parse_from(["crawler", "export"]).is_err());
+
+        assert!(Cli::try_parse_from(["crawler", "refresh"]).is_err());
+    }
 
     #[tokio::test]
     async fn test_run() {
@@ -515,17 +385,22 @@ mod tests {
         // let url = "https://feeds.bbci.co.uk/sport/football/rss.xml";
         let url = "https://cnnespanol.cnn.com/feed/";
 
-        let channel = SubscriptionParseController::parser_rss_from_url(url).await;
+        let channel = SubscriptionParseController::parser_rss_from_url(url, None).await;
         let channel = match channel {
-            Ok(channel) => channel,
+            Ok(SubscriptionParseResult::Modified(channel)) => *channel,
+            Ok(SubscriptionParseResult::Unchanged) => {
+                println!("订阅源没有变化");
+                return;
+            }
             Err(e) => {
                 println!("解析订阅源失败:{}", e);
                 return;
             }
         };
-        let SubscriptionWithLinksResp {
+        let lib_core::feed::schema::SubscriptionWithLinksResp {
             subscription: rss_subscription,
             links,
+            ..
         } = channel;
         println!("subscription.title :{:?}", rss_subscription.title);
         println!(
@@ -46,31 +46,35 @@ async fn append_removed_links_records(
     workspace: &Path,
     record: RemoveExpiredLinkRecord,
 ) -> anyhow::Result<()> {
-    let mut records = match load_removed_links_records(workspace).await {
-        Ok(records) => records,
-        Err(_) => vec![],
-    };
+    let mut records = load_removed_links_records(workspace)
+        .await
+        .unwrap_or_default();
     records.push(record);
     save_removed_links_records(workspace, records).await?;
     Ok(())
 }
 
+/// 删除过期的链接, 返回删除的数量
+///
+/// 定期任务每月只清理一次, 本月已经清理过时返回 None; `force` 为 true 时忽略这个限制
 pub async fn remove_expired_links(
     workspace: &Path,
     expired_at: &NaiveDateTime,
+    force: bool,
     conn: &DBConnection,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Option<u64>> {
     let now = chrono::Local::now().naive_local();
-    let records = match load_removed_links_records(workspace).await {
-        Ok(records) => records,
-        Err(_) => vec![],
-    };
+    let records = load_removed_links_records(workspace)
+        .await
+        .unwrap_or_default();
 
     // 如果本月已经移除过链接，则不再移除
-    if records.iter().any(|record| {
-        format!("{}", record.removed_at.format("%Y-%m")) == format!("{}", now.format("%Y-%m"))
-    }) {
-        return Ok(());
+    if !force
+        && records.iter().any(|record| {
+            format!("{}", record.removed_at.format("%Y-%m")) == format!("{}", now.format("%Y-%m"))
+        })
+    {
+        return Ok(None);
     }
 
     let removed_count = LinkController
@@ -81,5 +85,5 @@ pub async fn remove_expired_links(
     // 保存已经移除的链接记录
     let record = RemoveExpiredLinkRecord::new(now, Some(removed_count as i64));
     append_removed_links_records(workspace, record).await?;
-    Ok(())
+    Ok(Some(removed_count))
 }
@@ -11,7 +11,10 @@ use lib_core::{
     },
     DBConnection,
 };
+use lib_crawler::{HostScheduler, HostSchedulerOption};
+use lib_utils::Setting;
 use serde_json::json;
+use std::time::Duration;
 
 pub async fn load_categories_from_dir(
     category_dir: String,
@@ -39,7 +42,7 @@ pub async fn load_categories_from_dir(
 
     let mut categories: Vec<Vec<Category>> = Vec::new();
     for f in categories_files {
-        let category_json = match load_categories(f, &conn).await {
+        let category_json = match load_categories(f, conn).await {
             Ok(category_json) => category_json,
             Err(e) => {
                 println!("读取json文件失败:{}", e);
@@ -55,7 +58,7 @@ pub async fn load_categories_from_dir(
         .cloned()
         .collect::<Vec<Category>>();
 
-    return Ok(flatten_categories);
+    Ok(flatten_categories)
 }
 
 pub async fn load_categories(
@@ -332,6 +335,20 @@ pub async fn load_subscriptions(
     Ok(())
 }
 
+// 根据配置构建按站点调度的请求器
+pub fn build_host_scheduler(setting: &Setting) -> HostScheduler {
+    let crawler = &setting.crawler;
+    let option = HostSchedulerOption {
+        max_concurrency: crawler
+            .max_concurrency
+            .unwrap_or_else(|| num_cpus::get() + 2),
+        per_host_concurrency: crawler.per_host_concurrency,
+        per_host_delay: Duration::from_millis(crawler.per_host_delay_ms),
+        max_backoff: Duration::from_secs(crawler.max_backoff_secs),
+    };
+    HostScheduler::new(option)
+}
+
 pub async fn fetch_link_meta(
     url: String,
     request_url: String,
@@ -0,0 +1,464 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use lib_core::error::ErrorInService;
+use lib_core::feed::schema::{
+    FeedValidators, Image, InsertSubscriptionRecordRequestBuilder, LinkSummaryRequest,
+    QueryPreferUpdateSubscriptionRequest, QuerySubscriptionConfigRequest, SubscriptionParseResult,
+    UpdateLinkContentRequestBuilder, WebSubOption,
+};
+use lib_core::feed::{
+    LinkController, LinkSummaryController, SubscriptionBuildRecordStatus, SubscriptionController,
+    SubscriptionParseController, SubscritionConfigController, WebSubController,
+};
+use lib_core::job::schema::{EnqueueJobRequest, JobPayload, LeasedJob};
+use lib_core::job::{JobController, JobStatus};
+use lib_core::DBConnection;
+use lib_crawler::{FetchError, HostScheduler, RobotsCache, RobotsVerdict};
+use lib_openai::OpenAIConfig;
+use lib_utils::Setting;
+use tokio::task::JoinSet;
+
+use crate::remove_links::remove_expired_links;
+
+// 把需要更新的订阅源放入队列的间隔
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+// 队列为空时的等待时间
+const IDLE_INTERVAL: Duration = Duration::from_secs(5);
+// 文章保留的天数
+pub const KEEP_LINK_DAYS: i64 = 180;
+// 完成的任务保留的天数
+const KEEP_JOB_DAYS: i64 = 7;
+
+/// 从任务队列中取出任务并执行的 worker
+///
+/// 任务的状态都保存在数据库中, worker 退出后未完成的任务在租期过后会被重新执行
+pub struct Worker {
+    // 租用任务时使用的标识
+    id: String,
+    setting: Setting,
+    conn: DBConnection,
+    // 保存清理记录的目录
+    workspace: PathBuf,
+    // 按站点调度请求, 在多个任务之间共享, 失败的站点会持续退避
+    scheduler: HostScheduler,
+    // robots.txt 缓存, 关闭 respect_robots 时为空
+    robots: Option<RobotsCache>,
+}
+
+impl Worker {
+    pub fn new(setting: &Setting, conn: DBConnection, workspace: PathBuf) -> Self {
+        let robots = match setting.crawler.respect_robots {
+            true => Some(RobotsCache::new(setting.crawler.user_agent.clone())),
+            false => None,
+        };
+        Self {
+            id: format!("crawler-{}-{}", std::process::id(), rand::random::<u16>()),
+            setting: setting.clone(),
+            conn,
+            workspace,
+            scheduler: crate::utils::build_host_scheduler(setting),
+            robots,
+        }
+    }
+
+    pub fn conn(&self) -> &DBConnection {
+        &self.conn
+    }
+
+    // 每批最多租用的任务数量
+    fn batch_size(&self) -> u64 {
+        self.setting
+            .crawler
+            .max_concurrency
+            .unwrap_or_else(|| num_cpus::get() + 2) as u64
+    }
+
+    /// 常驻执行, 定期把需要更新的订阅源放入队列, 收到 ctrl-c 后执行完当前的任务再退出
+    pub async fn run(self: Arc<Self>) -> anyhow::Result<()> {
+        let stopping = Arc::new(AtomicBool::new(false));
+        let stopping_clone = Arc::clone(&stopping);
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                tracing::info!("收到退出信号, 执行完当前的任务后退出");
+                stopping_clone.store(true, Ordering::SeqCst);
+            }
+        });
+
+        let mut last_schedule: Option<tokio::time::Instant> = None;
+        while !stopping.load(Ordering::SeqCst) {
+            if last_schedule.is_none_or(|at| at.elapsed() >= SCHEDULE_INTERVAL) {
+                if let Err(e) = self.schedule().await {
+                    tracing::error!("调度任务失败:{}", e);
+                }
+                last_schedule = Some(tokio::time::Instant::now());
+            }
+            // 单个任务的失败在任务中处理, 这里的错误多是数据库暂时不可用, 等待后重试
+            match self.clone().run_once().await {
+                Ok(0) => tokio::time::sleep(IDLE_INTERVAL).await,
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::error!("执行任务失败:{}", e);
+                    tokio::time::sleep(IDLE_INTERVAL).await;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 把需要更新的订阅源和定期的清理任务放入队列, 返回新加入的任务数量
+    pub async fn schedule(&self) -> anyhow::Result<usize> {
+        // 续订即将到期的 WebSub 推送
+        if let Some(option) = WebSubOption::from_setting(&self.setting.websub) {
+            match WebSubController.renew_leases(&option, &self.conn).await {
+                Ok(renewed) => tracing::info!("续订 WebSub 推送:{}", renewed),
+                Err(e) => tracing::error!("续订 WebSub 推送失败:{}", e),
+            }
+        }
+
+        let req = QueryPreferUpdateSubscriptionRequest::new(3u32);
+        let subscriptions = SubscritionConfigController
+            .query_prefer_update_subscription(req, &self.conn)
+            .await?;
+        let mut created = 0;
+        for subscription_id in subscriptions.iter().flat_map(|s| s.id) {
+            if self
+                .enqueue(JobPayload::FeedRefresh { subscription_id })
+                .await?
+            {
+                created += 1;
+            }
+        }
+
+        // 每天清理一次过期的文章和任务
+        let tomorrow = chrono::Utc::now().naive_utc() + chrono::Duration::days(1);
+        let req = EnqueueJobRequest::new(JobPayload::Prune {
+            keep_days: KEEP_LINK_DAYS,
+        })
+        .with_run_at(tomorrow);
+        if JobController.enqueue(req, &self.conn).await?.0 {
+            created += 1;
+        }
+        tracing::info!("加入任务队列:{}", created);
+        Ok(created)
+    }
+
+    /// 租用一批任务并发执行, 返回执行的任务数量
+    pub async fn run_once(self: Arc<Self>) -> anyhow::Result<usize> {
+        let jobs = JobController
+            .lease(
+                &self.id,
+                self.batch_size(),
+                self.setting.crawler.job_lease_secs,
+                &self.conn,
+            )
+            .await?;
+        let count = jobs.len();
+        let mut tasks = JoinSet::new();
+        for job in jobs {
+            let worker = Arc::clone(&self);
+            tasks.spawn(async move { worker.execute(job).await });
+        }
+        while let Some(result) = tasks.join_next().await {
+            if let Err(e) = result {
+                tracing::error!("任务异常退出:{}", e);
+            }
+        }
+        Ok(count)
+    }
+
+    // 执行任务并记录结果, 失败的任务按照退避时间重试
+    async fn execute(&self, job: LeasedJob) {
+        let result = self.handle(&job.payload).await;
+        let recorded = match &result {
+            Ok(_) => JobController
+                .complete(job.id, &self.id, &self.conn)
+                .await
+                .map(|_| ()),
+            Err(e) => JobController
+                .fail(job.id, &self.id, &e.to_string(), &self.conn)
+                .await
+                .map(|status| {
+                    if status == Some(JobStatus::Dead) {
+                        tracing::error!("任务重试次数用完:{:?} {}", job.payload, e);
+                    }
+                }),
+        };
+        if let Err(e) = recorded {
+            tracing::error!("保存任务状态失败:{} {}", job.id, e);
+        }
+    }
+
+    /// 执行单个任务
+    pub async fn handle(&self, payload: &JobPayload) -> Result<(), ErrorInService> {
+        match payload {
+            JobPayload::FeedRefresh { subscription_id } => {
+                self.refresh_feed(*subscription_id).await.map(|_| ())
+            }
+            JobPayload::LinkMeta { link_id } => self.enrich_link(*link_id).await,
+            JobPayload::LinkSummary { link_id } => self.summarize_link(*link_id).await,
+            JobPayload::Prune { keep_days } => self.prune(*keep_days, false).await.map(|_| ()),
+        }
+    }
+
+    async fn enqueue(&self, payload: JobPayload) -> Result<bool, ErrorInService> {
+        let (created, _) = JobController
+            .enqueue(EnqueueJobRequest::new(payload), &self.conn)
+            .await?;
+        Ok(created)
+    }
+
+    // 记录订阅源的更新结果, 记录失败不影响任务
+    async fn record(
+        &self,
+        subscription_id: i64,
+        status: SubscriptionBuildRecordStatus,
+        error: Option<&ErrorInService>,
+        remark: Option<&str>,
+    ) {
+        let mut req = InsertSubscriptionRecordRequestBuilder::default();
+        req.subscription_id(subscription_id).status(status);
+        if let Some(e) = error {
+            req.reason(e.kind()).remark(e.to_string());
+        }
+        if let Some(remark) = remark {
+            req.remark(remark);
+        }
+        if let Ok(req) = req.build() {
+            _ = SubscritionConfigController
+                .insert_subscription_update_record(req, &self.conn)
+                .await;
+        }
+    }
+
+    /// 拉取订阅源并写入新的文章, 返回新增文章的数量
+    pub async fn refresh_feed(&self, subscription_id: i64) -> Result<usize, ErrorInService> {
+        let Some(subscription) = SubscriptionController
+            .find_subscription(subscription_id, &self.conn)
+            .await?
+        else {
+            // 订阅源已经被删除
+            return Ok(0);
+        };
+        let subscription: lib_core::feed::CreateOrUpdateSubscriptionRequest = subscription.into();
+
+        // 上次拉取保存的 ETag / Last-Modified, 用于发起条件请求
+        let config_req = QuerySubscriptionConfigRequest::new(Some(vec![subscription_id]));
+        let validators = SubscritionConfigController
+            .query_subscription_config(config_req, &self.conn)
+            .await?
+            .into_iter()
+            .next()
+            .map(|config| FeedValidators {
+                etag: config.etag,
+                last_modified: config.last_modified,
+            });
+
+        let permit = self.scheduler.acquire(&subscription.link).await;
+        let result = SubscriptionParseController::parser_rss_from_url(
+            subscription.link.as_str(),
+            validators.as_ref(),
+        )
+        .await;
+        match &result {
+            // 解析失败不是站点的问题, 不需要退避
+            Err(ErrorInService::Fetch(e)) if !matches!(e, FetchError::Parse(_)) => {
+                permit.failed_with(e)
+            }
+            _ => permit.succeeded(),
+        }
+        let resp = match result {
+            Ok(SubscriptionParseResult::Modified(resp)) => *resp,
+            // 订阅源没有变化, 记录为一次成功的更新
+            Ok(SubscriptionParseResult::Unchanged) => {
+                self.record(
+                    subscription_id,
+                    SubscriptionBuildRecordStatus::Success,
+                    None,
+                    Some("not modified"),
+                )
+                .await;
+                return Ok(0);
+            }
+            Err(e) => {
+                self.record(
+                    subscription_id,
+                    SubscriptionBuildRecordStatus::Faild,
+                    Some(&e),
+                    None,
+                )
+                .await;
+                return Err(e);
+            }
+        };
+
+        // 订阅源声明了 hub 时订阅推送, 不再声明时取消
+        if let Some(option) = WebSubOption::from_setting(&self.setting.websub) {
+            let result = match resp.websub.clone() {
+                Some(hub) => WebSubController
+                    .request_subscription(subscription_id, hub, &option, &self.conn)
+                    .await
+                    .map(|_| ()),
+                None => {
+                    WebSubController
+                        .unsubscribe(subscription_id, &option, &self.conn)
+                        .await
+                }
+            };
+            if let Err(e) = result {
+                tracing::warn!("订阅 WebSub 失败:{} {}", subscription.link, e);
+            }
+        }
+
+        let links =
+            SubscriptionParseController::apply_parse_result(&subscription, resp, &self.conn)
+                .await?;
+        for link in links.iter() {
+            // 没有图片的文章需要补全正文和图片
+            let has_images = link
+                .images
+                .as_ref()
+                .and_then(|images| images.as_array())
+                .is_some_and(|images| !images.is_empty());
+            if !has_images {
+                self.enqueue(JobPayload::LinkMeta { link_id: link.id })
+                    .await?;
+            }
+            if self.setting.crawler.auto_summary && self.setting.openai.api_key.is_some() {
+                self.enqueue(JobPayload::LinkSummary { link_id: link.id })
+                    .await?;
+            }
+        }
+        SubscritionConfigController
+            .update_subscription_config(vec![subscription_id], &self.conn)
+            .await?;
+        Ok(links.len())
+    }
+
+    /// 提取文章页面的正文和图片, 提取失败时使用配置的解析服务
+    pub async fn enrich_link(&self, link_id: i64) -> Result<(), ErrorInService> {
+        let Some(link) = LinkController.find_link(link_id, &self.conn).await? else {
+            return Ok(());
+        };
+        if let Some(robots) = &self.robots {
+            match robots.check(&link.link).await {
+                RobotsVerdict::Disallowed { rule } => {
+                    tracing::info!("跳过链接 {}: robots.txt 不允许抓取 ({})", link.link, rule);
+                    return Ok(());
+                }
+                RobotsVerdict::Allowed {
+                    crawl_delay: Some(delay),
+                } => self.scheduler.set_host_delay(&link.link, delay),
+                RobotsVerdict::Allowed { crawl_delay: None } => {}
+            }
+        }
+        // 按文章的站点调度
+        let permit = self.scheduler.acquire(&link.link).await;
+        let (content, lead_image_url) = match lib_crawler::fetch_article(
+            &link.link,
+            Some(self.setting.crawler.user_agent.clone()),
+        )
+        .await
+        {
+            Ok(article) => {
+                permit.succeeded();
+                (Some(article.content), article.lead_image_url)
+            }
+            Err(e) => {
+                let Some(js_server_host) = self.setting.services.js_server_host.as_ref() else {
+                    permit.failed_with(&e);
+                    return Err(e.into());
+                };
+                tracing::warn!("提取正文失败, 使用解析服务:{} {}", link.link, e);
+                let request_url = format!("{}/parse", js_server_host);
+                match crate::utils::fetch_link_meta(link.link.clone(), request_url).await {
+                    Ok(meta) => {
+                        permit.succeeded();
+                        (
+                            meta["content"].as_str().map(|c| c.to_string()),
+                            meta["lead_image_url"].as_str().map(|c| c.to_string()),
+                        )
+                    }
+                    Err(fallback_err) => {
+                        permit.failed_with(&e);
+                        return Err(fallback_err);
+                    }
+                }
+            }
+        };
+
+        // 只修改正文和图片, 订阅源中已有的正文不会被覆盖
+        let mut req = UpdateLinkContentRequestBuilder::default();
+        req.id(link.id);
+        if let Some(content) = content {
+            req.description(content);
+        }
+        if let Some(lead_image_url) = lead_image_url {
+            req.images(vec![Image {
+                url: lead_image_url,
+                title: None,
+                link: None,
+                width: None,
+                height: None,
+                description: None,
+            }]);
+        }
+        LinkController
+            .update_link_content(req.build()?, &self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 为文章生成总结, 已经有总结时跳过
+    pub async fn summarize_link(&self, link_id: i64) -> Result<(), ErrorInService> {
+        let Some(api_key) = self.setting.openai.api_key.clone() else {
+            return Err(ErrorInService::Custom("OpenAI API Key 未配置".to_string()));
+        };
+        let Some(link) = LinkController.find_link(link_id, &self.conn).await? else {
+            return Ok(());
+        };
+        let content = link
+            .desc_pure_txt
+            .clone()
+            .filter(|c| !c.trim().is_empty())
+            .or(link.description.clone());
+        if content.as_deref().unwrap_or_default().trim().is_empty() {
+            // 没有正文时无法总结, 不需要重试
+            return Ok(());
+        }
+        let mut config = OpenAIConfig::default().with_api_key(api_key);
+        if let Some(api_base) = self.setting.openai.api_base.clone() {
+            config = config.with_api_base(api_base);
+        }
+        let req = LinkSummaryRequest {
+            link_url: link.link,
+            content,
+        };
+        LinkSummaryController
+            .insert_link_summary(req, config, &self.conn)
+            .await?;
+        Ok(())
+    }
+
+    /// 清理过期的文章和已经完成的任务, 返回删除的文章数量
+    ///
+    /// 定期任务每月只清理一次文章, 已经清理过时返回 None; `force` 为 true 时总是清理
+    pub async fn prune(&self, keep_days: i64, force: bool) -> Result<Option<u64>, ErrorInService> {
+        let now = chrono::Utc::now();
+        let expired_at = now - chrono::Duration::days(keep_days);
+        let removed =
+            remove_expired_links(&self.workspace, &expired_at.naive_utc(), force, &self.conn)
+                .await
+                .map_err(|e| ErrorInService::Custom(format!("清理过期文章失败:{}", e)))?;
+        let purged = JobController
+            .purge_finished(
+                (now - chrono::Duration::days(KEEP_JOB_DAYS)).naive_utc(),
+                &self.conn,
+            )
+            .await?;
+        tracing::info!("清理完成的任务:{}", purged);
+        Ok(removed)
+    }
+}
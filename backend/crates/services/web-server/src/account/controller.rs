@@ -1,18 +1,30 @@
 use crate::{
-    account, api_error::APIError, middlewares::auth_claim::ecode_to_jwt,
-    middlewares::auth_claim::AuthClaims, response::APIResponse, AppState,
+    api_error::APIError,
+    middlewares::{
+        auth_claim::{revoke_tokens, AuthClaims},
+        role::require_role,
+    },
+    response::APIResponse,
+    AppState,
 };
 use axum::{
+    middleware::from_fn_with_state,
     routing::{get, post},
     Router,
 };
 use axum::{Extension, Json};
 use axum_extra::routing::RouterExt;
-use chrono::{DateTime, NaiveDateTime};
+use chrono::NaiveDateTime;
 use lib_core::auth::{
-    schema::{AccountModel, LoginAccountRequest, LoginAccountResponse, RegisterAccountRequest},
-    AccountController,
+    schema::{
+        AccessToken, AccountModel, ApiKeyModel, CreateApiKeyRequest, CreateApiKeyResponse,
+        LoginAccountRequest, LoginAccountResponse, RefreshTokenRequest, RegisterAccountRequest,
+        RevokeApiKeyRequest, UpdateAccountRoleRequest, UpdateFeverPasswordRequest,
+    },
+    AccountController, AccountTokenController, ApiKeyController,
 };
+use lib_entity::account::AccountRole;
+use lib_utils::password::random_hex;
 
 use std::sync::Arc;
 
@@ -32,6 +44,41 @@ pub(crate) async fn register_account(
         .with_data(true))
 }
 
+// 生成新的访问令牌标识和过期时间
+fn new_access_token(app: &AppState) -> AccessToken {
+    let expired_at = chrono::Utc::now() + chrono::Duration::seconds(app.setting.jwt.exp);
+    AccessToken {
+        jti: random_hex(16),
+        expired_at: expired_at.naive_utc(),
+    }
+}
+
+// 刷新令牌的过期时间
+fn refresh_expired_at(app: &AppState) -> NaiveDateTime {
+    (chrono::Utc::now() + chrono::Duration::seconds(app.setting.jwt.refresh_exp)).naive_utc()
+}
+
+// 签发访问令牌, sub 为用户 id, jti 为随机的令牌标识
+fn sign_access_token(
+    app: &AppState,
+    account_id: i64,
+    access: &AccessToken,
+) -> Result<String, APIError> {
+    let now = chrono::Utc::now().timestamp() as usize;
+    let claims = AuthClaims::new(
+        None,
+        Some(account_id.to_string()),
+        Some(vec!["users".to_string()]),
+        access.expired_at.and_utc().timestamp() as usize,
+        now,
+        now,
+        access.jti.clone(),
+    );
+    claims
+        .encode(app.setting.jwt.secret.as_bytes())
+        .ok_or(APIError::Internal("jwt error".to_string()))
+}
+
 // login account
 pub async fn login_account(
     app: Extension<Arc<AppState>>,
@@ -39,34 +86,80 @@ pub async fn login_account(
 ) -> Result<APIResponse<LoginAccountResponse>, APIError> {
     let conn = &app.pool;
     let account_controller = AccountController;
-    let login_account = account_controller.login_account(req, conn).await?;
-    tracing::info!("login account: {:?}", login_account);
-    let now = chrono::Utc::now();
-    let one_month_after = now + chrono::Duration::days(30);
+    let account = account_controller.login_account(req, conn).await?;
+    tracing::info!("login account: {:?}", account.id);
 
-    let claims = AuthClaims::new(
-        None,
-        Some("auth".to_string()),
-        None,
-        one_month_after.timestamp() as usize,
-        now.timestamp() as usize,
-        now.timestamp() as usize,
-        login_account.token,
-    );
-    let jwt = ecode_to_jwt(&claims, app.setting.jwt.secret.as_bytes());
-    let jwt_resp = match jwt {
-        Some(j) => j,
-        None => return Err(APIError::Internal("jwt error".to_string())),
+    let access = new_access_token(&app);
+    let refresh_token = AccountTokenController
+        .create_session(account.id, &access, refresh_expired_at(&app), conn)
+        .await?;
+    let new_resp = LoginAccountResponse {
+        token: sign_access_token(&app, account.id, &access)?,
+        refresh_token,
+        expires_in: app.setting.jwt.exp,
+        account,
     };
+    Ok(APIResponse::<LoginAccountResponse>::new()
+        .with_code(200_i32)
+        .with_data(new_resp))
+}
+
+// 使用刷新令牌换取新的令牌, 旧的访问令牌立即失效
+pub async fn refresh_token(
+    app: Extension<Arc<AppState>>,
+    Json(req): Json<RefreshTokenRequest>,
+) -> Result<APIResponse<LoginAccountResponse>, APIError> {
+    let conn = &app.pool;
+    let access = new_access_token(&app);
+    let refreshed = AccountTokenController
+        .refresh_session(&req.refresh_token, &access, refresh_expired_at(&app), conn)
+        .await?;
+    revoke_tokens(&[refreshed.revoked]);
+    let account = AccountController
+        .account_info(refreshed.account_id, conn)
+        .await?
+        .ok_or(APIError::Toast("用户不存在".to_string()))?;
     let new_resp = LoginAccountResponse {
-        token: jwt_resp,
-        account: login_account.account,
+        token: sign_access_token(&app, account.id, &access)?,
+        refresh_token: refreshed.refresh_token,
+        expires_in: app.setting.jwt.exp,
+        account,
     };
     Ok(APIResponse::<LoginAccountResponse>::new()
         .with_code(200_i32)
         .with_data(new_resp))
 }
 
+// 退出登录
+pub async fn logout(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+) -> Result<APIResponse<bool>, APIError> {
+    claims.require_session()?;
+    let revoked = AccountTokenController
+        .revoke_session(claims.account_id()?, claims.access_token(), &app.pool)
+        .await?;
+    revoke_tokens(&revoked);
+    Ok(APIResponse::<bool>::new()
+        .with_code(200_i32)
+        .with_data(true))
+}
+
+// 退出所有设备
+pub async fn logout_all(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+) -> Result<APIResponse<bool>, APIError> {
+    claims.require_session()?;
+    let revoked = AccountTokenController
+        .revoke_all_sessions(claims.account_id()?, claims.access_token(), &app.pool)
+        .await?;
+    revoke_tokens(&revoked);
+    Ok(APIResponse::<bool>::new()
+        .with_code(200_i32)
+        .with_data(true))
+}
+
 // account info
 pub async fn account_info(
     app: Extension<Arc<AppState>>,
@@ -79,10 +172,96 @@ pub async fn account_info(
         .with_data(account))
 }
 
+// 创建 API Key, Key 只在这里返回一次
+pub async fn create_api_key(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(mut req): Json<CreateApiKeyRequest>,
+) -> Result<APIResponse<CreateApiKeyResponse>, APIError> {
+    claims.require_session()?;
+    req.account_id = Some(claims.account_id()?);
+    let created = ApiKeyController.create_api_key(req, &app.pool).await?;
+    Ok(APIResponse::<CreateApiKeyResponse>::new()
+        .with_code(200_i32)
+        .with_data(created))
+}
+
+// 当前用户的 API Key 列表
+pub async fn list_api_keys(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+) -> Result<APIResponse<Vec<ApiKeyModel>>, APIError> {
+    claims.require_session()?;
+    let keys = ApiKeyController
+        .list_api_keys(claims.account_id()?, &app.pool)
+        .await?;
+    Ok(APIResponse::<Vec<ApiKeyModel>>::new()
+        .with_code(200_i32)
+        .with_data(keys))
+}
+
+// 吊销 API Key
+pub async fn revoke_api_key(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(mut req): Json<RevokeApiKeyRequest>,
+) -> Result<APIResponse<bool>, APIError> {
+    claims.require_session()?;
+    req.account_id = Some(claims.account_id()?);
+    let revoked = ApiKeyController.revoke_api_key(req, &app.pool).await?;
+    Ok(APIResponse::<bool>::new()
+        .with_code(200_i32)
+        .with_data(revoked))
+}
+
+// 修改用户角色, 只有管理员可以调用
+pub async fn update_account_role(
+    app: Extension<Arc<AppState>>,
+    Json(req): Json<UpdateAccountRoleRequest>,
+) -> Result<APIResponse<AccountModel>, APIError> {
+    let account = AccountController.update_role(req, &app.pool).await?;
+    Ok(APIResponse::<AccountModel>::new()
+        .with_code(200_i32)
+        .with_data(account))
+}
+
+// 设置 Fever API 的密码, 客户端使用邮箱和这个密码登录
+pub async fn update_fever_password(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(req): Json<UpdateFeverPasswordRequest>,
+) -> Result<APIResponse<bool>, APIError> {
+    claims.require_session()?;
+    let enabled = AccountController
+        .update_fever_password(claims.account_id()?, &req.password, &app.pool)
+        .await?;
+    Ok(APIResponse::<bool>::new()
+        .with_code(200_i32)
+        .with_data(enabled))
+}
+
 pub(crate) fn build_routes() -> axum::Router {
     Router::new()
         // 注册用户
         .route_with_tsr("/register", post(register_account))
         .route_with_tsr("/login", post(login_account))
+        // 刷新令牌
+        .route_with_tsr("/token/refresh", post(refresh_token))
+        // 退出登录
+        .route_with_tsr("/logout", post(logout))
+        // 退出所有设备
+        .route_with_tsr("/logout/all", post(logout_all))
+        // API Key
+        .route_with_tsr("/api_key/create", post(create_api_key))
+        .route_with_tsr("/api_key/list", get(list_api_keys))
+        .route_with_tsr("/api_key/revoke", post(revoke_api_key))
+        // Fever API 密码
+        .route_with_tsr("/fever/update", post(update_fever_password))
+        // 用户角色
+        .route_with_tsr(
+            "/role/update",
+            post(update_account_role)
+                .route_layer(from_fn_with_state(AccountRole::Admin, require_role)),
+        )
         .route_with_tsr("/info", get(account_info))
 }
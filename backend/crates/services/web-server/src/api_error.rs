@@ -12,6 +12,9 @@ pub enum APIError {
 
     #[error("{0}")]
     Toast(String),
+
+    #[error("{0}")]
+    Forbidden(String),
 }
 
 impl From<ErrorInService> for APIError {
@@ -19,6 +22,7 @@ impl From<ErrorInService> for APIError {
         match e {
             ErrorInService::Custom(s) => APIError::Toast(s),
             ErrorInService::DBError(s) => APIError::Internal(format!("数据库错误:{}", s)),
+            ErrorInService::Fetch(e) => APIError::Toast(format!("拉取失败:{}", e)),
             ErrorInService::ErrorInRss(rss_e) => match rss_e {
                 lib_core::error::RssError::RssSubscriptionNotFound => {
                     APIError::Toast("订阅不存在".to_string())
@@ -1,26 +1,40 @@
 use crate::{
-    api_error::APIError, middlewares::auth_claim::AuthClaims, response::APIResponse, AppState,
+    api_error::APIError,
+    middlewares::{auth_claim::AuthClaims, role::require_role},
+    response::APIResponse,
+    AppState,
 };
 use axum::{
+    middleware::from_fn_with_state,
     routing::{get, post},
     Router,
 };
 use axum::{Extension, Json};
 use axum_extra::routing::RouterExt;
 use lib_core::{
+    auth::schema::ApiKeyScope,
     common_schema::{PageRequest, PageResponse},
     error::ErrorInService,
     feed::{
         schema::{
             CategoryModel, CreateAiTokenRecordRequestBuilder, CreateOrUpdateCategoryRequest,
-            CreateOrUpdateSubscriptionRequest, LinkMindMapRequest, LinkModel, LinkSummaryModel,
-            LinkSummaryRequest, QueryCategoryRequest, QueryRssLinkRequest,
-            QueryRssLinkRequestBuilder, QuerySubscriptionRequest,
-            QuerySubscriptionsWithLinksRequest, SubscriptionModel, UpdateSubscriptionCountRequest,
+            CreateOrUpdateSubscriptionRequest, DiscoverSubscriptionRequest,
+            ExportSavedLinksRequest, FeedDiscovery, ImportOpmlRequest, LinkMindMapRequest,
+            LinkModel, LinkSearchHit, LinkSummaryModel, LinkSummaryRequest, MarkLinksReadRequest,
+            OpmlImportReport, QueryCategoryRequest, QueryRssLinkRequest,
+            QueryRssLinkRequestBuilder, QuerySavedLinkRequest, QuerySubscriptionRecordRequest,
+            QuerySubscriptionRequest, QuerySubscriptionsWithLinksRequest, RemoveSavedLinkRequest,
+            SaveLinkRequest, SaveLinkRequestBuilder, SavedLinkModel, SearchLinkRequest,
+            SubscriptionModel, UnsubscribeRequest, UpdateLinkStateRequest,
+            UpdateSubscriptionCountRequest,
         },
-        CategoryController, LinkController, LinkSummaryController, SubscriptionController,
+        CategoryController, LinkController, LinkSearchController, LinkStateController,
+        LinkSummaryController, OpmlController, SavedLinkController, SubscriptionController,
+        SubscriptionParseController, SubscritionConfigController,
     },
 };
+use lib_crawler::RobotsVerdict;
+use lib_entity::{account::AccountRole, feed_build_record};
 use lib_openai::{AISummaryController, OpenAIConfig};
 use lib_utils::Setting;
 use serde_json::json;
@@ -31,9 +45,12 @@ use std::sync::Arc;
 /// 该方法用于创建或更新分类。
 pub(crate) async fn update_category(
     app: Extension<Arc<AppState>>,
-    Json(req): Json<CreateOrUpdateCategoryRequest>,
+    claims: AuthClaims,
+    Json(mut req): Json<CreateOrUpdateCategoryRequest>,
 ) -> Result<APIResponse<CategoryModel>, APIError> {
+    claims.require_scope(ApiKeyScope::ManageSubscriptions)?;
     let conn = &app.pool;
+    req.account_id = Some(claims.account_id()?);
     let category_controller = CategoryController;
     let updated = category_controller.insert_category(req, conn).await?;
     Ok(APIResponse::<CategoryModel>::new()
@@ -44,9 +61,12 @@ pub(crate) async fn update_category(
 /// 获取类别信息。
 async fn query_categories_by_option(
     app: Extension<Arc<AppState>>,
-    Json(req): Json<QueryCategoryRequest>,
+    claims: AuthClaims,
+    Json(mut req): Json<QueryCategoryRequest>,
 ) -> Result<APIResponse<Vec<CategoryModel>>, APIError> {
+    claims.require_scope(ApiKeyScope::ReadFeeds)?;
     let conn = &app.pool;
+    req.account_id = Some(claims.account_id()?);
     let category_controller = CategoryController;
     let categories = category_controller.query_category(req, conn).await?;
     Ok(APIResponse::<Vec<CategoryModel>>::new()
@@ -54,12 +74,15 @@ async fn query_categories_by_option(
         .with_data(categories))
 }
 
-// 查找订阅源
+// 查找当前用户订阅的订阅源
 async fn query_rss_subscription_by_options(
     app: Extension<Arc<AppState>>,
-    Json(find_rss_req): Json<QuerySubscriptionRequest>,
+    claims: AuthClaims,
+    Json(mut find_rss_req): Json<QuerySubscriptionRequest>,
 ) -> Result<APIResponse<PageResponse<SubscriptionModel>>, APIError> {
+    claims.require_scope(ApiKeyScope::ReadFeeds)?;
     let pool = &app.pool;
+    find_rss_req.account_id = Some(claims.account_id()?);
     // 检测耗时
     let controller = SubscriptionController;
     let page_with_model = controller
@@ -75,52 +98,347 @@ async fn query_rss_subscription_by_options(
         .with_data(page_info))
 }
 
-// 创建/更新订阅源
+// 订阅 / 更新当前用户的订阅, 共享的订阅源不会被修改
 async fn update_rss_subscription(
     app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
     Json(req): Json<CreateOrUpdateSubscriptionRequest>,
 ) -> Result<APIResponse<i64>, APIError> {
+    claims.require_scope(ApiKeyScope::ManageSubscriptions)?;
     let conn = &app.pool;
+    let account_id = claims.account_id()?;
 
     let (_, updated) = SubscriptionController
-        .insert_subscription(req, conn)
+        .subscribe(account_id, req, conn)
         .await?;
     Ok(APIResponse::<i64>::new()
         .with_code(200_i32)
         .with_data(updated))
 }
 
+// 取消当前用户的订阅
+async fn unsubscribe_rss_subscription(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(req): Json<UnsubscribeRequest>,
+) -> Result<APIResponse<bool>, APIError> {
+    claims.require_scope(ApiKeyScope::ManageSubscriptions)?;
+    let conn = &app.pool;
+    let account_id = claims.account_id()?;
+    let removed = SubscriptionController
+        .unsubscribe(account_id, req.subscription_id, conn)
+        .await?;
+    Ok(APIResponse::<bool>::new()
+        .with_code(200_i32)
+        .with_data(removed))
+}
+
+// 根据页面地址查找订阅源, 会代替用户请求任意地址, 只对编辑者开放
+async fn discover_rss_subscriptions(
+    claims: AuthClaims,
+    Json(req): Json<DiscoverSubscriptionRequest>,
+) -> Result<APIResponse<FeedDiscovery>, APIError> {
+    claims.require_scope(ApiKeyScope::ManageSubscriptions)?;
+    let discovery = SubscriptionParseController::discover_subscriptions(req)
+        .await
+        .map_err(|e| {
+            tracing::error!("discover_rss_subscriptions error:{}", e);
+            e
+        })?;
+    Ok(APIResponse::<FeedDiscovery>::new()
+        .with_code(200_i32)
+        .with_data(discovery))
+}
+
+// 导入 OPML, 返回新建 / 重复 / 无效的条目
+async fn import_opml(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(req): Json<ImportOpmlRequest>,
+) -> Result<APIResponse<OpmlImportReport>, APIError> {
+    claims.require_scope(ApiKeyScope::ManageSubscriptions)?;
+    let conn = &app.pool;
+    let report = OpmlController
+        .import_opml(claims.account_id()?, &req.content, conn)
+        .await
+        .map_err(|e| {
+            tracing::error!("import_opml error:{}", e);
+            e
+        })?;
+    Ok(APIResponse::<OpmlImportReport>::new()
+        .with_code(200_i32)
+        .with_data(report))
+}
+
+// 导出当前用户的分类和订阅源为 OPML
+async fn export_opml(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+) -> Result<APIResponse<String>, APIError> {
+    claims.require_scope(ApiKeyScope::ReadFeeds)?;
+    let conn = &app.pool;
+    let content = OpmlController
+        .export_opml(claims.account_id()?, conn)
+        .await?;
+    Ok(APIResponse::<String>::new()
+        .with_code(200_i32)
+        .with_data(content))
+}
+
+// 查询订阅源的更新记录, 可以按失败原因过滤
+async fn query_rss_subscription_records(
+    app: Extension<Arc<AppState>>,
+    Json(req): Json<QuerySubscriptionRecordRequest>,
+) -> Result<APIResponse<PageResponse<feed_build_record::Model>>, APIError> {
+    let conn = &app.pool;
+    let records = SubscritionConfigController
+        .query_subscription_record(req, conn)
+        .await?;
+    Ok(APIResponse::<PageResponse<feed_build_record::Model>>::new()
+        .with_code(200_i32)
+        .with_data(records))
+}
+
 // 查询订阅链接
 async fn query_rss_links(
     app: Extension<Arc<AppState>>,
-    Json(req): Json<QueryRssLinkRequest>,
+    claims: AuthClaims,
+    Json(mut req): Json<QueryRssLinkRequest>,
 ) -> Result<APIResponse<PageResponse<LinkModel>>, APIError> {
+    claims.require_scope(ApiKeyScope::ReadFeeds)?;
     let conn = &app.pool;
+    req.account_id = Some(claims.account_id()?);
     let page_with_model = LinkController.query_links(req, conn).await?;
     Ok(APIResponse::<PageResponse<LinkModel>>::new()
         .with_code(200_i32)
         .with_data(page_with_model))
 }
 
+/// 全文搜索文章
+/// 搜索标题、内容和总结的关键词, 返回高亮的标题和匹配的片段
+async fn search_rss_links(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(mut req): Json<SearchLinkRequest>,
+) -> Result<APIResponse<PageResponse<LinkSearchHit>>, APIError> {
+    claims.require_scope(ApiKeyScope::ReadFeeds)?;
+    let conn = &app.pool;
+    req.account_id = Some(claims.account_id()?);
+    let page_with_hits = LinkSearchController.search(req, conn).await?;
+    Ok(APIResponse::<PageResponse<LinkSearchHit>>::new()
+        .with_code(200_i32)
+        .with_data(page_with_hits))
+}
+
+/// 修改文章的已读 / 收藏 / 归档状态, 返回修改的文章数量
+async fn update_rss_link_state(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(mut req): Json<UpdateLinkStateRequest>,
+) -> Result<APIResponse<u64>, APIError> {
+    claims.require_scope(ApiKeyScope::WriteFeeds)?;
+    let conn = &app.pool;
+    req.account_id = Some(claims.account_id()?);
+    let updated = LinkStateController.update_link_state(req, conn).await?;
+    Ok(APIResponse::<u64>::new()
+        .with_code(200_i32)
+        .with_data(updated))
+}
+
+/// 把订阅源或分类下某个时间之前的文章全部标记为已读
+async fn mark_rss_links_read(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(mut req): Json<MarkLinksReadRequest>,
+) -> Result<APIResponse<u64>, APIError> {
+    claims.require_scope(ApiKeyScope::WriteFeeds)?;
+    let conn = &app.pool;
+    req.account_id = Some(claims.account_id()?);
+    let updated = LinkStateController.mark_read_until(req, conn).await?;
+    Ok(APIResponse::<u64>::new()
+        .with_code(200_i32)
+        .with_data(updated))
+}
+
 /// 查询订阅链接数量
 /// 提供 ids/ idfs/ title / 订阅源 / 发布时间范围 维度的查询
 async fn query_rss_links_count(
     app: Extension<Arc<AppState>>,
-    Json(req): Json<QueryRssLinkRequest>,
+    claims: AuthClaims,
+    Json(mut req): Json<QueryRssLinkRequest>,
 ) -> Result<APIResponse<u64>, APIError> {
+    claims.require_scope(ApiKeyScope::ReadFeeds)?;
     let conn = &app.pool;
+    req.account_id = Some(claims.account_id()?);
     let count = req.fetch_count(conn).await?;
     Ok(APIResponse::<u64>::new()
         .with_code(200_i32)
         .with_data(count))
 }
 
+/// 保存文章, 没有提供正文时通过链接解析服务生成 Markdown 快照
+async fn save_rss_link(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(mut req): Json<SaveLinkRequest>,
+) -> Result<APIResponse<SavedLinkModel>, APIError> {
+    claims.require_scope(ApiKeyScope::WriteFeeds)?;
+    let conn = &app.pool;
+    let account_id = claims.account_id()?;
+    req.account_id = Some(account_id);
+    let need_snapshot = req.content.is_none();
+    let mut saved = SavedLinkController.save_link(req, conn).await?;
+    if need_snapshot && saved.content.is_none() {
+        // 解析失败时保留已保存的记录, 之后可以再次保存补充快照
+        match fetch_link_markdown(&app, &saved.link).await {
+            Ok(content) => {
+                let mut snapshot = SaveLinkRequestBuilder::default();
+                snapshot.account_id(account_id).content(content);
+                match saved.link_id {
+                    Some(link_id) => snapshot.link_id(link_id),
+                    None => snapshot.link(saved.link.clone()),
+                };
+                saved = SavedLinkController
+                    .save_link(snapshot.build()?, conn)
+                    .await?;
+            }
+            Err(e) => tracing::error!("save_rss_link snapshot error:{}", e),
+        }
+    }
+    Ok(APIResponse::<SavedLinkModel>::new()
+        .with_code(200_i32)
+        .with_data(saved))
+}
+
+/// 查询保存的文章
+async fn query_saved_links(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(mut req): Json<QuerySavedLinkRequest>,
+) -> Result<APIResponse<PageResponse<SavedLinkModel>>, APIError> {
+    claims.require_scope(ApiKeyScope::ReadFeeds)?;
+    let conn = &app.pool;
+    req.account_id = Some(claims.account_id()?);
+    let page = SavedLinkController.query_saved_links(req, conn).await?;
+    Ok(APIResponse::<PageResponse<SavedLinkModel>>::new()
+        .with_code(200_i32)
+        .with_data(page))
+}
+
+/// 删除保存的文章
+async fn remove_saved_link(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(req): Json<RemoveSavedLinkRequest>,
+) -> Result<APIResponse<bool>, APIError> {
+    claims.require_scope(ApiKeyScope::WriteFeeds)?;
+    let conn = &app.pool;
+    let removed = SavedLinkController
+        .remove_saved_link(claims.account_id()?, req.id, conn)
+        .await?;
+    Ok(APIResponse::<bool>::new()
+        .with_code(200_i32)
+        .with_data(removed))
+}
+
+/// 导出保存的文章为 Markdown 或 HTML
+async fn export_saved_links(
+    app: Extension<Arc<AppState>>,
+    claims: AuthClaims,
+    Json(req): Json<ExportSavedLinksRequest>,
+) -> Result<APIResponse<String>, APIError> {
+    claims.require_scope(ApiKeyScope::ReadFeeds)?;
+    let conn = &app.pool;
+    let content = SavedLinkController
+        .export_saved_links(claims.account_id()?, req.format, conn)
+        .await?;
+    Ok(APIResponse::<String>::new()
+        .with_code(200_i32)
+        .with_data(content))
+}
+
+/// 提取文章正文的 Markdown, 遵守 robots.txt 和站点限速
+///
+/// 提取失败时使用配置的链接解析服务
+async fn fetch_link_markdown(app: &AppState, link_url: &str) -> Result<String, APIError> {
+    let setting = Setting::global();
+    // 链接由用户提供, 拒绝内网地址, 解析服务同样不能代为请求
+    if let Err(e) = lib_crawler::ensure_public_url(link_url).await {
+        tracing::info!("fetch_link_markdown skip {}: {}", link_url, e);
+        return Err(ErrorInService::Fetch(e).into());
+    }
+    if let Some(robots) = &app.robots {
+        match robots.check(link_url).await {
+            RobotsVerdict::Disallowed { rule } => {
+                tracing::info!(
+                    "fetch_link_markdown skip {}: robots.txt 不允许抓取 ({})",
+                    link_url,
+                    rule
+                );
+                return Err(APIError::Toast("该站点不允许抓取此页面".to_string()));
+            }
+            RobotsVerdict::Allowed {
+                crawl_delay: Some(delay),
+            } => app.scheduler.set_host_delay(link_url, delay),
+            RobotsVerdict::Allowed { crawl_delay: None } => {}
+        }
+    }
+    // 按站点限速, 遵守 Crawl-delay
+    let _permit = app.scheduler.acquire(link_url).await;
+    let err = match lib_crawler::fetch_article(link_url, Some(setting.crawler.user_agent.clone()))
+        .await
+    {
+        Ok(article) => return Ok(article.markdown),
+        Err(e) => e,
+    };
+    let js_server_host = match setting.services.js_server_host {
+        Some(js_server_host) => js_server_host,
+        None => {
+            tracing::error!("fetch_link_markdown error:{}", err);
+            return Err(ErrorInService::Fetch(err).into());
+        }
+    };
+    tracing::warn!(
+        "fetch_link_markdown 提取正文失败, 使用解析服务:{} {}",
+        link_url,
+        err
+    );
+    let request_url = format!("{}/parse/md", js_server_host);
+    let resp = reqwest::Client::new()
+        .post(request_url)
+        .json(&json!({ "url": link_url }))
+        .send()
+        .await
+        .map_err(|e| {
+            tracing::error!("fetch_link_markdown error:{}", e);
+            ErrorInService::Custom("请求解析链接失败".to_string())
+        })?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| {
+            tracing::error!("fetch_link_markdown error:{}", e);
+            ErrorInService::Custom("解析链接失败".to_string())
+        })?;
+    let content = resp["content"]
+        .as_str()
+        .ok_or_else(|| {
+            tracing::error!(
+                "fetch_link_markdown error:{}",
+                "链接解析服务返回内容提取失败"
+            );
+            ErrorInService::Custom("链接解析服务返回内容提取失败".to_string())
+        })?
+        .to_string();
+    Ok(content)
+}
+
 /// 总结链接
 async fn summary_rss_link(
     app: Extension<Arc<AppState>>,
     claims: AuthClaims,
     Json(req): Json<super::entities::SummaryLinkRequest>,
 ) -> Result<APIResponse<LinkSummaryModel>, APIError> {
+    claims.require_scope(ApiKeyScope::RequestSummaries)?;
     let conn = &app.pool;
 
     let mut summary_cache_query = LinkSummaryRequest {
@@ -153,38 +471,7 @@ async fn summary_rss_link(
     // 如果请求体里面已经包含了文章的正文内容了，则直接使用
     let content = match req.link_content {
         Some(content) => content,
-        None => {
-            let js_server_host = match setting.services.js_server_host {
-                Some(js_server_host) => js_server_host,
-                None => {
-                    tracing::error!("summary_rss_link error:{}", "链接解析服务未就绪");
-                    return Err(ErrorInService::Custom("链接解析服务未就绪".to_string()).into());
-                }
-            };
-            let request_url = format!("{}/parse/md", js_server_host);
-            let resp = reqwest::Client::new()
-                .post(request_url)
-                .json(&json!({ "url": req.link_url }))
-                .send()
-                .await
-                .map_err(|e| {
-                    tracing::error!("summary_rss_link error:{}", e);
-                    ErrorInService::Custom("请求解析链接失败".to_string())
-                })?
-                .json::<serde_json::Value>()
-                .await
-                .map_err(|e| {
-                    tracing::error!("summary_rss_link error:{}", e);
-                    ErrorInService::Custom("解析链接失败".to_string())
-                })?;
-            resp["content"]
-                .as_str()
-                .ok_or_else(|| {
-                    tracing::error!("summary_rss_link error:{}", "链接解析服务返回内容提取失败");
-                    ErrorInService::Custom("链接解析服务返回内容提取失败".to_string())
-                })?
-                .to_string()
-        }
+        None => fetch_link_markdown(&app, &req.link_url).await?,
     };
 
     // 这里获得用户文章消耗的 token 数量， 并以此作为消费的凭证
@@ -238,6 +525,9 @@ async fn summary_rss_link(
 }
 
 pub(crate) fn build_routes() -> axum::Router {
+    // 各个路由需要的最低角色
+    let editor = || from_fn_with_state(AccountRole::Editor, require_role);
+    let admin = || from_fn_with_state(AccountRole::Admin, require_role);
     Router::new()
         // 订阅源
         .route_with_tsr(
@@ -245,14 +535,50 @@ pub(crate) fn build_routes() -> axum::Router {
             post(query_rss_subscription_by_options),
         )
         // 订阅源更新
-        .route_with_tsr("/subscrition/update", post(update_rss_subscription))
+        .route_with_tsr(
+            "/subscrition/update",
+            post(update_rss_subscription).route_layer(editor()),
+        )
+        .route_with_tsr(
+            "/subscrition/unsubscribe",
+            post(unsubscribe_rss_subscription).route_layer(editor()),
+        )
+        // 查找页面中的订阅源
+        .route_with_tsr(
+            "/subscription/discover",
+            post(discover_rss_subscriptions).route_layer(editor()),
+        )
+        // 订阅源更新记录
+        .route_with_tsr(
+            "/subscrition/record/query",
+            post(query_rss_subscription_records).route_layer(admin()),
+        )
+        // OPML 导入导出, 作用于当前用户的订阅
+        .route_with_tsr("/opml/import", post(import_opml).route_layer(editor()))
+        .route_with_tsr("/opml/export", post(export_opml))
         // 分类更新
-        .route_with_tsr("/category/update", post(update_category))
+        .route_with_tsr(
+            "/category/update",
+            post(update_category).route_layer(editor()),
+        )
         .route_with_tsr("/category/query", post(query_categories_by_option))
         // 链接查询
         .route_with_tsr("/link/query", post(query_rss_links))
+        // 全文搜索链接
+        .route_with_tsr("/link/search", post(search_rss_links))
+        // 已读 / 收藏状态
+        .route_with_tsr("/link/state/update", post(update_rss_link_state))
+        .route_with_tsr("/link/state/mark_read", post(mark_rss_links_read))
         // 查询链接数量
         .route_with_tsr("/link/query_count", post(query_rss_links_count))
         // 总结链接
-        .route_with_tsr("/link/summary", post(summary_rss_link))
+        .route_with_tsr(
+            "/link/summary",
+            post(summary_rss_link).route_layer(editor()),
+        )
+        // 保存的文章
+        .route_with_tsr("/saved/save", post(save_rss_link))
+        .route_with_tsr("/saved/query", post(query_saved_links))
+        .route_with_tsr("/saved/remove", post(remove_saved_link))
+        .route_with_tsr("/saved/export", post(export_saved_links))
 }
@@ -0,0 +1,172 @@
+use crate::{api_error::APIError, AppState};
+use axum::{
+    extract::Query,
+    routing::{get, post},
+    Extension, Form, Json, Router,
+};
+use lib_core::{
+    auth::AccountController,
+    feed::{
+        schema::{FeverItemsRequest, FeverMarkAs, FeverMarkRequest, FeverMarkTarget},
+        FeverController,
+    },
+};
+use serde_json::{json, Map, Value};
+use std::{collections::HashMap, sync::Arc};
+
+// Fever API 的版本
+const FEVER_API_VERSION: i32 = 3;
+
+fn join_ids(ids: &[i64]) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// 解析标记参数, `mark=item&as=read&id=1`, 订阅源和分组可以带 `before`
+fn parse_mark(params: &HashMap<String, String>) -> Option<FeverMarkRequest> {
+    let target = match params.get("mark")?.as_str() {
+        "item" => FeverMarkTarget::Item,
+        "feed" => FeverMarkTarget::Feed,
+        "group" => FeverMarkTarget::Group,
+        _ => return None,
+    };
+    let mark_as = match params.get("as")?.as_str() {
+        "read" => FeverMarkAs::Read,
+        "unread" => FeverMarkAs::Unread,
+        "saved" => FeverMarkAs::Saved,
+        "unsaved" => FeverMarkAs::Unsaved,
+        _ => return None,
+    };
+    let id = params.get("id")?.parse::<i64>().ok()?;
+    let before = params
+        .get("before")
+        .and_then(|b| b.parse::<i64>().ok())
+        .and_then(|b| chrono::DateTime::from_timestamp(b, 0))
+        .map(|b| b.naive_utc());
+    Some(FeverMarkRequest {
+        target,
+        mark_as,
+        id,
+        before,
+    })
+}
+
+/// Fever API
+///
+/// 参数可以放在查询字符串或表单中, `api_key` 为 md5(email:password)
+pub(crate) async fn fever_api(
+    app: Extension<Arc<AppState>>,
+    Query(query): Query<HashMap<String, String>>,
+    form: Option<Form<HashMap<String, String>>>,
+) -> Result<Json<Value>, APIError> {
+    let conn = &app.pool;
+    let mut params = query;
+    if let Some(Form(form)) = form {
+        params.extend(form);
+    }
+    let mut resp = Map::new();
+    resp.insert("api_version".to_string(), json!(FEVER_API_VERSION));
+
+    let api_key = params.get("api_key").cloned().unwrap_or_default();
+    let Some(account) = AccountController
+        .account_by_fever_key(&api_key, conn)
+        .await?
+    else {
+        resp.insert("auth".to_string(), json!(0));
+        return Ok(Json(Value::Object(resp)));
+    };
+    let account_id = account.id;
+    let controller = FeverController;
+    resp.insert("auth".to_string(), json!(1));
+    resp.insert(
+        "last_refreshed_on_time".to_string(),
+        json!(chrono::Utc::now().timestamp()),
+    );
+
+    // 先处理标记, 之后返回的数据是标记后的状态
+    if let Some(mark) = parse_mark(&params) {
+        controller.mark(account_id, mark, conn).await?;
+    }
+    if params.contains_key("groups") {
+        let groups = controller.groups(account_id, conn).await?;
+        resp.insert("groups".to_string(), json!(groups));
+    }
+    if params.contains_key("feeds") {
+        let feeds = controller.feeds(account_id, conn).await?;
+        resp.insert("feeds".to_string(), json!(feeds));
+    }
+    if params.contains_key("groups") || params.contains_key("feeds") {
+        let feeds_groups = controller.feeds_groups(account_id, conn).await?;
+        resp.insert("feeds_groups".to_string(), json!(feeds_groups));
+    }
+    if params.contains_key("favicons") {
+        resp.insert("favicons".to_string(), json!([]));
+    }
+    if params.contains_key("links") {
+        resp.insert("links".to_string(), json!([]));
+    }
+    if params.contains_key("items") {
+        let req = FeverItemsRequest {
+            since_id: params.get("since_id").and_then(|v| v.parse().ok()),
+            max_id: params.get("max_id").and_then(|v| v.parse().ok()),
+            with_ids: params
+                .get("with_ids")
+                .map(|v| {
+                    v.split(',')
+                        .filter_map(|id| id.trim().parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+        let items = controller.items(account_id, req, conn).await?;
+        let total_items = controller.total_items(account_id, conn).await?;
+        resp.insert("items".to_string(), json!(items));
+        resp.insert("total_items".to_string(), json!(total_items));
+    }
+    if params.contains_key("unread_item_ids") {
+        let ids = controller.unread_item_ids(account_id, conn).await?;
+        resp.insert("unread_item_ids".to_string(), json!(join_ids(&ids)));
+    }
+    if params.contains_key("saved_item_ids") {
+        let ids = controller.saved_item_ids(account_id, conn).await?;
+        resp.insert("saved_item_ids".to_string(), json!(join_ids(&ids)));
+    }
+    Ok(Json(Value::Object(resp)))
+}
+
+pub(crate) fn build_routes() -> Router {
+    // 客户端一般请求 `/fever/?api`, 同时支持不带斜杠的地址
+    Router::new()
+        .route("/fever", get(fever_api).post(fever_api))
+        .route("/fever/", get(fever_api).post(fever_api))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mark() {
+        let params: HashMap<String, String> = [
+            ("mark", "group"),
+            ("as", "read"),
+            ("id", "3"),
+            ("before", "1700000000"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let mark = parse_mark(&params).unwrap();
+        assert_eq!(mark.target, FeverMarkTarget::Group);
+        assert_eq!(mark.mark_as, FeverMarkAs::Read);
+        assert_eq!(mark.id, 3);
+        assert_eq!(mark.before.unwrap().and_utc().timestamp(), 1700000000);
+
+        let mut params = params;
+        params.insert("as".to_string(), "starred".to_string());
+        assert!(parse_mark(&params).is_none());
+        assert_eq!(join_ids(&[1, 2, 3]), "1,2,3");
+    }
+}
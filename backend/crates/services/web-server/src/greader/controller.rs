@@ -0,0 +1,353 @@
+use crate::{api_error::APIError, middlewares::auth_claim::AuthClaims, AppState};
+use axum::{
+    body::Bytes,
+    extract::{Path, RawQuery},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use lib_core::{
+    auth::{
+        schema::{ApiKeyScope, CreateApiKeyRequestBuilder, LoginAccountRequest},
+        AccountController, ApiKeyController,
+    },
+    error::ErrorInService,
+    feed::{
+        parse_greader_item_id,
+        schema::{GReaderEditTagRequest, GReaderStream, GReaderStreamRequest},
+        GReaderController,
+    },
+};
+use lib_utils::password::random_hex;
+use serde_json::json;
+use std::sync::Arc;
+
+// ClientLogin 创建的 API Key 名称
+const GREADER_API_KEY_NAME: &str = "Google Reader";
+// ClientLogin 创建的 API Key 有效天数, 过期后客户端收到 401 会重新登录
+const GREADER_API_KEY_TTL_DAYS: i64 = 90;
+
+// Google Reader 客户端需要区分认证失败和其他错误
+#[derive(Debug)]
+enum GReaderError {
+    Unauthorized,
+    BadRequest(String),
+    Api(APIError),
+}
+
+impl From<APIError> for GReaderError {
+    fn from(e: APIError) -> Self {
+        Self::Api(e)
+    }
+}
+
+impl From<ErrorInService> for GReaderError {
+    fn from(e: ErrorInService) -> Self {
+        Self::Api(e.into())
+    }
+}
+
+impl IntoResponse for GReaderError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Unauthorized => (StatusCode::UNAUTHORIZED, "Unauthorized!").into_response(),
+            Self::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
+            Self::Api(e) => e.into_response(),
+        }
+    }
+}
+
+// 查询字符串和表单中的参数, 同一个参数可以出现多次, 例如 edit-tag 的 `i`
+struct GReaderParams(Vec<(String, String)>);
+
+impl GReaderParams {
+    fn new(query: Option<String>, body: &[u8]) -> Self {
+        let query = query.unwrap_or_default();
+        let params = url::form_urlencoded::parse(query.as_bytes())
+            .chain(url::form_urlencoded::parse(body))
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        Self(params)
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn get_all(&self, key: &str) -> Vec<String> {
+        self.0
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+            .collect()
+    }
+
+    // 秒级时间戳
+    fn timestamp(&self, key: &str) -> Option<chrono::NaiveDateTime> {
+        self.get(key)
+            .and_then(|v| v.parse::<i64>().ok())
+            .and_then(|v| chrono::DateTime::from_timestamp(v, 0))
+            .map(|v| v.naive_utc())
+    }
+
+    fn stream_request(
+        &self,
+        stream_id: Option<&str>,
+    ) -> Result<GReaderStreamRequest, GReaderError> {
+        let stream_id = stream_id
+            .filter(|s| !s.is_empty())
+            .or(self.get("s"))
+            .unwrap_or("user/-/state/com.google/reading-list");
+        let stream = GReaderStream::parse(stream_id).ok_or(GReaderError::BadRequest(format!(
+            "unknown stream: {}",
+            stream_id
+        )))?;
+        Ok(GReaderStreamRequest {
+            stream,
+            count: self.get("n").and_then(|n| n.parse().ok()).unwrap_or(0),
+            continuation: self.get("c").map(|c| c.to_string()),
+            exclude_read: self
+                .get_all("xt")
+                .iter()
+                .any(|xt| xt.ends_with("/state/com.google/read")),
+            newer_than: self.timestamp("ot"),
+            older_than: self.timestamp("nt"),
+        })
+    }
+
+    // `i` 中的文章 id
+    fn item_ids(&self) -> Vec<i64> {
+        self.get_all("i")
+            .iter()
+            .filter_map(|i| parse_greader_item_id(i))
+            .collect()
+    }
+}
+
+// 校验令牌, 返回用户 id
+fn greader_account(claims: Result<AuthClaims, APIError>) -> Result<i64, GReaderError> {
+    greader_account_with_scope(claims, ApiKeyScope::ReadFeeds)
+}
+
+// 校验令牌是否有指定的权限, 返回用户 id
+fn greader_account_with_scope(
+    claims: Result<AuthClaims, APIError>,
+    scope: ApiKeyScope,
+) -> Result<i64, GReaderError> {
+    let claims = claims.map_err(|_| GReaderError::Unauthorized)?;
+    claims
+        .require_scope(scope)
+        .map_err(|_| GReaderError::Unauthorized)?;
+    claims.account_id().map_err(|_| GReaderError::Unauthorized)
+}
+
+/// 使用邮箱和密码登录, 返回的 Auth 是一个只能读取文章和修改阅读状态的 API Key
+///
+/// 每个客户端登录都会创建新的 Key, Key 会在一段时间后过期, 避免一直累积有效的 Key
+async fn client_login(
+    app: Extension<Arc<AppState>>,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<Response, GReaderError> {
+    let params = GReaderParams::new(query, &body);
+    let req = LoginAccountRequest {
+        email: params.get("Email").unwrap_or_default().to_string(),
+        password: params.get("Passwd").unwrap_or_default().to_string(),
+    };
+    let account = match AccountController.login_account(req, &app.pool).await {
+        Ok(account) => account,
+        Err(ErrorInService::Custom(_)) => {
+            return Ok((StatusCode::UNAUTHORIZED, "Error=BadAuthentication\n").into_response())
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let req = CreateApiKeyRequestBuilder::default()
+        .account_id(account.id)
+        .name(GREADER_API_KEY_NAME)
+        .scopes(vec![ApiKeyScope::ReadFeeds, ApiKeyScope::WriteFeeds])
+        .expired_at(
+            chrono::Utc::now().naive_utc() + chrono::Duration::days(GREADER_API_KEY_TTL_DAYS),
+        )
+        .build()?;
+    let key = ApiKeyController.create_api_key(req, &app.pool).await?.key;
+    Ok(format!("SID={key}\nLSID={key}\nAuth={key}\n").into_response())
+}
+
+/// 写操作前客户端会请求这个令牌, 认证使用请求头, 因此不校验令牌
+async fn token(claims: Result<AuthClaims, APIError>) -> Result<String, GReaderError> {
+    greader_account(claims)?;
+    Ok(random_hex(28))
+}
+
+async fn user_info(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+) -> Result<Json<serde_json::Value>, GReaderError> {
+    let account_id = greader_account(claims)?;
+    let account = AccountController
+        .account_info(account_id, &app.pool)
+        .await?
+        .ok_or(GReaderError::Unauthorized)?;
+    let email = account.email.unwrap_or_default();
+    Ok(Json(json!({
+        "userId": account.id.to_string(),
+        "userName": account.nick_name.unwrap_or(email.clone()),
+        "userProfileId": account.id.to_string(),
+        "userEmail": email,
+    })))
+}
+
+async fn subscription_list(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+) -> Result<Json<serde_json::Value>, GReaderError> {
+    let account_id = greader_account(claims)?;
+    let subscriptions = GReaderController
+        .subscriptions(account_id, &app.pool)
+        .await?;
+    Ok(Json(json!({ "subscriptions": subscriptions })))
+}
+
+async fn tag_list(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+) -> Result<Json<serde_json::Value>, GReaderError> {
+    let account_id = greader_account(claims)?;
+    let tags = GReaderController.tags(account_id, &app.pool).await?;
+    Ok(Json(json!({ "tags": tags })))
+}
+
+// 流的 id 放在路径中, 例如 `/stream/contents/feed%2F1`
+async fn stream_contents_by_path(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+    Path(stream_id): Path<String>,
+    RawQuery(query): RawQuery,
+) -> Result<Json<serde_json::Value>, GReaderError> {
+    stream_contents(app, claims, Some(stream_id), query).await
+}
+
+async fn stream_contents_by_query(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+    RawQuery(query): RawQuery,
+) -> Result<Json<serde_json::Value>, GReaderError> {
+    stream_contents(app, claims, None, query).await
+}
+
+async fn stream_contents(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+    stream_id: Option<String>,
+    query: Option<String>,
+) -> Result<Json<serde_json::Value>, GReaderError> {
+    let account_id = greader_account(claims)?;
+    let req = GReaderParams::new(query, &[]).stream_request(stream_id.as_deref())?;
+    let contents = GReaderController
+        .stream_contents(account_id, req, &app.pool)
+        .await?;
+    Ok(Json(json!(contents)))
+}
+
+async fn stream_item_ids(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+    RawQuery(query): RawQuery,
+) -> Result<Json<serde_json::Value>, GReaderError> {
+    let account_id = greader_account(claims)?;
+    let req = GReaderParams::new(query, &[]).stream_request(None)?;
+    let ids = GReaderController
+        .stream_item_ids(account_id, req, &app.pool)
+        .await?;
+    Ok(Json(json!(ids)))
+}
+
+// 根据 `i` 获取文章内容, 一般在 stream/items/ids 之后调用
+async fn stream_items_contents(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, GReaderError> {
+    let account_id = greader_account(claims)?;
+    let link_ids = GReaderParams::new(query, &body).item_ids();
+    let items = GReaderController
+        .items_contents(account_id, link_ids, &app.pool)
+        .await?;
+    Ok(Json(json!({
+        "id": "user/-/state/com.google/reading-list",
+        "updated": chrono::Utc::now().timestamp(),
+        "items": items,
+    })))
+}
+
+async fn edit_tag(
+    app: Extension<Arc<AppState>>,
+    claims: Result<AuthClaims, APIError>,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<&'static str, GReaderError> {
+    let account_id = greader_account_with_scope(claims, ApiKeyScope::WriteFeeds)?;
+    let params = GReaderParams::new(query, &body);
+    let req = GReaderEditTagRequest {
+        link_ids: params.item_ids(),
+        add: params.get_all("a"),
+        remove: params.get_all("r"),
+    };
+    GReaderController
+        .edit_tag(account_id, req, &app.pool)
+        .await?;
+    Ok("OK")
+}
+
+pub(crate) fn build_routes() -> Router {
+    let api = Router::new()
+        .route("/token", get(token))
+        .route("/user-info", get(user_info))
+        .route("/subscription/list", get(subscription_list))
+        .route("/tag/list", get(tag_list))
+        .route("/stream/contents", get(stream_contents_by_query))
+        .route("/stream/contents/*stream", get(stream_contents_by_path))
+        .route("/stream/items/ids", get(stream_item_ids))
+        .route(
+            "/stream/items/contents",
+            get(stream_items_contents).post(stream_items_contents),
+        )
+        .route("/edit-tag", post(edit_tag));
+    Router::new()
+        .route(
+            "/accounts/ClientLogin",
+            get(client_login).post(client_login),
+        )
+        .nest("/reader/api/0", api)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_greader_params() {
+        let params = GReaderParams::new(
+            Some("n=2&c=3&xt=user%2F-%2Fstate%2Fcom.google%2Fread&ot=1700000000".to_string()),
+            b"i=tag%3Agoogle.com%2C2005%3Areader%2Fitem%2F00000000000000ff&i=12&a=x",
+        );
+        assert_eq!(params.item_ids(), vec![255, 12]);
+        assert_eq!(params.get_all("a"), vec!["x".to_string()]);
+
+        let req = params.stream_request(Some("feed/1")).unwrap();
+        assert_eq!(req.stream, GReaderStream::Feed(1));
+        assert_eq!(req.count, 2);
+        assert_eq!(req.continuation.as_deref(), Some("3"));
+        assert!(req.exclude_read);
+        assert_eq!(req.newer_than.unwrap().and_utc().timestamp(), 1700000000);
+        assert!(params.stream_request(Some("unknown")).is_err());
+
+        let req = GReaderParams::new(None, &[]).stream_request(None).unwrap();
+        assert_eq!(req.stream, GReaderStream::ReadingList);
+    }
+}
@@ -1,5 +1,6 @@
 use axum_extra::routing::RouterExt;
-use lib_core::{get_db_conn, DBConnection};
+use lib_core::{auth::AccountTokenController, get_db_conn, DBConnection};
+use lib_crawler::{HostScheduler, HostSchedulerOption, RobotsCache};
 // use middlewares::verification::VerificationHeaderFields;
 // use middlewares::VerificationHeaderFields;
 use axum::{
@@ -24,16 +25,26 @@ use tower_http::{
 mod account;
 mod api_error;
 mod feed;
+mod fever;
+mod greader;
 mod middlewares;
 mod response;
 mod route;
 mod utils;
+mod websub;
 use lib_utils::Setting;
 
+// 从数据库同步已注销令牌的间隔
+const REVOCATION_SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Debug)]
 pub struct AppState {
     pub pool: DBConnection,
     pub setting: Setting,
+    // robots.txt 缓存, 关闭 respect_robots 时为空
+    pub robots: Option<RobotsCache>,
+    // 抓取文章页面时按站点限速
+    pub scheduler: HostScheduler,
 }
 
 async fn handler_404() -> Result<APIResponse<()>, api_error::APIError> {
@@ -61,8 +72,17 @@ async fn handle_timeout_error(
     (StatusCode::INTERNAL_SERVER_ERROR, error_message.to_string())
 }
 
+async fn sync_revocations(conn: &DBConnection) {
+    match AccountTokenController.active_revocations(conn).await {
+        Ok(tokens) => middlewares::auth_claim::revoke_tokens(&tokens),
+        Err(e) => tracing::error!("加载已注销的令牌失败: {}", e),
+    }
+}
+
 pub async fn build_router(setting: &Setting) -> Router {
     let connection = get_db_conn(setting.database.uri.clone()).await;
+    // 加载还没过期的已注销令牌
+    sync_revocations(&connection).await;
 
     let cors = CorsLayer::new()
         .allow_headers(Any)
@@ -80,20 +100,39 @@ pub async fn build_router(setting: &Setting) -> Router {
         }
         false => router,
     };
+    let robots = match setting.crawler.respect_robots {
+        true => Some(RobotsCache::new(setting.crawler.user_agent.clone())),
+        false => None,
+    };
+    let scheduler = HostScheduler::new(HostSchedulerOption {
+        per_host_concurrency: setting.crawler.per_host_concurrency,
+        per_host_delay: Duration::from_millis(setting.crawler.per_host_delay_ms),
+        max_backoff: Duration::from_secs(setting.crawler.max_backoff_secs),
+        ..Default::default()
+    });
     let state = Arc::new(AppState {
         pool: connection,
         setting: setting.clone(),
+        robots,
+        scheduler,
+    });
+    // 注销列表保存在进程内, 定时同步其他实例注销的令牌
+    let sync_state = Arc::clone(&state);
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(REVOCATION_SYNC_INTERVAL).await;
+            sync_revocations(&sync_state.pool).await;
+        }
     });
     let router = router
         .layer(HandleErrorLayer::new(|err| async move {
             let error_message = format!("unhandled error: {:?}", err);
             tracing::error!("got an error: {}", error_message);
 
-            let response = axum::response::Response::builder()
+            axum::response::Response::builder()
                 .status(StatusCode::INTERNAL_SERVER_ERROR)
                 .body(axum::body::Body::from("Internal Server Error"))
-                .unwrap_or_default();
-            response
+                .unwrap_or_default()
         }))
         .layer(cors)
         .layer(ValidateRequestHeaderLayer::accept("application/json"))
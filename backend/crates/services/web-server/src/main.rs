@@ -1,4 +1,5 @@
 use clap::Parser;
+use lib_core::auth::AccountController;
 use lib_utils::Setting;
 use migration::{Migrator, MigratorTrait};
 use std::error::Error;
@@ -92,6 +93,21 @@ impl Server {
             if let Err(e) = Migrator::up(&conn, None).await {
                 tracing::error!("数据库迁移失败:{}", e);
             }
+            // 根据配置创建管理员, 没有配置密码时不创建
+            match (
+                setting.web.admin_name.as_deref(),
+                setting.web.admin_password.as_deref(),
+            ) {
+                (Some(admin_name), Some(admin_password)) => match AccountController
+                    .bootstrap_admin(admin_name, admin_password, &conn)
+                    .await
+                {
+                    Ok(admin) => tracing::info!("管理员账户: {}", admin.id),
+                    Err(e) => tracing::error!("创建管理员失败:{}", e),
+                },
+                (Some(_), None) => tracing::warn!("未配置管理员密码, 跳过创建管理员"),
+                _ => {}
+            }
 
             tracing::info!("将开始在: {:?} 创建服务", &addr);
             let tcp_listener = tokio::net::TcpListener::bind(&addr).await.unwrap();
@@ -16,19 +16,26 @@ use jwt::Algorithm;
 use jwt::Validation;
 use lib_core::{
     auth::{
-        schema::{AccountModel, QueryAccountByIDRequest},
-        AccountController,
+        schema::{AccessToken, AccountModel, ApiKeyModel, ApiKeyScope, QueryAccountByIDRequest},
+        AccountController, ApiKeyController, API_KEY_PREFIX,
     },
     DBConnection,
 };
 use lib_entity::account;
 use lib_utils::Setting;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
 
-use crate::api_error;
+use crate::{api_error, AppState};
 use chrono::Utc;
 
-#[derive(Debug, Serialize, Deserialize)]
+// API Key 使用的请求头
+const API_KEY_HEADER: &str = "X-Api-Key";
+// Google Reader API 的认证方式
+const GOOGLE_LOGIN_PREFIX: &str = "GoogleLogin auth=";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents the claims of an authenticated user.
 /// 认证声明结构体
 pub struct AuthClaims {
@@ -59,6 +66,40 @@ pub struct AuthClaims {
     /// 令牌的唯一标识符。它为令牌提供了一个唯一标识符。
     /// 此字段是必需的，必须是一个字符串值。
     pub jti: String,
+
+    /// 使用 API Key 认证时 Key 的权限范围, 登录令牌为空, 拥有全部权限。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<ApiKeyScope>>,
+}
+
+// 已注销但还没过期的访问令牌, jti -> 过期时间
+// 只保存在当前进程, 启动时从数据库加载并定时同步, 注销时同时写入数据库和这里;
+// 多实例部署时其他实例注销的令牌最多延迟一个同步间隔生效
+static REVOKED_TOKENS: OnceLock<RwLock<HashMap<String, usize>>> = OnceLock::new();
+
+fn revoked_tokens() -> &'static RwLock<HashMap<String, usize>> {
+    REVOKED_TOKENS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// 把访问令牌加入注销列表, 同时清理已经过期的令牌
+pub fn revoke_tokens(tokens: &[AccessToken]) {
+    let now = Utc::now().timestamp() as usize;
+    let mut revoked = revoked_tokens().write().unwrap_or_else(|e| e.into_inner());
+    revoked.retain(|_, exp| *exp > now);
+    for token in tokens {
+        let exp = token.expired_at.and_utc().timestamp() as usize;
+        if exp > now {
+            revoked.insert(token.jti.clone(), exp);
+        }
+    }
+}
+
+/// 访问令牌是否已注销
+pub fn is_token_revoked(jti: &str) -> bool {
+    revoked_tokens()
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains_key(jti)
 }
 
 pub fn ecode_to_jwt(auth: &AuthClaims, secret: &[u8]) -> Option<String> {
@@ -108,6 +149,24 @@ impl AuthClaims {
             nbf,
             iat,
             jti,
+            scopes: None,
+        }
+    }
+
+    /// API Key 对应的声明, 只在本次请求中使用, 不会签发为令牌
+    pub fn from_api_key(api_key: ApiKeyModel) -> Self {
+        let now = Utc::now().timestamp() as usize;
+        Self {
+            iss: None,
+            sub: Some(api_key.account_id.to_string()),
+            aud: None,
+            exp: api_key
+                .expired_at
+                .map_or(usize::MAX, |t| t.and_utc().timestamp() as usize),
+            nbf: now,
+            iat: now,
+            jti: format!("api_key:{}", api_key.id),
+            scopes: Some(api_key.scopes),
         }
     }
 
@@ -115,6 +174,86 @@ impl AuthClaims {
         super::auth_claim::ecode_to_jwt(self, secret)
     }
 
+    /// 解析令牌, 已注销的令牌视为无效
+    pub fn from_token(token: &str, secret: &[u8]) -> Result<Self, api_error::APIError> {
+        let claims = decode_to_claims(token, secret)?;
+        if is_token_revoked(&claims.jti) {
+            return Err(api_error::APIError::ErrorParams(
+                "token revoked".to_string(),
+            ));
+        }
+        Ok(claims)
+    }
+
+    /// 令牌的 jti 和过期时间, 用于注销
+    pub fn access_token(&self) -> AccessToken {
+        AccessToken {
+            jti: self.jti.clone(),
+            expired_at: chrono::DateTime::from_timestamp(self.exp as i64, 0)
+                .unwrap_or_default()
+                .naive_utc(),
+        }
+    }
+
+    /// 校验权限范围, 登录令牌拥有全部权限
+    pub fn require_scope(&self, scope: ApiKeyScope) -> Result<(), api_error::APIError> {
+        match &self.scopes {
+            Some(scopes) if !scopes.contains(&scope) => Err(api_error::APIError::Forbidden(
+                "API Key 权限不足".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 只允许登录令牌访问, 例如管理 API Key 和退出登录
+    pub fn require_session(&self) -> Result<(), api_error::APIError> {
+        match self.scopes {
+            Some(_) => Err(api_error::APIError::Forbidden("需要登录令牌".to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// 从请求头认证, 依次尝试 `X-Api-Key`、`Authorization: Bearer` 和 `Token`
+    ///
+    /// Bearer 后面可以是 API Key 或者 JWT
+    pub async fn authenticate(
+        headers: &HeaderMap,
+        state: &AppState,
+    ) -> Result<Self, api_error::APIError> {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix("Bearer"))
+            .map(|h| h.trim());
+        // Google Reader 客户端使用 `GoogleLogin auth=<api key>`
+        let google_login = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.strip_prefix(GOOGLE_LOGIN_PREFIX))
+            .map(|h| h.trim());
+        let api_key = headers
+            .get(API_KEY_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .map(|h| h.trim())
+            .or(bearer.filter(|t| t.starts_with(API_KEY_PREFIX)))
+            .or(google_login);
+        if let Some(key) = api_key {
+            return match ApiKeyController.authenticate(key, &state.pool).await? {
+                Some(api_key) => Ok(Self::from_api_key(api_key)),
+                None => Err(api_error::APIError::ErrorParams("api key".to_string())),
+            };
+        }
+        let secret = state.setting.jwt.secret.clone();
+        if bearer.is_some() {
+            return Self::extract_from_request(headers, secret);
+        }
+        let token = headers
+            .get("Token")
+            .and_then(|h| h.to_str().ok())
+            .ok_or(api_error::APIError::ErrorParams("Token".to_string()))?;
+        Self::from_token(token, secret.as_bytes())
+    }
+
     /// Extract claims from request headers
     pub fn extract_from_request(
         headers: &HeaderMap,
@@ -127,18 +266,24 @@ impl AuthClaims {
                 let words = h.split("Bearer").collect::<Vec<&str>>();
                 words.get(1).map(|w| w.trim())
             })
-            .map(|token| decode_to_claims(token, decoding_key.as_bytes()));
+            .map(|token| Self::from_token(token, decoding_key.as_bytes()));
         match claim {
             Some(claim) => claim,
             None => Err(api_error::APIError::ErrorParams("claims".to_string())),
         }
     }
 
-    pub async fn get_user(&self, pool: &DBConnection) -> Result<AccountModel, api_error::APIError> {
-        let uid = self
-            .jti
+    /// 令牌对应的用户 id
+    pub fn account_id(&self) -> Result<i64, api_error::APIError> {
+        self.sub
+            .as_deref()
+            .unwrap_or_default()
             .parse::<i64>()
-            .map_err(|_| api_error::APIError::ErrorParams("id".to_string()))?;
+            .map_err(|_| api_error::APIError::ErrorParams("id".to_string()))
+    }
+
+    pub async fn get_user(&self, pool: &DBConnection) -> Result<AccountModel, api_error::APIError> {
+        let uid = self.account_id()?;
         let controller = AccountController;
         let req = QueryAccountByIDRequest::new(uid);
 
@@ -157,31 +302,25 @@ where
 {
     type Rejection = api_error::APIError;
 
-    async fn from_request_parts(parts: &mut Parts, state: &B) -> Result<Self, Self::Rejection> {
-        // You can either call them directly...
-        if !parts.headers.contains_key("Token") {
-            return Err(api_error::APIError::ErrorParams("Token".to_string()));
+    async fn from_request_parts(parts: &mut Parts, _state: &B) -> Result<Self, Self::Rejection> {
+        // require_role 等中间件已经认证过
+        if let Some(claims) = parts.extensions.get::<AuthClaims>() {
+            return Ok(claims.clone());
         }
+        if let Some(app) = parts.extensions.get::<Arc<AppState>>() {
+            return Self::authenticate(&parts.headers, app).await;
+        }
+        // 没有数据库时只支持 JWT
         let token = parts
             .headers
             .get("Token")
             .ok_or(api_error::APIError::ErrorParams("Token".to_string()))?
             .to_str()
             .unwrap_or("");
-        // let TypedHeader(Authorization(bearer)) =
-        //     TypedHeader::<Authorization<Bearer>>::from_request_parts(parts, state)
-        //         .await
-        //         .map_err(|_e| api_error::APIError::ErrorParams("Authorization".to_string()))?;
-
-        // let token = bearer.token();
         let setting = Setting::global();
 
         let secret = setting.jwt.secret.clone();
-        let info = match decode_to_claims(token, secret.as_bytes()) {
-            Ok(info) => info,
-            Err(e) => return Err(e),
-        };
-        Ok(info)
+        Self::from_token(token, secret.as_bytes())
     }
 }
 
@@ -225,5 +364,28 @@ mod tests {
         let decoded_claims = decode_to_claims(&token, secret.as_bytes()).unwrap();
         println!("decoded_claims: {:?}", decoded_claims);
         assert_eq!(decoded_claims.iss, Some(issuer));
+
+        // 注销后令牌失效
+        assert!(AuthClaims::from_token(&token, secret.as_bytes()).is_ok());
+        revoke_tokens(&[decoded_claims.access_token()]);
+        assert!(AuthClaims::from_token(&token, secret.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_require_scope() {
+        let claims = AuthClaims::from_api_key(ApiKeyModel {
+            id: 1,
+            account_id: 1,
+            name: "reader".to_string(),
+            prefix: "ak_".to_string(),
+            scopes: vec![ApiKeyScope::ReadFeeds],
+            last_used_at: None,
+            expired_at: None,
+            created_at: Utc::now().naive_utc(),
+        });
+        assert!(claims.require_scope(ApiKeyScope::ReadFeeds).is_ok());
+        // 只读的 API Key 不能修改阅读状态
+        assert!(claims.require_scope(ApiKeyScope::WriteFeeds).is_err());
+        assert!(claims.require_session().is_err());
     }
 }
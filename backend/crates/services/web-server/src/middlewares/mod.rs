@@ -1,4 +1,4 @@
 pub(crate) mod auth_claim;
-pub(crate) mod jwt;
 pub(crate) mod logger;
+pub(crate) mod role;
 pub(crate) mod verification;
@@ -0,0 +1,47 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use lib_core::auth::AccountController;
+use lib_entity::account::AccountRole;
+use std::sync::Arc;
+
+use super::auth_claim::AuthClaims;
+use crate::{api_error::APIError, AppState};
+
+/// 路由守卫, 要求当前用户至少拥有指定的角色
+///
+/// 用法: `post(handler).route_layer(from_fn_with_state(AccountRole::Editor, require_role))`
+pub(crate) async fn require_role(
+    State(required): State<AccountRole>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match check_role(required, &mut request).await {
+        Ok(()) => next.run(request).await,
+        Err(e) => e.into_response(),
+    }
+}
+
+// 认证并检查角色, 认证结果放到请求扩展中, 处理函数中的 AuthClaims 不再重复认证
+async fn check_role(required: AccountRole, request: &mut Request) -> Result<(), APIError> {
+    let app = request
+        .extensions()
+        .get::<Arc<AppState>>()
+        .cloned()
+        .ok_or(APIError::Internal("app state not found".to_string()))?;
+    let claims = match request.extensions().get::<AuthClaims>() {
+        Some(claims) => claims.clone(),
+        None => AuthClaims::authenticate(request.headers(), &app).await?,
+    };
+    let account = AccountController
+        .account_info(claims.account_id()?, &app.pool)
+        .await?
+        .ok_or(APIError::Toast("用户不存在".to_string()))?;
+    if !account.role.allows(required) {
+        return Err(APIError::Forbidden("没有权限".to_string()));
+    }
+    request.extensions_mut().insert(claims);
+    Ok(())
+}
@@ -14,7 +14,7 @@ async fn validate_request_header_sign(
     next: Next,
 ) -> Result<impl IntoResponse, Response> {
     let headers = request.headers();
-    let required_header = vec![
+    let required_header = [
         "OS".to_string().to_uppercase(),
         "TS".to_string().to_uppercase(),
         "LANG".to_string().to_uppercase(),
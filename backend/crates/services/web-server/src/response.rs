@@ -65,6 +65,7 @@ pub fn code_for_error(e: &APIError) -> i32 {
     match e {
         APIError::ErrorParams(_) => 10001,
         APIError::Toast(_) => 10002,
+        APIError::Forbidden(_) => 10003,
         APIError::Internal(_) => 99999,
     }
 }
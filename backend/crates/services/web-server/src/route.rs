@@ -4,10 +4,16 @@ use axum::{
 };
 use axum_extra::routing::RouterExt;
 
-use crate::{account, feed};
+use crate::{account, feed, fever, greader, websub};
 
 pub fn build_routes() -> Router {
     Router::new()
         .nest("/feed", feed::controller::build_routes())
         .nest("/account", account::controller::build_routes())
+        // Fever API 兼容层
+        .merge(fever::controller::build_routes())
+        // Google Reader API 兼容层
+        .merge(greader::controller::build_routes())
+        // WebSub 推送的回调
+        .merge(websub::controller::build_routes())
 }
@@ -0,0 +1,105 @@
+use crate::{api_error::APIError, AppState};
+use axum::{
+    body::Bytes,
+    extract::{Path, Query},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Router,
+};
+use lib_core::feed::{schema::WebSubOption, WebSubController};
+use lib_crawler::WebSubMode;
+use serde::Deserialize;
+use std::sync::Arc;
+
+// hub 验证订阅意图时的参数
+#[derive(Debug, Deserialize)]
+struct VerifyIntentParams {
+    #[serde(rename = "hub.mode")]
+    mode: String,
+    #[serde(rename = "hub.topic")]
+    topic: String,
+    #[serde(rename = "hub.challenge")]
+    challenge: Option<String>,
+    #[serde(rename = "hub.lease_seconds")]
+    lease_seconds: Option<i64>,
+}
+
+/// hub 验证订阅意图, 确认后原样返回 `hub.challenge`, 否则返回 404
+async fn verify_intent(
+    app: Extension<Arc<AppState>>,
+    Path(subscription_id): Path<i64>,
+    Query(params): Query<VerifyIntentParams>,
+) -> Result<Response, APIError> {
+    let not_found = (StatusCode::NOT_FOUND, "").into_response();
+    let Some(option) = WebSubOption::from_setting(&app.setting.websub) else {
+        return Ok(not_found);
+    };
+    let mode = match params.mode.as_str() {
+        "subscribe" => WebSubMode::Subscribe,
+        "unsubscribe" => WebSubMode::Unsubscribe,
+        "denied" => {
+            WebSubController
+                .deny(subscription_id, &params.topic, &app.pool)
+                .await?;
+            return Ok((StatusCode::OK, "").into_response());
+        }
+        _ => return Ok(not_found),
+    };
+    let Some(challenge) = params.challenge else {
+        return Ok(not_found);
+    };
+    let verified = WebSubController
+        .verify_intent(
+            subscription_id,
+            mode,
+            &params.topic,
+            params.lease_seconds,
+            &option,
+            &app.pool,
+        )
+        .await?;
+    match verified {
+        true => Ok((StatusCode::OK, challenge).into_response()),
+        false => Ok(not_found),
+    }
+}
+
+/// 接收 hub 推送的内容, 签名不正确时也返回 2xx, 避免 hub 重复推送
+async fn receive(
+    app: Extension<Arc<AppState>>,
+    Path(subscription_id): Path<i64>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, APIError> {
+    let signature = headers
+        .get("X-Hub-Signature-256")
+        .or(headers.get("X-Hub-Signature"))
+        .and_then(|v| v.to_str().ok());
+    match WebSubController
+        .receive(subscription_id, signature, &body, &app.pool)
+        .await
+    {
+        Ok(Some(links)) => {
+            tracing::info!(
+                "收到 WebSub 推送:{} 新增链接:{}",
+                subscription_id,
+                links.len()
+            );
+        }
+        Ok(None) => {}
+        // 推送的内容无法解析时丢弃
+        Err(lib_core::error::ErrorInService::Fetch(e)) => {
+            tracing::warn!("WebSub 推送的内容无法解析:{} {}", subscription_id, e);
+        }
+        Err(e) => return Err(e.into()),
+    }
+    Ok((StatusCode::ACCEPTED, "").into_response())
+}
+
+pub(crate) fn build_routes() -> Router {
+    Router::new().route(
+        "/websub/callback/:subscription_id",
+        get(verify_intent).post(receive),
+    )
+}